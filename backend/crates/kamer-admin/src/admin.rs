@@ -14,7 +14,7 @@ pub struct Host {
 
 // Local extract_user_id removed in favor of kamer_auth::extract_user_id
 
-async fn is_admin(pool: &PgPool, user_id: i32) -> bool {
+pub(crate) async fn is_admin(pool: &PgPool, user_id: i32) -> bool {
     let count: i64 =
         sqlx::query_scalar("SELECT COUNT(*) FROM user_roles WHERE user_id = $1 AND role = 'admin'")
             .bind(user_id)
@@ -0,0 +1,125 @@
+use crate::admin::is_admin;
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+use kamer_db::exchange_rates::{self, RateInput};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRatesRequest {
+    pub rates: Vec<RateInput>,
+}
+
+/// Path of the rates file read by the import endpoint (`EXCHANGE_RATES_FILE`).
+fn rates_file_path() -> String {
+    std::env::var("EXCHANGE_RATES_FILE").unwrap_or_else(|_| "exchange_rates.csv".to_string())
+}
+
+async fn save(pool: &PgPool, rates: &[RateInput], source: &str) -> HttpResponse {
+    let rates = match exchange_rates::validate_rates(rates) {
+        Ok(rates) => rates,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if let Err(e) = exchange_rates::save_rates(&mut tx, &rates, source).await {
+        log::error!("Failed to save exchange rates: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to save exchange rates" }));
+    }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit exchange rates: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to save exchange rates" }));
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({ "updated": rates.len() }))
+}
+
+/// GET /api/admin/exchange-rates - List exchange rates against the base currency
+#[get("/exchange-rates")]
+pub async fn get_exchange_rates(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    match exchange_rates::list_rates(pool.get_ref()).await {
+        Ok(rates) => HttpResponse::Ok().json(serde_json::json!({
+            "base_currency": exchange_rates::BASE_CURRENCY,
+            "rates": rates
+        })),
+        Err(e) => {
+            log::error!("Failed to fetch exchange rates: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch exchange rates" }))
+        }
+    }
+}
+
+/// PUT /api/admin/exchange-rates - Set one or more exchange rates
+#[put("/exchange-rates")]
+pub async fn update_exchange_rates(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<UpdateRatesRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    save(pool.get_ref(), &body.rates, "admin").await
+}
+
+/// POST /api/admin/exchange-rates/import - Load rates from the server's rates file
+///
+/// The file has `currency,rate_to_base[,rate_date]` lines; see `EXCHANGE_RATES_FILE`.
+#[post("/exchange-rates/import")]
+pub async fn import_exchange_rates(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let path = rates_file_path();
+    let contents = match tokio::fs::read_to_string(&path).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            log::error!("Failed to read exchange rates file {}: {:?}", path, e);
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Exchange rates file could not be read"
+            }));
+        }
+    };
+
+    match exchange_rates::parse_rates_file(&contents) {
+        Ok(rates) => save(pool.get_ref(), &rates, "import").await,
+        Err(message) => HttpResponse::BadRequest().json(serde_json::json!({ "error": message })),
+    }
+}
@@ -0,0 +1,102 @@
+use crate::admin::is_admin;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Deserialize)]
+pub struct JobListQuery {
+    pub status: Option<String>,
+    pub kind: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// GET /api/admin/jobs - List background jobs, newest first
+#[get("/jobs")]
+pub async fn get_jobs(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<JobListQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match kamer_jobs::store::list_jobs(
+        pool.get_ref(),
+        query.status.as_deref(),
+        query.kind.as_deref(),
+        limit,
+        offset,
+    )
+    .await
+    {
+        Ok(jobs) => HttpResponse::Ok().json(jobs),
+        Err(e) => {
+            log::error!("Failed to fetch jobs: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch jobs" }))
+        }
+    }
+}
+
+/// GET /api/admin/jobs/stats - Job counts per kind and status
+#[get("/jobs/stats")]
+pub async fn get_job_stats(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    match kamer_jobs::store::job_stats(pool.get_ref()).await {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(e) => {
+            log::error!("Failed to fetch job stats: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch job stats" }))
+        }
+    }
+}
+
+/// POST /api/admin/jobs/{id}/retry - Requeue a failed job
+#[post("/jobs/{id}/retry")]
+pub async fn retry_job(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    match kamer_jobs::store::retry_job(pool.get_ref(), path.into_inner()).await {
+        Ok(true) => HttpResponse::Ok().json(serde_json::json!({ "status": "queued" })),
+        Ok(false) => HttpResponse::NotFound()
+            .json(serde_json::json!({ "error": "No failed job with this id" })),
+        Err(e) => {
+            log::error!("Failed to retry job: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to retry job" }))
+        }
+    }
+}
@@ -1,8 +1,18 @@
 pub mod admin;
+pub mod exchange_rates;
+pub mod jobs;
+pub mod payouts;
+pub mod promo_codes;
 pub mod reports;
 pub mod roles;
+pub mod tax_rules;
 
 // Re-export all route handlers
 pub use admin::*;
+pub use exchange_rates::*;
+pub use jobs::*;
+pub use payouts::*;
+pub use promo_codes::*;
 pub use reports::*;
 pub use roles::*;
+pub use tax_rules::*;
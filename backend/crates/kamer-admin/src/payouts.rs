@@ -0,0 +1,151 @@
+use crate::admin::is_admin;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use kamer_payments::payouts::{self, MarkPaidResult};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Deserialize)]
+pub struct PayoutBatchQuery {
+    pub status: Option<String>,
+    pub host_id: Option<i32>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PayoutExportQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkPaidRequest {
+    /// Bank or mobile-money transfer reference.
+    pub reference: Option<String>,
+}
+
+/// GET /api/admin/payouts - List payout batches, newest first
+#[get("/payouts")]
+pub async fn get_payout_batches(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<PayoutBatchQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match payouts::list_batches(
+        pool.get_ref(),
+        query.host_id,
+        query.status.as_deref(),
+        limit,
+        offset,
+    )
+    .await
+    {
+        Ok(batches) => HttpResponse::Ok().json(batches),
+        Err(e) => {
+            log::error!("Failed to fetch payout batches: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch payout batches" }))
+        }
+    }
+}
+
+/// GET /api/admin/payouts/export.csv - Payout batches with host payout details as CSV
+#[get("/payouts/export.csv")]
+pub async fn export_payout_batches(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<PayoutExportQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let rows = match payouts::export_rows(pool.get_ref(), query.status.as_deref()).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            log::error!("Failed to fetch payout batches for export: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to export payout batches" }));
+        }
+    };
+
+    match payouts::export_csv(&rows) {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                format!(
+                    "attachment; filename=\"payouts-{}.csv\"",
+                    chrono::Utc::now().format("%Y-%m-%d")
+                ),
+            ))
+            .body(csv),
+        Err(e) => {
+            log::error!("Failed to render payout CSV: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to export payout batches" }))
+        }
+    }
+}
+
+/// POST /api/admin/payouts/{id}/mark-paid - Record that a payout batch has been sent
+#[post("/payouts/{id}/mark-paid")]
+pub async fn mark_payout_batch_paid(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: Option<web::Json<MarkPaidRequest>>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let reference = body.and_then(|b| b.into_inner().reference);
+
+    match payouts::mark_batch_paid(
+        pool.get_ref(),
+        &path.into_inner(),
+        user_id,
+        reference.as_deref(),
+    )
+    .await
+    {
+        Ok(MarkPaidResult::Paid) => {
+            HttpResponse::Ok().json(serde_json::json!({ "status": "paid" }))
+        }
+        Ok(MarkPaidResult::NotFound) => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Payout batch not found" }))
+        }
+        Ok(MarkPaidResult::AlreadyPaid) => HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": "Payout batch is already paid" })),
+        Err(e) => {
+            log::error!("Failed to mark payout batch as paid: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to update payout batch" }))
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::admin::is_admin;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use kamer_core::{Currency, Money};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PromoCode {
+    pub id: String,
+    pub code: String,
+    pub description: Option<String>,
+    pub discount_type: String,
+    pub discount_value: f64,
+    pub currency: Option<String>,
+    pub max_discount: Option<f64>,
+    pub min_total: Option<f64>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub max_uses: Option<i32>,
+    pub max_uses_per_user: Option<i32>,
+    pub active: bool,
+    pub created_at: Option<NaiveDateTime>,
+    /// Redemptions by bookings that are still live.
+    pub uses: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePromoCodeRequest {
+    pub code: String,
+    pub description: Option<String>,
+    /// `percentage` or `fixed`.
+    pub discount_type: String,
+    pub discount_value: f64,
+    /// Required for fixed discounts and discount limits. The code then only applies to bookings
+    /// in this currency.
+    pub currency: Option<String>,
+    pub max_discount: Option<f64>,
+    pub min_total: Option<f64>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub max_uses: Option<i32>,
+    /// Defaults to one use per guest; send `0` for unlimited.
+    pub max_uses_per_user: Option<i32>,
+}
+
+/// A validated code, ready to store.
+struct PromoCodeValues {
+    code: String,
+    discount_percent: Option<f64>,
+    discount_amount: Option<Money>,
+    currency: Option<Currency>,
+    max_discount: Option<Money>,
+    min_total: Option<Money>,
+}
+
+const PROMO_CODE_SELECT: &str = r#"
+    SELECT
+        p.id, p.code, p.description, p.discount_type,
+        COALESCE(
+            p.discount_percent,
+            (p.discount_amount_minor / power(10::NUMERIC, currency_exponent(p.currency)))::FLOAT8
+        ) as discount_value,
+        p.currency,
+        (p.max_discount_minor / power(10::NUMERIC, currency_exponent(p.currency)))::FLOAT8
+            as max_discount,
+        (p.min_total_minor / power(10::NUMERIC, currency_exponent(p.currency)))::FLOAT8
+            as min_total,
+        p.starts_at, p.ends_at, p.max_uses, p.max_uses_per_user, p.active, p.created_at,
+        (
+            SELECT COUNT(*) FROM promo_code_redemptions r
+            JOIN bookings b ON r.booking_id = b.id
+            WHERE r.promo_code_id = p.id
+              AND COALESCE(b.status, 'pending') NOT IN ('declined', 'expired', 'cancelled')
+        ) as uses
+    FROM promo_codes p
+"#;
+
+fn validate_promo_code(body: &CreatePromoCodeRequest) -> Result<PromoCodeValues, String> {
+    let code = body.code.trim().to_uppercase();
+    if code.len() < 3
+        || code.len() > 32
+        || !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Code must be 3-32 letters, digits, dashes or underscores".to_string());
+    }
+
+    let currency = match body.currency.as_deref().map(str::trim) {
+        Some(code) if !code.is_empty() => Some(Currency::new(code).map_err(|e| e.to_string())?),
+        _ => None,
+    };
+    // Amounts are in the code's currency
+    let amount = |amount: f64, required: &str| -> Result<Money, String> {
+        let currency = currency.ok_or_else(|| required.to_string())?;
+        Money::from_major(amount, currency).map_err(|e| e.to_string())
+    };
+
+    let (discount_percent, discount_amount) = match body.discount_type.as_str() {
+        "percentage" => {
+            if !(body.discount_value > 0.0 && body.discount_value <= 100.0) {
+                return Err("Percentage must be between 0 and 100".to_string());
+            }
+            (Some(body.discount_value), None)
+        }
+        "fixed" => {
+            if body.discount_value <= 0.0 {
+                return Err("Discount must be greater than zero".to_string());
+            }
+            let discount = amount(body.discount_value, "Fixed discounts require a currency")?;
+            (None, Some(discount))
+        }
+        _ => return Err("Invalid discount type".to_string()),
+    };
+
+    if body.max_discount.is_some_and(|v| v <= 0.0) || body.min_total.is_some_and(|v| v < 0.0) {
+        return Err("Invalid discount limits".to_string());
+    }
+    let max_discount = body
+        .max_discount
+        .map(|max| amount(max, "Discount limits require a currency"))
+        .transpose()?;
+    let min_total = body
+        .min_total
+        .map(|min| amount(min, "Discount limits require a currency"))
+        .transpose()?;
+
+    if body.max_uses.is_some_and(|v| v <= 0) || body.max_uses_per_user.is_some_and(|v| v < 0) {
+        return Err("Invalid usage limits".to_string());
+    }
+    if let (Some(starts_at), Some(ends_at)) = (body.starts_at, body.ends_at) {
+        if ends_at <= starts_at {
+            return Err("ends_at must be after starts_at".to_string());
+        }
+    }
+    Ok(PromoCodeValues {
+        code,
+        discount_percent,
+        discount_amount,
+        currency,
+        max_discount,
+        min_total,
+    })
+}
+
+/// GET /api/admin/promo-codes - List promo codes with their usage
+#[get("/promo-codes")]
+pub async fn get_promo_codes(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let codes = sqlx::query_as::<_, PromoCode>(&format!(
+        "{} ORDER BY p.created_at DESC",
+        PROMO_CODE_SELECT
+    ))
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match codes {
+        Ok(codes) => HttpResponse::Ok().json(codes),
+        Err(e) => {
+            log::error!("Failed to fetch promo codes: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch promo codes" }))
+        }
+    }
+}
+
+/// POST /api/admin/promo-codes - Create a platform promo code
+#[post("/promo-codes")]
+pub async fn create_promo_code(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<CreatePromoCodeRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let values = match validate_promo_code(&body) {
+        Ok(values) => values,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let result = sqlx::query(
+        r#"
+        INSERT INTO promo_codes (
+            id, code, description, discount_type, discount_percent, discount_amount_minor,
+            currency, max_discount_minor, min_total_minor, starts_at, ends_at, max_uses,
+            max_uses_per_user, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        "#,
+    )
+    .bind(&id)
+    .bind(&values.code)
+    .bind(&body.description)
+    .bind(&body.discount_type)
+    .bind(values.discount_percent)
+    .bind(values.discount_amount.map(|amount| amount.minor()))
+    .bind(values.currency)
+    .bind(values.max_discount.map(|amount| amount.minor()))
+    .bind(values.min_total.map(|amount| amount.minor()))
+    .bind(body.starts_at)
+    .bind(body.ends_at)
+    .bind(body.max_uses)
+    .bind(match body.max_uses_per_user {
+        Some(0) => None,
+        Some(max) => Some(max),
+        None => Some(1),
+    })
+    .bind(user_id)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "id": id, "code": values.code })),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": "A promo code with this code already exists" })),
+        Err(e) => {
+            log::error!("Failed to create promo code: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to create promo code" }))
+        }
+    }
+}
+
+/// POST /api/admin/promo-codes/{id}/deactivate - Stop accepting a promo code
+#[post("/promo-codes/{id}/deactivate")]
+pub async fn deactivate_promo_code(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let result = sqlx::query(
+        "UPDATE promo_codes SET active = FALSE, updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+    )
+    .bind(path.into_inner())
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Promo code not found" }))
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "deactivated" })),
+        Err(e) => {
+            log::error!("Failed to deactivate promo code: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to deactivate promo code" }))
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::admin::is_admin;
+use actix_web::{get, post, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{NaiveDate, NaiveDateTime};
+use kamer_core::{Currency, Money};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct TaxRule {
+    pub id: String,
+    pub country: String,
+    pub city: Option<String>,
+    pub name: String,
+    pub tax_type: String,
+    pub rate: Option<f64>,
+    pub amount: Option<f64>,
+    pub currency: Option<String>,
+    pub vat_registered_only: bool,
+    pub effective_from: NaiveDate,
+    pub effective_to: Option<NaiveDate>,
+    pub active: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaxRuleRequest {
+    pub country: String,
+    /// Leave out to apply the rule to the whole country.
+    pub city: Option<String>,
+    pub name: String,
+    /// `per_night`, `per_guest_night` or `percentage`.
+    pub tax_type: String,
+    /// Required for percentage taxes.
+    pub rate: Option<f64>,
+    /// Required for per-night taxes, with its currency.
+    pub amount: Option<f64>,
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub vat_registered_only: bool,
+    pub effective_from: NaiveDate,
+    /// Last day the rule applies; leave out while it is still in force.
+    pub effective_to: Option<NaiveDate>,
+}
+
+/// A validated rule, ready to store.
+struct TaxRuleValues {
+    country: String,
+    city: Option<String>,
+    name: String,
+    rate: Option<f64>,
+    amount: Option<Money>,
+}
+
+const TAX_RULE_SELECT: &str = r#"
+    SELECT
+        id, country, city, name, tax_type, rate,
+        (amount_minor / power(10::NUMERIC, currency_exponent(currency)))::FLOAT8 as amount,
+        currency, vat_registered_only, effective_from, effective_to, active,
+        created_at, updated_at
+    FROM tax_rules
+"#;
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
+fn validate_tax_rule(body: &TaxRuleRequest) -> Result<TaxRuleValues, String> {
+    let country = non_empty(Some(&body.country)).ok_or("Country is required")?;
+    let name = non_empty(Some(&body.name)).ok_or("Name is required")?;
+
+    let (rate, amount) = match body.tax_type.as_str() {
+        "percentage" => match body.rate {
+            Some(rate) if rate > 0.0 && rate <= 100.0 => (Some(rate), None),
+            _ => return Err("Rate must be between 0 and 100".to_string()),
+        },
+        "per_night" | "per_guest_night" => {
+            let currency = body
+                .currency
+                .as_deref()
+                .filter(|c| !c.trim().is_empty())
+                .ok_or("Fixed taxes require a currency")?;
+            let currency = Currency::new(currency.trim()).map_err(|e| e.to_string())?;
+            let amount = match body.amount {
+                Some(amount) if amount > 0.0 => {
+                    Money::from_major(amount, currency).map_err(|e| e.to_string())?
+                }
+                _ => return Err("Amount must be greater than zero".to_string()),
+            };
+            (None, Some(amount))
+        }
+        _ => return Err("Invalid tax type".to_string()),
+    };
+
+    if body
+        .effective_to
+        .is_some_and(|effective_to| effective_to < body.effective_from)
+    {
+        return Err("effective_to must not be before effective_from".to_string());
+    }
+
+    Ok(TaxRuleValues {
+        country,
+        city: non_empty(body.city.as_deref()),
+        name,
+        rate,
+        amount,
+    })
+}
+
+/// GET /api/admin/tax-rules - List tax rules by location
+#[get("/tax-rules")]
+pub async fn get_tax_rules(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let rules = sqlx::query_as::<_, TaxRule>(&format!(
+        "{} ORDER BY country, city NULLS FIRST, effective_from DESC",
+        TAX_RULE_SELECT
+    ))
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match rules {
+        Ok(rules) => HttpResponse::Ok().json(rules),
+        Err(e) => {
+            log::error!("Failed to fetch tax rules: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch tax rules" }))
+        }
+    }
+}
+
+/// POST /api/admin/tax-rules - Create a tax rule
+#[post("/tax-rules")]
+pub async fn create_tax_rule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<TaxRuleRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let values = match validate_tax_rule(&body) {
+        Ok(values) => values,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let result = sqlx::query(
+        r#"
+        INSERT INTO tax_rules (
+            id, country, city, name, tax_type, rate, amount_minor, currency,
+            vat_registered_only, effective_from, effective_to, created_by
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        "#,
+    )
+    .bind(&id)
+    .bind(&values.country)
+    .bind(&values.city)
+    .bind(&values.name)
+    .bind(&body.tax_type)
+    .bind(values.rate)
+    .bind(values.amount.map(|amount| amount.minor()))
+    .bind(values.amount.map(|amount| amount.currency()))
+    .bind(body.vat_registered_only)
+    .bind(body.effective_from)
+    .bind(body.effective_to)
+    .bind(user_id)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "id": id })),
+        Err(e) => {
+            log::error!("Failed to create tax rule: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to create tax rule" }))
+        }
+    }
+}
+
+/// PUT /api/admin/tax-rules/{id} - Replace a tax rule
+///
+/// Quotes already given keep the taxes in their price snapshot.
+#[put("/tax-rules/{id}")]
+pub async fn update_tax_rule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<TaxRuleRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let values = match validate_tax_rule(&body) {
+        Ok(values) => values,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let result = sqlx::query(
+        r#"
+        UPDATE tax_rules
+        SET country = $2, city = $3, name = $4, tax_type = $5, rate = $6, amount_minor = $7,
+            currency = $8, vat_registered_only = $9, effective_from = $10, effective_to = $11,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(path.into_inner())
+    .bind(&values.country)
+    .bind(&values.city)
+    .bind(&values.name)
+    .bind(&body.tax_type)
+    .bind(values.rate)
+    .bind(values.amount.map(|amount| amount.minor()))
+    .bind(values.amount.map(|amount| amount.currency()))
+    .bind(body.vat_registered_only)
+    .bind(body.effective_from)
+    .bind(body.effective_to)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Tax rule not found" }))
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "updated" })),
+        Err(e) => {
+            log::error!("Failed to update tax rule: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to update tax rule" }))
+        }
+    }
+}
+
+/// POST /api/admin/tax-rules/{id}/deactivate - Stop charging a tax
+#[post("/tax-rules/{id}/deactivate")]
+pub async fn deactivate_tax_rule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    if !is_admin(pool.get_ref(), user_id).await {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Admin access required" }));
+    }
+
+    let result = sqlx::query(
+        "UPDATE tax_rules SET active = FALSE, updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+    )
+    .bind(path.into_inner())
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Tax rule not found" }))
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "deactivated" })),
+        Err(e) => {
+            log::error!("Failed to deactivate tax rule: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to deactivate tax rule" }))
+        }
+    }
+}
@@ -28,6 +28,7 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                 .service(kamer_listings::add_review)
                 .service(kamer_listings::create_listing)
                 .service(kamer_listings::get_my_listings)
+                .service(kamer_listings::get_listing_availability)
                 .service(kamer_listings::get_listing)
                 .service(kamer_listings::update_listing)
                 .service(kamer_listings::delete_listing)
@@ -48,6 +49,10 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
                 .service(kamer_messages::get_conversations)
                 .service(kamer_messages::get_messages)
                 .service(kamer_messages::send_message)
+                .service(kamer_messages::mark_conversation_read)
+                .service(kamer_messages::send_typing)
+                .service(kamer_messages::messaging_socket)
+                .service(kamer_messages::messaging_events)
                 .service(kamer_messages::get_unread_count)
                 .service(kamer_messages::get_message_templates),
         )
@@ -67,16 +72,68 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         )
         .service(
             web::scope("/bookings")
+                .service(kamer_bookings::quote_booking_price)
                 .service(kamer_bookings::create_booking)
                 .service(kamer_bookings::get_today_bookings)
                 .service(kamer_bookings::get_upcoming_bookings)
+                .service(kamer_bookings::get_host_bookings)
+                .service(kamer_bookings::export_host_bookings)
                 .service(kamer_bookings::get_my_bookings)
                 .service(kamer_bookings::approve_booking)
                 .service(kamer_bookings::decline_booking)
-                .service(kamer_bookings::cancel_booking),
+                .service(kamer_bookings::get_cancellation_preview)
+                .service(kamer_bookings::cancel_booking)
+                .service(kamer_bookings::host_cancel_booking)
+                .service(kamer_bookings::create_alteration)
+                .service(kamer_bookings::get_alterations)
+                .service(kamer_bookings::accept_alteration)
+                .service(kamer_bookings::decline_alteration)
+                .service(kamer_bookings::withdraw_alteration)
+                .service(kamer_bookings::get_booking_receipt)
+                .service(kamer_bookings::get_booking_check_in)
+                .service(kamer_bookings::create_offer)
+                .service(kamer_bookings::get_offer)
+                .service(kamer_bookings::withdraw_offer)
+                .service(kamer_bookings::book_offer),
+        )
+        .service(
+            web::scope("/promotions")
+                .service(kamer_bookings::get_listing_promotions)
+                .service(kamer_bookings::create_listing_promotion)
+                .service(kamer_bookings::delete_listing_promotion),
+        )
+        .service(
+            web::scope("/check-in")
+                .service(kamer_bookings::get_check_in_details)
+                .service(kamer_bookings::update_check_in_details),
+        )
+        .service(
+            web::scope("/payments")
+                .service(kamer_payments::initiate_payment)
+                .service(kamer_payments::payment_webhook)
+                .service(kamer_payments::get_payment),
+        )
+        .service(
+            web::scope("/earnings")
+                .service(kamer_payments::get_earnings_balance)
+                .service(kamer_payments::get_my_payouts),
         )
         .service(
             web::scope("/calendar")
+                .service(kamer_calendar::get_multi_calendar)
+                .service(kamer_calendar::bulk_update_calendar_dates)
+                .service(kamer_calendar::export_calendar)
+                .service(kamer_calendar::get_export_feed)
+                .service(kamer_calendar::regenerate_export_feed)
+                .service(kamer_calendar::get_import_feeds)
+                .service(kamer_calendar::create_import_feed)
+                .service(kamer_calendar::sync_import_feed)
+                .service(kamer_calendar::delete_import_feed)
+                .service(kamer_calendar::get_external_blocks)
+                .service(kamer_calendar::get_pricing_rules)
+                .service(kamer_calendar::create_pricing_rule)
+                .service(kamer_calendar::update_pricing_rule)
+                .service(kamer_calendar::delete_pricing_rule)
                 .service(kamer_calendar::get_calendar)
                 .service(kamer_calendar::update_calendar_dates)
                 .service(kamer_calendar::get_settings)
@@ -88,6 +145,22 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             web::scope("/admin")
                 .service(kamer_admin::get_hosts)
                 .service(kamer_admin::delete_host)
-                .service(kamer_admin::get_reports),
+                .service(kamer_admin::get_reports)
+                .service(kamer_admin::get_job_stats)
+                .service(kamer_admin::get_jobs)
+                .service(kamer_admin::retry_job)
+                .service(kamer_admin::export_payout_batches)
+                .service(kamer_admin::get_payout_batches)
+                .service(kamer_admin::mark_payout_batch_paid)
+                .service(kamer_admin::get_promo_codes)
+                .service(kamer_admin::create_promo_code)
+                .service(kamer_admin::deactivate_promo_code)
+                .service(kamer_admin::get_tax_rules)
+                .service(kamer_admin::create_tax_rule)
+                .service(kamer_admin::update_tax_rule)
+                .service(kamer_admin::deactivate_tax_rule)
+                .service(kamer_admin::get_exchange_rates)
+                .service(kamer_admin::update_exchange_rates)
+                .service(kamer_admin::import_exchange_rates),
         );
 }
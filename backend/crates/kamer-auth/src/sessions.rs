@@ -0,0 +1,16 @@
+use sqlx::PgPool;
+
+/// Job kind: delete expired cookie sessions.
+pub const CLEANUP_EXPIRED_SESSIONS: &str = "auth.cleanup_sessions";
+
+/// Delete sessions whose `expires_at` is in the past.
+pub async fn cleanup_expired_sessions(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM sessions WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() > 0 {
+        log::info!("Deleted {} expired sessions", result.rows_affected());
+    }
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,926 @@
+use crate::availability::{find_date_conflict, find_stay_restriction};
+use crate::guests::{GuestCounts, GuestRules};
+use crate::pricing::PriceSnapshot;
+use crate::promotions::{carry_forward_discounts, PromotionError};
+use crate::routes::post_booking_message;
+use crate::taxes::{apply_taxes, TaxError};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use kamer_calendar::{nightly_prices, PricingError};
+use kamer_core::{Currency, Money};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BookingAlteration {
+    pub id: String,
+    pub booking_id: String,
+    pub requested_by: i32,
+    pub old_check_in: String,
+    pub old_check_out: String,
+    pub old_guests: i32,
+    pub old_total_price: Money,
+    pub new_check_in: String,
+    pub new_check_out: String,
+    pub new_guests: i32,
+    pub new_adults: Option<i32>,
+    pub new_children: Option<i32>,
+    pub new_infants: Option<i32>,
+    pub new_pets: Option<i32>,
+    pub new_total_price: Money,
+    pub price_difference: Money,
+    pub currency: String,
+    pub status: String,
+    pub responded_at: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// Fields left out keep their current value.
+#[derive(Debug, Deserialize)]
+pub struct CreateAlterationRequest {
+    pub check_in: Option<String>,
+    pub check_out: Option<String>,
+    /// Legacy total; used as the adult count when `adults` is not sent.
+    pub guests: Option<i32>,
+    pub adults: Option<i32>,
+    pub children: Option<i32>,
+    pub infants: Option<i32>,
+    pub pets: Option<i32>,
+}
+
+/// Booking and listing fields needed to quote a change.
+#[derive(Debug, sqlx::FromRow)]
+struct AlterationBooking {
+    listing_id: String,
+    guest_id: i32,
+    host_id: i32,
+    status: String,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    guests: i32,
+    #[sqlx(flatten)]
+    counts: GuestCounts,
+    total_price_minor: i64,
+    currency: Currency,
+    price_snapshot: Option<sqlx::types::Json<PriceSnapshot>>,
+    listing_currency: Currency,
+    /// Paid online; price increases can't be collected yet.
+    paid: bool,
+    #[sqlx(flatten)]
+    rules: GuestRules,
+}
+
+/// A pending alteration together with the current state of its booking.
+#[derive(Debug, sqlx::FromRow)]
+struct AlterationContext {
+    booking_id: String,
+    listing_id: String,
+    guest_id: i32,
+    host_id: i32,
+    status: String,
+    booking_status: String,
+    booking_check_in: NaiveDate,
+    booking_check_out: NaiveDate,
+    booking_guests: i32,
+    /// Guest composition and total still match what the request was quoted against.
+    quote_current: bool,
+    /// Paid online; a lower price is refunded, a higher one can't be collected yet.
+    paid: bool,
+    old_check_in: NaiveDate,
+    old_check_out: NaiveDate,
+    old_guests: i32,
+    new_check_in: NaiveDate,
+    new_check_out: NaiveDate,
+    new_guests: i32,
+    new_adults: Option<i32>,
+    new_children: Option<i32>,
+    new_infants: Option<i32>,
+    new_pets: Option<i32>,
+    new_total_price: Money,
+    price_difference: Money,
+    price_snapshot: Option<sqlx::types::Json<PriceSnapshot>>,
+}
+
+const ALTERATION_COLUMNS: &str = r#"
+    id, booking_id, requested_by,
+    old_check_in::TEXT as old_check_in, old_check_out::TEXT as old_check_out, old_guests,
+    to_money(old_total_price_minor, currency) as old_total_price,
+    new_check_in::TEXT as new_check_in, new_check_out::TEXT as new_check_out, new_guests,
+    new_adults, new_children, new_infants, new_pets,
+    to_money(new_total_price_minor, currency) as new_total_price,
+    to_money(price_difference_minor, currency) as price_difference, currency, status,
+    responded_at::TEXT as responded_at, created_at::TEXT as created_at
+"#;
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+fn parse_date(value: Option<&str>, current: NaiveDate) -> Result<NaiveDate, ()> {
+    match value {
+        Some(v) => NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| ()),
+        None => Ok(current),
+    }
+}
+
+fn describe_change(check_in: NaiveDate, check_out: NaiveDate, guests: i32) -> String {
+    format!(
+        "{} to {}, {} guest{}",
+        check_in,
+        check_out,
+        guests,
+        if guests == 1 { "" } else { "s" }
+    )
+}
+
+/// The new total, with the change in price when there is one.
+fn describe_total(total: Money, difference: Money) -> String {
+    if difference.is_positive() {
+        format!("{} (+{})", total, difference)
+    } else if difference.is_negative() {
+        format!("{} ({})", total, difference)
+    } else {
+        total.to_string()
+    }
+}
+
+async fn fetch_alteration(
+    pool: &PgPool,
+    alteration_id: &str,
+) -> Result<Option<BookingAlteration>, sqlx::Error> {
+    sqlx::query_as::<_, BookingAlteration>(&format!(
+        "SELECT {} FROM booking_alterations WHERE id = $1",
+        ALTERATION_COLUMNS
+    ))
+    .bind(alteration_id)
+    .fetch_optional(pool)
+    .await
+}
+
+async fn fetch_alteration_context<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    alteration_id: &str,
+    lock: bool,
+) -> Result<Option<AlterationContext>, sqlx::Error> {
+    sqlx::query_as::<_, AlterationContext>(&format!(
+        r#"
+        SELECT
+            a.booking_id, b.listing_id, b.guest_id, l.host_id, a.status,
+            COALESCE(b.status, 'pending') as booking_status,
+            b.check_in as booking_check_in, b.check_out as booking_check_out,
+            b.guests as booking_guests,
+            b.total_price_minor = a.old_total_price_minor
+                AND (a.old_adults IS NULL
+                     OR (a.old_adults, a.old_children, a.old_infants, a.old_pets)
+                        = (COALESCE(b.adults, b.guests), b.children, b.infants, b.pets))
+                as quote_current,
+            EXISTS(
+                SELECT 1 FROM payments p WHERE p.booking_id = b.id AND p.status = 'succeeded'
+            ) as paid,
+            a.old_check_in, a.old_check_out, a.old_guests,
+            a.new_check_in, a.new_check_out, a.new_guests,
+            a.new_adults, a.new_children, a.new_infants, a.new_pets,
+            to_money(a.new_total_price_minor, a.currency) as new_total_price,
+            to_money(a.price_difference_minor, a.currency) as price_difference,
+            a.price_snapshot
+        FROM booking_alterations a
+        JOIN bookings b ON a.booking_id = b.id
+        JOIN listings l ON b.listing_id = l.id
+        WHERE a.id = $1
+        {}
+        "#,
+        if lock { "FOR UPDATE OF a, b" } else { "" }
+    ))
+    .bind(alteration_id)
+    .fetch_optional(executor)
+    .await
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// POST /api/bookings/{id}/alterations - Propose new dates or guest count for a booking (guest)
+#[post("/{id}/alterations")]
+pub async fn create_alteration(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateAlterationRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let booking_id = path.into_inner();
+
+    let booking = sqlx::query_as::<_, AlterationBooking>(
+        r#"
+        SELECT
+            b.listing_id, b.guest_id, l.host_id, COALESCE(b.status, 'pending') as status,
+            b.check_in, b.check_out, b.guests,
+            COALESCE(b.adults, b.guests) as adults, b.children, b.infants, b.pets,
+            b.total_price_minor, b.currency, b.price_snapshot,
+            COALESCE(l.currency, 'XAF') as listing_currency,
+            EXISTS(
+                SELECT 1 FROM payments p WHERE p.booking_id = b.id AND p.status = 'succeeded'
+            ) as paid,
+            COALESCE(l.max_guests, 0) as max_guests,
+            l.pets_allowed, l.max_pets, l.guests_included, l.extra_guest_fee_minor
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1
+        "#,
+    )
+    .bind(&booking_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    let booking = match booking {
+        Ok(Some(booking)) => booking,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Booking not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking for alteration: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if booking.guest_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only the guest can request changes to this booking"
+        }));
+    }
+
+    if booking.status != "pending" && booking.status != "confirmed" {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("A {} booking cannot be changed", booking.status)
+        }));
+    }
+
+    let (new_check_in, new_check_out) = match (
+        parse_date(body.check_in.as_deref(), booking.check_in),
+        parse_date(body.check_out.as_deref(), booking.check_out),
+    ) {
+        (Ok(check_in), Ok(check_out)) => (check_in, check_out),
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid date format" }));
+        }
+    };
+
+    let current = booking.counts;
+    let children = body.children.unwrap_or(current.children);
+    let new_counts = GuestCounts {
+        adults: body
+            .adults
+            .or(body.guests.map(|guests| guests - children))
+            .unwrap_or(current.adults),
+        children,
+        infants: body.infants.unwrap_or(current.infants),
+        pets: body.pets.unwrap_or(current.pets),
+    };
+    let new_guests = new_counts.capacity();
+
+    if new_check_in == booking.check_in
+        && new_check_out == booking.check_out
+        && new_counts == current
+    {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "No changes requested" }));
+    }
+
+    let nights = (new_check_out - new_check_in).num_days();
+    if nights <= 0 {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Check-out must be after check-in" }));
+    }
+
+    if new_check_in != booking.check_in && new_check_in < chrono::Utc::now().date_naive() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Check-in cannot be in the past" }));
+    }
+
+    if let Err(message) = new_counts.validate(&booking.rules) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+    }
+
+    match find_date_conflict(
+        pool.get_ref(),
+        &booking.listing_id,
+        new_check_in,
+        new_check_out,
+        Some(&booking_id),
+    )
+    .await
+    {
+        Ok(None) => {}
+        Ok(Some(conflict)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": conflict.message() }));
+        }
+        Err(e) => {
+            log::error!("Failed to check alteration availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    // Restrictions only bind the dates being changed to
+    if new_check_in != booking.check_in || new_check_out != booking.check_out {
+        match find_stay_restriction(
+            pool.get_ref(),
+            &booking.listing_id,
+            new_check_in,
+            new_check_out,
+        )
+        .await
+        {
+            Ok(None) => {}
+            Ok(Some(restriction)) => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({ "error": restriction.message() }));
+            }
+            Err(e) => {
+                log::error!("Failed to check alteration restrictions: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Database error" }));
+            }
+        }
+    }
+
+    // Re-quote at the listing's current prices, in the currency the booking settles in
+    if booking.listing_currency != booking.currency {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "The listing's currency has changed since this booking was made"
+        }));
+    }
+    let old_total = Money::from_minor(booking.total_price_minor, booking.currency);
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to price alteration: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+    let quote = nightly_prices(
+        &mut conn,
+        &booking.listing_id,
+        new_check_in,
+        new_check_out,
+        Some(nights),
+    )
+    .await
+    .and_then(|nights| {
+        PriceSnapshot::from_nights(booking.currency, &nights)?
+            .with_extra_guests(
+                new_counts.extra_guests(&booking.rules),
+                Money::from_minor(booking.rules.extra_guest_fee_minor, booking.currency),
+            )
+            .map_err(PricingError::from)
+    });
+    let mut price_snapshot = match quote {
+        Ok(snapshot) => snapshot,
+        Err(PricingError::Price(e)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": format!("Unable to price this stay: {}", e) }));
+        }
+        Err(PricingError::Database(e)) => {
+            log::error!("Failed to price alteration: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Keep the booking's host promotions and promo code, then tax the discounted price
+    if let Some(original) = &booking.price_snapshot {
+        match carry_forward_discounts(&mut conn, original, &mut price_snapshot).await {
+            Ok(()) => {}
+            Err(PromotionError::Price(e)) => {
+                return HttpResponse::BadRequest().json(
+                    serde_json::json!({ "error": format!("Unable to price this stay: {}", e) }),
+                );
+            }
+            Err(PromotionError::Database(e)) => {
+                log::error!("Failed to apply discounts to alteration: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Database error" }));
+            }
+            Err(PromotionError::Invalid(message)) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+            }
+        }
+    }
+
+    match apply_taxes(
+        &mut conn,
+        &booking.listing_id,
+        new_check_in,
+        &new_counts,
+        &mut price_snapshot,
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(TaxError::Price(e)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": format!("Unable to price this stay: {}", e) }));
+        }
+        Err(TaxError::Database(e)) => {
+            log::error!("Failed to apply taxes to alteration: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    // The pool may hold a single connection; give it back before the insert below
+    drop(conn);
+
+    let new_total = price_snapshot.total;
+    let price_difference = match new_total.checked_sub(old_total) {
+        Ok(difference) => difference,
+        Err(e) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": format!("Unable to price this stay: {}", e) }));
+        }
+    };
+    if booking.paid && price_difference.is_positive() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Changes that raise the price of a paid booking are not supported yet"
+        }));
+    }
+    let alteration_id = uuid::Uuid::new_v4().to_string();
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO booking_alterations (
+            id, booking_id, requested_by,
+            old_check_in, old_check_out, old_guests, old_total_price_minor,
+            new_check_in, new_check_out, new_guests, new_total_price_minor,
+            price_difference_minor, currency, price_snapshot,
+            new_adults, new_children, new_infants, new_pets,
+            old_adults, old_children, old_infants, old_pets
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+                $19, $20, $21, $22)
+        "#,
+    )
+    .bind(&alteration_id)
+    .bind(&booking_id)
+    .bind(user_id)
+    .bind(booking.check_in)
+    .bind(booking.check_out)
+    .bind(booking.guests)
+    .bind(old_total.minor())
+    .bind(new_check_in)
+    .bind(new_check_out)
+    .bind(new_guests)
+    .bind(new_total.minor())
+    .bind(price_difference.minor())
+    .bind(booking.currency)
+    .bind(sqlx::types::Json(&price_snapshot))
+    .bind(new_counts.adults)
+    .bind(new_counts.children)
+    .bind(new_counts.infants)
+    .bind(new_counts.pets)
+    .bind(current.adults)
+    .bind(current.children)
+    .bind(current.infants)
+    .bind(current.pets)
+    .execute(pool.get_ref())
+    .await;
+
+    if let Err(e) = inserted {
+        // The partial unique index allows only one pending request per booking
+        if let sqlx::Error::Database(db_err) = &e {
+            if db_err.is_unique_violation() {
+                return HttpResponse::Conflict().json(serde_json::json!({
+                    "error": "A change request is already pending for this booking"
+                }));
+            }
+        }
+        log::error!("Failed to create booking alteration: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to request change" }));
+    }
+
+    let message_content = format!(
+        "Guest requested a change: {} (was {}). New total: {}.",
+        describe_change(new_check_in, new_check_out, new_guests),
+        describe_change(booking.check_in, booking.check_out, booking.guests),
+        describe_total(new_total, price_difference)
+    );
+    post_booking_message(
+        pool.get_ref(),
+        &booking.listing_id,
+        booking.guest_id,
+        booking.host_id,
+        user_id,
+        &message_content,
+        serde_json::json!({
+            "event": "alteration_requested",
+            "booking_id": booking_id,
+            "alteration_id": alteration_id
+        }),
+    )
+    .await;
+
+    match fetch_alteration(pool.get_ref(), &alteration_id).await {
+        Ok(Some(alteration)) => HttpResponse::Ok().json(alteration),
+        Ok(None) => HttpResponse::NotFound()
+            .json(serde_json::json!({ "error": "Change request not found" })),
+        Err(e) => {
+            log::error!("Failed to fetch booking alteration: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// GET /api/bookings/{id}/alterations - List change requests for a booking (guest or host)
+#[get("/{id}/alterations")]
+pub async fn get_alterations(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let booking_id = path.into_inner();
+
+    let is_party = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM bookings b
+            JOIN listings l ON b.listing_id = l.id
+            WHERE b.id = $1 AND (b.guest_id = $2 OR l.host_id = $2)
+        )
+        "#,
+    )
+    .bind(&booking_id)
+    .bind(user_id)
+    .fetch_one(pool.get_ref())
+    .await;
+
+    match is_party {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "You do not have permission to view this booking"
+            }));
+        }
+        Err(e) => {
+            log::error!("Failed to check booking access: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    let alterations = sqlx::query_as::<_, BookingAlteration>(&format!(
+        "SELECT {} FROM booking_alterations WHERE booking_id = $1 ORDER BY created_at DESC",
+        ALTERATION_COLUMNS
+    ))
+    .bind(&booking_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match alterations {
+        Ok(alterations) => HttpResponse::Ok().json(alterations),
+        Err(e) => {
+            log::error!("Failed to fetch booking alterations: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST /api/bookings/alterations/{id}/accept - Accept a change request and update the booking (host)
+#[post("/alterations/{id}/accept")]
+pub async fn accept_alteration(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let alteration_id = path.into_inner();
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Lock the request and its booking so concurrent changes/cancellations serialize
+    let ctx = match fetch_alteration_context(&mut *tx, &alteration_id, true).await {
+        Ok(Some(ctx)) => ctx,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Change request not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking alteration: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if ctx.host_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to accept this change"
+        }));
+    }
+
+    if ctx.status != "pending" {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("This change request is already {}", ctx.status)
+        }));
+    }
+
+    // The booking must still be what the quote was based on
+    if (ctx.booking_status != "pending" && ctx.booking_status != "confirmed")
+        || ctx.booking_check_in != ctx.old_check_in
+        || ctx.booking_check_out != ctx.old_check_out
+        || ctx.booking_guests != ctx.old_guests
+        || !ctx.quote_current
+    {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "The booking has changed since this request was made"
+        }));
+    }
+
+    if ctx.paid && ctx.price_difference.is_positive() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Changes that raise the price of a paid booking are not supported yet"
+        }));
+    }
+
+    match find_date_conflict(
+        &mut *tx,
+        &ctx.listing_id,
+        ctx.new_check_in,
+        ctx.new_check_out,
+        Some(&ctx.booking_id),
+    )
+    .await
+    {
+        Ok(None) => {}
+        Ok(Some(conflict)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": conflict.message() }));
+        }
+        Err(e) => {
+            log::error!("Failed to check alteration availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    // Restrictions on the new dates may have changed since the request was made
+    if ctx.new_check_in != ctx.old_check_in || ctx.new_check_out != ctx.old_check_out {
+        match find_stay_restriction(
+            &mut *tx,
+            &ctx.listing_id,
+            ctx.new_check_in,
+            ctx.new_check_out,
+        )
+        .await
+        {
+            Ok(None) => {}
+            Ok(Some(restriction)) => {
+                return HttpResponse::BadRequest()
+                    .json(serde_json::json!({ "error": restriction.message() }));
+            }
+            Err(e) => {
+                log::error!("Failed to check alteration restrictions: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Database error" }));
+            }
+        }
+    }
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE bookings
+        SET check_in = $2, check_out = $3, guests = $4, total_price_minor = $5,
+            price_snapshot = COALESCE($6, price_snapshot),
+            adults = COALESCE($7, adults), children = COALESCE($8, children),
+            infants = COALESCE($9, infants), pets = COALESCE($10, pets),
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(&ctx.booking_id)
+    .bind(ctx.new_check_in)
+    .bind(ctx.new_check_out)
+    .bind(ctx.new_guests)
+    .bind(ctx.new_total_price.minor())
+    .bind(&ctx.price_snapshot)
+    .bind(ctx.new_adults)
+    .bind(ctx.new_children)
+    .bind(ctx.new_infants)
+    .bind(ctx.new_pets)
+    .execute(&mut *tx)
+    .await;
+
+    if let Err(e) = updated {
+        log::error!("Failed to apply booking alteration: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update booking" }));
+    }
+
+    if let Err(e) = sqlx::query(
+        "UPDATE booking_alterations SET status = 'accepted', responded_at = CURRENT_TIMESTAMP WHERE id = $1",
+    )
+    .bind(&alteration_id)
+    .execute(&mut *tx)
+    .await
+    {
+        log::error!("Failed to accept booking alteration: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update booking" }));
+    }
+
+    // A lower price is refunded to the guest's mobile money; if the guest paid the host
+    // directly, the fee the host owes follows the new total instead
+    let decrease = Money::from_minor(
+        -ctx.price_difference.minor(),
+        ctx.price_difference.currency(),
+    );
+    let settled = match kamer_payments::request_alteration_refund(
+        &mut tx,
+        &ctx.booking_id,
+        &alteration_id,
+        decrease,
+    )
+    .await
+    {
+        Ok(_) => {
+            kamer_payments::ledger::post_offline_alteration(
+                &mut tx,
+                &ctx.booking_id,
+                &alteration_id,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = settled {
+        log::error!("Failed to settle booking alteration: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update booking" }));
+    }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit booking alteration: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update booking" }));
+    }
+
+    let message_content = format!(
+        "Host accepted the change: {}. New total: {}.",
+        describe_change(ctx.new_check_in, ctx.new_check_out, ctx.new_guests),
+        describe_total(ctx.new_total_price, ctx.price_difference)
+    );
+    post_booking_message(
+        pool.get_ref(),
+        &ctx.listing_id,
+        ctx.guest_id,
+        ctx.host_id,
+        user_id,
+        &message_content,
+        serde_json::json!({
+            "event": "alteration_accepted",
+            "booking_id": ctx.booking_id,
+            "alteration_id": alteration_id
+        }),
+    )
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "accepted",
+        "booking_id": ctx.booking_id,
+        "total_price": ctx.new_total_price.to_major(),
+        "price_difference": ctx.price_difference.to_major()
+    }))
+}
+
+/// POST /api/bookings/alterations/{id}/decline - Decline a change request (host)
+#[post("/alterations/{id}/decline")]
+pub async fn decline_alteration(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    close_alteration(pool, req, path.into_inner(), "declined").await
+}
+
+/// POST /api/bookings/alterations/{id}/withdraw - Withdraw a change request (guest)
+#[post("/alterations/{id}/withdraw")]
+pub async fn withdraw_alteration(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    close_alteration(pool, req, path.into_inner(), "withdrawn").await
+}
+
+/// Decline (host) or withdraw (guest) a pending request without touching the booking.
+async fn close_alteration(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    alteration_id: String,
+    new_status: &'static str,
+) -> HttpResponse {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let ctx = match fetch_alteration_context(pool.get_ref(), &alteration_id, false).await {
+        Ok(Some(ctx)) => ctx,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Change request not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking alteration: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let allowed_user = if new_status == "withdrawn" {
+        ctx.guest_id
+    } else {
+        ctx.host_id
+    };
+    if allowed_user != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to update this change request"
+        }));
+    }
+
+    let result = sqlx::query(
+        r#"
+        UPDATE booking_alterations SET status = $2, responded_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = 'pending'
+        "#,
+    )
+    .bind(&alteration_id)
+    .bind(new_status)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("This change request is already {}", ctx.status)
+        })),
+        Ok(_) => {
+            let message_content = if new_status == "withdrawn" {
+                "Guest withdrew the change request.".to_string()
+            } else {
+                format!(
+                    "Host declined the change to {}. The booking is unchanged.",
+                    describe_change(ctx.new_check_in, ctx.new_check_out, ctx.new_guests)
+                )
+            };
+            post_booking_message(
+                pool.get_ref(),
+                &ctx.listing_id,
+                ctx.guest_id,
+                ctx.host_id,
+                user_id,
+                &message_content,
+                serde_json::json!({
+                    "event": format!("alteration_{}", new_status),
+                    "booking_id": ctx.booking_id,
+                    "alteration_id": alteration_id
+                }),
+            )
+            .await;
+
+            HttpResponse::Ok().json(serde_json::json!({ "status": new_status }))
+        }
+        Err(e) => {
+            log::error!("Failed to update booking alteration: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to update change request" }))
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::NaiveDate;
+use sqlx::PgExecutor;
+
+/// Why a date range cannot be booked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateConflict {
+    /// Overlaps a confirmed booking or one awaiting payment.
+    Booked,
+    /// Contains dates the host blocked on the calendar, or that are booked on another platform.
+    Blocked,
+}
+
+impl DateConflict {
+    pub fn message(&self) -> &'static str {
+        match self {
+            DateConflict::Booked => "Selected dates are already booked",
+            DateConflict::Blocked => "Selected dates are unavailable",
+        }
+    }
+}
+
+/// Check `[check_in, check_out)` against existing bookings, host-blocked dates and dates
+/// blocked by imported calendars.
+///
+/// `exclude_booking_id` skips the booking being modified, so it doesn't conflict with itself.
+pub async fn find_date_conflict<'e>(
+    executor: impl PgExecutor<'e>,
+    listing_id: &str,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    exclude_booking_id: Option<&str>,
+) -> Result<Option<DateConflict>, sqlx::Error> {
+    // The range is [check_in, check_out) so check_out itself is not counted
+    let (booked, blocked): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM bookings
+             WHERE listing_id = $1
+               AND status IN ('confirmed', 'awaiting_payment')
+               AND check_in < $3 AND check_out > $2
+               AND ($4::TEXT IS NULL OR id <> $4)),
+            (SELECT COUNT(*) FROM calendar_pricing
+             WHERE listing_id = $1
+               AND date >= $2
+               AND date < $3
+               AND is_available = FALSE)
+            + (SELECT COUNT(*) FROM external_blocks
+               WHERE listing_id = $1 AND start_date < $3 AND end_date > $2)
+        "#,
+    )
+    .bind(listing_id)
+    .bind(check_in)
+    .bind(check_out)
+    .bind(exclude_booking_id)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(if booked > 0 {
+        Some(DateConflict::Booked)
+    } else if blocked > 0 {
+        Some(DateConflict::Blocked)
+    } else {
+        None
+    })
+}
+
+/// A host's stay restriction that a date range breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StayRestriction {
+    /// The host closed the check-in day to arrivals.
+    ClosedToArrival,
+    /// The host closed the check-out day to departures.
+    ClosedToDeparture,
+    /// The stay is shorter than the minimum for one of its nights.
+    MinNights(i32),
+}
+
+impl StayRestriction {
+    pub fn message(&self) -> String {
+        match self {
+            StayRestriction::ClosedToArrival => "Check-in is not possible on this date".to_string(),
+            StayRestriction::ClosedToDeparture => {
+                "Check-out is not possible on this date".to_string()
+            }
+            StayRestriction::MinNights(nights) => {
+                format!("These dates require a stay of at least {} nights", nights)
+            }
+        }
+    }
+}
+
+/// Check `[check_in, check_out)` against the host's stay restrictions.
+///
+/// Each night's minimum stay is its calendar override or else the listing's usual minimum, and
+/// the stay must be as long as the largest of them.
+pub async fn find_stay_restriction<'e>(
+    executor: impl PgExecutor<'e>,
+    listing_id: &str,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+) -> Result<Option<StayRestriction>, sqlx::Error> {
+    let (closed_to_arrival, closed_to_departure, min_nights): (bool, bool, Option<i32>) =
+        sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE((SELECT closed_to_arrival FROM calendar_pricing
+                          WHERE listing_id = $1 AND date = $2), FALSE),
+                COALESCE((SELECT closed_to_departure FROM calendar_pricing
+                          WHERE listing_id = $1 AND date = $3), FALSE),
+                (SELECT MAX(COALESCE(cp.min_nights_override, m.min_nights))::INT
+                 FROM generate_series($2::DATE, $3::DATE - 1, INTERVAL '1 day') AS n
+                 CROSS JOIN (
+                     SELECT GREATEST(COALESCE(s.min_nights, l.min_nights, 1), 1) as min_nights
+                     FROM listings l
+                     LEFT JOIN listing_settings s ON s.listing_id = l.id
+                     WHERE l.id = $1
+                 ) m
+                 LEFT JOIN calendar_pricing cp ON cp.listing_id = $1 AND cp.date = n::DATE)
+            "#,
+        )
+        .bind(listing_id)
+        .bind(check_in)
+        .bind(check_out)
+        .fetch_one(executor)
+        .await?;
+
+    let nights = (check_out - check_in).num_days();
+    Ok(if closed_to_arrival {
+        Some(StayRestriction::ClosedToArrival)
+    } else if closed_to_departure {
+        Some(StayRestriction::ClosedToDeparture)
+    } else {
+        min_nights
+            .filter(|&min| nights < i64::from(min))
+            .map(StayRestriction::MinNights)
+    })
+}
@@ -0,0 +1,376 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use kamer_core::{Currency, Money, MoneyError};
+use serde::Serialize;
+
+/// Hours after booking during which a guest may cancel for free.
+pub const FREE_CANCELLATION_HOURS: i64 = 48;
+
+/// Under the strict policy the free-cancellation window only applies when check-in
+/// is at least this many days away.
+const STRICT_FREE_CANCELLATION_MIN_DAYS: i64 = 14;
+
+/// Cancellation policy chosen by the host for a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CancellationPolicy {
+    /// Full refund up to 1 day before check-in.
+    Flexible,
+    /// Full refund up to 5 days before check-in, 50% afterwards.
+    Moderate,
+    /// 50% refund up to 7 days before check-in, nothing afterwards.
+    Strict,
+}
+
+/// A refund tier: cancelling at least `min_days` before check-in refunds `percent` of the total.
+struct RefundTier {
+    min_days: i64,
+    percent: u8,
+}
+
+impl CancellationPolicy {
+    /// Parse the free-text `listings.cancellation_policy` column.
+    ///
+    /// Listings without a policy (or with an unknown value) are treated as flexible, which is
+    /// what the listing page advertises to guests in that case.
+    pub fn from_listing(value: Option<&str>) -> Self {
+        match value.map(|v| v.trim().to_lowercase()).as_deref() {
+            Some("moderate") => CancellationPolicy::Moderate,
+            Some("strict") => CancellationPolicy::Strict,
+            _ => CancellationPolicy::Flexible,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancellationPolicy::Flexible => "flexible",
+            CancellationPolicy::Moderate => "moderate",
+            CancellationPolicy::Strict => "strict",
+        }
+    }
+
+    fn tiers(&self) -> &'static [RefundTier] {
+        match self {
+            CancellationPolicy::Flexible => &[RefundTier {
+                min_days: 1,
+                percent: 100,
+            }],
+            CancellationPolicy::Moderate => &[
+                RefundTier {
+                    min_days: 5,
+                    percent: 100,
+                },
+                RefundTier {
+                    min_days: 0,
+                    percent: 50,
+                },
+            ],
+            CancellationPolicy::Strict => &[RefundTier {
+                min_days: 7,
+                percent: 50,
+            }],
+        }
+    }
+
+    /// Whether a cancellation at `now` still falls in the free-cancellation window.
+    fn in_grace_period(
+        &self,
+        booked_at: NaiveDateTime,
+        days_before: i64,
+        now: NaiveDateTime,
+    ) -> bool {
+        if now - booked_at > Duration::hours(FREE_CANCELLATION_HOURS) {
+            return false;
+        }
+        match self {
+            CancellationPolicy::Strict => days_before >= STRICT_FREE_CANCELLATION_MIN_DAYS,
+            _ => days_before >= 0,
+        }
+    }
+}
+
+/// Who initiated the cancellation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CancelledBy {
+    Guest,
+    Host,
+}
+
+impl CancelledBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CancelledBy::Guest => "guest",
+            CancelledBy::Host => "host",
+        }
+    }
+}
+
+/// Result of applying a cancellation policy to a booking.
+///
+/// For guest cancellations `penalty_amount` is the part of the total the guest forfeits;
+/// for host cancellations the guest is refunded in full and `penalty_amount` is charged to the host.
+#[derive(Debug, Clone, Serialize)]
+pub struct CancellationOutcome {
+    pub policy: CancellationPolicy,
+    pub cancelled_by: CancelledBy,
+    pub currency: Currency,
+    pub total: Money,
+    pub days_before_check_in: i64,
+    pub refund_percent: u8,
+    pub refund_amount: Money,
+    pub penalty_amount: Money,
+}
+
+/// Compute the refund owed to a guest who cancels at `now`.
+///
+/// Requests that were never confirmed are refunded in full. The refund is rounded to the
+/// nearest minor unit and the penalty is the rest of the total, so the two always add up.
+pub fn guest_cancellation(
+    policy: CancellationPolicy,
+    total: Money,
+    confirmed: bool,
+    booked_at: NaiveDateTime,
+    check_in: NaiveDate,
+    now: NaiveDateTime,
+) -> Result<CancellationOutcome, MoneyError> {
+    let days_before = (check_in - now.date()).num_days();
+
+    let refund_percent = if !confirmed || policy.in_grace_period(booked_at, days_before, now) {
+        100
+    } else if days_before < 0 {
+        0
+    } else {
+        policy
+            .tiers()
+            .iter()
+            .find(|tier| days_before >= tier.min_days)
+            .map(|tier| tier.percent)
+            .unwrap_or(0)
+    };
+
+    let refund_amount = total.percent(f64::from(refund_percent))?;
+    Ok(CancellationOutcome {
+        policy,
+        cancelled_by: CancelledBy::Guest,
+        currency: total.currency(),
+        total,
+        days_before_check_in: days_before,
+        refund_percent,
+        refund_amount,
+        penalty_amount: total.checked_sub(refund_amount)?,
+    })
+}
+
+/// Compute the outcome of a host cancelling a confirmed booking at `now`.
+///
+/// The guest always gets a full refund. The host is charged a penalty that grows as check-in
+/// approaches: 10% of the total more than 30 days out, 25% within 30 days, 50% within 7 days.
+pub fn host_cancellation(
+    policy: CancellationPolicy,
+    total: Money,
+    check_in: NaiveDate,
+    now: NaiveDateTime,
+) -> Result<CancellationOutcome, MoneyError> {
+    let days_before = (check_in - now.date()).num_days();
+    let penalty_percent = if days_before > 30 {
+        10.0
+    } else if days_before >= 7 {
+        25.0
+    } else {
+        50.0
+    };
+
+    Ok(CancellationOutcome {
+        policy,
+        cancelled_by: CancelledBy::Host,
+        currency: total.currency(),
+        total,
+        days_before_check_in: days_before,
+        refund_percent: 100,
+        refund_amount: total,
+        penalty_amount: total.percent(penalty_percent)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xaf(minor: i64) -> Money {
+        Money::from_minor(minor, Currency::XAF)
+    }
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    /// Refund percent for a confirmed booking made well outside the grace window.
+    fn refund_percent(policy: CancellationPolicy, check_in: &str, now: &str) -> u8 {
+        guest_cancellation(
+            policy,
+            xaf(100_000),
+            true,
+            at("2026-01-01", "12:00"),
+            day(check_in),
+            at(now, "12:00"),
+        )
+        .unwrap()
+        .refund_percent
+    }
+
+    #[test]
+    fn from_listing() {
+        assert_eq!(
+            CancellationPolicy::from_listing(Some(" Strict ")),
+            CancellationPolicy::Strict
+        );
+        assert_eq!(
+            CancellationPolicy::from_listing(Some("moderate")),
+            CancellationPolicy::Moderate
+        );
+        assert_eq!(
+            CancellationPolicy::from_listing(Some("super_strict")),
+            CancellationPolicy::Flexible
+        );
+        assert_eq!(
+            CancellationPolicy::from_listing(None),
+            CancellationPolicy::Flexible
+        );
+    }
+
+    #[test]
+    fn flexible_tiers() {
+        let policy = CancellationPolicy::Flexible;
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-01"), 100);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-09"), 100);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-10"), 0);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-12"), 0);
+    }
+
+    #[test]
+    fn moderate_tiers() {
+        let policy = CancellationPolicy::Moderate;
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-05"), 100);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-06"), 50);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-10"), 50);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-11"), 0);
+    }
+
+    #[test]
+    fn strict_tiers() {
+        let policy = CancellationPolicy::Strict;
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-02-01"), 50);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-03"), 50);
+        assert_eq!(refund_percent(policy, "2026-03-10", "2026-03-04"), 0);
+    }
+
+    #[test]
+    fn grace_window_refunds_in_full() {
+        let booked_at = at("2026-03-01", "12:00");
+        let outcome = |policy, check_in: &str, now: NaiveDateTime| {
+            guest_cancellation(policy, xaf(100_000), true, booked_at, day(check_in), now)
+                .unwrap()
+                .refund_percent
+        };
+
+        // Inside 48 hours of booking, even a cancellation the day before check-in is free
+        let moderate = CancellationPolicy::Moderate;
+        assert_eq!(
+            outcome(moderate, "2026-03-03", at("2026-03-02", "12:00")),
+            100
+        );
+        assert_eq!(
+            outcome(moderate, "2026-03-03", at("2026-03-03", "12:00")),
+            100
+        );
+        assert_eq!(
+            outcome(moderate, "2026-03-04", at("2026-03-03", "12:01")),
+            50
+        );
+
+        // The strict grace window only applies when check-in is at least 14 days out
+        let strict = CancellationPolicy::Strict;
+        assert_eq!(
+            outcome(strict, "2026-03-16", at("2026-03-02", "12:00")),
+            100
+        );
+        assert_eq!(outcome(strict, "2026-03-15", at("2026-03-02", "12:00")), 50);
+
+        // Never after check-in, whatever the booking date
+        assert_eq!(
+            outcome(
+                CancellationPolicy::Flexible,
+                "2026-03-01",
+                at("2026-03-02", "12:00")
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn unconfirmed_requests_refund_in_full() {
+        let outcome = guest_cancellation(
+            CancellationPolicy::Strict,
+            xaf(100_000),
+            false,
+            at("2026-01-01", "12:00"),
+            day("2026-03-10"),
+            at("2026-03-09", "12:00"),
+        )
+        .unwrap();
+        assert_eq!(outcome.refund_percent, 100);
+        assert_eq!(outcome.refund_amount, xaf(100_000));
+        assert_eq!(outcome.penalty_amount, xaf(0));
+    }
+
+    #[test]
+    fn refund_and_penalty_add_up() {
+        let total = Money::from_minor(33_333, Currency::USD);
+        let outcome = guest_cancellation(
+            CancellationPolicy::Moderate,
+            total,
+            true,
+            at("2026-01-01", "12:00"),
+            day("2026-03-10"),
+            at("2026-03-08", "12:00"),
+        )
+        .unwrap();
+        assert_eq!(
+            outcome.refund_amount,
+            Money::from_minor(16_667, Currency::USD)
+        );
+        assert_eq!(
+            outcome.penalty_amount,
+            Money::from_minor(16_666, Currency::USD)
+        );
+        assert_eq!(
+            outcome.refund_amount.checked_add(outcome.penalty_amount),
+            Ok(total)
+        );
+    }
+
+    #[test]
+    fn host_penalties() {
+        let penalty = |check_in: &str| {
+            let outcome = host_cancellation(
+                CancellationPolicy::Flexible,
+                xaf(100_000),
+                day(check_in),
+                at("2026-03-01", "12:00"),
+            )
+            .unwrap();
+            assert_eq!(outcome.refund_amount, xaf(100_000));
+            outcome.penalty_amount
+        };
+
+        assert_eq!(penalty("2026-04-01"), xaf(10_000));
+        assert_eq!(penalty("2026-03-31"), xaf(25_000));
+        assert_eq!(penalty("2026-03-08"), xaf(25_000));
+        assert_eq!(penalty("2026-03-07"), xaf(50_000));
+        assert_eq!(penalty("2026-02-28"), xaf(50_000));
+    }
+}
@@ -0,0 +1,346 @@
+use crate::promotions::verify_listing_host;
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+
+const CHECK_IN_METHODS: &[&str] = &[
+    "keypad",
+    "lockbox",
+    "smart_lock",
+    "building_staff",
+    "host_greets",
+    "other",
+];
+
+/// The most days before check-in a host can release arrival details.
+const MAX_RELEASE_DAYS: i32 = 14;
+
+/// A listing's private arrival details. Never part of the public listing.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CheckInDetails {
+    pub check_in_method: String,
+    pub instructions: Option<String>,
+    pub wifi_network: Option<String>,
+    pub wifi_password: Option<String>,
+    pub exact_address: Option<String>,
+    pub release_days_before: i32,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCheckInDetailsRequest {
+    /// `keypad`, `lockbox`, `smart_lock`, `building_staff`, `host_greets` or `other`.
+    pub check_in_method: String,
+    pub instructions: Option<String>,
+    pub wifi_network: Option<String>,
+    pub wifi_password: Option<String>,
+    pub exact_address: Option<String>,
+    /// Days before check-in that confirmed guests can see the details; defaults to 2.
+    pub release_days_before: Option<i32>,
+}
+
+/// A confirmed booking whose arrival details are due.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct CheckInRelease {
+    pub booking_id: String,
+    pub listing_id: String,
+    pub guest_id: i32,
+    pub host_id: i32,
+    #[sqlx(flatten)]
+    pub details: CheckInDetails,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct BookingCheckIn {
+    listing_id: String,
+    guest_id: i32,
+    host_id: i32,
+    status: String,
+    check_in: NaiveDate,
+}
+
+const CHECK_IN_DETAILS_COLUMNS: &str = r#"
+    d.check_in_method, d.instructions, d.wifi_network, d.wifi_password, d.exact_address,
+    d.release_days_before, d.updated_at
+"#;
+
+fn method_label(method: &str) -> &'static str {
+    match method {
+        "keypad" => "Keypad",
+        "lockbox" => "Lockbox",
+        "smart_lock" => "Smart lock",
+        "building_staff" => "Building staff",
+        "host_greets" => "Host greets you",
+        _ => "Other",
+    }
+}
+
+/// The conversation message that hands the details to the guest.
+pub(crate) fn check_in_message(details: &CheckInDetails) -> String {
+    let mut lines = vec![
+        "Your check-in details".to_string(),
+        format!(
+            "Check-in method: {}",
+            method_label(&details.check_in_method)
+        ),
+    ];
+    if let Some(address) = &details.exact_address {
+        lines.push(format!("Address: {}", address));
+    }
+    if let Some(network) = &details.wifi_network {
+        lines.push(format!("Wi-Fi: {}", network));
+    }
+    if let Some(password) = &details.wifi_password {
+        lines.push(format!("Wi-Fi password: {}", password));
+    }
+    if let Some(instructions) = &details.instructions {
+        lines.push(String::new());
+        lines.push(instructions.clone());
+    }
+    lines.join("\n")
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Confirmed bookings whose release day has arrived and that have not been sent their details.
+pub(crate) async fn due_releases(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT b.id
+        FROM bookings b
+        JOIN listing_check_in_details d ON d.listing_id = b.listing_id
+        WHERE b.status = 'confirmed'
+          AND b.check_in_details_sent_at IS NULL
+          AND b.check_in - d.release_days_before <= CURRENT_DATE
+          AND b.check_out > CURRENT_DATE
+        ORDER BY b.check_in, b.id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Lock a due booking and mark its details as sent. Call inside the transaction that posts the
+/// details, so the mark only sticks once they are posted. Returns `None` when the booking was
+/// sent its details meanwhile or another worker holds it (SKIP LOCKED).
+pub(crate) async fn claim_release(
+    conn: &mut PgConnection,
+    booking_id: &str,
+) -> Result<Option<CheckInRelease>, sqlx::Error> {
+    sqlx::query_as::<_, CheckInRelease>(&format!(
+        r#"
+        WITH due AS (
+            SELECT b.id
+            FROM bookings b
+            WHERE b.id = $1
+              AND b.status = 'confirmed'
+              AND b.check_in_details_sent_at IS NULL
+            FOR UPDATE OF b SKIP LOCKED
+        )
+        UPDATE bookings b
+        SET check_in_details_sent_at = CURRENT_TIMESTAMP
+        FROM due, listings l, listing_check_in_details d
+        WHERE b.id = due.id AND l.id = b.listing_id AND d.listing_id = b.listing_id
+        RETURNING b.id as booking_id, b.listing_id, b.guest_id, l.host_id, {}
+        "#,
+        CHECK_IN_DETAILS_COLUMNS
+    ))
+    .bind(booking_id)
+    .fetch_optional(conn)
+    .await
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/check-in/listings/{listing_id} - Arrival details for a listing (host)
+#[get("/listings/{listing_id}")]
+pub async fn get_check_in_details(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_host(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let details = sqlx::query_as::<_, CheckInDetails>(&format!(
+        "SELECT {} FROM listing_check_in_details d WHERE d.listing_id = $1",
+        CHECK_IN_DETAILS_COLUMNS
+    ))
+    .bind(&listing_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    match details {
+        Ok(details) => HttpResponse::Ok().json(details),
+        Err(e) => {
+            log::error!("Failed to fetch check-in details: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// PUT /api/check-in/listings/{listing_id} - Set a listing's arrival details (host)
+#[put("/listings/{listing_id}")]
+pub async fn update_check_in_details(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<UpdateCheckInDetailsRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_host(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    if !CHECK_IN_METHODS.contains(&body.check_in_method.as_str()) {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Invalid check-in method" }));
+    }
+    let release_days_before = body.release_days_before.unwrap_or(2);
+    if !(0..=MAX_RELEASE_DAYS).contains(&release_days_before) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("release_days_before must be between 0 and {}", MAX_RELEASE_DAYS)
+        }));
+    }
+
+    let details = sqlx::query_as::<_, CheckInDetails>(&format!(
+        r#"
+        INSERT INTO listing_check_in_details AS d (
+            listing_id, check_in_method, instructions, wifi_network, wifi_password,
+            exact_address, release_days_before
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (listing_id) DO UPDATE SET
+            check_in_method = EXCLUDED.check_in_method,
+            instructions = EXCLUDED.instructions,
+            wifi_network = EXCLUDED.wifi_network,
+            wifi_password = EXCLUDED.wifi_password,
+            exact_address = EXCLUDED.exact_address,
+            release_days_before = EXCLUDED.release_days_before,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING {}
+        "#,
+        CHECK_IN_DETAILS_COLUMNS
+    ))
+    .bind(&listing_id)
+    .bind(&body.check_in_method)
+    .bind(non_empty(body.instructions.as_deref()))
+    .bind(non_empty(body.wifi_network.as_deref()))
+    .bind(non_empty(body.wifi_password.as_deref()))
+    .bind(non_empty(body.exact_address.as_deref()))
+    .bind(release_days_before)
+    .fetch_one(pool.get_ref())
+    .await;
+
+    match details {
+        Ok(details) => HttpResponse::Ok().json(details),
+        Err(e) => {
+            log::error!("Failed to update check-in details: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to update check-in details" }))
+        }
+    }
+}
+
+/// GET /api/bookings/{id}/check-in - Arrival details, once released to the guest
+#[get("/{id}/check-in")]
+pub async fn get_booking_check_in(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let booking = sqlx::query_as::<_, BookingCheckIn>(
+        r#"
+        SELECT b.listing_id, b.guest_id, l.host_id, COALESCE(b.status, 'pending') as status, b.check_in
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1
+        "#,
+    )
+    .bind(path.into_inner())
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    let booking = match booking {
+        Ok(Some(booking)) => booking,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Booking not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking for check-in details: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if booking.guest_id != user_id && booking.host_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to view this booking"
+        }));
+    }
+
+    let details = sqlx::query_as::<_, CheckInDetails>(&format!(
+        "SELECT {} FROM listing_check_in_details d WHERE d.listing_id = $1",
+        CHECK_IN_DETAILS_COLUMNS
+    ))
+    .bind(&booking.listing_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    let details = match details {
+        Ok(Some(details)) => details,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "The host has not added check-in details yet"
+            }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch check-in details: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Hosts can always preview what their guest will receive
+    if booking.host_id != user_id {
+        if booking.status != "confirmed" {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "Check-in details are only shared with confirmed bookings"
+            }));
+        }
+        let available_from =
+            booking.check_in - chrono::Duration::days(i64::from(details.release_days_before));
+        if available_from > chrono::Utc::now().date_naive() {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "Check-in details are not available yet",
+                "available_from": available_from
+            }));
+        }
+    }
+
+    HttpResponse::Ok().json(details)
+}
@@ -0,0 +1,659 @@
+use crate::guests::GuestCounts;
+use crate::pricing::PriceSnapshot;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use kamer_core::{Currency, Money, MoneyError};
+use printpdf::{BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+/// Characters used in booking references; 0/O and 1/I are left out so references can be read
+/// over the phone.
+const REFERENCE_ALPHABET: &[u8; 32] = b"23456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+const REFERENCE_PREFIX: &str = "KMR-";
+const REFERENCE_LENGTH: usize = 5;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct DocumentQuery {
+    /// `en` or `fr`; defaults to the requesting user's profile language.
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentLanguage {
+    En,
+    Fr,
+}
+
+impl DocumentLanguage {
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        let value = value?.trim().to_ascii_lowercase();
+        if value.starts_with("fr") {
+            Some(Self::Fr)
+        } else if value.starts_with("en") {
+            Some(Self::En)
+        } else {
+            None
+        }
+    }
+
+    fn labels(self) -> &'static Labels {
+        match self {
+            Self::En => &EN_LABELS,
+            Self::Fr => &FR_LABELS,
+        }
+    }
+}
+
+/// Booking, listing and party details printed on a receipt or invoice.
+#[derive(Debug, sqlx::FromRow)]
+struct DocumentRow {
+    status: String,
+    guest_id: i32,
+    host_id: i32,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    #[sqlx(flatten)]
+    counts: GuestCounts,
+    total_price_minor: i64,
+    currency: Currency,
+    price_snapshot: Option<sqlx::types::Json<PriceSnapshot>>,
+    refund_amount_minor: Option<i64>,
+    listing_title: Option<String>,
+    listing_address: Option<String>,
+    listing_city: Option<String>,
+    listing_country: Option<String>,
+    guest_name: String,
+    guest_legal_name: Option<String>,
+    guest_address: Option<String>,
+    guest_travel_for_work: Option<bool>,
+    guest_language: Option<String>,
+    host_name: String,
+    host_legal_name: Option<String>,
+    host_tax_id: Option<String>,
+    host_language: Option<String>,
+}
+
+impl DocumentRow {
+    fn price_snapshot(&self) -> Result<PriceSnapshot, MoneyError> {
+        match &self.price_snapshot {
+            Some(snapshot) => Ok(snapshot.0.clone()),
+            // Bookings made before snapshots existed only have a total
+            None => PriceSnapshot::flat(
+                Money::from_minor(self.total_price_minor, self.currency),
+                (self.check_out - self.check_in).num_days().max(1),
+            ),
+        }
+    }
+
+    /// Refunded on cancellation, capped at the total; zero for bookings that weren't cancelled.
+    fn refunded(&self) -> Money {
+        let refunded = if self.status == "cancelled" {
+            self.refund_amount_minor
+                .unwrap_or(self.total_price_minor)
+                .clamp(0, self.total_price_minor.max(0))
+        } else {
+            0
+        };
+        Money::from_minor(refunded, self.currency)
+    }
+
+    /// Cancelled bookings get a document only for the part of the price that was kept.
+    fn has_charge(&self) -> bool {
+        match self.status.as_str() {
+            "confirmed" | "completed" => true,
+            "cancelled" => self.refunded().minor() < self.total_price_minor,
+            _ => false,
+        }
+    }
+
+    fn host_tax_id(&self) -> Option<&str> {
+        self.host_tax_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+    }
+}
+
+/// Everything needed to render one document.
+struct BookingDocument<'a> {
+    row: &'a DocumentRow,
+    reference: &'a str,
+    /// Set when the host has a tax ID, turning the receipt into an invoice.
+    invoice_number: Option<String>,
+    issued_on: NaiveDate,
+    snapshot: PriceSnapshot,
+}
+
+struct Labels {
+    receipt: &'static str,
+    invoice: &'static str,
+    reference: &'static str,
+    invoice_number: &'static str,
+    issued_on: &'static str,
+    issued_by: &'static str,
+    tax_id: &'static str,
+    billed_to: &'static str,
+    stay: &'static str,
+    check_in: &'static str,
+    check_out: &'static str,
+    guests: &'static str,
+    price_details: &'static str,
+    extra_guests: &'static str,
+    discount: &'static str,
+    total: &'static str,
+    refunded: &'static str,
+    total_paid: &'static str,
+    night: (&'static str, &'static str),
+    adult: (&'static str, &'static str),
+    child: (&'static str, &'static str),
+    infant: (&'static str, &'static str),
+    pet: (&'static str, &'static str),
+    footer: &'static str,
+    date_format: &'static str,
+}
+
+const EN_LABELS: Labels = Labels {
+    receipt: "Receipt",
+    invoice: "Invoice",
+    reference: "Booking reference",
+    invoice_number: "Invoice number",
+    issued_on: "Issued on",
+    issued_by: "Issued by",
+    tax_id: "Tax ID",
+    billed_to: "Billed to",
+    stay: "Stay",
+    check_in: "Check-in",
+    check_out: "Check-out",
+    guests: "Guests",
+    price_details: "Price details",
+    extra_guests: "Extra guest fee",
+    discount: "Discount",
+    total: "Total",
+    refunded: "Refunded on cancellation",
+    total_paid: "Total paid",
+    night: ("night", "nights"),
+    adult: ("adult", "adults"),
+    child: ("child", "children"),
+    infant: ("infant", "infants"),
+    pet: ("pet", "pets"),
+    footer: "Thank you for booking with Kamer.",
+    date_format: "%d %b %Y",
+};
+
+const FR_LABELS: Labels = Labels {
+    receipt: "Reçu",
+    invoice: "Facture",
+    reference: "Référence de réservation",
+    invoice_number: "Numéro de facture",
+    issued_on: "Émis le",
+    issued_by: "Émis par",
+    tax_id: "Numéro fiscal",
+    billed_to: "Facturé à",
+    stay: "Séjour",
+    check_in: "Arrivée",
+    check_out: "Départ",
+    guests: "Voyageurs",
+    price_details: "Détail du prix",
+    extra_guests: "Frais de voyageur supplémentaire",
+    discount: "Réduction",
+    total: "Total",
+    refunded: "Remboursé à l'annulation",
+    total_paid: "Total payé",
+    night: ("nuit", "nuits"),
+    adult: ("adulte", "adultes"),
+    child: ("enfant", "enfants"),
+    infant: ("bébé", "bébés"),
+    pet: ("animal", "animaux"),
+    footer: "Merci d'avoir réservé avec Kamer.",
+    date_format: "%d/%m/%Y",
+};
+
+// ============================================================================
+// References and Invoice Numbers
+// ============================================================================
+
+/// Random reference such as `KMR-7QX2F`.
+pub fn generate_reference() -> String {
+    let bytes = uuid::Uuid::new_v4();
+    let code: String = bytes.as_bytes()[..REFERENCE_LENGTH]
+        .iter()
+        .map(|b| REFERENCE_ALPHABET[(*b as usize) % REFERENCE_ALPHABET.len()] as char)
+        .collect();
+    format!("{}{}", REFERENCE_PREFIX, code)
+}
+
+/// Return the booking's reference, assigning one on first use.
+pub async fn ensure_reference(pool: &PgPool, booking_id: &str) -> Result<String, sqlx::Error> {
+    loop {
+        let assigned = sqlx::query_scalar::<_, String>(
+            "UPDATE bookings SET reference = $2 WHERE id = $1 AND reference IS NULL RETURNING reference",
+        )
+        .bind(booking_id)
+        .bind(generate_reference())
+        .fetch_optional(pool)
+        .await;
+
+        match assigned {
+            Ok(Some(reference)) => return Ok(reference),
+            Ok(None) => {
+                return sqlx::query_scalar::<_, String>(
+                    "SELECT reference FROM bookings WHERE id = $1 AND reference IS NOT NULL",
+                )
+                .bind(booking_id)
+                .fetch_one(pool)
+                .await;
+            }
+            // Reference already taken by another booking; draw again
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Return the booking's invoice number and issue date, taking the host's next number on first
+/// use. Downloading the invoice again keeps the original date.
+async fn ensure_invoice(
+    pool: &PgPool,
+    booking_id: &str,
+    host_id: i32,
+) -> Result<(i32, NaiveDate), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Serialize concurrent downloads of the same booking so it gets exactly one number
+    sqlx::query("SELECT 1 FROM bookings WHERE id = $1 FOR UPDATE")
+        .bind(booking_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let existing: Option<(i32, NaiveDate)> = sqlx::query_as(
+        "SELECT invoice_number, issued_at::DATE FROM booking_invoices WHERE booking_id = $1",
+    )
+    .bind(booking_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(invoice) = existing {
+        tx.commit().await?;
+        return Ok(invoice);
+    }
+
+    let number: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO host_invoice_counters (host_id, last_number) VALUES ($1, 1)
+        ON CONFLICT (host_id) DO UPDATE SET last_number = host_invoice_counters.last_number + 1
+        RETURNING last_number
+        "#,
+    )
+    .bind(host_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let issued_on: NaiveDate = sqlx::query_scalar(
+        r#"
+        INSERT INTO booking_invoices (booking_id, host_id, invoice_number) VALUES ($1, $2, $3)
+        RETURNING issued_at::DATE
+        "#,
+    )
+    .bind(booking_id)
+    .bind(host_id)
+    .bind(number)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok((number, issued_on))
+}
+
+fn format_invoice_number(host_id: i32, number: i32) -> String {
+    format!("INV-{}-{:05}", host_id, number)
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+fn plural(count: impl Into<i64>, forms: (&str, &str)) -> String {
+    let count = count.into();
+    format!("{} {}", count, if count == 1 { forms.0 } else { forms.1 })
+}
+
+fn describe_guests(counts: &GuestCounts, labels: &Labels) -> String {
+    let mut parts = vec![plural(counts.adults, labels.adult)];
+    if counts.children > 0 {
+        parts.push(plural(counts.children, labels.child));
+    }
+    if counts.infants > 0 {
+        parts.push(plural(counts.infants, labels.infant));
+    }
+    if counts.pets > 0 {
+        parts.push(plural(counts.pets, labels.pet));
+    }
+    parts.join(", ")
+}
+
+/// Writes top-to-bottom on a single A4 page.
+struct PageWriter {
+    layer: PdfLayerReference,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    y: f32,
+}
+
+impl PageWriter {
+    const LEFT: f32 = 20.0;
+    const RIGHT: f32 = 190.0;
+
+    fn text(&mut self, text: &str, size: f32, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        self.layer
+            .use_text(text, size, Mm(Self::LEFT), Mm(self.y), font);
+        self.y -= size * 0.5 + 2.0;
+    }
+
+    /// Label on the left, value right-aligned by approximating Helvetica's average glyph width.
+    fn row(&mut self, label: &str, value: &str, bold: bool) {
+        let font = if bold { &self.bold } else { &self.regular };
+        let value_width = value.chars().count() as f32 * 1.9;
+        self.layer
+            .use_text(label, 10.0, Mm(Self::LEFT), Mm(self.y), font);
+        self.layer
+            .use_text(value, 10.0, Mm(Self::RIGHT - value_width), Mm(self.y), font);
+        self.y -= 7.0;
+    }
+
+    fn rule(&mut self) {
+        self.layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(Self::LEFT), Mm(self.y + 3.0)), false),
+                (Point::new(Mm(Self::RIGHT), Mm(self.y + 3.0)), false),
+            ],
+            is_closed: false,
+        });
+        self.y -= 4.0;
+    }
+
+    fn gap(&mut self) {
+        self.y -= 5.0;
+    }
+}
+
+fn render_pdf(doc: &BookingDocument, labels: &Labels) -> Result<Vec<u8>, printpdf::Error> {
+    let row = doc.row;
+    let title = if doc.invoice_number.is_some() {
+        labels.invoice
+    } else {
+        labels.receipt
+    };
+
+    let (pdf, page, layer) = PdfDocument::new(
+        format!("{} {}", title, doc.reference),
+        Mm(210.0),
+        Mm(297.0),
+        "Layer 1",
+    );
+    let mut page = PageWriter {
+        layer: pdf.get_page(page).get_layer(layer),
+        regular: pdf.add_builtin_font(BuiltinFont::Helvetica)?,
+        bold: pdf.add_builtin_font(BuiltinFont::HelveticaBold)?,
+        y: 270.0,
+    };
+
+    page.text(title, 22.0, true);
+    page.gap();
+    page.row(labels.reference, doc.reference, false);
+    if let Some(invoice_number) = &doc.invoice_number {
+        page.row(labels.invoice_number, invoice_number, false);
+    }
+    page.row(
+        labels.issued_on,
+        &doc.issued_on.format(labels.date_format).to_string(),
+        false,
+    );
+    page.gap();
+
+    if doc.invoice_number.is_some() {
+        page.text(labels.issued_by, 12.0, true);
+        page.text(
+            row.host_legal_name.as_deref().unwrap_or(&row.host_name),
+            10.0,
+            false,
+        );
+        if let Some(tax_id) = row.host_tax_id() {
+            page.text(&format!("{}: {}", labels.tax_id, tax_id), 10.0, false);
+        }
+        page.gap();
+    }
+
+    page.text(labels.billed_to, 12.0, true);
+    page.text(
+        row.guest_legal_name.as_deref().unwrap_or(&row.guest_name),
+        10.0,
+        false,
+    );
+    // Business travellers need their billing address on the document
+    if row.guest_travel_for_work.unwrap_or(false) {
+        if let Some(address) = row.guest_address.as_deref().filter(|a| !a.is_empty()) {
+            page.text(address, 10.0, false);
+        }
+    }
+    page.gap();
+
+    page.text(labels.stay, 12.0, true);
+    if let Some(title) = &row.listing_title {
+        page.text(title, 10.0, false);
+    }
+    let location: Vec<&str> = [
+        &row.listing_address,
+        &row.listing_city,
+        &row.listing_country,
+    ]
+    .into_iter()
+    .filter_map(|part| part.as_deref())
+    .filter(|part| !part.is_empty())
+    .collect();
+    if !location.is_empty() {
+        page.text(&location.join(", "), 10.0, false);
+    }
+    page.row(
+        labels.check_in,
+        &row.check_in.format(labels.date_format).to_string(),
+        false,
+    );
+    page.row(
+        labels.check_out,
+        &row.check_out.format(labels.date_format).to_string(),
+        false,
+    );
+    page.row(labels.guests, &describe_guests(&row.counts, labels), false);
+    page.gap();
+
+    let snapshot = &doc.snapshot;
+    page.text(labels.price_details, 12.0, true);
+    page.row(
+        &format!(
+            "{} x {}",
+            snapshot.nightly_price,
+            plural(snapshot.nights, labels.night)
+        ),
+        &snapshot.subtotal.to_string(),
+        false,
+    );
+    if snapshot.extra_guest_total.is_positive() {
+        page.row(
+            &format!(
+                "{} ({} x {})",
+                labels.extra_guests,
+                snapshot.extra_guests,
+                plural(snapshot.nights, labels.night)
+            ),
+            &snapshot.extra_guest_total.to_string(),
+            false,
+        );
+    }
+    for discount in &snapshot.discounts {
+        let label = match &discount.code {
+            Some(code) => format!("{} ({})", labels.discount, code),
+            None => labels.discount.to_string(),
+        };
+        page.row(&label, &format!("-{}", discount.amount), false);
+    }
+    for tax in &snapshot.taxes {
+        page.row(&tax.name, &tax.amount.to_string(), false);
+    }
+    page.rule();
+    let refunded = row.refunded();
+    if refunded.is_positive() {
+        page.row(labels.total, &snapshot.total.to_string(), false);
+        page.row(labels.refunded, &format!("-{}", refunded), false);
+        let kept = snapshot
+            .total
+            .checked_sub(refunded)
+            .unwrap_or(snapshot.total);
+        page.row(labels.total_paid, &kept.to_string(), true);
+    } else {
+        page.row(labels.total_paid, &snapshot.total.to_string(), true);
+    }
+    page.gap();
+    page.text(labels.footer, 9.0, false);
+
+    pdf.save_to_bytes()
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/bookings/{id}/receipt.pdf - Receipt for a booking, or an invoice when the host has a tax ID
+#[get("/{id}/receipt.pdf")]
+pub async fn get_booking_receipt(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<DocumentQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let booking_id = path.into_inner();
+
+    let row = sqlx::query_as::<_, DocumentRow>(
+        r#"
+        SELECT
+            COALESCE(b.status, 'pending') as status, b.guest_id, l.host_id,
+            b.check_in, b.check_out,
+            COALESCE(b.adults, b.guests) as adults, b.children, b.infants, b.pets,
+            b.total_price_minor, b.currency, b.price_snapshot, b.refund_amount_minor,
+            l.title as listing_title,
+            l.address as listing_address, l.city as listing_city, l.country as listing_country,
+            gu.username as guest_name, gp.legal_name as guest_legal_name,
+            gp.mailing_address as guest_address, gp.travel_for_work as guest_travel_for_work,
+            gp.language as guest_language,
+            hu.username as host_name, hp.legal_name as host_legal_name,
+            hp.tax_id as host_tax_id, hp.language as host_language
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        JOIN users gu ON b.guest_id = gu.id
+        JOIN users hu ON l.host_id = hu.id
+        LEFT JOIN user_profiles gp ON b.guest_id = gp.user_id
+        LEFT JOIN user_profiles hp ON l.host_id = hp.user_id
+        WHERE b.id = $1
+        "#,
+    )
+    .bind(&booking_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    let row = match row {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Booking not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking for receipt: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if row.guest_id != user_id && row.host_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to view this booking"
+        }));
+    }
+
+    if !row.has_charge() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Receipts are only available for confirmed bookings, or cancelled ones that were not fully refunded"
+        }));
+    }
+
+    let profile_language = if user_id == row.guest_id {
+        row.guest_language.as_deref()
+    } else {
+        row.host_language.as_deref()
+    };
+    let language = DocumentLanguage::parse(query.lang.as_deref())
+        .or_else(|| DocumentLanguage::parse(profile_language))
+        .unwrap_or(DocumentLanguage::En);
+
+    let reference = match ensure_reference(pool.get_ref(), &booking_id).await {
+        Ok(reference) => reference,
+        Err(e) => {
+            log::error!("Failed to assign booking reference: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to generate receipt" }));
+        }
+    };
+
+    // Receipts are dated the day they're downloaded; invoices keep the date they were issued
+    let (invoice_number, issued_on) = match row.host_tax_id() {
+        Some(_) => match ensure_invoice(pool.get_ref(), &booking_id, row.host_id).await {
+            Ok((number, issued_on)) => {
+                (Some(format_invoice_number(row.host_id, number)), issued_on)
+            }
+            Err(e) => {
+                log::error!("Failed to assign invoice number: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Failed to generate receipt" }));
+            }
+        },
+        None => (None, chrono::Utc::now().date_naive()),
+    };
+
+    let snapshot = match row.price_snapshot() {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            log::error!("Failed to price booking document: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to generate receipt" }));
+        }
+    };
+    let document = BookingDocument {
+        row: &row,
+        reference: &reference,
+        invoice_number,
+        issued_on,
+        snapshot,
+    };
+
+    match render_pdf(&document, language.labels()) {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header((
+                "Content-Disposition",
+                format!("inline; filename=\"{}.pdf\"", reference),
+            ))
+            .body(bytes),
+        Err(e) => {
+            log::error!("Failed to render receipt PDF: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to generate receipt" }))
+        }
+    }
+}
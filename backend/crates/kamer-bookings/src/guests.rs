@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+/// Infants don't count toward capacity, but a listing can't sensibly host more than this.
+pub const MAX_INFANTS: i32 = 5;
+
+/// Who is coming on a booking.
+///
+/// `guests` on the booking row is kept as adults + children, which is what listing
+/// capacity (`max_guests`) is measured against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GuestCounts {
+    pub adults: i32,
+    pub children: i32,
+    pub infants: i32,
+    pub pets: i32,
+}
+
+/// Guest and pet settings of a listing.
+#[derive(Debug, Clone, Copy, sqlx::FromRow)]
+pub struct GuestRules {
+    pub max_guests: i32,
+    pub pets_allowed: bool,
+    pub max_pets: Option<i32>,
+    pub guests_included: Option<i32>,
+    /// Per extra guest per night, in the listing currency's minor units.
+    pub extra_guest_fee_minor: i64,
+}
+
+impl GuestCounts {
+    /// Build counts from a request. Clients that only send the legacy `guests` total are
+    /// treated as booking for that many adults.
+    pub fn from_request(
+        guests: Option<i32>,
+        adults: Option<i32>,
+        children: Option<i32>,
+        infants: Option<i32>,
+        pets: Option<i32>,
+    ) -> Self {
+        let children = children.unwrap_or(0);
+        Self {
+            adults: adults.unwrap_or_else(|| guests.unwrap_or(0) - children),
+            children,
+            infants: infants.unwrap_or(0),
+            pets: pets.unwrap_or(0),
+        }
+    }
+
+    /// Guests that count toward capacity and extra-guest fees.
+    pub fn capacity(&self) -> i32 {
+        self.adults + self.children
+    }
+
+    /// Check the counts against the listing's settings.
+    pub fn validate(&self, rules: &GuestRules) -> Result<(), String> {
+        if self.adults < 1 {
+            return Err("At least one adult is required".to_string());
+        }
+        if self.children < 0 || self.infants < 0 || self.pets < 0 {
+            return Err("Guest counts cannot be negative".to_string());
+        }
+        if rules.max_guests > 0 && self.capacity() > rules.max_guests {
+            return Err(format!(
+                "Guest count exceeds maximum allowed ({})",
+                rules.max_guests
+            ));
+        }
+        if self.infants > MAX_INFANTS {
+            return Err(format!("At most {} infants are allowed", MAX_INFANTS));
+        }
+        if self.pets > 0 && !rules.pets_allowed {
+            return Err("This listing does not allow pets".to_string());
+        }
+        if let Some(max_pets) = rules.max_pets.filter(|max| *max > 0) {
+            if self.pets > max_pets {
+                return Err(format!("At most {} pets are allowed", max_pets));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of guests beyond those covered by the nightly price.
+    pub fn extra_guests(&self, rules: &GuestRules) -> i32 {
+        match rules.guests_included {
+            Some(included) if included > 0 && rules.extra_guest_fee_minor > 0 => {
+                (self.capacity() - included).max(0)
+            }
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use crate::check_in::{check_in_message, claim_release, due_releases};
+use chrono::{NaiveDate, NaiveDateTime};
+use kamer_messages::system::MESSAGE_SYSTEM;
+use kamer_messages::ConversationParties;
+use sqlx::PgPool;
+
+/// Job kind: expire booking requests the host never answered.
+pub const EXPIRE_PENDING_BOOKINGS: &str = "bookings.expire_pending";
+/// Job kind: release bookings whose payment hold ran out.
+pub const EXPIRE_UNPAID_BOOKINGS: &str = "bookings.expire_unpaid";
+/// Job kind: mark confirmed stays as completed once check-out has passed.
+pub const COMPLETE_STAYS: &str = "bookings.complete_stays";
+/// Job kind: post check-in details to confirmed guests once their release day arrives.
+pub const RELEASE_CHECK_IN_DETAILS: &str = "bookings.release_check_in_details";
+/// Job kind: remind hosts of unanswered requests and guests of unpaid bookings before they lapse.
+pub const SEND_BOOKING_REMINDERS: &str = "bookings.send_reminders";
+
+/// Hosts are reminded of a pending request this many hours before it expires.
+const REQUEST_REMINDER_HOURS: i32 = 4;
+/// Guests are reminded to pay this many minutes before their booking releases its dates.
+const PAYMENT_REMINDER_MINUTES: i32 = 15;
+
+/// A booking about to lapse, as claimed for its reminder.
+#[derive(Debug, sqlx::FromRow)]
+struct DueReminder {
+    booking_id: String,
+    listing_id: String,
+    guest_id: i32,
+    host_id: i32,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    deadline: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Reminder {
+    /// To the host, about a request they haven't answered.
+    Request,
+    /// To the guest, about a booking they haven't paid for.
+    Payment,
+}
+
+/// Expire pending requests older than `window_hours`, or whose check-in date has already arrived.
+pub async fn expire_pending_bookings(pool: &PgPool, window_hours: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE bookings
+        SET status = 'expired', updated_at = CURRENT_TIMESTAMP
+        WHERE status = 'pending'
+          AND (created_at < NOW() - make_interval(hours => $1) OR check_in <= CURRENT_DATE)
+        "#,
+    )
+    .bind(window_hours as i32)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        log::info!("Expired {} pending bookings", result.rows_affected());
+    }
+    Ok(result.rows_affected())
+}
+
+/// Expire bookings in `awaiting_payment` whose payment hold has run out, freeing the dates.
+/// A payment that still settles afterwards is refunded by the payments webhook.
+pub async fn expire_unpaid_bookings(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE bookings
+        SET status = 'expired', updated_at = CURRENT_TIMESTAMP
+        WHERE status = 'awaiting_payment'
+          AND (payment_due_at < CURRENT_TIMESTAMP OR check_in < CURRENT_DATE)
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        log::info!("Expired {} unpaid bookings", result.rows_affected());
+    }
+    Ok(result.rows_affected())
+}
+
+/// Mark confirmed bookings whose check-out date has arrived as completed.
+pub async fn complete_finished_stays(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE bookings
+        SET status = 'completed', updated_at = CURRENT_TIMESTAMP
+        WHERE status = 'confirmed' AND check_out <= CURRENT_DATE
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        log::info!("Completed {} stays", result.rows_affected());
+    }
+    Ok(result.rows_affected())
+}
+
+/// Post each listing's check-in details into the conversation of confirmed bookings that are
+/// within the host's release window. Each booking is sent its details once.
+///
+/// A booking is only marked as sent in the transaction that posts its message. Failures are
+/// retried by the job runner; one failing booking doesn't hold back the others.
+pub async fn release_check_in_details(pool: &PgPool) -> Result<u64, String> {
+    let due = due_releases(pool).await.map_err(|e| e.to_string())?;
+
+    let mut released = 0;
+    let mut failed = 0;
+    for booking_id in &due {
+        match release_booking_check_in(pool, booking_id).await {
+            Ok(true) => released += 1,
+            Ok(false) => {}
+            Err(e) => {
+                log::error!(
+                    "Failed to release check-in details for booking {}: {:?}",
+                    booking_id,
+                    e
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    if released > 0 {
+        log::info!("Released check-in details for {} bookings", released);
+    }
+    if failed > 0 {
+        return Err(format!(
+            "Failed to release check-in details for {} bookings",
+            failed
+        ));
+    }
+    Ok(released)
+}
+
+/// Post one booking's check-in details. Returns whether they were posted by this call.
+async fn release_booking_check_in(pool: &PgPool, booking_id: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(release) = claim_release(&mut tx, booking_id).await? else {
+        return Ok(false);
+    };
+
+    kamer_messages::post_message_on(
+        &mut tx,
+        ConversationParties {
+            listing_id: &release.listing_id,
+            guest_id: release.guest_id,
+            host_id: release.host_id,
+        },
+        release.host_id,
+        MESSAGE_SYSTEM,
+        &check_in_message(&release.details),
+        Some(serde_json::json!({
+            "event": "check_in_details",
+            "booking_id": release.booking_id
+        })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Post a reminder into the conversation of each pending request about to expire unanswered
+/// and each unpaid booking about to release its dates. Each booking gets each reminder once.
+pub async fn send_booking_reminders(
+    pool: &PgPool,
+    pending_window_hours: i64,
+) -> Result<u64, String> {
+    let mut sent = 0;
+    let mut failed = 0;
+    for reminder in [Reminder::Request, Reminder::Payment] {
+        let due = due_reminders(pool, reminder, pending_window_hours)
+            .await
+            .map_err(|e| e.to_string())?;
+        for booking_id in &due {
+            match send_reminder(pool, reminder, booking_id, pending_window_hours).await {
+                Ok(true) => sent += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!(
+                        "Failed to send {:?} reminder for booking {}: {:?}",
+                        reminder,
+                        booking_id,
+                        e
+                    );
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if sent > 0 {
+        log::info!("Sent {} booking reminders", sent);
+    }
+    if failed > 0 {
+        return Err(format!("Failed to send {} booking reminders", failed));
+    }
+    Ok(sent)
+}
+
+impl Reminder {
+    fn status(self) -> &'static str {
+        match self {
+            Reminder::Request => "pending",
+            Reminder::Payment => "awaiting_payment",
+        }
+    }
+
+    fn sent_column(self) -> &'static str {
+        match self {
+            Reminder::Request => "request_reminder_sent_at",
+            Reminder::Payment => "payment_reminder_sent_at",
+        }
+    }
+
+    /// SQL for when booking `b` lapses.
+    fn deadline(self, pending_window_hours: i64) -> String {
+        match self {
+            Reminder::Request => format!(
+                "b.created_at + make_interval(hours => {})",
+                pending_window_hours
+            ),
+            Reminder::Payment => "b.payment_due_at".to_string(),
+        }
+    }
+
+    /// How long before the deadline the reminder goes out.
+    fn lead(self) -> String {
+        match self {
+            Reminder::Request => format!("make_interval(hours => {})", REQUEST_REMINDER_HOURS),
+            Reminder::Payment => format!("make_interval(mins => {})", PAYMENT_REMINDER_MINUTES),
+        }
+    }
+
+    fn message(self, due: &DueReminder) -> String {
+        let deadline = due.deadline.format("%Y-%m-%d %H:%M UTC");
+        match self {
+            Reminder::Request => format!(
+                "Reminder: the booking request for {} to {} expires at {} unless the host responds.",
+                due.check_in, due.check_out, deadline
+            ),
+            Reminder::Payment => format!(
+                "Reminder: pay for the booking of {} to {} before {} to keep these dates.",
+                due.check_in, due.check_out, deadline
+            ),
+        }
+    }
+}
+
+async fn due_reminders(
+    pool: &PgPool,
+    reminder: Reminder,
+    pending_window_hours: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(&format!(
+        r#"
+        SELECT b.id
+        FROM bookings b
+        WHERE b.status = $1
+          AND b.{sent} IS NULL
+          AND {deadline} - {lead} <= CURRENT_TIMESTAMP
+          AND {deadline} > CURRENT_TIMESTAMP
+        ORDER BY b.id
+        "#,
+        sent = reminder.sent_column(),
+        deadline = reminder.deadline(pending_window_hours),
+        lead = reminder.lead(),
+    ))
+    .bind(reminder.status())
+    .fetch_all(pool)
+    .await
+}
+
+/// Post one booking's reminder. Returns whether it was posted by this call.
+async fn send_reminder(
+    pool: &PgPool,
+    reminder: Reminder,
+    booking_id: &str,
+    pending_window_hours: i64,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Mark it sent in the transaction that posts it; SKIP LOCKED leaves it to a busy worker
+    let due = sqlx::query_as::<_, DueReminder>(&format!(
+        r#"
+        WITH due AS (
+            SELECT b.id
+            FROM bookings b
+            WHERE b.id = $2 AND b.status = $1 AND b.{sent} IS NULL
+            FOR UPDATE OF b SKIP LOCKED
+        )
+        UPDATE bookings b
+        SET {sent} = CURRENT_TIMESTAMP
+        FROM due, listings l
+        WHERE b.id = due.id AND l.id = b.listing_id
+        RETURNING b.id as booking_id, b.listing_id, b.guest_id, l.host_id,
+            b.check_in, b.check_out, {deadline} as deadline
+        "#,
+        sent = reminder.sent_column(),
+        deadline = reminder.deadline(pending_window_hours),
+    ))
+    .bind(reminder.status())
+    .bind(booking_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(due) = due else {
+        return Ok(false);
+    };
+
+    // Posted on behalf of the party waiting for an answer
+    let sender_id = match reminder {
+        Reminder::Request => due.guest_id,
+        Reminder::Payment => due.host_id,
+    };
+    kamer_messages::post_message_on(
+        &mut tx,
+        ConversationParties {
+            listing_id: &due.listing_id,
+            guest_id: due.guest_id,
+            host_id: due.host_id,
+        },
+        sender_id,
+        MESSAGE_SYSTEM,
+        &reminder.message(&due),
+        Some(serde_json::json!({
+            "event": match reminder {
+                Reminder::Request => "request_reminder",
+                Reminder::Payment => "payment_reminder",
+            },
+            "booking_id": due.booking_id,
+            "deadline": due.deadline
+        })),
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
@@ -1,4 +1,22 @@
+pub mod alterations;
+pub mod availability;
+pub mod cancellation;
+pub mod check_in;
+pub mod documents;
+pub mod guests;
+pub mod jobs;
+pub mod offers;
+pub mod pricing;
+pub mod promotions;
+pub mod reservations;
 pub mod routes;
+pub mod taxes;
 
 // Re-export all route handlers
+pub use alterations::*;
+pub use check_in::{get_booking_check_in, get_check_in_details, update_check_in_details};
+pub use documents::get_booking_receipt;
+pub use offers::{book_offer, create_offer, get_offer, withdraw_offer};
+pub use promotions::{create_listing_promotion, delete_listing_promotion, get_listing_promotions};
+pub use reservations::{export_host_bookings, get_host_bookings};
 pub use routes::*;
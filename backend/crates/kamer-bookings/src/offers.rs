@@ -0,0 +1,744 @@
+use crate::availability::find_date_conflict;
+use crate::documents::ensure_reference;
+use crate::guests::GuestCounts;
+use crate::pricing::PriceSnapshot;
+use crate::promotions::{apply_host_promotions, PromotionError};
+use crate::routes::{
+    accepted_status, fetch_booking_listing, insert_booking, BookingListing, NewBooking,
+};
+use crate::taxes::{apply_taxes, TaxError};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::{NaiveDate, NaiveDateTime};
+use kamer_calendar::PricingError;
+use kamer_core::{Money, MoneyError};
+use kamer_messages::system::{MESSAGE_PRE_APPROVAL, MESSAGE_SPECIAL_OFFER};
+use kamer_messages::ConversationParties;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+
+pub const OFFER_SPECIAL: &str = "special_offer";
+pub const OFFER_PRE_APPROVAL: &str = "pre_approval";
+
+const DEFAULT_EXPIRY_HOURS: i64 = 24;
+const MAX_EXPIRY_HOURS: i64 = 14 * 24;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BookingOffer {
+    pub id: String,
+    pub conversation_id: String,
+    pub listing_id: String,
+    pub guest_id: i32,
+    pub host_id: i32,
+    pub offer_type: String,
+    pub check_in: NaiveDate,
+    pub check_out: NaiveDate,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub counts: GuestCounts,
+    /// Host-set total for special offers; pre-approvals book at the standard price.
+    pub total_price: Option<Money>,
+    pub currency: String,
+    pub status: String,
+    pub expires_at: NaiveDateTime,
+    pub message_id: Option<String>,
+    pub booking_id: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl BookingOffer {
+    /// `active` offers past their expiry are reported as `expired`.
+    fn effective_status(&self, now: NaiveDateTime) -> &str {
+        if self.status == "active" && self.expires_at <= now {
+            "expired"
+        } else {
+            &self.status
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateOfferRequest {
+    pub conversation_id: String,
+    /// `special_offer` (default) or `pre_approval`.
+    pub offer_type: Option<String>,
+    pub check_in: String,
+    pub check_out: String,
+    pub guests: Option<i32>,
+    pub adults: Option<i32>,
+    pub children: Option<i32>,
+    pub infants: Option<i32>,
+    pub pets: Option<i32>,
+    /// Required for special offers.
+    pub total_price: Option<f64>,
+    pub expires_in_hours: Option<i64>,
+}
+
+const OFFER_COLUMNS: &str = r#"
+    id, conversation_id, listing_id, guest_id, host_id, offer_type, check_in, check_out,
+    adults, children, infants, pets, to_money(total_price_minor, currency) as total_price,
+    currency, status, expires_at,
+    message_id, booking_id, created_at
+"#;
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+async fn fetch_offer<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    offer_id: &str,
+    lock: bool,
+) -> Result<Option<BookingOffer>, sqlx::Error> {
+    sqlx::query_as::<_, BookingOffer>(&format!(
+        "SELECT {} FROM booking_offers WHERE id = $1 {}",
+        OFFER_COLUMNS,
+        if lock { "FOR UPDATE" } else { "" }
+    ))
+    .bind(offer_id)
+    .fetch_optional(executor)
+    .await
+}
+
+/// Keep the offer message in the conversation in step with the offer.
+async fn sync_offer_message(pool: &PgPool, message_id: Option<&str>, patch: serde_json::Value) {
+    let Some(message_id) = message_id else {
+        return;
+    };
+    if let Err(e) = kamer_messages::update_message_metadata(pool, message_id, patch).await {
+        log::error!("Failed to update offer message: {:?}", e);
+    }
+}
+
+/// What booking the offer costs the guest: the host's offered total, or the listing's
+/// standard price after host promotions for pre-approvals, plus taxes either way.
+async fn price_offer(
+    conn: &mut PgConnection,
+    listing: &BookingListing,
+    listing_id: &str,
+    counts: &GuestCounts,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    offered_total: Option<Money>,
+) -> Result<PriceSnapshot, HttpResponse> {
+    let database_error = |e: sqlx::Error| {
+        log::error!("Failed to price offer: {:?}", e);
+        HttpResponse::InternalServerError().json(serde_json::json!({ "error": "Database error" }))
+    };
+    let price_error = |e: MoneyError| {
+        HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": format!("Unable to price this stay: {}", e) }))
+    };
+
+    let mut snapshot =
+        match offered_total {
+            Some(total) => PriceSnapshot::flat(total, (check_out - check_in).num_days())
+                .map_err(price_error)?,
+            None => {
+                let mut snapshot = match listing
+                    .quote(&mut *conn, listing_id, counts, check_in, check_out)
+                    .await
+                {
+                    Ok(snapshot) => snapshot,
+                    Err(PricingError::Price(e)) => return Err(price_error(e)),
+                    Err(PricingError::Database(e)) => return Err(database_error(e)),
+                };
+                match apply_host_promotions(&mut *conn, listing_id, check_in, &mut snapshot).await {
+                    Ok(()) => {}
+                    Err(PromotionError::Price(e)) => return Err(price_error(e)),
+                    Err(PromotionError::Database(e)) => return Err(database_error(e)),
+                    Err(PromotionError::Invalid(message)) => {
+                        return Err(HttpResponse::BadRequest()
+                            .json(serde_json::json!({ "error": message })));
+                    }
+                }
+                snapshot
+            }
+        };
+
+    match apply_taxes(&mut *conn, listing_id, check_in, counts, &mut snapshot).await {
+        Ok(()) => {}
+        Err(TaxError::Price(e)) => return Err(price_error(e)),
+        Err(TaxError::Database(e)) => return Err(database_error(e)),
+    }
+    Ok(snapshot)
+}
+
+fn offer_message(
+    offer_type: &str,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    counts: &GuestCounts,
+    total: Money,
+    expires_at: NaiveDateTime,
+) -> String {
+    let guests = counts.capacity();
+    let stay = format!(
+        "{} to {}, {} guest{}",
+        check_in,
+        check_out,
+        guests,
+        if guests == 1 { "" } else { "s" }
+    );
+    let expires = expires_at.format("%Y-%m-%d %H:%M UTC");
+    if offer_type == OFFER_PRE_APPROVAL {
+        format!(
+            "Host pre-approved your trip: {}. Total: {}. Book before {}.",
+            stay, total, expires
+        )
+    } else {
+        format!(
+            "Host sent a special offer: {} for {}. Valid until {}.",
+            stay, total, expires
+        )
+    }
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// POST /api/bookings/offers - Send a special offer or pre-approval in a conversation (host)
+#[post("/offers")]
+pub async fn create_offer(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<CreateOfferRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let conversation = sqlx::query_as::<_, (String, i32, i32)>(
+        "SELECT listing_id, guest_id, host_id FROM conversations WHERE id = $1",
+    )
+    .bind(&body.conversation_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    let (listing_id, guest_id, host_id) = match conversation {
+        Ok(Some(conversation)) => conversation,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Conversation not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch conversation for offer: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if host_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "Only the host can send offers in this conversation"
+        }));
+    }
+
+    let offer_type = match body.offer_type.as_deref().unwrap_or(OFFER_SPECIAL) {
+        OFFER_SPECIAL => OFFER_SPECIAL,
+        OFFER_PRE_APPROVAL => OFFER_PRE_APPROVAL,
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid offer type" }));
+        }
+    };
+
+    let (check_in, check_out) = match (
+        NaiveDate::parse_from_str(&body.check_in, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(&body.check_out, "%Y-%m-%d"),
+    ) {
+        (Ok(check_in), Ok(check_out)) => (check_in, check_out),
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid date format" }));
+        }
+    };
+
+    let nights = (check_out - check_in).num_days();
+    if nights <= 0 {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Check-out must be after check-in" }));
+    }
+    if check_in < chrono::Utc::now().date_naive() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Check-in cannot be in the past" }));
+    }
+
+    let listing = match fetch_booking_listing(pool.get_ref(), &listing_id).await {
+        Ok(Some(listing)) => listing,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Listing not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch listing info: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let counts = GuestCounts::from_request(
+        body.guests,
+        body.adults,
+        body.children,
+        body.infants,
+        body.pets,
+    );
+    if let Err(message) = counts.validate(&listing.rules) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+    }
+
+    let offered_total = if offer_type == OFFER_SPECIAL {
+        match body.total_price {
+            Some(total) if total > 0.0 => match Money::from_major(total, listing.currency) {
+                Ok(total) => Some(total),
+                Err(e) => {
+                    return HttpResponse::BadRequest().json(
+                        serde_json::json!({ "error": format!("Invalid total price: {}", e) }),
+                    );
+                }
+            },
+            _ => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "Special offers need a total price greater than zero"
+                }));
+            }
+        }
+    } else {
+        None
+    };
+
+    match find_date_conflict(pool.get_ref(), &listing_id, check_in, check_out, None).await {
+        Ok(None) => {}
+        Ok(Some(conflict)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": conflict.message() }));
+        }
+        Err(e) => {
+            log::error!("Failed to check offer availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    let expiry_hours = body
+        .expires_in_hours
+        .unwrap_or(DEFAULT_EXPIRY_HOURS)
+        .clamp(1, MAX_EXPIRY_HOURS);
+    let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::hours(expiry_hours);
+    let offer_id = uuid::Uuid::new_v4().to_string();
+
+    // A new offer replaces any still-active one in the conversation
+    let replaced = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        UPDATE booking_offers SET status = 'withdrawn', updated_at = CURRENT_TIMESTAMP
+        WHERE conversation_id = $1 AND status = 'active'
+        RETURNING message_id
+        "#,
+    )
+    .bind(&body.conversation_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match replaced {
+        Ok(message_ids) => {
+            for message_id in message_ids.iter().flatten() {
+                sync_offer_message(
+                    pool.get_ref(),
+                    Some(message_id),
+                    serde_json::json!({ "status": "withdrawn" }),
+                )
+                .await;
+            }
+        }
+        Err(e) => {
+            log::error!("Failed to withdraw previous offers: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to send offer" }));
+        }
+    }
+
+    // Quote what booking will charge, taxes included, so the message shows the final total
+    let display_total = {
+        let mut conn = match pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Failed to acquire connection: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Database error" }));
+            }
+        };
+        match price_offer(
+            &mut conn,
+            &listing,
+            &listing_id,
+            &counts,
+            check_in,
+            check_out,
+            offered_total,
+        )
+        .await
+        {
+            Ok(snapshot) => snapshot.total,
+            Err(response) => return response,
+        }
+    };
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO booking_offers (
+            id, conversation_id, listing_id, guest_id, host_id, offer_type, check_in, check_out,
+            adults, children, infants, pets, total_price_minor, currency, expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        "#,
+    )
+    .bind(&offer_id)
+    .bind(&body.conversation_id)
+    .bind(&listing_id)
+    .bind(guest_id)
+    .bind(host_id)
+    .bind(offer_type)
+    .bind(check_in)
+    .bind(check_out)
+    .bind(counts.adults)
+    .bind(counts.children)
+    .bind(counts.infants)
+    .bind(counts.pets)
+    .bind(offered_total.map(|total| total.minor()))
+    .bind(listing.currency)
+    .bind(expires_at)
+    .execute(pool.get_ref())
+    .await;
+
+    if let Err(e) = inserted {
+        log::error!("Failed to create booking offer: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to send offer" }));
+    }
+
+    let message_type = if offer_type == OFFER_PRE_APPROVAL {
+        MESSAGE_PRE_APPROVAL
+    } else {
+        MESSAGE_SPECIAL_OFFER
+    };
+    let posted = kamer_messages::post_message(
+        pool.get_ref(),
+        ConversationParties {
+            listing_id: &listing_id,
+            guest_id,
+            host_id,
+        },
+        user_id,
+        message_type,
+        &offer_message(
+            offer_type,
+            check_in,
+            check_out,
+            &counts,
+            display_total,
+            expires_at,
+        ),
+        Some(serde_json::json!({
+            "offer_id": offer_id,
+            "offer_type": offer_type,
+            "listing_id": listing_id,
+            "check_in": check_in,
+            "check_out": check_out,
+            "adults": counts.adults,
+            "children": counts.children,
+            "infants": counts.infants,
+            "pets": counts.pets,
+            "total_price": display_total,
+            "currency": listing.currency,
+            "expires_at": expires_at,
+            "status": "active"
+        })),
+    )
+    .await;
+
+    match posted {
+        Ok(message_id) => {
+            if let Err(e) = sqlx::query("UPDATE booking_offers SET message_id = $2 WHERE id = $1")
+                .bind(&offer_id)
+                .bind(&message_id)
+                .execute(pool.get_ref())
+                .await
+            {
+                log::error!("Failed to link offer message: {:?}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to post offer message: {:?}", e),
+    }
+
+    match fetch_offer(pool.get_ref(), &offer_id, false).await {
+        Ok(Some(offer)) => HttpResponse::Ok().json(offer),
+        Ok(None) => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Offer not found" }))
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking offer: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// GET /api/bookings/offers/{id} - Get an offer (guest or host)
+#[get("/offers/{id}")]
+pub async fn get_offer(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    match fetch_offer(pool.get_ref(), &path.into_inner(), false).await {
+        Ok(Some(mut offer)) if offer.guest_id == user_id || offer.host_id == user_id => {
+            offer.status = offer
+                .effective_status(chrono::Utc::now().naive_utc())
+                .to_string();
+            HttpResponse::Ok().json(offer)
+        }
+        Ok(Some(_)) => HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to view this offer"
+        })),
+        Ok(None) => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Offer not found" }))
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking offer: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST /api/bookings/offers/{id}/withdraw - Withdraw an active offer (host)
+#[post("/offers/{id}/withdraw")]
+pub async fn withdraw_offer(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let offer_id = path.into_inner();
+
+    let withdrawn = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+        UPDATE booking_offers SET status = 'withdrawn', updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND host_id = $2 AND status = 'active'
+        RETURNING message_id
+        "#,
+    )
+    .bind(&offer_id)
+    .bind(user_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    match withdrawn {
+        Ok(Some(message_id)) => {
+            sync_offer_message(
+                pool.get_ref(),
+                message_id.as_deref(),
+                serde_json::json!({ "status": "withdrawn" }),
+            )
+            .await;
+            HttpResponse::Ok().json(serde_json::json!({ "status": "withdrawn" }))
+        }
+        Ok(None) => HttpResponse::NotFound()
+            .json(serde_json::json!({ "error": "No active offer found for this host" })),
+        Err(e) => {
+            log::error!("Failed to withdraw booking offer: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to withdraw offer" }))
+        }
+    }
+}
+
+/// POST /api/bookings/offers/{id}/book - Book an offer at its price, skipping the request step (guest)
+#[post("/offers/{id}/book")]
+pub async fn book_offer(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let offer_id = path.into_inner();
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Lock the offer so it can only be booked once
+    let offer = match fetch_offer(&mut *tx, &offer_id, true).await {
+        Ok(Some(offer)) => offer,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Offer not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking offer: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if offer.guest_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "This offer was made to another guest"
+        }));
+    }
+
+    let status = offer.effective_status(chrono::Utc::now().naive_utc());
+    if status != "active" {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("This offer is {}", status)
+        }));
+    }
+
+    let listing = match fetch_booking_listing(&mut *tx, &offer.listing_id).await {
+        Ok(Some(listing)) => listing,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Listing not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch listing info: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    match find_date_conflict(
+        &mut *tx,
+        &offer.listing_id,
+        offer.check_in,
+        offer.check_out,
+        None,
+    )
+    .await
+    {
+        Ok(None) => {}
+        Ok(Some(conflict)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": conflict.message() }));
+        }
+        Err(e) => {
+            log::error!("Failed to check offer availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    // Charge what the offer message quoted: the offered total or promoted price, plus taxes
+    let price_snapshot = match price_offer(
+        &mut tx,
+        &listing,
+        &offer.listing_id,
+        &offer.counts,
+        offer.check_in,
+        offer.check_out,
+        offer.total_price,
+    )
+    .await
+    {
+        Ok(snapshot) => snapshot,
+        Err(response) => return response,
+    };
+    let total = price_snapshot.total;
+    let booking_id = uuid::Uuid::new_v4().to_string();
+    let booking_status = accepted_status();
+
+    let inserted = insert_booking(
+        &mut *tx,
+        &NewBooking {
+            id: &booking_id,
+            listing_id: &offer.listing_id,
+            guest_id: user_id,
+            check_in: offer.check_in,
+            check_out: offer.check_out,
+            counts: offer.counts,
+            total,
+            price_snapshot: &price_snapshot,
+            status: booking_status,
+            policy: listing.policy(),
+        },
+    )
+    .await;
+
+    if let Err(e) = inserted {
+        log::error!("Failed to create booking from offer: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    if let Err(e) = kamer_payments::ledger::post_offline_booking(&mut tx, &booking_id).await {
+        log::error!("Failed to record booking fee: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    if let Err(e) = sqlx::query(
+        r#"
+        UPDATE booking_offers SET status = 'booked', booking_id = $2, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(&offer_id)
+    .bind(&booking_id)
+    .execute(&mut *tx)
+    .await
+    {
+        log::error!("Failed to mark offer as booked: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit offer booking: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    sync_offer_message(
+        pool.get_ref(),
+        offer.message_id.as_deref(),
+        serde_json::json!({ "status": "booked", "booking_id": booking_id }),
+    )
+    .await;
+
+    let reference = match ensure_reference(pool.get_ref(), &booking_id).await {
+        Ok(reference) => Some(reference),
+        Err(e) => {
+            log::error!("Failed to assign booking reference: {:?}", e);
+            None
+        }
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": booking_id,
+        "reference": reference,
+        "status": booking_status,
+        "total_price": price_snapshot.total.to_major()
+    }))
+}
@@ -0,0 +1,194 @@
+use chrono::NaiveDate;
+use kamer_calendar::{AppliedRule, NightPrice, PriceFactor};
+use kamer_core::{Currency, Money, MoneyError};
+use kamer_db::exchange_rates::ConvertedAmount;
+use serde::{Deserialize, Serialize};
+
+/// Price breakdown captured when a booking is created.
+///
+/// Stored as JSONB on `bookings.price_snapshot` so refunds and documents are computed
+/// from what the guest actually agreed to pay, even if the host later edits the listing.
+/// Every amount is a [`Money`] in the snapshot's currency, exact in its minor units.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceSnapshot {
+    pub currency: Currency,
+    /// The average when nights are priced differently.
+    pub nightly_price: Money,
+    pub nights: i64,
+    pub subtotal: Money,
+    /// Price of each night and the pricing rules behind it. Empty for flat prices and older
+    /// snapshots.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nightly: Vec<PriceNight>,
+    /// Guests beyond the listing's included count, charged `extra_guest_fee` per night each.
+    #[serde(default)]
+    pub extra_guests: i32,
+    pub extra_guest_fee: Money,
+    pub extra_guest_total: Money,
+    /// Host promotions and promo codes, in the order they were applied.
+    #[serde(default)]
+    pub discounts: Vec<PriceDiscount>,
+    pub discount_total: Money,
+    /// Tourist taxes and VAT, charged on top of the discounted price of the stay.
+    #[serde(default)]
+    pub taxes: Vec<PriceTax>,
+    pub tax_total: Money,
+    pub total: Money,
+    /// The total in the guest's currency at booking time. Display only: bookings settle in
+    /// `currency`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<ConvertedAmount>,
+}
+
+/// A discount line in the price breakdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceDiscount {
+    /// `last_minute`, `early_bird`, `first_bookings` or `promo_code`.
+    pub kind: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promotion_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub amount: Money,
+}
+
+/// A night in the price breakdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceNight {
+    pub date: NaiveDate,
+    pub price: Money,
+    /// `base`, `weekend`, `calendar`, `rule` or `smart`.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<AppliedRule>,
+    /// Why smart pricing set the price.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub factors: Vec<PriceFactor>,
+}
+
+/// A tax line in the price breakdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceTax {
+    pub tax_rule_id: String,
+    pub name: String,
+    /// `per_night`, `per_guest_night` or `percentage`.
+    pub tax_type: String,
+    /// Percentage, for percentage taxes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<f64>,
+    pub amount: Money,
+}
+
+impl PriceSnapshot {
+    pub fn new(nightly_price: Money, nights: i64) -> Result<Self, MoneyError> {
+        let subtotal = nightly_price.checked_mul(nights)?;
+        let zero = Money::zero(nightly_price.currency());
+        Ok(Self {
+            currency: nightly_price.currency(),
+            nightly_price,
+            nights,
+            subtotal,
+            nightly: Vec::new(),
+            extra_guests: 0,
+            extra_guest_fee: zero,
+            extra_guest_total: zero,
+            discounts: Vec::new(),
+            discount_total: zero,
+            taxes: Vec::new(),
+            tax_total: zero,
+            total: subtotal,
+            display: None,
+        })
+    }
+
+    /// Snapshot for a total set by the host (special offers); the nightly price is the average.
+    pub fn flat(total: Money, nights: i64) -> Result<Self, MoneyError> {
+        let mut snapshot = Self::new(total.checked_div(nights.max(1))?, nights)?;
+        snapshot.subtotal = total;
+        snapshot.total = total;
+        Ok(snapshot)
+    }
+
+    /// Snapshot for nights priced one by one, e.g. by the listing's calendar and pricing rules.
+    pub fn from_nights(currency: Currency, nights: &[NightPrice]) -> Result<Self, MoneyError> {
+        let total = nights
+            .iter()
+            .try_fold(Money::zero(currency), |total, night| {
+                total.checked_add(night.price)
+            })?;
+        let mut snapshot = Self::flat(total, nights.len() as i64)?;
+        snapshot.nightly = nights
+            .iter()
+            .map(|night| PriceNight {
+                date: night.date,
+                price: night.price,
+                source: night.source.to_string(),
+                rules: night.rules.clone(),
+                factors: night.factors.clone(),
+            })
+            .collect();
+        Ok(snapshot)
+    }
+
+    /// Add a per-night fee for each guest beyond those included in the nightly price.
+    pub fn with_extra_guests(
+        mut self,
+        extra_guests: i32,
+        fee_per_night: Money,
+    ) -> Result<Self, MoneyError> {
+        if extra_guests <= 0 || !fee_per_night.is_positive() {
+            return Ok(self);
+        }
+        let extra_total = fee_per_night
+            .checked_mul(i64::from(extra_guests))?
+            .checked_mul(self.nights)?;
+        self.extra_guests = extra_guests;
+        self.extra_guest_fee = fee_per_night;
+        self.extra_guest_total = extra_total;
+        self.recompute_total()?;
+        Ok(self)
+    }
+
+    /// Price of the stay before discounts.
+    pub fn stay_total(&self) -> Result<Money, MoneyError> {
+        self.subtotal.checked_add(self.extra_guest_total)
+    }
+
+    /// Price of the stay after discounts, which percentage taxes are charged on.
+    pub fn taxable_total(&self) -> Result<Money, MoneyError> {
+        Ok(self
+            .stay_total()?
+            .checked_sub(self.discount_total)?
+            .non_negative())
+    }
+
+    /// Apply a discount, capped so the stay price never goes below zero. Returns the amount
+    /// applied.
+    pub fn apply_discount(&mut self, mut discount: PriceDiscount) -> Result<Money, MoneyError> {
+        let amount = discount.amount.non_negative().min(self.taxable_total()?)?;
+        if amount.is_zero() {
+            return Ok(amount);
+        }
+        discount.amount = amount;
+        self.discounts.push(discount);
+        self.discount_total = self.discount_total.checked_add(amount)?;
+        self.recompute_total()?;
+        Ok(amount)
+    }
+
+    /// Add a tax line. Apply taxes after all discounts.
+    pub fn add_tax(&mut self, tax: PriceTax) -> Result<(), MoneyError> {
+        if !tax.amount.is_positive() {
+            return Ok(());
+        }
+        self.tax_total = self.tax_total.checked_add(tax.amount)?;
+        self.taxes.push(tax);
+        self.recompute_total()
+    }
+
+    fn recompute_total(&mut self) -> Result<(), MoneyError> {
+        self.total = self.taxable_total()?.checked_add(self.tax_total)?;
+        Ok(())
+    }
+}
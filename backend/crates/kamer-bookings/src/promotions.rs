@@ -0,0 +1,588 @@
+use crate::pricing::{PriceDiscount, PriceSnapshot};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::{NaiveDate, NaiveDateTime};
+use kamer_calendar::PricingError;
+use kamer_core::{Currency, Money, MoneyError};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+
+pub const RULE_LAST_MINUTE: &str = "last_minute";
+pub const RULE_EARLY_BIRD: &str = "early_bird";
+pub const RULE_FIRST_BOOKINGS: &str = "first_bookings";
+pub const DISCOUNT_PROMO_CODE: &str = "promo_code";
+
+/// Bookings in these statuses don't count toward usage limits.
+const RELEASED_STATUSES: &str = "('declined', 'expired', 'cancelled')";
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct HostPromotion {
+    pub id: String,
+    pub listing_id: String,
+    pub host_id: i32,
+    pub rule_type: String,
+    pub discount_percent: f64,
+    pub days_before: Option<i32>,
+    pub max_bookings: Option<i32>,
+    pub starts_on: Option<NaiveDate>,
+    pub ends_on: Option<NaiveDate>,
+    pub active: bool,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateHostPromotionRequest {
+    pub rule_type: String,
+    pub discount_percent: f64,
+    pub days_before: Option<i32>,
+    pub max_bookings: Option<i32>,
+    /// Booking dates (not stay dates) during which the rule is offered.
+    pub starts_on: Option<String>,
+    pub ends_on: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct PromoCode {
+    id: String,
+    code: String,
+    description: Option<String>,
+    discount_type: String,
+    /// Percentage codes.
+    discount_percent: Option<f64>,
+    /// Fixed codes.
+    discount_amount: Option<Money>,
+    /// Codes with a fixed amount or limits only apply to bookings in their currency.
+    currency: Option<Currency>,
+    max_discount: Option<Money>,
+    min_total: Option<Money>,
+    starts_at: Option<NaiveDateTime>,
+    ends_at: Option<NaiveDateTime>,
+    max_uses: Option<i32>,
+    max_uses_per_user: Option<i32>,
+    active: bool,
+}
+
+/// Why a promotion could not be applied.
+#[derive(Debug)]
+pub enum PromotionError {
+    /// Shown to the guest as-is.
+    Invalid(String),
+    Database(sqlx::Error),
+    Price(MoneyError),
+}
+
+impl From<sqlx::Error> for PromotionError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<MoneyError> for PromotionError {
+    fn from(e: MoneyError) -> Self {
+        Self::Price(e)
+    }
+}
+
+impl From<PricingError> for PromotionError {
+    fn from(e: PricingError) -> Self {
+        match e {
+            PricingError::Database(e) => Self::Database(e),
+            PricingError::Price(e) => Self::Price(e),
+        }
+    }
+}
+
+const HOST_PROMOTION_COLUMNS: &str = r#"
+    id, listing_id, host_id, rule_type, discount_percent, days_before, max_bookings,
+    starts_on, ends_on, active, created_at
+"#;
+
+const PROMO_CODE_COLUMNS: &str = r#"
+    id, code, description, discount_type, discount_percent,
+    to_money(discount_amount_minor, currency) as discount_amount, currency,
+    to_money(max_discount_minor, currency) as max_discount,
+    to_money(min_total_minor, currency) as min_total,
+    starts_at, ends_at, max_uses, max_uses_per_user, active
+"#;
+
+// ============================================================================
+// Applying Promotions
+// ============================================================================
+
+impl HostPromotion {
+    fn label(&self) -> String {
+        let name = match self.rule_type.as_str() {
+            RULE_LAST_MINUTE => "Last-minute discount",
+            RULE_EARLY_BIRD => "Early-bird discount",
+            RULE_FIRST_BOOKINGS => "New listing discount",
+            _ => "Discount",
+        };
+        format!("{} ({}%)", name, self.discount_percent)
+    }
+
+    fn applies(&self, today: NaiveDate, check_in: NaiveDate, listing_bookings: i64) -> bool {
+        if self.starts_on.is_some_and(|start| today < start)
+            || self.ends_on.is_some_and(|end| today > end)
+        {
+            return false;
+        }
+        let days_ahead = (check_in - today).num_days();
+        match self.rule_type.as_str() {
+            RULE_LAST_MINUTE => self
+                .days_before
+                .is_some_and(|days| days_ahead >= 0 && days_ahead <= days as i64),
+            RULE_EARLY_BIRD => self
+                .days_before
+                .is_some_and(|days| days_ahead >= days as i64),
+            RULE_FIRST_BOOKINGS => self
+                .max_bookings
+                .is_some_and(|max| listing_bookings < max as i64),
+            _ => false,
+        }
+    }
+}
+
+/// Apply the listing's best matching host promotion. Host promotions don't stack with each other.
+pub(crate) async fn apply_host_promotions(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    check_in: NaiveDate,
+    snapshot: &mut PriceSnapshot,
+) -> Result<(), PromotionError> {
+    let promotions = sqlx::query_as::<_, HostPromotion>(&format!(
+        "SELECT {} FROM host_promotions WHERE listing_id = $1 AND active",
+        HOST_PROMOTION_COLUMNS
+    ))
+    .bind(listing_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if promotions.is_empty() {
+        return Ok(());
+    }
+
+    let listing_bookings = if promotions
+        .iter()
+        .any(|p| p.rule_type == RULE_FIRST_BOOKINGS)
+    {
+        sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM bookings WHERE listing_id = $1 AND COALESCE(status, 'pending') NOT IN {}",
+            RELEASED_STATUSES
+        ))
+        .bind(listing_id)
+        .fetch_one(&mut *conn)
+        .await?
+    } else {
+        0
+    };
+
+    let today = chrono::Utc::now().date_naive();
+    let best = promotions
+        .iter()
+        .filter(|p| p.applies(today, check_in, listing_bookings))
+        .max_by(|a, b| a.discount_percent.total_cmp(&b.discount_percent));
+
+    if let Some(promotion) = best {
+        let amount = snapshot.stay_total()?.percent(promotion.discount_percent)?;
+        snapshot.apply_discount(PriceDiscount {
+            kind: promotion.rule_type.clone(),
+            label: promotion.label(),
+            promotion_id: Some(promotion.id.clone()),
+            code: None,
+            amount,
+        })?;
+    }
+    Ok(())
+}
+
+/// Validate a promo code for `user_id` and apply it after any host promotion.
+///
+/// With `lock`, the code row is locked until the surrounding transaction ends so concurrent
+/// bookings can't exceed its usage limits. Returns the code's id for recording the redemption.
+pub(crate) async fn apply_promo_code(
+    conn: &mut PgConnection,
+    code: &str,
+    user_id: i32,
+    snapshot: &mut PriceSnapshot,
+    lock: bool,
+) -> Result<String, PromotionError> {
+    let promo = sqlx::query_as::<_, PromoCode>(&format!(
+        "SELECT {} FROM promo_codes WHERE code = $1 {}",
+        PROMO_CODE_COLUMNS,
+        if lock { "FOR UPDATE" } else { "" }
+    ))
+    .bind(code.trim().to_uppercase())
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let invalid = |message: &str| PromotionError::Invalid(message.to_string());
+
+    let Some(promo) = promo else {
+        return Err(invalid("Promo code not found"));
+    };
+
+    let now = chrono::Utc::now().naive_utc();
+    if !promo.active
+        || promo.starts_at.is_some_and(|start| now < start)
+        || promo.ends_at.is_some_and(|end| now > end)
+    {
+        return Err(invalid("This promo code is not currently valid"));
+    }
+
+    if promo
+        .currency
+        .is_some_and(|currency| currency != snapshot.currency)
+    {
+        return Err(invalid("This promo code can't be used in this currency"));
+    }
+
+    if let Some(min_total) = promo.min_total {
+        if snapshot.total < min_total {
+            return Err(PromotionError::Invalid(format!(
+                "This promo code requires a total of at least {}",
+                min_total
+            )));
+        }
+    }
+
+    if promo.max_uses.is_some() || promo.max_uses_per_user.is_some() {
+        let (total_uses, user_uses) = sqlx::query_as::<_, (i64, i64)>(&format!(
+            r#"
+            SELECT COUNT(*), COUNT(*) FILTER (WHERE r.user_id = $2)
+            FROM promo_code_redemptions r
+            JOIN bookings b ON r.booking_id = b.id
+            WHERE r.promo_code_id = $1 AND COALESCE(b.status, 'pending') NOT IN {}
+            "#,
+            RELEASED_STATUSES
+        ))
+        .bind(&promo.id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if promo.max_uses.is_some_and(|max| total_uses >= max as i64) {
+            return Err(invalid("This promo code has reached its usage limit"));
+        }
+        if promo
+            .max_uses_per_user
+            .is_some_and(|max| user_uses >= max as i64)
+        {
+            return Err(invalid("You have already used this promo code"));
+        }
+    }
+
+    let amount = promo.discount(snapshot)?;
+    snapshot.apply_discount(PriceDiscount {
+        kind: DISCOUNT_PROMO_CODE.to_string(),
+        label: promo
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("Promo code {}", promo.code)),
+        promotion_id: Some(promo.id.clone()),
+        code: Some(promo.code.clone()),
+        amount,
+    })?;
+    Ok(promo.id)
+}
+
+impl PromoCode {
+    /// The code's discount on the snapshot's current total.
+    fn discount(&self, snapshot: &PriceSnapshot) -> Result<Money, MoneyError> {
+        if self.discount_type == "percentage" {
+            let amount = snapshot
+                .total
+                .percent(self.discount_percent.unwrap_or(0.0))?;
+            match self.max_discount {
+                Some(cap) => amount.min(cap),
+                None => Ok(amount),
+            }
+        } else {
+            self.discount_amount.ok_or(MoneyError::InvalidAmount)
+        }
+    }
+}
+
+/// Give a new quote for an existing booking, e.g. for changed dates, the discounts the booking
+/// was made with.
+///
+/// Percentage discounts are recomputed on the new price. The promotions and promo code are not
+/// checked again: the guest qualified when booking, and the code's redemption already counts
+/// this booking. Discounts whose promotion no longer exists keep their original amount.
+pub(crate) async fn carry_forward_discounts(
+    conn: &mut PgConnection,
+    original: &PriceSnapshot,
+    snapshot: &mut PriceSnapshot,
+) -> Result<(), PromotionError> {
+    for discount in &original.discounts {
+        let amount = match (discount.kind.as_str(), discount.promotion_id.as_deref()) {
+            (DISCOUNT_PROMO_CODE, Some(promo_code_id)) => {
+                let promo = sqlx::query_as::<_, PromoCode>(&format!(
+                    "SELECT {} FROM promo_codes WHERE id = $1",
+                    PROMO_CODE_COLUMNS
+                ))
+                .bind(promo_code_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+                match promo {
+                    Some(promo) => promo.discount(snapshot)?,
+                    None => discount.amount,
+                }
+            }
+            (_, Some(promotion_id)) => {
+                let percent = sqlx::query_scalar::<_, f64>(
+                    "SELECT discount_percent FROM host_promotions WHERE id = $1",
+                )
+                .bind(promotion_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+                match percent {
+                    Some(percent) => snapshot.stay_total()?.percent(percent)?,
+                    None => discount.amount,
+                }
+            }
+            (_, None) => discount.amount,
+        };
+        snapshot.apply_discount(PriceDiscount {
+            amount,
+            ..discount.clone()
+        })?;
+    }
+    Ok(())
+}
+
+/// Record that a booking used a promo code.
+pub(crate) async fn record_redemption(
+    conn: &mut PgConnection,
+    promo_code_id: &str,
+    booking_id: &str,
+    user_id: i32,
+    snapshot: &PriceSnapshot,
+) -> Result<(), PromotionError> {
+    let amount = snapshot
+        .discounts
+        .iter()
+        .filter(|d| d.kind == DISCOUNT_PROMO_CODE)
+        .try_fold(Money::zero(snapshot.currency), |sum, d| {
+            sum.checked_add(d.amount)
+        })?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO promo_code_redemptions
+            (promo_code_id, booking_id, user_id, amount_minor, currency)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(promo_code_id)
+    .bind(booking_id)
+    .bind(user_id)
+    .bind(amount.minor())
+    .bind(amount.currency())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+pub(crate) async fn verify_listing_host(
+    pool: &PgPool,
+    listing_id: &str,
+    user_id: i32,
+) -> Result<(), HttpResponse> {
+    let owner = sqlx::query_scalar::<_, i32>("SELECT host_id FROM listings WHERE id = $1")
+        .bind(listing_id)
+        .fetch_optional(pool)
+        .await;
+
+    match owner {
+        Ok(Some(host_id)) if host_id == user_id => Ok(()),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You don't have permission to manage this listing"
+        }))),
+        Ok(None) => Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "Listing not found"
+        }))),
+        Err(e) => {
+            log::error!("Failed to verify listing owner: {:?}", e);
+            Err(HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" })))
+        }
+    }
+}
+
+fn validate_host_promotion(body: &CreateHostPromotionRequest) -> Result<(), String> {
+    if !(body.discount_percent > 0.0 && body.discount_percent < 100.0) {
+        return Err("Discount must be between 0 and 100 percent".to_string());
+    }
+    match body.rule_type.as_str() {
+        RULE_LAST_MINUTE | RULE_EARLY_BIRD => {
+            if body.days_before.is_none_or(|days| days <= 0) {
+                return Err("days_before must be at least 1 for this rule".to_string());
+            }
+        }
+        RULE_FIRST_BOOKINGS => {
+            if body.max_bookings.is_none_or(|max| max <= 0) {
+                return Err("max_bookings must be at least 1 for this rule".to_string());
+            }
+        }
+        _ => return Err("Invalid rule type".to_string()),
+    }
+    Ok(())
+}
+
+fn parse_optional_date(value: Option<&str>) -> Result<Option<NaiveDate>, String> {
+    match value.filter(|v| !v.is_empty()) {
+        Some(v) => NaiveDate::parse_from_str(v, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| "Invalid date format".to_string()),
+        None => Ok(None),
+    }
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/promotions/listings/{listing_id} - List a listing's host promotions (host)
+#[get("/listings/{listing_id}")]
+pub async fn get_listing_promotions(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_host(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let promotions = sqlx::query_as::<_, HostPromotion>(&format!(
+        "SELECT {} FROM host_promotions WHERE listing_id = $1 AND active ORDER BY created_at DESC",
+        HOST_PROMOTION_COLUMNS
+    ))
+    .bind(&listing_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match promotions {
+        Ok(promotions) => HttpResponse::Ok().json(promotions),
+        Err(e) => {
+            log::error!("Failed to fetch host promotions: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST /api/promotions/listings/{listing_id} - Add a last-minute, early-bird or first-bookings discount (host)
+#[post("/listings/{listing_id}")]
+pub async fn create_listing_promotion(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateHostPromotionRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_host(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    if let Err(message) = validate_host_promotion(&body) {
+        return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+    }
+
+    let (starts_on, ends_on) = match (
+        parse_optional_date(body.starts_on.as_deref()),
+        parse_optional_date(body.ends_on.as_deref()),
+    ) {
+        (Ok(starts_on), Ok(ends_on)) => (starts_on, ends_on),
+        (Err(message), _) | (_, Err(message)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let result = sqlx::query_as::<_, HostPromotion>(&format!(
+        r#"
+        INSERT INTO host_promotions (
+            id, listing_id, host_id, rule_type, discount_percent, days_before, max_bookings,
+            starts_on, ends_on
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING {}
+        "#,
+        HOST_PROMOTION_COLUMNS
+    ))
+    .bind(&id)
+    .bind(&listing_id)
+    .bind(user_id)
+    .bind(&body.rule_type)
+    .bind(body.discount_percent)
+    .bind(body.days_before)
+    .bind(body.max_bookings)
+    .bind(starts_on)
+    .bind(ends_on)
+    .fetch_one(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(promotion) => HttpResponse::Ok().json(promotion),
+        Err(e) => {
+            log::error!("Failed to create host promotion: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to create promotion" }))
+        }
+    }
+}
+
+/// DELETE /api/promotions/{id} - Turn off a host promotion (host)
+#[delete("/{id}")]
+pub async fn delete_listing_promotion(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    // Deactivate rather than delete: existing price snapshots reference the promotion id
+    let result = sqlx::query(
+        r#"
+        UPDATE host_promotions SET active = FALSE, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND host_id = $2 AND active
+        "#,
+    )
+    .bind(path.into_inner())
+    .bind(user_id)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Promotion not found" }))
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "deleted" })),
+        Err(e) => {
+            log::error!("Failed to delete host promotion: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to delete promotion" }))
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::routes::BookingWithDetails;
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDate;
+use kamer_db::exchange_rates::DisplayCurrency;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::borrow::Cow;
+
+/// Cap on rows in a single CSV export.
+const EXPORT_LIMIT: i64 = 10_000;
+
+// ============================================================================
+// Shared Query Builder
+// ============================================================================
+
+/// Show booking totals in the viewer's currency.
+///
+/// The rate snapshotted at booking time is kept when it is already in that currency; otherwise
+/// today's rate is used. Without a currency for the viewer, only the listing's currency is shown,
+/// so a snapshot taken for the guest never reaches the host. Failures are only logged.
+pub(crate) async fn convert_booking_totals(
+    pool: &PgPool,
+    user_id: i32,
+    requested: Option<&str>,
+    bookings: &mut [BookingWithDetails],
+) {
+    let display = match pool.acquire().await {
+        Ok(mut conn) => DisplayCurrency::resolve(&mut conn, requested, Some(user_id)).await,
+        Err(e) => Err(e),
+    };
+    let display = display.unwrap_or_else(|e| {
+        log::error!("Failed to load exchange rates: {:?}", e);
+        None
+    });
+
+    for details in bookings.iter_mut() {
+        let booking = &details.booking;
+        details.display_price = match &display {
+            Some(display) if booking.currency != display.currency => {
+                match details.display_price.take() {
+                    Some(price) if price.currency == display.currency => Some(price),
+                    _ => display
+                        .convert(booking.total_price, &booking.currency)
+                        .map(sqlx::types::Json),
+                }
+            }
+            _ => None,
+        };
+    }
+}
+
+/// Columns of `BookingWithDetails`, joined from bookings, listings and the guest's profile.
+/// Callers append ` AND ...` conditions, then ordering and paging.
+pub(crate) fn booking_details_query<'a>() -> QueryBuilder<'a, Postgres> {
+    QueryBuilder::new(
+        r#"
+        SELECT
+            b.id, b.reference, b.listing_id, b.guest_id, b.check_in::TEXT as check_in, b.check_out::TEXT as check_out,
+            b.guests, COALESCE(b.adults, b.guests) as adults, b.children, b.infants, b.pets,
+            b.total_price, b.currency,
+            b.status, b.created_at::TEXT as created_at, b.updated_at::TEXT as updated_at,
+            u.username as guest_name,
+            u.email as guest_email,
+            up.phone as guest_phone,
+            l.title as listing_title,
+            l.city as listing_city,
+            l.country as listing_country,
+            p.url as listing_photo,
+            to_money(b.total_price_minor, b.currency) as total,
+            b.price_snapshot->'tax_total' as tax_total,
+            b.price_snapshot->'display' as display_price
+        FROM bookings b
+        INNER JOIN listings l ON b.listing_id = l.id
+        INNER JOIN users u ON b.guest_id = u.id
+        LEFT JOIN user_profiles up ON u.id = up.user_id
+        LEFT JOIN LATERAL (
+            SELECT url FROM listing_photos
+            WHERE listing_id = l.id
+            ORDER BY is_cover DESC, display_order, id
+            LIMIT 1
+        ) p ON true
+        WHERE TRUE
+        "#,
+    )
+}
+
+// ============================================================================
+// Filters
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct HostBookingQuery {
+    /// Comma-separated statuses, e.g. `confirmed,completed`.
+    pub status: Option<String>,
+    pub listing_id: Option<String>,
+    /// Stays overlapping `[from, to]` (YYYY-MM-DD); either bound may be omitted.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Matches the guest's username or legal name.
+    pub guest: Option<String>,
+    /// Currency to show totals in; defaults to the host's profile preference.
+    pub currency: Option<String>,
+    /// `check_in`, `created_at` or `total_price`; prefix with `-` for descending.
+    pub sort: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Validated form of `HostBookingQuery`.
+struct HostBookingFilters {
+    statuses: Vec<String>,
+    listing_id: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    guest: Option<String>,
+    order_by: &'static str,
+}
+
+impl HostBookingQuery {
+    fn filters(&self) -> Result<HostBookingFilters, String> {
+        let parse_date =
+            |value: &Option<String>, name: &str| -> Result<Option<NaiveDate>, String> {
+                match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                    Some(v) => NaiveDate::parse_from_str(v, "%Y-%m-%d")
+                        .map(Some)
+                        .map_err(|_| format!("Invalid {} date format", name)),
+                    None => Ok(None),
+                }
+            };
+
+        let order_by = match self.sort.as_deref().unwrap_or("-check_in") {
+            "check_in" => "b.check_in ASC, b.id",
+            "-check_in" => "b.check_in DESC, b.id",
+            "created_at" => "b.created_at ASC, b.id",
+            "-created_at" => "b.created_at DESC, b.id",
+            "total_price" => "b.total_price ASC, b.id",
+            "-total_price" => "b.total_price DESC, b.id",
+            other => return Err(format!("Unsupported sort: {}", other)),
+        };
+
+        Ok(HostBookingFilters {
+            statuses: self
+                .status
+                .as_deref()
+                .unwrap_or("")
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            listing_id: self.listing_id.clone().filter(|id| !id.is_empty()),
+            from: parse_date(&self.from, "from")?,
+            to: parse_date(&self.to, "to")?,
+            guest: self
+                .guest
+                .as_deref()
+                .map(str::trim)
+                .filter(|g| !g.is_empty())
+                .map(str::to_string),
+            order_by,
+        })
+    }
+}
+
+impl HostBookingFilters {
+    fn push_conditions<'a>(&'a self, qb: &mut QueryBuilder<'a, Postgres>, host_id: i32) {
+        qb.push(" AND l.host_id = ").push_bind(host_id);
+        if !self.statuses.is_empty() {
+            qb.push(" AND b.status = ANY(")
+                .push_bind(&self.statuses)
+                .push(")");
+        }
+        if let Some(listing_id) = &self.listing_id {
+            qb.push(" AND b.listing_id = ").push_bind(listing_id);
+        }
+        if let Some(from) = self.from {
+            qb.push(" AND b.check_out > ").push_bind(from);
+        }
+        if let Some(to) = self.to {
+            qb.push(" AND b.check_in <= ").push_bind(to);
+        }
+        if let Some(guest) = &self.guest {
+            let pattern = format!("%{}%", escape_like(guest));
+            qb.push(" AND (u.username ILIKE ")
+                .push_bind(pattern.clone())
+                .push(" OR up.legal_name ILIKE ")
+                .push_bind(pattern)
+                .push(")");
+        }
+    }
+}
+
+/// Escape `LIKE` wildcards so a search matches them literally.
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// One reservation as exported for accounting.
+#[derive(Debug, Serialize)]
+struct HostBookingCsvRow<'a> {
+    booking_id: &'a str,
+    reference: Option<&'a str>,
+    listing_id: &'a str,
+    listing_title: Cow<'a, str>,
+    guest_name: Cow<'a, str>,
+    guest_email: Cow<'a, str>,
+    check_in: &'a str,
+    check_out: &'a str,
+    adults: i32,
+    children: i32,
+    infants: i32,
+    pets: i32,
+    status: &'a str,
+    total_price: String,
+    tax_total: String,
+    currency: &'a str,
+    created_at: Option<&'a str>,
+}
+
+/// Quote text that a spreadsheet would otherwise run as a formula.
+fn spreadsheet_text(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+fn export_csv(bookings: &[BookingWithDetails]) -> Result<String, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for details in bookings {
+        let booking = &details.booking;
+        writer.serialize(HostBookingCsvRow {
+            booking_id: &booking.id,
+            reference: booking.reference.as_deref(),
+            listing_id: &booking.listing_id,
+            listing_title: spreadsheet_text(&details.listing_title),
+            guest_name: spreadsheet_text(&details.guest_name),
+            guest_email: spreadsheet_text(&details.guest_email),
+            check_in: &booking.check_in,
+            check_out: &booking.check_out,
+            adults: booking.adults,
+            children: booking.children,
+            infants: booking.infants,
+            pets: booking.pets,
+            status: &booking.status,
+            total_price: details.total.to_decimal(),
+            tax_total: details
+                .tax_total
+                .as_ref()
+                .map_or_else(|| "0".to_string(), |tax| tax.to_decimal()),
+            currency: &booking.currency,
+            created_at: booking.created_at.as_deref(),
+        })?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/bookings/host - Search the authenticated host's reservations
+#[get("/host")]
+pub async fn get_host_bookings(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<HostBookingQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let filters = match query.filters() {
+        Ok(filters) => filters,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let mut count_query = QueryBuilder::new(
+        r#"
+        SELECT COUNT(*)
+        FROM bookings b
+        INNER JOIN listings l ON b.listing_id = l.id
+        INNER JOIN users u ON b.guest_id = u.id
+        LEFT JOIN user_profiles up ON u.id = up.user_id
+        WHERE TRUE
+        "#,
+    );
+    filters.push_conditions(&mut count_query, user_id);
+
+    let total: i64 = match count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(total) => total,
+        Err(e) => {
+            log::error!("Failed to count host bookings: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let mut qb = booking_details_query();
+    filters.push_conditions(&mut qb, user_id);
+    qb.push(" ORDER BY ")
+        .push(filters.order_by)
+        .push(" LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+
+    match qb
+        .build_query_as::<BookingWithDetails>()
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(mut bookings) => {
+            convert_booking_totals(
+                pool.get_ref(),
+                user_id,
+                query.currency.as_deref(),
+                &mut bookings,
+            )
+            .await;
+            HttpResponse::Ok().json(serde_json::json!({
+                "bookings": bookings,
+                "total": total,
+                "limit": limit,
+                "offset": offset
+            }))
+        }
+        Err(e) => {
+            log::error!("Failed to fetch host bookings: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// GET /api/bookings/host/export.csv - Host reservations matching the search filters as CSV
+#[get("/host/export.csv")]
+pub async fn export_host_bookings(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<HostBookingQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let filters = match query.filters() {
+        Ok(filters) => filters,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let mut qb = booking_details_query();
+    filters.push_conditions(&mut qb, user_id);
+    qb.push(" ORDER BY ")
+        .push(filters.order_by)
+        .push(" LIMIT ")
+        .push_bind(EXPORT_LIMIT);
+
+    let bookings = match qb
+        .build_query_as::<BookingWithDetails>()
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(bookings) => bookings,
+        Err(e) => {
+            log::error!("Failed to fetch host bookings for export: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to export reservations" }));
+        }
+    };
+
+    match export_csv(&bookings) {
+        Ok(csv) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header((
+                "Content-Disposition",
+                format!(
+                    "attachment; filename=\"reservations-{}.csv\"",
+                    chrono::Utc::now().format("%Y-%m-%d")
+                ),
+            ))
+            .body(csv),
+        Err(e) => {
+            log::error!("Failed to render reservations CSV: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to export reservations" }))
+        }
+    }
+}
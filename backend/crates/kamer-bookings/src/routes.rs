@@ -1,7 +1,23 @@
+use crate::availability::{find_date_conflict, find_stay_restriction};
+use crate::cancellation::{self, CancellationOutcome, CancellationPolicy};
+use crate::documents::ensure_reference;
+use crate::guests::{GuestCounts, GuestRules};
+use crate::pricing::PriceSnapshot;
+use crate::promotions::{
+    apply_host_promotions, apply_promo_code, record_redemption, PromotionError,
+};
+use crate::reservations::{booking_details_query, convert_booking_totals};
+use crate::taxes::{apply_taxes, TaxError};
 use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
 use chrono::NaiveDate;
+use kamer_calendar::{nightly_prices, PricingError};
+use kamer_core::{Currency, Money, MoneyError};
+use kamer_db::exchange_rates::{ConvertedAmount, DisplayCurrency};
+use kamer_db::idempotency::{self, KeyLock};
+use kamer_messages::system::MESSAGE_SYSTEM;
+use kamer_messages::ConversationParties;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 
 // ============================================================================
 // Data Structures
@@ -10,39 +26,24 @@ use sqlx::PgPool;
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Booking {
     pub id: String,
+    pub reference: Option<String>,
     pub listing_id: String,
     pub guest_id: i32,
     pub check_in: String,
     pub check_out: String,
     pub guests: i32,
+    pub adults: i32,
+    pub children: i32,
+    pub infants: i32,
+    pub pets: i32,
     pub total_price: f64,
+    pub currency: String,
     pub status: String,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
 }
 
 /// GET /api/bookings/my - Get bookings for the authenticated guest (history)
-#[derive(Debug, sqlx::FromRow)]
-struct BookingRow {
-    id: String,
-    listing_id: String,
-    guest_id: i32,
-    check_in: String,
-    check_out: String,
-    guests: i32,
-    total_price: f64,
-    status: String,
-    created_at: Option<String>,
-    updated_at: Option<String>,
-    guest_name: String,
-    guest_email: String,
-    guest_phone: Option<String>,
-    listing_title: String,
-    listing_city: Option<String>,
-    listing_country: Option<String>,
-    listing_photo: Option<String>,
-}
-
 #[get("/my")]
 pub async fn get_my_bookings(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
     let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
@@ -50,55 +51,19 @@ pub async fn get_my_bookings(pool: web::Data<PgPool>, req: HttpRequest) -> impl
         Err(err) => return HttpResponse::from_error(err),
     };
 
-    let query = r#"
-        SELECT
-            b.id, b.listing_id, b.guest_id, b.check_in::TEXT as check_in, b.check_out::TEXT as check_out, b.guests, b.total_price, b.status, b.created_at::TEXT as created_at, b.updated_at::TEXT as updated_at,
-            u.username as guest_name,
-            u.email as guest_email,
-            up.phone as guest_phone,
-            l.title as listing_title,
-            l.city as listing_city,
-            l.country as listing_country,
-            (SELECT url FROM listing_photos WHERE listing_id = b.listing_id ORDER BY is_cover DESC, display_order LIMIT 1) as listing_photo
-        FROM bookings b
-        INNER JOIN listings l ON b.listing_id = l.id
-        INNER JOIN users u ON b.guest_id = u.id
-        LEFT JOIN user_profiles up ON u.id = up.user_id
-        WHERE b.guest_id = $1
-        ORDER BY b.created_at DESC
-    "#;
-
-    match sqlx::query_as::<_, BookingRow>(query)
-        .bind(user_id)
+    let mut query = booking_details_query();
+    query
+        .push(" AND b.guest_id = ")
+        .push_bind(user_id)
+        .push(" ORDER BY b.created_at DESC");
+
+    match query
+        .build_query_as::<BookingWithDetails>()
         .fetch_all(pool.get_ref())
         .await
     {
-        Ok(rows) => {
-            let bookings: Vec<BookingWithDetails> = rows
-                .into_iter()
-                .map(|row| BookingWithDetails {
-                    booking: Booking {
-                        id: row.id,
-                        listing_id: row.listing_id,
-                        guest_id: row.guest_id,
-                        check_in: row.check_in,
-                        check_out: row.check_out,
-                        guests: row.guests,
-                        total_price: row.total_price,
-                        status: row.status,
-                        created_at: row.created_at,
-                        updated_at: row.updated_at,
-                    },
-                    guest_name: row.guest_name,
-                    guest_email: row.guest_email,
-                    guest_phone: row.guest_phone,
-                    listing_title: row.listing_title,
-                    listing_city: row.listing_city,
-                    listing_country: row.listing_country,
-                    listing_photo: row.listing_photo,
-                })
-                .collect();
-
+        Ok(mut bookings) => {
+            convert_booking_totals(pool.get_ref(), user_id, None, &mut bookings).await;
             HttpResponse::Ok().json(bookings)
         }
         Err(e) => {
@@ -110,8 +75,9 @@ pub async fn get_my_bookings(pool: web::Data<PgPool>, req: HttpRequest) -> impl
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct BookingWithDetails {
+    #[sqlx(flatten)]
     pub booking: Booking,
     pub guest_name: String,
     pub guest_email: String,
@@ -120,14 +86,30 @@ pub struct BookingWithDetails {
     pub listing_photo: Option<String>,
     pub listing_city: Option<String>,
     pub listing_country: Option<String>,
+    /// Exact total in the booking's currency; `booking.total_price` is kept for API clients.
+    #[serde(skip_serializing)]
+    pub total: Money,
+    /// Taxes included in the total; `None` for bookings priced before taxes were charged.
+    pub tax_total: Option<sqlx::types::Json<Money>>,
+    /// Total in the viewer's currency; starts as the rate snapshotted at booking time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_price: Option<sqlx::types::Json<ConvertedAmount>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateBookingRequest {
     pub listing_id: String,
     pub check_in: String,
     pub check_out: String,
-    pub guests: i32,
+    /// Legacy total; used as the adult count when `adults` is not sent.
+    pub guests: Option<i32>,
+    pub adults: Option<i32>,
+    pub children: Option<i32>,
+    pub infants: Option<i32>,
+    pub pets: Option<i32>,
+    pub promo_code: Option<String>,
+    /// Currency to show the total in; defaults to the guest's profile preference.
+    pub display_currency: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -141,163 +123,600 @@ pub struct DeclineBookingRequest {
 
 // Local extract_user_id removed in favor of kamer_auth::extract_user_id
 
-// ============================================================================
-// API Endpoints
-// ============================================================================
+/// Whether confirmed bookings must be paid online first (`PAYMENTS_REQUIRED=true`).
+/// When enabled, instant-book and approved bookings wait in `awaiting_payment`.
+fn payments_required() -> bool {
+    std::env::var("PAYMENTS_REQUIRED").unwrap_or_default() == "true"
+}
 
-/// POST /api/bookings - Create a new booking
-#[post("")]
-pub async fn create_booking(
-    pool: web::Data<PgPool>,
-    req: HttpRequest,
-    booking_data: web::Json<CreateBookingRequest>,
-) -> impl Responder {
-    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
-        Ok(id) => id,
-        Err(err) => return HttpResponse::from_error(err),
+/// Minutes a booking awaiting payment holds its dates (`PAYMENT_HOLD_MINUTES`, default 60).
+pub(crate) fn payment_hold_minutes() -> i32 {
+    std::env::var("PAYMENT_HOLD_MINUTES")
+        .ok()
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(60)
+}
+
+/// Status a booking moves to once the host has accepted it.
+pub(crate) fn accepted_status() -> &'static str {
+    if payments_required() {
+        "awaiting_payment"
+    } else {
+        "confirmed"
+    }
+}
+
+/// Post a system message about a booking into the guest/host conversation.
+///
+/// Failures are only logged: the booking change it describes has already been committed.
+pub(crate) async fn post_booking_message(
+    pool: &PgPool,
+    listing_id: &str,
+    guest_id: i32,
+    host_id: i32,
+    sender_id: i32,
+    content: &str,
+    metadata: serde_json::Value,
+) {
+    let parties = ConversationParties {
+        listing_id,
+        guest_id,
+        host_id,
     };
+    if let Err(e) = kamer_messages::post_message(
+        pool,
+        parties,
+        sender_id,
+        MESSAGE_SYSTEM,
+        content,
+        Some(metadata),
+    )
+    .await
+    {
+        log::error!("Failed to post booking message: {:?}", e);
+    }
+}
 
-    let id = uuid::Uuid::new_v4().to_string();
+/// Listing fields needed to price and validate a new booking.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct BookingListing {
+    pub(crate) instant_book: bool,
+    pub(crate) host_id: i32,
+    pub(crate) currency: Currency,
+    pub(crate) cancellation_policy: Option<String>,
+    #[sqlx(flatten)]
+    pub(crate) rules: GuestRules,
+}
 
-    // Fetch listing price, instant_book, host_id and max_guests
-    let listing_info_result = sqlx::query_as::<_, (f64, bool, i32, i32)>(
-        "SELECT COALESCE(price_per_night, 0), COALESCE(instant_book, FALSE), host_id, COALESCE(max_guests, 0) FROM listings WHERE id = $1"
+impl BookingListing {
+    /// Standard price for a stay at the listing's current rates, night by night after the
+    /// host's calendar prices and pricing rules.
+    pub(crate) async fn quote(
+        &self,
+        conn: &mut PgConnection,
+        listing_id: &str,
+        counts: &GuestCounts,
+        check_in: NaiveDate,
+        check_out: NaiveDate,
+    ) -> Result<PriceSnapshot, PricingError> {
+        let stay_nights = (check_out - check_in).num_days();
+        let nights =
+            nightly_prices(conn, listing_id, check_in, check_out, Some(stay_nights)).await?;
+        Ok(
+            PriceSnapshot::from_nights(self.currency, &nights)?.with_extra_guests(
+                counts.extra_guests(&self.rules),
+                Money::from_minor(self.rules.extra_guest_fee_minor, self.currency),
+            )?,
+        )
+    }
+
+    pub(crate) fn policy(&self) -> CancellationPolicy {
+        CancellationPolicy::from_listing(self.cancellation_policy.as_deref())
+    }
+}
+
+pub(crate) async fn fetch_booking_listing<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    listing_id: &str,
+) -> Result<Option<BookingListing>, sqlx::Error> {
+    sqlx::query_as::<_, BookingListing>(
+        r#"
+        SELECT
+            COALESCE(instant_book, FALSE) as instant_book,
+            host_id,
+            COALESCE(currency, 'XAF') as currency,
+            cancellation_policy,
+            COALESCE(max_guests, 0) as max_guests,
+            pets_allowed, max_pets, guests_included, extra_guest_fee_minor
+        FROM listings
+        WHERE id = $1
+        "#,
     )
-    .bind(&booking_data.listing_id)
-    .fetch_optional(pool.get_ref())
-    .await;
+    .bind(listing_id)
+    .fetch_optional(executor)
+    .await
+}
 
-    let (price_per_night, instant_book, host_id, max_guests) = match listing_info_result {
+/// A booking ready to be stored.
+pub(crate) struct NewBooking<'a> {
+    pub(crate) id: &'a str,
+    pub(crate) listing_id: &'a str,
+    pub(crate) guest_id: i32,
+    pub(crate) check_in: NaiveDate,
+    pub(crate) check_out: NaiveDate,
+    pub(crate) counts: GuestCounts,
+    /// What the guest pays: the snapshot's total, in the listing's currency.
+    pub(crate) total: Money,
+    pub(crate) price_snapshot: &'a PriceSnapshot,
+    pub(crate) status: &'a str,
+    pub(crate) policy: CancellationPolicy,
+}
+
+pub(crate) async fn insert_booking<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    booking: &NewBooking<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO bookings (id, listing_id, guest_id, check_in, check_out, guests, adults, children, infants, pets, total_price_minor, currency, status, price_snapshot, cancellation_policy, payment_due_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15,
+            CASE WHEN $13 = 'awaiting_payment' THEN CURRENT_TIMESTAMP + make_interval(mins => $16) END)
+        "#
+    )
+    .bind(booking.id)
+    .bind(booking.listing_id)
+    .bind(booking.guest_id)
+    .bind(booking.check_in)
+    .bind(booking.check_out)
+    .bind(booking.counts.capacity())
+    .bind(booking.counts.adults)
+    .bind(booking.counts.children)
+    .bind(booking.counts.infants)
+    .bind(booking.counts.pets)
+    .bind(booking.total.minor())
+    .bind(booking.total.currency())
+    .bind(booking.status)
+    .bind(sqlx::types::Json(booking.price_snapshot))
+    .bind(booking.policy.as_str())
+    .bind(payment_hold_minutes())
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Booking fields needed to apply a cancellation policy.
+#[derive(Debug, sqlx::FromRow)]
+struct CancellationInfo {
+    guest_id: i32,
+    host_id: i32,
+    listing_id: String,
+    status: String,
+    check_in: NaiveDate,
+    total_price: Money,
+    created_at: Option<chrono::NaiveDateTime>,
+    price_snapshot: Option<sqlx::types::Json<PriceSnapshot>>,
+    booking_policy: Option<String>,
+    listing_policy: Option<String>,
+}
+
+impl CancellationInfo {
+    /// Policy snapshotted on the booking, falling back to the listing's current policy
+    /// for bookings created before snapshots existed.
+    fn policy(&self) -> CancellationPolicy {
+        CancellationPolicy::from_listing(
+            self.booking_policy
+                .as_deref()
+                .or(self.listing_policy.as_deref()),
+        )
+    }
+
+    fn total(&self) -> Money {
+        match &self.price_snapshot {
+            Some(snapshot) => snapshot.total,
+            None => self.total_price,
+        }
+    }
+
+    fn guest_outcome(&self, now: chrono::NaiveDateTime) -> Result<CancellationOutcome, MoneyError> {
+        cancellation::guest_cancellation(
+            self.policy(),
+            self.total(),
+            self.status == "confirmed",
+            self.created_at.unwrap_or(now),
+            self.check_in,
+            now,
+        )
+    }
+
+    fn host_outcome(&self, now: chrono::NaiveDateTime) -> Result<CancellationOutcome, MoneyError> {
+        cancellation::host_cancellation(self.policy(), self.total(), self.check_in, now)
+    }
+}
+
+async fn fetch_cancellation_info(
+    pool: &PgPool,
+    booking_id: &str,
+) -> Result<Option<CancellationInfo>, sqlx::Error> {
+    sqlx::query_as::<_, CancellationInfo>(
+        r#"
+        SELECT
+            b.guest_id, l.host_id, b.listing_id, COALESCE(b.status, 'pending') as status,
+            b.check_in, to_money(b.total_price_minor, b.currency) as total_price,
+            b.created_at, b.price_snapshot,
+            b.cancellation_policy as booking_policy,
+            l.cancellation_policy as listing_policy
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1
+        "#,
+    )
+    .bind(booking_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// A priced booking request, validated against the listing and its promotions.
+struct QuotedBooking {
+    listing: BookingListing,
+    counts: GuestCounts,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    price_snapshot: PriceSnapshot,
+    total: Money,
+    promo_code_id: Option<String>,
+}
+
+/// Validate a booking request and price it, applying host promotions and the promo code.
+///
+/// `lock` is set when booking, so the promo code's usage limits hold until the transaction ends.
+async fn quote_booking(
+    conn: &mut PgConnection,
+    user_id: i32,
+    booking_data: &CreateBookingRequest,
+    lock: bool,
+) -> Result<QuotedBooking, HttpResponse> {
+    let database_error = |e: sqlx::Error| {
+        log::error!("Failed to quote booking: {:?}", e);
+        HttpResponse::InternalServerError().json(serde_json::json!({ "error": "Database error" }))
+    };
+    let price_error = |e: MoneyError| {
+        HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": format!("Unable to price this stay: {}", e) }))
+    };
+
+    // Fetch listing price, instant_book, host, guest rules, currency and cancellation policy
+    let listing = match fetch_booking_listing(&mut *conn, &booking_data.listing_id).await {
         Ok(Some(info)) => info,
         Ok(None) => {
-            return HttpResponse::NotFound()
-                .json(serde_json::json!({ "error": "Listing not found" }));
-        }
-        Err(e) => {
-            log::error!("Failed to fetch listing info: {:?}", e);
-            return HttpResponse::InternalServerError()
-                .json(serde_json::json!({ "error": "Database error" }));
+            return Err(
+                HttpResponse::NotFound().json(serde_json::json!({ "error": "Listing not found" }))
+            );
         }
+        Err(e) => return Err(database_error(e)),
     };
 
     // Forbid booking own listing
-    if host_id == user_id {
-        return HttpResponse::Forbidden()
-            .json(serde_json::json!({ "error": "You cannot book your own listing" }));
+    if listing.host_id == user_id {
+        return Err(HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "You cannot book your own listing" })));
     }
 
-    // Validate guests count
-    if booking_data.guests <= 0 {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({ "error": "Guests must be at least 1" }));
-    }
-
-    // Enforce maximum guests configured by host
-    if max_guests > 0 && booking_data.guests > max_guests {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({ "error": format!("Guest count exceeds maximum allowed ({})", max_guests) }));
+    // Validate guest composition against capacity and pet rules
+    let counts = GuestCounts::from_request(
+        booking_data.guests,
+        booking_data.adults,
+        booking_data.children,
+        booking_data.infants,
+        booking_data.pets,
+    );
+    if let Err(message) = counts.validate(&listing.rules) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })));
     }
 
-    // Calculate total price
-    let check_in_date = match NaiveDate::parse_from_str(&booking_data.check_in, "%Y-%m-%d") {
+    let check_in = match NaiveDate::parse_from_str(&booking_data.check_in, "%Y-%m-%d") {
         Ok(date) => date,
         Err(_) => {
-            return HttpResponse::BadRequest()
-                .json(serde_json::json!({ "error": "Invalid check-in date format" }))
+            return Err(HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid check-in date format" })))
         }
     };
 
-    let check_out_date = match NaiveDate::parse_from_str(&booking_data.check_out, "%Y-%m-%d") {
+    let check_out = match NaiveDate::parse_from_str(&booking_data.check_out, "%Y-%m-%d") {
         Ok(date) => date,
         Err(_) => {
-            return HttpResponse::BadRequest()
-                .json(serde_json::json!({ "error": "Invalid check-out date format" }))
+            return Err(HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid check-out date format" })))
         }
     };
 
-    let days = (check_out_date - check_in_date).num_days();
+    let days = (check_out - check_in).num_days();
     if days <= 0 {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({ "error": "Check-out must be after check-in" }));
+        return Err(HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Check-out must be after check-in" })));
     }
 
-    let total_price = price_per_night * days as f64;
+    // Closed arrival and departure days, and minimum stays
+    match find_stay_restriction(&mut *conn, &booking_data.listing_id, check_in, check_out).await {
+        Ok(None) => {}
+        Ok(Some(restriction)) => {
+            return Err(HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": restriction.message() })));
+        }
+        Err(e) => return Err(database_error(e)),
+    }
 
-    // Check for overlapping bookings
-    let overlap_count: i64 = sqlx::query_scalar(
-        r#"
-        SELECT COUNT(*) FROM bookings
-        WHERE listing_id = $1
-        AND status = 'confirmed'
-        AND check_in < $2 AND check_out > $3
-        "#,
+    // Calculate total price, then discounts: host promotions first, then the promo code, then taxes
+    let mut price_snapshot = match listing
+        .quote(
+            &mut *conn,
+            &booking_data.listing_id,
+            &counts,
+            check_in,
+            check_out,
+        )
+        .await
+    {
+        Ok(snapshot) => snapshot,
+        Err(PricingError::Price(e)) => return Err(price_error(e)),
+        Err(PricingError::Database(e)) => return Err(database_error(e)),
+    };
+    match apply_host_promotions(
+        &mut *conn,
+        &booking_data.listing_id,
+        check_in,
+        &mut price_snapshot,
     )
-    .bind(&booking_data.listing_id)
-    .bind(&booking_data.check_out)
-    .bind(&booking_data.check_in)
-    .fetch_one(pool.get_ref())
     .await
-    .unwrap_or(0);
+    {
+        Ok(()) => {}
+        Err(PromotionError::Price(e)) => return Err(price_error(e)),
+        Err(PromotionError::Database(e)) => return Err(database_error(e)),
+        Err(PromotionError::Invalid(message)) => {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({ "error": message })));
+        }
+    }
 
-    if overlap_count > 0 {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({ "error": "Selected dates are already booked" }));
+    let promo_code = booking_data
+        .promo_code
+        .as_deref()
+        .map(str::trim)
+        .filter(|code| !code.is_empty());
+    let promo_code_id = match promo_code {
+        Some(code) => {
+            match apply_promo_code(&mut *conn, code, user_id, &mut price_snapshot, lock).await {
+                Ok(id) => Some(id),
+                Err(PromotionError::Invalid(message)) => {
+                    return Err(
+                        HttpResponse::BadRequest().json(serde_json::json!({ "error": message }))
+                    );
+                }
+                Err(PromotionError::Database(e)) => return Err(database_error(e)),
+                Err(PromotionError::Price(e)) => return Err(price_error(e)),
+            }
+        }
+        None => None,
+    };
+
+    match apply_taxes(
+        &mut *conn,
+        &booking_data.listing_id,
+        check_in,
+        &counts,
+        &mut price_snapshot,
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(TaxError::Price(e)) => return Err(price_error(e)),
+        Err(TaxError::Database(e)) => return Err(database_error(e)),
+    }
+    if !price_snapshot.total.is_positive() {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Unable to price this stay: the total must be greater than zero"
+        })));
     }
 
-    // Check for host-blocked dates (calendar_pricing.is_available = 0) within the requested range
-    // We treat the date range as [check_in, check_out) so check_out itself is not counted
-    let blocked_count: i64 = sqlx::query_scalar(
-        r#"
-        SELECT COUNT(*) FROM calendar_pricing
-        WHERE listing_id = $1
-          AND date >= $2
-          AND date < $3
-          AND is_available = 0
-        "#,
+    // Show the total in the guest's currency; the booking still settles in the listing's
+    match DisplayCurrency::resolve(
+        &mut *conn,
+        booking_data.display_currency.as_deref(),
+        Some(user_id),
     )
-    .bind(&booking_data.listing_id)
-    .bind(&booking_data.check_in)
-    .bind(&booking_data.check_out)
-    .fetch_one(pool.get_ref())
     .await
-    .unwrap_or(0);
+    {
+        Ok(Some(display)) if display.currency != price_snapshot.currency.as_str() => {
+            price_snapshot.display = display.convert(
+                price_snapshot.total.to_major(),
+                price_snapshot.currency.as_str(),
+            );
+        }
+        Ok(_) => {}
+        Err(e) => return Err(database_error(e)),
+    }
 
-    if blocked_count > 0 {
-        return HttpResponse::BadRequest()
-            .json(serde_json::json!({ "error": "Selected dates are unavailable" }));
+    let total = price_snapshot.total;
+    Ok(QuotedBooking {
+        listing,
+        counts,
+        check_in,
+        check_out,
+        price_snapshot,
+        total,
+        promo_code_id,
+    })
+}
+
+/// POST /api/bookings/quote - Price a stay without booking it
+#[post("/quote")]
+pub async fn quote_booking_price(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    booking_data: web::Json<CreateBookingRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to acquire connection: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    match quote_booking(&mut conn, user_id, &booking_data, false).await {
+        Ok(quote) => HttpResponse::Ok().json(quote.price_snapshot),
+        Err(response) => response,
     }
+}
 
-    let status = if instant_book { "confirmed" } else { "pending" };
+/// POST /api/bookings - Create a new booking
+///
+/// Honours `Idempotency-Key`, so app retries on flaky networks don't create duplicate bookings.
+#[post("")]
+pub async fn create_booking(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    booking_data: web::Json<CreateBookingRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
 
-    let result = sqlx::query(
-        r#"
-        INSERT INTO bookings (id, listing_id, guest_id, check_in, check_out, guests, total_price, status)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        "#
+    idempotency::run(pool.get_ref(), &req, user_id, &*booking_data, |lock| {
+        place_booking(pool.get_ref(), user_id, &booking_data, lock)
+    })
+    .await
+}
+
+async fn place_booking(
+    pool: &PgPool,
+    user_id: i32,
+    booking_data: &CreateBookingRequest,
+    lock: Option<KeyLock>,
+) -> HttpResponse {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let quote = match quote_booking(&mut tx, user_id, booking_data, true).await {
+        Ok(quote) => quote,
+        Err(response) => return response,
+    };
+    let total_price = quote.price_snapshot.total;
+
+    // Check for overlapping bookings and host-blocked dates
+    match find_date_conflict(
+        &mut *tx,
+        &booking_data.listing_id,
+        quote.check_in,
+        quote.check_out,
+        None,
+    )
+    .await
+    {
+        Ok(None) => {}
+        Ok(Some(conflict)) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": conflict.message() }));
+        }
+        Err(e) => {
+            log::error!("Failed to check booking availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    let status = if quote.listing.instant_book {
+        accepted_status()
+    } else {
+        "pending"
+    };
+
+    let result = insert_booking(
+        &mut *tx,
+        &NewBooking {
+            id: &id,
+            listing_id: &booking_data.listing_id,
+            guest_id: user_id,
+            check_in: quote.check_in,
+            check_out: quote.check_out,
+            counts: quote.counts,
+            total: quote.total,
+            price_snapshot: &quote.price_snapshot,
+            status,
+            policy: quote.listing.policy(),
+        },
     )
-    .bind(&id)
-    .bind(&booking_data.listing_id)
-    .bind(user_id)
-    .bind(check_in_date)
-    .bind(check_out_date)
-    .bind(booking_data.guests)
-    .bind(total_price)
-    .bind(status)
-    .execute(pool.get_ref())
     .await;
 
-    match result {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({
-            "id": id,
-            "status": status,
-            "total_price": total_price
-        })),
-        Err(e) => {
-            log::error!("Failed to create booking: {:?}", e);
-            HttpResponse::InternalServerError()
-                .json(serde_json::json!({ "error": "Failed to create booking" }))
+    if let Err(e) = result {
+        log::error!("Failed to create booking: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    if let Err(e) = kamer_payments::ledger::post_offline_booking(&mut tx, &id).await {
+        log::error!("Failed to record booking fee: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    if let Some(promo_code_id) = &quote.promo_code_id {
+        if let Err(e) =
+            record_redemption(&mut tx, promo_code_id, &id, user_id, &quote.price_snapshot).await
+        {
+            log::error!("Failed to record promo code redemption: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to create booking" }));
+        }
+    }
+
+    if let Some(lock) = &lock {
+        if let Err(e) = lock.mark_committed(&mut *tx).await {
+            log::error!("Failed to mark idempotency key committed: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to create booking" }));
         }
     }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit booking: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to create booking" }));
+    }
+
+    let reference = match ensure_reference(pool, &id).await {
+        Ok(reference) => Some(reference),
+        Err(e) => {
+            log::error!("Failed to assign booking reference: {:?}", e);
+            None
+        }
+    };
+    HttpResponse::Ok().json(serde_json::json!({
+        "id": id,
+        "reference": reference,
+        "status": status,
+        "total_price": total_price.to_major(),
+        "currency": quote.price_snapshot.currency,
+        "discounts": quote.price_snapshot.discounts,
+        "display_total": quote.price_snapshot.display
+    }))
 }
 
 /// POST /api/bookings/{id}/approve - Approve a booking
@@ -313,35 +732,109 @@ pub async fn approve_booking(
     };
     let booking_id = path.into_inner();
 
-    // Verify host owns the listing
-    let is_owner: bool = sqlx::query_scalar(
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Verify host owns the listing, locking the booking until it's approved
+    let booking: Option<(String, NaiveDate, NaiveDate, String)> = match sqlx::query_as(
         r#"
-        SELECT EXISTS(
-            SELECT 1 FROM bookings b
-            JOIN listings l ON b.listing_id = l.id
-            WHERE b.id = $1 AND l.host_id = $2
-        )
+        SELECT b.listing_id, b.check_in, b.check_out, COALESCE(b.status, 'pending')
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1 AND l.host_id = $2
+        FOR UPDATE OF b
         "#,
     )
     .bind(&booking_id)
     .bind(user_id)
-    .fetch_one(pool.get_ref())
+    .fetch_optional(&mut *tx)
     .await
-    .unwrap_or(false);
+    {
+        Ok(booking) => booking,
+        Err(e) => {
+            log::error!("Failed to fetch booking for approval: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
 
-    if !is_owner {
+    let Some((listing_id, check_in, check_out, current_status)) = booking else {
         return HttpResponse::Forbidden().json(serde_json::json!({
             "error": "You do not have permission to approve this booking"
         }));
+    };
+
+    if current_status != "pending" {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("This booking is already {}", current_status)
+        }));
     }
 
-    let result = sqlx::query("UPDATE bookings SET status = 'confirmed' WHERE id = $1")
-        .bind(&booking_id)
-        .execute(pool.get_ref())
-        .await;
+    // Another booking or a calendar block may have taken the dates since the request was made
+    match find_date_conflict(
+        &mut *tx,
+        &listing_id,
+        check_in,
+        check_out,
+        Some(&booking_id),
+    )
+    .await
+    {
+        Ok(None) => {}
+        Ok(Some(conflict)) => {
+            return HttpResponse::Conflict()
+                .json(serde_json::json!({ "error": conflict.message() }));
+        }
+        Err(e) => {
+            log::error!("Failed to check booking availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    let status = accepted_status();
+    let result = sqlx::query(
+        r#"
+        UPDATE bookings
+        SET status = $2,
+            payment_due_at = CASE
+                WHEN $2 = 'awaiting_payment' THEN CURRENT_TIMESTAMP + make_interval(mins => $3)
+            END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND COALESCE(status, 'pending') = 'pending'
+        "#,
+    )
+    .bind(&booking_id)
+    .bind(status)
+    .bind(payment_hold_minutes())
+    .execute(&mut *tx)
+    .await;
 
     match result {
-        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "confirmed" })),
+        Ok(done) if done.rows_affected() == 0 => HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": "This booking is no longer pending" })),
+        Ok(_) => {
+            if let Err(e) = kamer_payments::ledger::post_offline_booking(&mut tx, &booking_id).await
+            {
+                log::error!("Failed to record booking fee: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Failed to approve booking" }));
+            }
+            match tx.commit().await {
+                Ok(()) => HttpResponse::Ok().json(serde_json::json!({ "status": status })),
+                Err(e) => {
+                    log::error!("Failed to commit booking approval: {:?}", e);
+                    HttpResponse::InternalServerError()
+                        .json(serde_json::json!({ "error": "Failed to approve booking" }))
+                }
+            }
+        }
         Err(e) => {
             log::error!("Failed to approve booking: {:?}", e);
             HttpResponse::InternalServerError()
@@ -365,7 +858,7 @@ pub async fn decline_booking(
     let booking_id = path.into_inner();
 
     // Verify host owns the listing and get guest_id/listing_id
-    let booking_info: Option<(i32, String)> = sqlx::query_as(
+    let booking_info = sqlx::query_as::<_, (i32, String)>(
         r#"
         SELECT b.guest_id, b.listing_id
         FROM bookings b
@@ -376,72 +869,47 @@ pub async fn decline_booking(
     .bind(&booking_id)
     .bind(user_id)
     .fetch_optional(pool.get_ref())
-    .await
-    .unwrap_or(None);
+    .await;
 
     let (guest_id, listing_id) = match booking_info {
-        Some(info) => info,
-        None => {
+        Ok(Some(info)) => info,
+        Ok(None) => {
             return HttpResponse::Forbidden().json(serde_json::json!({
                 "error": "You do not have permission to decline this booking"
             }));
         }
+        Err(e) => {
+            log::error!("Failed to fetch booking for decline: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
     };
 
-    let result = sqlx::query("UPDATE bookings SET status = 'declined' WHERE id = $1")
-        .bind(&booking_id)
-        .execute(pool.get_ref())
-        .await;
+    let result = sqlx::query(
+        r#"
+        UPDATE bookings SET status = 'declined', updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND COALESCE(status, 'pending') = 'pending'
+        "#,
+    )
+    .bind(&booking_id)
+    .execute(pool.get_ref())
+    .await;
 
     match result {
+        Ok(done) if done.rows_affected() == 0 => HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": "This booking is no longer pending" })),
         Ok(_) => {
-            // Find or create conversation
-            let conversation_id = match sqlx::query_scalar::<_, String>(
-                "SELECT id FROM conversations WHERE listing_id = $1 AND guest_id = $2 AND host_id = $3"
-            )
-            .bind(&listing_id)
-            .bind(guest_id)
-            .bind(user_id)
-            .fetch_optional(pool.get_ref())
-            .await
-            .unwrap_or(None)
-            {
-                Some(id) => id,
-                None => {
-                    let new_id = uuid::Uuid::new_v4().to_string();
-                    let _ = sqlx::query(
-                        "INSERT INTO conversations (id, listing_id, guest_id, host_id) VALUES ($1, $2, $3, $4)"
-                    )
-                    .bind(&new_id)
-                    .bind(&listing_id)
-                    .bind(guest_id)
-                    .bind(user_id)
-                    .execute(pool.get_ref())
-                    .await;
-                    new_id
-                }
-            };
-
             // Send decline message
             let message_content = format!("Booking declined: {}", body.reason);
-            let message_id = uuid::Uuid::new_v4().to_string();
-
-            let _ = sqlx::query(
-                "INSERT INTO messages (id, conversation_id, sender_id, content) VALUES ($1, $2, $3, $4)"
-            )
-            .bind(&message_id)
-            .bind(&conversation_id)
-            .bind(user_id)
-            .bind(&message_content)
-            .execute(pool.get_ref())
-            .await;
-
-            // Update conversation timestamp
-            let _ = sqlx::query(
-                "UPDATE conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+            post_booking_message(
+                pool.get_ref(),
+                &listing_id,
+                guest_id,
+                user_id,
+                user_id,
+                &message_content,
+                serde_json::json!({ "event": "booking_declined", "booking_id": booking_id }),
             )
-            .bind(&conversation_id)
-            .execute(pool.get_ref())
             .await;
 
             HttpResponse::Ok().json(serde_json::json!({ "status": "declined" }))
@@ -462,96 +930,29 @@ pub async fn get_today_bookings(pool: web::Data<PgPool>, req: HttpRequest) -> im
         Err(err) => return HttpResponse::from_error(err),
     };
 
-    let query = r#"
-        SELECT
-            b.id, b.listing_id, b.guest_id, b.check_in::TEXT as check_in, b.check_out::TEXT as check_out, b.guests, b.total_price, b.status, b.created_at::TEXT as created_at, b.updated_at::TEXT as updated_at,
-            u.username as guest_name,
-            u.email as guest_email,
-            up.phone as guest_phone,
-            l.title as listing_title,
-            l.city as listing_city,
-            l.country as listing_country,
-            p.url as listing_photo
-        FROM bookings b
-        INNER JOIN listings l ON b.listing_id = l.id
-        INNER JOIN users u ON b.guest_id = u.id
-        LEFT JOIN user_profiles up ON u.id = up.user_id
-        LEFT JOIN LATERAL (
-            SELECT url FROM listing_photos 
-            WHERE listing_id = l.id 
-            ORDER BY is_cover DESC, display_order, id 
-            LIMIT 1
-        ) p ON true
-        WHERE l.host_id = $1
-        AND (b.check_in = CURRENT_DATE OR b.status = 'pending')
-        ORDER BY CASE WHEN b.status = 'pending' THEN 0 ELSE 1 END, b.check_in ASC
-    "#;
-
-    let bookings = match sqlx::query_as::<_, BookingRowSmall>(query)
-        .bind(user_id)
+    let mut query = booking_details_query();
+    query
+        .push(" AND l.host_id = ")
+        .push_bind(user_id)
+        .push(" AND (b.check_in = CURRENT_DATE OR b.status = 'pending')")
+        .push(" ORDER BY CASE WHEN b.status = 'pending' THEN 0 ELSE 1 END, b.check_in ASC");
+
+    match query
+        .build_query_as::<BookingWithDetails>()
         .fetch_all(pool.get_ref())
         .await
     {
-        Ok(rows) => rows,
+        Ok(mut bookings) => {
+            convert_booking_totals(pool.get_ref(), user_id, None, &mut bookings).await;
+            HttpResponse::Ok().json(bookings)
+        }
         Err(e) => {
             log::error!("Failed to fetch today's bookings: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
+            HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Database error: {}", e)
-            }));
+            }))
         }
-    };
-
-    if bookings.is_empty() {
-        return HttpResponse::Ok().json(Vec::<BookingWithDetails>::new());
-    }
-
-    let result: Vec<BookingWithDetails> = bookings
-        .into_iter()
-        .map(|row| BookingWithDetails {
-            booking: Booking {
-                id: row.id,
-                listing_id: row.listing_id,
-                guest_id: row.guest_id,
-                check_in: row.check_in,
-                check_out: row.check_out,
-                guests: row.guests,
-                total_price: row.total_price,
-                status: row.status,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-            },
-            guest_name: row.guest_name,
-            guest_email: row.guest_email,
-            guest_phone: row.guest_phone,
-            listing_title: row.listing_title,
-            listing_city: row.listing_city,
-            listing_country: row.listing_country,
-            listing_photo: row.listing_photo,
-        })
-        .collect();
-
-    HttpResponse::Ok().json(result)
-}
-
-#[derive(Debug, sqlx::FromRow)]
-struct BookingRowSmall {
-    id: String,
-    listing_id: String,
-    guest_id: i32,
-    check_in: String,
-    check_out: String,
-    guests: i32,
-    total_price: f64,
-    status: String,
-    created_at: Option<String>,
-    updated_at: Option<String>,
-    guest_name: String,
-    guest_email: String,
-    guest_phone: Option<String>,
-    listing_title: String,
-    listing_city: Option<String>,
-    listing_country: Option<String>,
-    listing_photo: Option<String>,
+    }
 }
 
 /// GET /api/bookings/host/upcoming - Get upcoming reservations for host
@@ -562,78 +963,98 @@ pub async fn get_upcoming_bookings(pool: web::Data<PgPool>, req: HttpRequest) ->
         Err(err) => return HttpResponse::from_error(err),
     };
 
-    let query = r#"
-        SELECT
-            b.id, b.listing_id, b.guest_id, b.check_in::TEXT as check_in, b.check_out::TEXT as check_out, b.guests, b.total_price, b.status, b.created_at::TEXT as created_at, b.updated_at::TEXT as updated_at,
-            u.username as guest_name,
-            u.email as guest_email,
-            up.phone as guest_phone,
-            l.title as listing_title,
-            l.city as listing_city,
-            l.country as listing_country,
-            p.url as listing_photo
-        FROM bookings b
-        INNER JOIN listings l ON b.listing_id = l.id
-        INNER JOIN users u ON b.guest_id = u.id
-        LEFT JOIN user_profiles up ON u.id = up.user_id
-        LEFT JOIN LATERAL (
-            SELECT url FROM listing_photos 
-            WHERE listing_id = l.id 
-            ORDER BY is_cover DESC, display_order, id 
-            LIMIT 1
-        ) p ON true
-        WHERE l.host_id = $1
-        AND b.check_in > CURRENT_DATE
-        AND b.status != 'pending'
-        ORDER BY b.check_in ASC
-    "#;
-
-    let bookings = match sqlx::query_as::<_, BookingRowSmall>(query)
-        .bind(user_id)
+    let mut query = booking_details_query();
+    query
+        .push(" AND l.host_id = ")
+        .push_bind(user_id)
+        .push(" AND b.check_in > CURRENT_DATE AND b.status != 'pending'")
+        .push(" ORDER BY b.check_in ASC");
+
+    match query
+        .build_query_as::<BookingWithDetails>()
         .fetch_all(pool.get_ref())
         .await
     {
-        Ok(rows) => rows,
+        Ok(mut bookings) => {
+            convert_booking_totals(pool.get_ref(), user_id, None, &mut bookings).await;
+            HttpResponse::Ok().json(bookings)
+        }
         Err(e) => {
             log::error!("Failed to fetch upcoming bookings: {:?}", e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
+            HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HostCancelBookingRequest {
+    pub reason: String,
+}
+
+/// GET /api/bookings/{id}/cancellation-preview - Show the refund and penalty before cancelling
+#[get("/{id}/cancellation-preview")]
+pub async fn get_cancellation_preview(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let booking_id = path.into_inner();
+
+    let info = match fetch_cancellation_info(pool.get_ref(), &booking_id).await {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Booking not found"
             }));
         }
+        Err(e) => {
+            log::error!("Failed to fetch booking for cancellation preview: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if !matches!(
+        info.status.as_str(),
+        "pending" | "awaiting_payment" | "confirmed"
+    ) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("A {} booking cannot be cancelled", info.status)
+        }));
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let outcome = if info.guest_id == user_id {
+        info.guest_outcome(now)
+    } else if info.host_id == user_id {
+        info.host_outcome(now)
+    } else {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to view this booking"
+        }));
     };
 
-    if bookings.is_empty() {
-        return HttpResponse::Ok().json(Vec::<BookingWithDetails>::new());
-    }
-
-    let result: Vec<BookingWithDetails> = bookings
-        .into_iter()
-        .map(|row| BookingWithDetails {
-            booking: Booking {
-                id: row.id,
-                listing_id: row.listing_id,
-                guest_id: row.guest_id,
-                check_in: row.check_in,
-                check_out: row.check_out,
-                guests: row.guests,
-                total_price: row.total_price,
-                status: row.status,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-            },
-            guest_name: row.guest_name,
-            guest_email: row.guest_email,
-            guest_phone: row.guest_phone,
-            listing_title: row.listing_title,
-            listing_city: row.listing_city,
-            listing_country: row.listing_country,
-            listing_photo: row.listing_photo,
-        })
-        .collect();
-    HttpResponse::Ok().json(result)
+    match outcome {
+        Ok(outcome) => HttpResponse::Ok().json(outcome),
+        Err(e) => {
+            log::error!(
+                "Failed to compute cancellation for booking {}: {:?}",
+                booking_id,
+                e
+            );
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Unable to compute the refund" }))
+        }
+    }
 }
 
-/// POST /api/bookings/{id}/cancel - Cancel a booking
+/// POST /api/bookings/{id}/cancel - Cancel a booking (guest)
 #[post("/{id}/cancel")]
 pub async fn cancel_booking(
     pool: web::Data<PgPool>,
@@ -646,111 +1067,286 @@ pub async fn cancel_booking(
     };
     let booking_id = path.into_inner();
 
+    let info = match fetch_cancellation_info(pool.get_ref(), &booking_id).await {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(serde_json::json!({
+                "error": "Booking not found"
+            }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking for cancellation: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
     // Verify user is the guest of the booking
-    let booking_info: Option<(i32, String, String)> = sqlx::query_as(
+    if info.guest_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({
+            "error": "You do not have permission to cancel this booking"
+        }));
+    }
+
+    if !matches!(
+        info.status.as_str(),
+        "pending" | "awaiting_payment" | "confirmed"
+    ) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Booking is already cancelled, declined or completed"
+        }));
+    }
+
+    let outcome = match info.guest_outcome(chrono::Utc::now().naive_utc()) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            log::error!(
+                "Failed to compute cancellation for booking {}: {:?}",
+                booking_id,
+                e
+            );
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Unable to compute the refund" }));
+        }
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Guard on status so a concurrent approve/cancel cannot be overwritten
+    let updated = sqlx::query(
         r#"
-        SELECT guest_id, listing_id, status
-        FROM bookings
-        WHERE id = $1
+        UPDATE bookings
+        SET status = 'cancelled', cancelled_at = CURRENT_TIMESTAMP, cancelled_by = $2,
+            refund_amount_minor = $3, cancellation_penalty_minor = $4,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = $5
         "#,
     )
     .bind(&booking_id)
-    .fetch_optional(pool.get_ref())
+    .bind(outcome.cancelled_by.as_str())
+    .bind(outcome.refund_amount.minor())
+    .bind(outcome.penalty_amount.minor())
+    .bind(&info.status)
+    .execute(&mut *tx)
+    .await;
+
+    match updated {
+        Ok(res) if res.rows_affected() == 0 => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Booking status changed, please retry"
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            log::error!("Failed to cancel booking: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to cancel booking" }));
+        }
+    }
+
+    // Refund the guest's online payment, if any, together with the cancellation
+    if let Err(e) = kamer_payments::request_refund(
+        &mut tx,
+        &booking_id,
+        outcome.refund_amount,
+        "Booking cancelled by guest",
+    )
     .await
-    .unwrap_or(None);
+    {
+        log::error!("Failed to request refund for cancellation: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to cancel booking" }));
+    }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit cancellation: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to cancel booking" }));
+    }
+
+    let message_content = format!(
+        "Booking cancelled by guest. Refund: {} ({}% under {} policy).",
+        outcome.refund_amount,
+        outcome.refund_percent,
+        outcome.policy.as_str()
+    );
+    post_booking_message(
+        pool.get_ref(),
+        &info.listing_id,
+        info.guest_id,
+        info.host_id,
+        user_id,
+        &message_content,
+        serde_json::json!({ "event": "booking_cancelled", "booking_id": booking_id }),
+    )
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "cancelled",
+        "cancellation": outcome
+    }))
+}
+
+/// POST /api/bookings/{id}/host-cancel - Cancel a confirmed booking as the host
+#[post("/{id}/host-cancel")]
+pub async fn host_cancel_booking(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<HostCancelBookingRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let booking_id = path.into_inner();
 
-    let (guest_id, listing_id, status) = match booking_info {
-        Some(info) => info,
-        None => {
+    let info = match fetch_cancellation_info(pool.get_ref(), &booking_id).await {
+        Ok(Some(info)) => info,
+        Ok(None) => {
             return HttpResponse::NotFound().json(serde_json::json!({
                 "error": "Booking not found"
             }));
         }
+        Err(e) => {
+            log::error!("Failed to fetch booking for host cancellation: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
     };
 
-    if guest_id != user_id {
+    if info.host_id != user_id {
         return HttpResponse::Forbidden().json(serde_json::json!({
             "error": "You do not have permission to cancel this booking"
         }));
     }
 
-    if status == "cancelled" || status == "declined" {
+    // Pending requests are declined, not cancelled
+    if info.status != "confirmed" {
         return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Booking is already cancelled or declined"
+            "error": "Only confirmed bookings can be cancelled by the host"
         }));
     }
 
-    let result = sqlx::query("UPDATE bookings SET status = 'cancelled' WHERE id = $1")
-        .bind(&booking_id)
-        .execute(pool.get_ref())
-        .await;
+    if body.reason.trim().is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "A cancellation reason is required" }));
+    }
 
-    match result {
-        Ok(_) => {
-            // Get host_id
-            let host_id: i32 = sqlx::query_scalar("SELECT host_id FROM listings WHERE id = $1")
-                .bind(&listing_id)
-                .fetch_one(pool.get_ref())
-                .await
-                .unwrap_or(0);
-
-            if host_id != 0 {
-                // Find or create conversation
-                let conversation_id = match sqlx::query_scalar::<_, String>(
-                    "SELECT id FROM conversations WHERE listing_id = $1 AND guest_id = $2 AND host_id = $3"
-                )
-                .bind(&listing_id)
-                .bind(user_id) // guest is user_id
-                .bind(host_id)
-                .fetch_optional(pool.get_ref())
-                .await
-                .unwrap_or(None)
-                {
-                    Some(id) => id,
-                    None => {
-                        let new_id = uuid::Uuid::new_v4().to_string();
-                        let _ = sqlx::query(
-                            "INSERT INTO conversations (id, listing_id, guest_id, host_id) VALUES ($1, $2, $3, $4)"
-                        )
-                        .bind(&new_id)
-                        .bind(&listing_id)
-                        .bind(user_id)
-                        .bind(host_id)
-                        .execute(pool.get_ref())
-                        .await;
-                        new_id
-                    }
-                };
-
-                // Send cancel message
-                let message_content = "Booking cancelled by guest.";
-                let message_id = uuid::Uuid::new_v4().to_string();
-
-                let _ = sqlx::query(
-                    "INSERT INTO messages (id, conversation_id, sender_id, content) VALUES ($1, $2, $3, $4)"
-                )
-                .bind(&message_id)
-                .bind(&conversation_id)
-                .bind(user_id)
-                .bind(message_content)
-                .execute(pool.get_ref())
-                .await;
-
-                // Update conversation timestamp
-                let _ = sqlx::query(
-                    "UPDATE conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = $1",
-                )
-                .bind(&conversation_id)
-                .execute(pool.get_ref())
-                .await;
-            }
+    let outcome = match info.host_outcome(chrono::Utc::now().naive_utc()) {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            log::error!(
+                "Failed to compute cancellation for booking {}: {:?}",
+                booking_id,
+                e
+            );
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Unable to compute the refund" }));
+        }
+    };
 
-            HttpResponse::Ok().json(serde_json::json!({ "status": "cancelled" }))
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
         }
+    };
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE bookings
+        SET status = 'cancelled', cancelled_at = CURRENT_TIMESTAMP, cancelled_by = $2,
+            refund_amount_minor = $3, cancellation_penalty_minor = $4,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = 'confirmed'
+        "#,
+    )
+    .bind(&booking_id)
+    .bind(outcome.cancelled_by.as_str())
+    .bind(outcome.refund_amount.minor())
+    .bind(outcome.penalty_amount.minor())
+    .execute(&mut *tx)
+    .await;
+
+    match updated {
+        Ok(res) if res.rows_affected() == 0 => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Booking status changed, please retry"
+            }));
+        }
+        Ok(_) => {}
         Err(e) => {
-            log::error!("Failed to cancel booking: {:?}", e);
-            HttpResponse::InternalServerError()
-                .json(serde_json::json!({ "error": "Failed to cancel booking" }))
+            log::error!("Failed to cancel booking as host: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to cancel booking" }));
         }
     }
+
+    if let Err(e) = sqlx::query(
+        r#"
+        INSERT INTO host_cancellation_penalties (booking_id, host_id, amount_minor, currency, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(&booking_id)
+    .bind(user_id)
+    .bind(outcome.penalty_amount.minor())
+    .bind(outcome.currency)
+    .bind(&body.reason)
+    .execute(&mut *tx)
+    .await
+    {
+        log::error!("Failed to record host cancellation penalty: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to cancel booking" }));
+    }
+
+    if let Err(e) = kamer_payments::request_refund(
+        &mut tx,
+        &booking_id,
+        outcome.refund_amount,
+        "Booking cancelled by host",
+    )
+    .await
+    {
+        log::error!("Failed to request refund for host cancellation: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to cancel booking" }));
+    }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit host cancellation: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to cancel booking" }));
+    }
+
+    let message_content = format!(
+        "Booking cancelled by host: {}. You will receive a full refund of {}.",
+        body.reason, outcome.refund_amount
+    );
+    post_booking_message(
+        pool.get_ref(),
+        &info.listing_id,
+        info.guest_id,
+        user_id,
+        user_id,
+        &message_content,
+        serde_json::json!({ "event": "booking_cancelled_by_host", "booking_id": booking_id }),
+    )
+    .await;
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "cancelled",
+        "cancellation": outcome
+    }))
 }
@@ -0,0 +1,138 @@
+use crate::guests::GuestCounts;
+use crate::pricing::{PriceSnapshot, PriceTax};
+use chrono::NaiveDate;
+use kamer_core::{Currency, Money, MoneyError};
+use kamer_db::exchange_rates::ExchangeRates;
+use sqlx::PgConnection;
+
+pub const TAX_PER_NIGHT: &str = "per_night";
+pub const TAX_PER_GUEST_NIGHT: &str = "per_guest_night";
+pub const TAX_PERCENTAGE: &str = "percentage";
+
+/// A tax rule in force for a listing.
+#[derive(Debug, sqlx::FromRow)]
+struct TaxRule {
+    id: String,
+    name: String,
+    tax_type: String,
+    rate: Option<f64>,
+    amount_minor: Option<i64>,
+    currency: Option<String>,
+}
+
+/// Why taxes could not be computed.
+#[derive(Debug)]
+pub enum TaxError {
+    Database(sqlx::Error),
+    /// Includes fixed taxes in a currency there is no exchange rate for.
+    Price(MoneyError),
+}
+
+impl From<sqlx::Error> for TaxError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<MoneyError> for TaxError {
+    fn from(e: MoneyError) -> Self {
+        Self::Price(e)
+    }
+}
+
+impl TaxRule {
+    fn is_foreign(&self, currency: Currency) -> bool {
+        self.currency
+            .as_deref()
+            .is_some_and(|c| !c.eq_ignore_ascii_case(currency.as_str()))
+    }
+
+    /// The rule's fixed amount in `currency`, converted at today's rate if needed.
+    fn fixed_amount(
+        &self,
+        currency: Currency,
+        rates: Option<&ExchangeRates>,
+    ) -> Result<Money, MoneyError> {
+        let rule_currency = match self.currency.as_deref() {
+            Some(code) => Currency::new(code)?,
+            None => currency,
+        };
+        let amount = Money::from_minor(self.amount_minor.unwrap_or(0), rule_currency);
+        if rule_currency == currency {
+            return Ok(amount);
+        }
+        let converted = rates
+            .and_then(|rates| {
+                rates.convert(amount.to_major(), rule_currency.as_str(), currency.as_str())
+            })
+            .ok_or(MoneyError::CurrencyMismatch(rule_currency, currency))?;
+        Money::from_major(converted.amount, currency)
+    }
+}
+
+/// Add the taxes in force at the listing's location on the check-in date.
+///
+/// Apply after discounts: percentage taxes are charged on the discounted price of the stay,
+/// never on other taxes.
+pub(crate) async fn apply_taxes(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    check_in: NaiveDate,
+    counts: &GuestCounts,
+    snapshot: &mut PriceSnapshot,
+) -> Result<(), TaxError> {
+    let rules = sqlx::query_as::<_, TaxRule>(
+        r#"
+        SELECT t.id, t.name, t.tax_type, t.rate, t.amount_minor, t.currency
+        FROM tax_rules t
+        JOIN listings l ON l.id = $1
+        LEFT JOIN user_profiles p ON p.user_id = l.host_id
+        WHERE t.active
+          AND LOWER(TRIM(t.country)) = LOWER(TRIM(l.country))
+          AND (t.city IS NULL OR LOWER(TRIM(t.city)) = LOWER(TRIM(l.city)))
+          AND t.effective_from <= $2
+          AND (t.effective_to IS NULL OR t.effective_to >= $2)
+          AND (NOT t.vat_registered_only OR NULLIF(TRIM(p.tax_id), '') IS NOT NULL)
+        ORDER BY t.effective_from, t.created_at, t.id
+        "#,
+    )
+    .bind(listing_id)
+    .bind(check_in)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    let currency = snapshot.currency;
+    let rates = if rules.iter().any(|rule| rule.is_foreign(currency)) {
+        Some(ExchangeRates::load(&mut *conn).await?)
+    } else {
+        None
+    };
+
+    let taxable = snapshot.taxable_total()?;
+    let nights = snapshot.nights;
+    for rule in &rules {
+        let amount = match rule.tax_type.as_str() {
+            TAX_PERCENTAGE => taxable.percent(rule.rate.unwrap_or(0.0))?,
+            TAX_PER_NIGHT => rule
+                .fixed_amount(currency, rates.as_ref())?
+                .checked_mul(nights)?,
+            TAX_PER_GUEST_NIGHT => rule
+                .fixed_amount(currency, rates.as_ref())?
+                .checked_mul(i64::from(counts.capacity()))?
+                .checked_mul(nights)?,
+            _ => continue,
+        };
+        snapshot.add_tax(PriceTax {
+            tax_rule_id: rule.id.clone(),
+            name: rule.name.clone(),
+            tax_type: rule.tax_type.clone(),
+            rate: rule.rate.filter(|_| rule.tax_type == TAX_PERCENTAGE),
+            amount,
+        })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use crate::pricing_rules::{nightly_prices, PricingError};
+use crate::routes::MAX_MIN_NIGHTS_OVERRIDE;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use kamer_core::Currency;
+use serde::Serialize;
+use sqlx::PgConnection;
+use std::collections::{HashMap, HashSet};
+
+/// Longest range the public availability calendar returns at once.
+pub const MAX_AVAILABILITY_RANGE_DAYS: i64 = 366;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+/// A day of a listing's public calendar, as a guest's date picker needs it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailabilityDay {
+    pub date: NaiveDate,
+    /// Whether the night of this date can be booked.
+    pub available: bool,
+    /// Nightly price for a one-night stay, in the listing's currency.
+    pub price: f64,
+    /// A stay cannot start on this date, because the host closed it to arrivals or the
+    /// shortest stay from it runs into an unavailable night.
+    pub closed_to_arrival: bool,
+    /// A stay cannot end on this date, because the host closed it to departures or the night
+    /// before it is unavailable.
+    pub closed_to_departure: bool,
+    /// Shortest stay that may start on this date.
+    pub min_nights: i32,
+}
+
+/// A day the host put stay restrictions on.
+#[derive(Debug, sqlx::FromRow)]
+struct RestrictedDay {
+    date: NaiveDate,
+    closed_to_arrival: bool,
+    closed_to_departure: bool,
+    min_nights_override: Option<i32>,
+}
+
+/// The listing's usual minimum stay and the days with their own restrictions.
+pub(crate) struct StayRestrictions {
+    default_min_nights: i32,
+    days: HashMap<NaiveDate, RestrictedDay>,
+}
+
+impl StayRestrictions {
+    fn day(&self, date: NaiveDate) -> Option<&RestrictedDay> {
+        self.days.get(&date)
+    }
+
+    /// Minimum stay for stays that include the night of `date`.
+    fn night_min(&self, date: NaiveDate) -> i32 {
+        self.day(date)
+            .and_then(|day| day.min_nights_override)
+            .unwrap_or(self.default_min_nights)
+    }
+
+    /// Shortest stay that can start on `date`: long enough for the minimum of every night in it.
+    pub(crate) fn shortest_stay(&self, date: NaiveDate) -> i32 {
+        let mut nights = self.night_min(date).max(1);
+        loop {
+            let required = (0..nights)
+                .map(|n| self.night_min(date + Duration::days(i64::from(n))))
+                .max()
+                .unwrap_or(1);
+            if required <= nights || nights >= MAX_MIN_NIGHTS_OVERRIDE {
+                return nights;
+            }
+            nights = required.min(MAX_MIN_NIGHTS_OVERRIDE);
+        }
+    }
+}
+
+/// What decides a listing's public calendar; changes whenever the calendar may have.
+#[derive(Debug, sqlx::FromRow)]
+pub struct CalendarVersion {
+    pub currency: Currency,
+    pub version: i64,
+    pub listing_updated_at: Option<NaiveDateTime>,
+    /// Smart prices follow other listings' bookings, which the version doesn't track.
+    pub smart_pricing_enabled: bool,
+}
+
+// ============================================================================
+// Availability
+// ============================================================================
+
+/// The calendar version of a published listing, or `None` if there is no such listing.
+pub async fn calendar_version(
+    conn: &mut PgConnection,
+    listing_id: &str,
+) -> Result<Option<CalendarVersion>, sqlx::Error> {
+    sqlx::query_as::<_, CalendarVersion>(
+        r#"
+        SELECT
+            COALESCE(l.currency, 'XAF') as currency,
+            COALESCE(v.version, 0) as version,
+            l.updated_at as listing_updated_at,
+            COALESCE(s.smart_pricing_enabled, FALSE) as smart_pricing_enabled
+        FROM listings l
+        LEFT JOIN listing_calendar_versions v ON v.listing_id = l.id
+        LEFT JOIN listing_settings s ON s.listing_id = l.id
+        WHERE l.id = $1 AND l.status = 'published'
+        "#,
+    )
+    .bind(listing_id)
+    .fetch_optional(conn)
+    .await
+}
+
+/// The listing's stay restrictions for the days from `from` up to, not including, `to`.
+pub(crate) async fn stay_restrictions(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<StayRestrictions, sqlx::Error> {
+    let default_min_nights = sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT GREATEST(COALESCE(s.min_nights, l.min_nights, 1), 1)
+        FROM listings l
+        LEFT JOIN listing_settings s ON s.listing_id = l.id
+        WHERE l.id = $1
+        "#,
+    )
+    .bind(listing_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or(1);
+
+    let days = sqlx::query_as::<_, RestrictedDay>(
+        r#"
+        SELECT date, closed_to_arrival, closed_to_departure, min_nights_override
+        FROM calendar_pricing
+        WHERE listing_id = $1 AND date >= $2 AND date < $3
+          AND (closed_to_arrival OR closed_to_departure OR min_nights_override IS NOT NULL)
+        "#,
+    )
+    .bind(listing_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(StayRestrictions {
+        default_min_nights,
+        days: days.into_iter().map(|day| (day.date, day)).collect(),
+    })
+}
+
+/// Booked and blocked nights from `from` up to, not including, `to`.
+async fn unavailable_nights(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashSet<NaiveDate>, sqlx::Error> {
+    let nights = sqlx::query_scalar::<_, NaiveDate>(
+        r#"
+        SELECT d::DATE
+        FROM generate_series($2::DATE, $3::DATE - 1, INTERVAL '1 day') AS d
+        WHERE EXISTS (
+                SELECT 1 FROM bookings b
+                WHERE b.listing_id = $1
+                  AND b.status IN ('confirmed', 'awaiting_payment')
+                  AND b.check_in <= d::DATE AND b.check_out > d::DATE
+            )
+           OR EXISTS (
+                SELECT 1 FROM calendar_pricing cp
+                WHERE cp.listing_id = $1 AND cp.date = d::DATE AND cp.is_available = FALSE
+            )
+           OR EXISTS (
+                SELECT 1 FROM external_blocks e
+                WHERE e.listing_id = $1 AND e.start_date <= d::DATE AND e.end_date > d::DATE
+            )
+        "#,
+    )
+    .bind(listing_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(nights.into_iter().collect())
+}
+
+/// The public calendar for the days from `from` to `to`, inclusive.
+///
+/// Past nights are unavailable. Besides the host's own closures, a day is closed to arrival when
+/// any night of the shortest stay starting on it is unavailable, and closed to departure when the
+/// night before it is.
+pub async fn listing_availability(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<AvailabilityDay>, PricingError> {
+    // Look a day back for departures and far enough ahead for the longest minimum stay
+    let window_start = from - Duration::days(1);
+    let window_end = to + Duration::days(i64::from(MAX_MIN_NIGHTS_OVERRIDE));
+    let restrictions = stay_restrictions(&mut *conn, listing_id, window_start, window_end).await?;
+    let shortest: Vec<(NaiveDate, i32)> = (0..=(to - from).num_days())
+        .map(|n| from + Duration::days(n))
+        .map(|date| (date, restrictions.shortest_stay(date)))
+        .collect();
+    let longest = shortest
+        .iter()
+        .map(|&(_, nights)| nights)
+        .max()
+        .unwrap_or(1);
+
+    let unavailable = unavailable_nights(
+        &mut *conn,
+        listing_id,
+        window_start,
+        to + Duration::days(i64::from(longest)),
+    )
+    .await?;
+    let today = chrono::Utc::now().date_naive();
+    let is_available = |date: NaiveDate| date >= today && !unavailable.contains(&date);
+
+    let nights = nightly_prices(&mut *conn, listing_id, from, to + Duration::days(1), None).await?;
+
+    Ok(nights
+        .into_iter()
+        .zip(shortest)
+        .map(|(night, (date, min_nights))| {
+            let day = restrictions.day(date);
+            let stay_fits =
+                (0..i64::from(min_nights)).all(|n| is_available(date + Duration::days(n)));
+            AvailabilityDay {
+                date,
+                available: is_available(date),
+                price: night.price.to_major(),
+                closed_to_arrival: day.is_some_and(|day| day.closed_to_arrival) || !stay_fits,
+                closed_to_departure: day.is_some_and(|day| day.closed_to_departure)
+                    || !is_available(date - Duration::days(1)),
+                min_nights,
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,836 @@
+use crate::routes::verify_listing_ownership;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use sha1::Digest;
+use sqlx::PgPool;
+
+/// How far back the feed still lists past stays and blocks.
+const EXPORT_HISTORY_DAYS: i32 = 30;
+
+/// RFC 5545 content lines are folded at 75 octets.
+const MAX_LINE_OCTETS: usize = 75;
+
+const UID_DOMAIN: &str = "kamer";
+
+#[derive(Debug, Deserialize)]
+pub struct ExportFeedQuery {
+    pub token: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ExportedBooking {
+    id: String,
+    check_in: NaiveDate,
+    check_out: NaiveDate,
+    stamp: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct BlockedDay {
+    date: NaiveDate,
+    updated_at: Option<NaiveDateTime>,
+}
+
+/// A day with stay restrictions, exported as a calendar property rather than an event, since
+/// other platforms treat every event as blocking its days.
+#[derive(Debug, sqlx::FromRow)]
+struct RestrictedDay {
+    date: NaiveDate,
+    closed_to_arrival: bool,
+    closed_to_departure: bool,
+    min_nights_override: Option<i32>,
+}
+
+impl RestrictedDay {
+    fn same_restrictions(&self, other: &RestrictedDay) -> bool {
+        (
+            self.closed_to_arrival,
+            self.closed_to_departure,
+            self.min_nights_override,
+        ) == (
+            other.closed_to_arrival,
+            other.closed_to_departure,
+            other.min_nights_override,
+        )
+    }
+
+    fn notes(&self) -> String {
+        let mut notes = Vec::new();
+        if self.closed_to_arrival {
+            notes.push("No check-in".to_string());
+        }
+        if self.closed_to_departure {
+            notes.push("No check-out".to_string());
+        }
+        if let Some(nights) = self.min_nights_override {
+            notes.push(format!("Minimum stay {} nights", nights));
+        }
+        notes.join("\n")
+    }
+}
+
+/// Consecutive unavailable days, exported as one all-day event.
+struct BlockedRange {
+    start: NaiveDate,
+    /// Exclusive, as DTEND is for all-day events.
+    end: NaiveDate,
+    stamp: Option<NaiveDateTime>,
+}
+
+/// Consecutive days with the same stay restrictions.
+struct RestrictionNote {
+    start: NaiveDate,
+    /// Exclusive, like DTEND.
+    end: NaiveDate,
+    notes: String,
+}
+
+/// One all-day VEVENT.
+struct CalendarEvent {
+    uid: String,
+    summary: &'static str,
+    start: NaiveDate,
+    end: NaiveDate,
+    stamp: NaiveDateTime,
+}
+
+// ============================================================================
+// iCalendar Writer
+// ============================================================================
+
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Append a content line, folded so no physical line exceeds 75 octets.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn render_calendar(
+    name: &str,
+    restrictions: &[RestrictionNote],
+    events: &[CalendarEvent],
+) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//Kamer//Listing Calendar//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+    push_line(&mut out, "METHOD:PUBLISH");
+    push_line(&mut out, &format!("X-WR-CALNAME:{}", escape_text(name)));
+    for note in restrictions {
+        push_line(
+            &mut out,
+            &format!(
+                "X-KAMER-STAY-RESTRICTION;X-START={};X-END={}:{}",
+                format_date(note.start),
+                format_date(note.end),
+                escape_text(&note.notes)
+            ),
+        );
+    }
+    for event in events {
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(&mut out, &format!("UID:{}", event.uid));
+        push_line(
+            &mut out,
+            &format!("DTSTAMP:{}", event.stamp.format("%Y%m%dT%H%M%SZ")),
+        );
+        push_line(
+            &mut out,
+            &format!("DTSTART;VALUE=DATE:{}", format_date(event.start)),
+        );
+        push_line(
+            &mut out,
+            &format!("DTEND;VALUE=DATE:{}", format_date(event.end)),
+        );
+        push_line(&mut out, &format!("SUMMARY:{}", event.summary));
+        push_line(&mut out, "TRANSP:OPAQUE");
+        push_line(&mut out, "END:VEVENT");
+    }
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Merge blocked days into ranges of consecutive dates. `days` must be sorted by date.
+fn blocked_ranges(days: &[BlockedDay]) -> Vec<BlockedRange> {
+    let mut ranges: Vec<BlockedRange> = Vec::new();
+    for day in days {
+        match ranges.last_mut() {
+            Some(range) if range.end == day.date => {
+                range.end = day.date + Duration::days(1);
+                range.stamp = range.stamp.max(day.updated_at);
+            }
+            _ => ranges.push(BlockedRange {
+                start: day.date,
+                end: day.date + Duration::days(1),
+                stamp: day.updated_at,
+            }),
+        }
+    }
+    ranges
+}
+
+/// Merge days with the same restrictions into notes. `days` must be sorted by date.
+fn restriction_notes(days: &[RestrictedDay]) -> Vec<RestrictionNote> {
+    let mut notes: Vec<(RestrictionNote, &RestrictedDay)> = Vec::new();
+    for day in days {
+        match notes.last_mut() {
+            Some((note, first)) if note.end == day.date && first.same_restrictions(day) => {
+                note.end = day.date + Duration::days(1);
+            }
+            _ => notes.push((
+                RestrictionNote {
+                    start: day.date,
+                    end: day.date + Duration::days(1),
+                    notes: day.notes(),
+                },
+                day,
+            )),
+        }
+    }
+    notes.into_iter().map(|(note, _)| note).collect()
+}
+
+// ============================================================================
+// iCalendar Reader
+// ============================================================================
+
+/// A busy period read from another platform's feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedEvent {
+    pub uid: String,
+    pub start: NaiveDate,
+    /// Exclusive: the first night that is free again.
+    pub end: NaiveDate,
+    pub summary: Option<String>,
+}
+
+/// The calendar date of a DTSTART/DTEND date or date-time. Times are read in the feed's own
+/// zone, which is the listing's for the platforms we sync with.
+fn parse_event_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()
+}
+
+/// Whole days in a `DURATION` such as `P3D` or `P1W`; shorter parts are ignored.
+fn parse_duration_days(value: &str) -> Option<i64> {
+    let value = value.strip_prefix('P')?;
+    let value = value.split('T').next().unwrap_or("");
+    if let Some(weeks) = value.strip_suffix('W') {
+        return weeks.parse::<i64>().ok().map(|w| w * 7);
+    }
+    value.strip_suffix('D')?.parse::<i64>().ok()
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Split a content line into its upper-cased name and its value, skipping any parameters.
+fn split_property(line: &str) -> Option<(String, &str)> {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => {
+                let name = line[..i].split(';').next().unwrap_or("");
+                return Some((name.to_ascii_uppercase(), &line[i + 1..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[derive(Default)]
+struct EventBuilder {
+    uid: Option<String>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    duration_days: Option<i64>,
+    summary: Option<String>,
+    cancelled: bool,
+    transparent: bool,
+}
+
+impl EventBuilder {
+    fn build(self) -> Option<ImportedEvent> {
+        if self.cancelled || self.transparent {
+            return None;
+        }
+        let start = self.start?;
+        let end = match (self.end, self.duration_days) {
+            // A check-out time on the last day does not occupy that night
+            (Some(end), _) => end,
+            (None, Some(days)) => start + Duration::days(days),
+            (None, None) => start + Duration::days(1),
+        };
+        let end = end.max(start + Duration::days(1));
+        Some(ImportedEvent {
+            uid: self
+                .uid
+                .unwrap_or_else(|| format!("{}-{}", format_date(start), format_date(end))),
+            start,
+            end,
+            summary: self.summary,
+        })
+    }
+}
+
+/// Read the busy periods from an iCalendar document.
+///
+/// Cancelled and transparent (free) events are skipped. Recurrence rules are not expanded:
+/// booking platforms export each reservation or block as its own event.
+pub fn parse_events(body: &str) -> Result<Vec<ImportedEvent>, String> {
+    // Unfold continuation lines first
+    let mut lines: Vec<String> = Vec::new();
+    for raw in body.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (
+            raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    if !lines
+        .iter()
+        .any(|line| line.trim().eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err("Not an iCalendar file".to_string());
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<EventBuilder> = None;
+    // Components nested in an event, such as VALARM, whose properties are not the event's
+    let mut nested = 0usize;
+    for line in &lines {
+        let Some((name, value)) = split_property(line) else {
+            continue;
+        };
+        let value = value.trim();
+        match name.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") && current.is_none() => {
+                current = Some(EventBuilder::default());
+                nested = 0;
+            }
+            "BEGIN" if current.is_some() => nested += 1,
+            "END" if current.is_some() && nested > 0 => nested -= 1,
+            "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                if let Some(event) = current.take().and_then(EventBuilder::build) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                let Some(event) = current.as_mut().filter(|_| nested == 0) else {
+                    continue;
+                };
+                match name.as_str() {
+                    "UID" => event.uid = Some(value.to_string()),
+                    "DTSTART" => event.start = parse_event_date(value),
+                    "DTEND" => event.end = parse_event_date(value),
+                    "DURATION" => event.duration_days = parse_duration_days(value),
+                    "SUMMARY" => event.summary = Some(unescape_text(value)),
+                    "STATUS" => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+                    "TRANSP" => event.transparent = value.eq_ignore_ascii_case("TRANSPARENT"),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+// ============================================================================
+// Feed Tokens
+// ============================================================================
+
+fn generate_token() -> String {
+    let mut bytes = uuid::Uuid::new_v4().as_bytes().to_vec();
+    bytes.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    hex::encode(bytes)
+}
+
+/// Compare without returning early, so response timing does not reveal how much matched.
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn feed_url(req: &HttpRequest, listing_id: &str, token: &str) -> String {
+    let info = req.connection_info();
+    format!(
+        "{}://{}/api/calendar/{}/export.ics?token={}",
+        info.scheme(),
+        info.host(),
+        listing_id,
+        token
+    )
+}
+
+/// The listing's feed token, created on first use. With `rotate`, always issue a new one.
+async fn ensure_feed_token(
+    pool: &PgPool,
+    listing_id: &str,
+    rotate: bool,
+) -> Result<String, sqlx::Error> {
+    let query = if rotate {
+        r#"
+        INSERT INTO calendar_export_feeds (listing_id, token) VALUES ($1, $2)
+        ON CONFLICT (listing_id) DO UPDATE
+        SET token = EXCLUDED.token, rotated_at = CURRENT_TIMESTAMP
+        RETURNING token
+        "#
+    } else {
+        r#"
+        INSERT INTO calendar_export_feeds (listing_id, token) VALUES ($1, $2)
+        ON CONFLICT (listing_id) DO UPDATE SET token = calendar_export_feeds.token
+        RETURNING token
+        "#
+    };
+    sqlx::query_scalar::<_, String>(query)
+        .bind(listing_id)
+        .bind(generate_token())
+        .fetch_one(pool)
+        .await
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/calendar/:listing_id/export.ics?token= - iCalendar feed of booked and blocked days
+///
+/// Authenticated by the feed token alone, so other platforms can poll it. Stay restrictions are
+/// included as `X-KAMER-STAY-RESTRICTION` properties, which other platforms ignore.
+#[get("/{listing_id}/export.ics")]
+pub async fn export_calendar(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<ExportFeedQuery>,
+) -> impl Responder {
+    let listing_id = path.into_inner();
+
+    let feed = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        SELECT f.token, l.title
+        FROM calendar_export_feeds f
+        JOIN listings l ON f.listing_id = l.id
+        WHERE f.listing_id = $1
+        "#,
+    )
+    .bind(&listing_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    // Unknown listings and wrong tokens look the same
+    let title = match feed {
+        Ok(Some((token, title)))
+            if query
+                .token
+                .as_deref()
+                .is_some_and(|given| tokens_match(&token, given)) =>
+        {
+            title
+        }
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Calendar feed not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch calendar feed: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let bookings = sqlx::query_as::<_, ExportedBooking>(
+        r#"
+        SELECT id, check_in, check_out, COALESCE(updated_at, created_at) as stamp
+        FROM bookings
+        WHERE listing_id = $1
+          AND status IN ('confirmed', 'awaiting_payment', 'completed')
+          AND check_out >= CURRENT_DATE - $2
+        ORDER BY check_in, id
+        "#,
+    )
+    .bind(&listing_id)
+    .bind(EXPORT_HISTORY_DAYS)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let blocked = sqlx::query_as::<_, BlockedDay>(
+        r#"
+        SELECT date, COALESCE(updated_at, created_at) as updated_at
+        FROM calendar_pricing
+        WHERE listing_id = $1 AND is_available = FALSE AND date >= CURRENT_DATE - $2
+        ORDER BY date
+        "#,
+    )
+    .bind(&listing_id)
+    .bind(EXPORT_HISTORY_DAYS)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let restricted = sqlx::query_as::<_, RestrictedDay>(
+        r#"
+        SELECT date, closed_to_arrival, closed_to_departure, min_nights_override
+        FROM calendar_pricing
+        WHERE listing_id = $1 AND date >= CURRENT_DATE - $2
+          AND (closed_to_arrival OR closed_to_departure OR min_nights_override IS NOT NULL)
+        ORDER BY date
+        "#,
+    )
+    .bind(&listing_id)
+    .bind(EXPORT_HISTORY_DAYS)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    let (bookings, blocked, restricted) = match (bookings, blocked, restricted) {
+        (Ok(bookings), Ok(blocked), Ok(restricted)) => (bookings, blocked, restricted),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            log::error!("Failed to fetch calendar export: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // DTSTAMP comes from the row, not the clock, so an unchanged calendar renders identically
+    let epoch = NaiveDateTime::default();
+    let mut events: Vec<CalendarEvent> = bookings
+        .into_iter()
+        .map(|booking| CalendarEvent {
+            uid: format!("booking-{}@{}", booking.id, UID_DOMAIN),
+            summary: "Reserved",
+            start: booking.check_in,
+            end: booking.check_out,
+            stamp: booking.stamp.unwrap_or(epoch),
+        })
+        .collect();
+    events.extend(
+        blocked_ranges(&blocked)
+            .into_iter()
+            .map(|range| CalendarEvent {
+                uid: format!(
+                    "blocked-{}-{}@{}",
+                    listing_id,
+                    format_date(range.start),
+                    UID_DOMAIN
+                ),
+                summary: "Not available",
+                start: range.start,
+                end: range.end,
+                stamp: range.stamp.unwrap_or(epoch),
+            }),
+    );
+    events.sort_by_key(|event| event.start);
+
+    let name = title.unwrap_or_else(|| "Kamer listing".to_string());
+    let body = render_calendar(&name, &restriction_notes(&restricted), &events);
+    let etag = format!("\"{}\"", hex::encode(sha1::Sha1::digest(body.as_bytes())));
+
+    if let Some(tag) = req.headers().get(actix_web::http::header::IF_NONE_MATCH) {
+        if tag.to_str().ok() == Some(etag.as_str()) {
+            return HttpResponse::NotModified().finish();
+        }
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header((actix_web::http::header::ETAG, etag))
+        .insert_header(("Cache-Control", "private, max-age=0, must-revalidate"))
+        .insert_header((
+            actix_web::http::header::CONTENT_DISPOSITION,
+            "inline; filename=\"calendar.ics\"",
+        ))
+        .body(body)
+}
+
+/// GET /api/calendar/:listing_id/export - URL of the listing's iCalendar feed (host)
+#[get("/{listing_id}/export")]
+pub async fn get_export_feed(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    match ensure_feed_token(pool.get_ref(), &listing_id, false).await {
+        Ok(token) => HttpResponse::Ok()
+            .json(serde_json::json!({ "url": feed_url(&req, &listing_id, &token) })),
+        Err(e) => {
+            log::error!("Failed to create calendar feed token: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST /api/calendar/:listing_id/export/regenerate - Replace the feed token (host)
+///
+/// The old URL stops working, so other platforms must be given the new one.
+#[post("/{listing_id}/export/regenerate")]
+pub async fn regenerate_export_feed(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    match ensure_feed_token(pool.get_ref(), &listing_id, true).await {
+        Ok(token) => HttpResponse::Ok()
+            .json(serde_json::json!({ "url": feed_url(&req, &listing_id, &token) })),
+        Err(e) => {
+            log::error!("Failed to regenerate calendar feed token: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn stamp(date: &str) -> NaiveDateTime {
+        day(date).and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn long_lines_fold_at_75_octets_without_splitting_characters() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+        let mut out = String::new();
+        push_line(&mut out, &line);
+
+        let physical: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert!(physical.len() > 1);
+        assert!(physical.iter().all(|part| part.len() <= MAX_LINE_OCTETS));
+        assert!(physical[1..].iter().all(|part| part.starts_with(' ')));
+        let unfolded: String = physical
+            .iter()
+            .enumerate()
+            .map(|(i, part)| if i == 0 { *part } else { &part[1..] })
+            .collect();
+        assert_eq!(unfolded, line);
+
+        let mut short = String::new();
+        push_line(&mut short, &"x".repeat(MAX_LINE_OCTETS));
+        assert_eq!(short, format!("{}\r\n", "x".repeat(MAX_LINE_OCTETS)));
+    }
+
+    #[test]
+    fn text_is_escaped_and_read_back() {
+        let text = "Villa, pool; \\ view\r\nSecond line";
+        let escaped = escape_text(text);
+        assert_eq!(escaped, r"Villa\, pool\; \\ view\nSecond line");
+        assert_eq!(unescape_text(&escaped), "Villa, pool; \\ view\nSecond line");
+    }
+
+    #[test]
+    fn consecutive_blocked_days_merge_into_ranges() {
+        let days = [
+            BlockedDay {
+                date: day("2026-12-24"),
+                updated_at: Some(stamp("2026-11-01")),
+            },
+            BlockedDay {
+                date: day("2026-12-25"),
+                updated_at: Some(stamp("2026-11-03")),
+            },
+            BlockedDay {
+                date: day("2026-12-28"),
+                updated_at: None,
+            },
+        ];
+        let ranges = blocked_ranges(&days);
+        let spans: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (day("2026-12-24"), day("2026-12-26")),
+                (day("2026-12-28"), day("2026-12-29")),
+            ]
+        );
+        assert_eq!(ranges[0].stamp, Some(stamp("2026-11-03")));
+    }
+
+    #[test]
+    fn restrictions_merge_by_kind_and_stay_out_of_events() {
+        let restricted = |date: &str, min_nights_override| RestrictedDay {
+            date: day(date),
+            closed_to_arrival: false,
+            closed_to_departure: false,
+            min_nights_override,
+        };
+        let notes = restriction_notes(&[
+            restricted("2026-12-30", Some(3)),
+            restricted("2026-12-31", Some(3)),
+            restricted("2027-01-01", Some(2)),
+        ]);
+        let spans: Vec<_> = notes
+            .iter()
+            .map(|note| (note.start, note.end, note.notes.as_str()))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (
+                    day("2026-12-30"),
+                    day("2027-01-01"),
+                    "Minimum stay 3 nights"
+                ),
+                (
+                    day("2027-01-01"),
+                    day("2027-01-02"),
+                    "Minimum stay 2 nights"
+                ),
+            ]
+        );
+
+        let body = render_calendar("Flat", &notes, &[]);
+        assert!(body.replace("\r\n ", "").contains(
+            "X-KAMER-STAY-RESTRICTION;X-START=20261230;X-END=20270101:Minimum stay 3 nights\r\n"
+        ));
+        assert!(!body.contains("BEGIN:VEVENT"));
+        assert!(parse_events(&body).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rendered_bookings_read_back_as_the_same_range() {
+        let events = [CalendarEvent {
+            uid: "booking-b1@kamer".to_string(),
+            summary: "Reserved",
+            start: day("2027-03-10"),
+            end: day("2027-03-14"),
+            stamp: stamp("2027-01-05"),
+        }];
+        let body = render_calendar("Studio, Bonapriso; sea view", &[], &events);
+        assert!(body.contains("X-WR-CALNAME:Studio\\, Bonapriso\\; sea view\r\n"));
+        assert!(body.contains("DTSTAMP:20270105T120000Z\r\n"));
+
+        assert_eq!(
+            parse_events(&body).unwrap(),
+            [ImportedEvent {
+                uid: "booking-b1@kamer".to_string(),
+                start: day("2027-03-10"),
+                end: day("2027-03-14"),
+                summary: Some("Reserved".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn other_platforms_feeds_are_read() {
+        let body = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:abc\r\n\
+            DTSTART:20270301T150000Z\r\n\
+            DURATION:P1W\r\n\
+            SUMMARY:Airbnb \r\n (Not available)\r\n\
+            BEGIN:VALARM\r\n\
+            UID:alarm\r\n\
+            END:VALARM\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            DTSTART;VALUE=DATE:20270401\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:cancelled\r\n\
+            DTSTART;VALUE=DATE:20270501\r\n\
+            STATUS:CANCELLED\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:free\r\n\
+            DTSTART;VALUE=DATE:20270601\r\n\
+            TRANSP:TRANSPARENT\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        assert_eq!(
+            parse_events(body).unwrap(),
+            [
+                ImportedEvent {
+                    uid: "abc".to_string(),
+                    start: day("2027-03-01"),
+                    end: day("2027-03-08"),
+                    summary: Some("Airbnb (Not available)".to_string()),
+                },
+                ImportedEvent {
+                    uid: "20270401-20270402".to_string(),
+                    start: day("2027-04-01"),
+                    end: day("2027-04-02"),
+                    summary: None,
+                },
+            ]
+        );
+        assert!(parse_events("<html>Not found</html>").is_err());
+    }
+}
@@ -0,0 +1,884 @@
+use crate::ical::{parse_events, ImportedEvent};
+use crate::routes::{verify_listing_ownership, CalendarQuery};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Job kind: fetch external calendar feeds that are due and refresh their blocks.
+pub const SYNC_CALENDAR_IMPORTS: &str = "calendar.sync_imports";
+
+/// Feeds are refreshed when their last attempt is older than this.
+const SYNC_INTERVAL_MINUTES: i32 = 30;
+/// Feeds claimed per job run, so one run cannot hold the runner for long.
+const MAX_FEEDS_PER_RUN: i64 = 50;
+/// Larger feeds are rejected rather than partially imported.
+const MAX_FEED_BYTES: usize = 5 * 1024 * 1024;
+const MAX_EVENTS_PER_FEED: usize = 5000;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(20);
+const MAX_REDIRECTS: usize = 5;
+
+// ============================================================================
+// Fetching
+// ============================================================================
+
+#[derive(Debug)]
+pub struct FetchError(pub String);
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        FetchError(err.to_string())
+    }
+}
+
+/// Downloads calendar feeds. Swapped out for a local file server in tests.
+#[async_trait]
+pub trait CalendarFetcher: Send + Sync {
+    async fn fetch(&self, url: &str) -> Result<String, FetchError>;
+}
+
+/// Fetches feeds over HTTP(S).
+///
+/// Feed URLs come from hosts, so only public addresses are fetched. Each request connects to the
+/// addresses checked by [`resolve_public`], and redirects are followed here one hop at a time so
+/// every hop is checked the same way.
+#[derive(Clone, Default)]
+pub struct HttpCalendarFetcher;
+
+impl HttpCalendarFetcher {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// A client that reaches `url`'s host only at its checked public addresses.
+    async fn client_for(url: &reqwest::Url) -> Result<reqwest::Client, FetchError> {
+        check_feed_url(url).map_err(|message| FetchError(message.to_string()))?;
+
+        let mut builder = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .user_agent("Kamer calendar sync")
+            .redirect(reqwest::redirect::Policy::none())
+            // A proxy would resolve the host itself, past our checks
+            .no_proxy();
+        if let Some(domain) = url.domain() {
+            let port = url.port_or_known_default().unwrap_or(443);
+            let addrs = resolve_public(domain, port).await?;
+            builder = builder.resolve_to_addrs(domain, &addrs);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[async_trait]
+impl CalendarFetcher for HttpCalendarFetcher {
+    async fn fetch(&self, url: &str) -> Result<String, FetchError> {
+        let mut url = reqwest::Url::parse(url).map_err(|e| FetchError(e.to_string()))?;
+        let mut redirects = 0;
+        let mut response = loop {
+            let client = Self::client_for(&url).await?;
+            let response = client.get(url.clone()).send().await?;
+            if !response.status().is_redirection() {
+                break response.error_for_status()?;
+            }
+
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(FetchError("Too many redirects".to_string()));
+            }
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| FetchError("Redirect without a location".to_string()))?;
+            url = url
+                .join(location)
+                .map_err(|e| FetchError(format!("Invalid redirect: {}", e)))?;
+        };
+
+        if response
+            .content_length()
+            .is_some_and(|len| len > MAX_FEED_BYTES as u64)
+        {
+            return Err(FetchError("Calendar is too large".to_string()));
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > MAX_FEED_BYTES {
+                return Err(FetchError("Calendar is too large".to_string()));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+}
+
+/// Look up `host`, failing unless all of its addresses are public.
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, FetchError> {
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| FetchError(format!("Could not resolve {}: {}", host, e)))?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(FetchError(format!("Could not resolve {}", host)));
+    }
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(FetchError(
+            "Calendar URL must point to a public address".to_string(),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Whether an address is reachable on the public internet, as opposed to this machine, the
+/// private network it sits on, or ranges reserved for other uses.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network", carrier-grade NAT, IETF protocol assignments, benchmarking, reserved
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+        || (a == 192 && b == 0 && c == 0)
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(ip) = embedded_ipv4(ip) {
+        return is_public_ipv4(ip);
+    }
+    let [first, second, ..] = ip.segments();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, link-local, documentation
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        || (first == 0x2001 && second == 0x0db8))
+}
+
+/// The IPv4 address a NAT64 (64:ff9b::/96), 6to4 (2002::/16) or IPv4-compatible (::a.b.c.d)
+/// address routes to, so it gets the same checks as the address itself.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let octets = ip.octets();
+    let [a, b, c, d, e, f, ..] = ip.segments();
+    let tail =
+        |at: usize| Ipv4Addr::new(octets[at], octets[at + 1], octets[at + 2], octets[at + 3]);
+    match (a, b, c, d, e, f) {
+        (0x64, 0xff9b, 0, 0, 0, 0) | (0, 0, 0, 0, 0, 0) => Some(tail(12)),
+        (0x2002, ..) => Some(tail(2)),
+        _ => None,
+    }
+}
+
+/// Check the parts of a feed URL that are known without a lookup: the scheme, and the address
+/// when the host is written as one.
+fn check_feed_url(url: &reqwest::Url) -> Result<(), &'static str> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("Calendar URL must be an http(s) or webcal address");
+    }
+    let host = url.host_str().ok_or("Calendar URL must have a host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) if !is_public_ip(ip) => Err("Calendar URL must point to a public address"),
+        _ => Ok(()),
+    }
+}
+
+/// Accept http(s) URLs, reading the `webcal://` scheme calendar apps hand out as https.
+///
+/// The host must resolve to public addresses only; feeds are fetched from inside our network.
+async fn normalize_feed_url(url: &str) -> Result<String, String> {
+    let url = url.trim();
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    if !(url.starts_with("https://") || url.starts_with("http://")) || url.len() > 2048 {
+        return Err("Calendar URL must be an http(s) or webcal address".to_string());
+    }
+
+    let parsed =
+        reqwest::Url::parse(&url).map_err(|_| "Calendar URL is not a valid address".to_string())?;
+    check_feed_url(&parsed)?;
+    if let Some(host) = parsed.domain() {
+        let port = parsed.port_or_known_default().unwrap_or(443);
+        resolve_public(host, port).await.map_err(|e| e.0)?;
+    }
+    Ok(url)
+}
+
+// ============================================================================
+// Sync
+// ============================================================================
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ImportFeed {
+    pub id: String,
+    pub listing_id: String,
+    pub name: Option<String>,
+    pub url: String,
+    pub status: String,
+    pub last_error: Option<String>,
+    pub event_count: i32,
+    pub last_synced_at: Option<NaiveDateTime>,
+    pub last_success_at: Option<NaiveDateTime>,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExternalBlock {
+    pub feed_id: String,
+    pub feed_name: Option<String>,
+    pub uid: String,
+    pub start_date: NaiveDate,
+    /// Exclusive, like a booking's check-out.
+    pub end_date: NaiveDate,
+    pub summary: Option<String>,
+}
+
+const IMPORT_FEED_COLUMNS: &str = r#"
+    id, listing_id, name, url, status, last_error, event_count, last_synced_at,
+    last_success_at, created_at
+"#;
+
+/// Replace the feed's blocks with `events`, dropping those already over.
+async fn store_events(
+    pool: &PgPool,
+    feed: &ImportFeed,
+    events: &[ImportedEvent],
+) -> Result<usize, sqlx::Error> {
+    let today = chrono::Utc::now().date_naive();
+    let upcoming: Vec<&ImportedEvent> = events.iter().filter(|e| e.end > today).collect();
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM external_blocks WHERE feed_id = $1")
+        .bind(&feed.id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Stay well under the bind-parameter limit
+    for chunk in upcoming.chunks(1000) {
+        let mut insert: QueryBuilder<Postgres> = QueryBuilder::new(
+            "INSERT INTO external_blocks (feed_id, listing_id, uid, start_date, end_date, summary) ",
+        );
+        insert.push_values(chunk, |mut row, event| {
+            row.push_bind(&feed.id)
+                .push_bind(&feed.listing_id)
+                .push_bind(&event.uid)
+                .push_bind(event.start)
+                .push_bind(event.end)
+                .push_bind(&event.summary);
+        });
+        insert.build().execute(&mut *tx).await?;
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE calendar_import_feeds
+        SET status = 'ok', last_error = NULL, event_count = $2,
+            last_success_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(&feed.id)
+    .bind(upcoming.len() as i32)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(upcoming.len())
+}
+
+/// Fetch one feed and replace its blocks. Events gone from the feed are unblocked.
+///
+/// A feed that cannot be fetched or read keeps its previous blocks and is marked `error`,
+/// so an outage on the other platform does not reopen dates booked there.
+pub async fn sync_feed(
+    pool: &PgPool,
+    fetcher: &dyn CalendarFetcher,
+    feed: &ImportFeed,
+) -> Result<(), sqlx::Error> {
+    let events = match fetcher.fetch(&feed.url).await {
+        Ok(body) => parse_events(&body).and_then(|events| {
+            if events.len() > MAX_EVENTS_PER_FEED {
+                Err(format!(
+                    "Calendar has more than {} events",
+                    MAX_EVENTS_PER_FEED
+                ))
+            } else {
+                Ok(events)
+            }
+        }),
+        Err(e) => Err(e.to_string()),
+    };
+
+    match events {
+        Ok(events) => {
+            store_events(pool, feed, &events).await?;
+        }
+        Err(message) => {
+            log::warn!("Calendar import {} failed: {}", feed.id, message);
+            sqlx::query(
+                r#"
+                UPDATE calendar_import_feeds
+                SET status = 'error', last_error = $2, updated_at = CURRENT_TIMESTAMP
+                WHERE id = $1
+                "#,
+            )
+            .bind(&feed.id)
+            .bind(&message)
+            .execute(pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Sync feeds never synced or last attempted more than `SYNC_INTERVAL_MINUTES` ago.
+/// Feeds are claimed with SKIP LOCKED so concurrent runners split the work.
+pub async fn sync_due_feeds(
+    pool: &PgPool,
+    fetcher: &dyn CalendarFetcher,
+) -> Result<u64, sqlx::Error> {
+    let feeds = sqlx::query_as::<_, ImportFeed>(&format!(
+        r#"
+        UPDATE calendar_import_feeds
+        SET last_synced_at = CURRENT_TIMESTAMP
+        WHERE id IN (
+            SELECT id FROM calendar_import_feeds
+            WHERE last_synced_at IS NULL
+               OR last_synced_at < NOW() - make_interval(mins => $1)
+            ORDER BY last_synced_at NULLS FIRST
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING {}
+        "#,
+        IMPORT_FEED_COLUMNS
+    ))
+    .bind(SYNC_INTERVAL_MINUTES)
+    .bind(MAX_FEEDS_PER_RUN)
+    .fetch_all(pool)
+    .await?;
+
+    // Feeds are already claimed, so one failure must not leave the rest until their next turn
+    for feed in &feeds {
+        if let Err(e) = sync_feed(pool, fetcher, feed).await {
+            log::error!("Failed to sync calendar import {}: {:?}", feed.id, e);
+        }
+    }
+
+    if !feeds.is_empty() {
+        log::info!("Synced {} calendar imports", feeds.len());
+    }
+    Ok(feeds.len() as u64)
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct CreateImportFeedRequest {
+    pub url: String,
+    pub name: Option<String>,
+}
+
+/// GET /api/calendar/:listing_id/imports - External calendars synced into this listing
+#[get("/{listing_id}/imports")]
+pub async fn get_import_feeds(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let feeds = sqlx::query_as::<_, ImportFeed>(&format!(
+        "SELECT {} FROM calendar_import_feeds WHERE listing_id = $1 ORDER BY created_at",
+        IMPORT_FEED_COLUMNS
+    ))
+    .bind(&listing_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match feeds {
+        Ok(feeds) => HttpResponse::Ok().json(feeds),
+        Err(e) => {
+            log::error!("Failed to fetch calendar imports: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST /api/calendar/:listing_id/imports - Register an external calendar URL
+///
+/// The feed is fetched by the next sync run, within a few minutes.
+#[post("/{listing_id}/imports")]
+pub async fn create_import_feed(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<CreateImportFeedRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let url = match normalize_feed_url(&body.url).await {
+        Ok(url) => url,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+    let name = body
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+
+    let feed = sqlx::query_as::<_, ImportFeed>(&format!(
+        r#"
+        INSERT INTO calendar_import_feeds (id, listing_id, name, url)
+        VALUES ($1, $2, $3, $4)
+        RETURNING {}
+        "#,
+        IMPORT_FEED_COLUMNS
+    ))
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(&listing_id)
+    .bind(name)
+    .bind(&url)
+    .fetch_one(pool.get_ref())
+    .await;
+
+    match feed {
+        Ok(feed) => HttpResponse::Ok().json(feed),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": "This calendar is already imported" })),
+        Err(e) => {
+            log::error!("Failed to create calendar import: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to add calendar" }))
+        }
+    }
+}
+
+/// POST /api/calendar/:listing_id/imports/:feed_id/sync - Queue a feed for the next sync run
+#[post("/{listing_id}/imports/{feed_id}/sync")]
+pub async fn sync_import_feed(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let (listing_id, feed_id) = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let result = sqlx::query(
+        r#"
+        UPDATE calendar_import_feeds
+        SET last_synced_at = NULL, status = 'pending', updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND listing_id = $2
+        "#,
+    )
+    .bind(&feed_id)
+    .bind(&listing_id)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => HttpResponse::NotFound()
+            .json(serde_json::json!({ "error": "Calendar import not found" })),
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "pending" })),
+        Err(e) => {
+            log::error!("Failed to queue calendar import: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// DELETE /api/calendar/:listing_id/imports/:feed_id - Stop syncing a calendar and unblock its dates
+#[delete("/{listing_id}/imports/{feed_id}")]
+pub async fn delete_import_feed(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let (listing_id, feed_id) = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let result = sqlx::query("DELETE FROM calendar_import_feeds WHERE id = $1 AND listing_id = $2")
+        .bind(&feed_id)
+        .bind(&listing_id)
+        .execute(pool.get_ref())
+        .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => HttpResponse::NotFound()
+            .json(serde_json::json!({ "error": "Calendar import not found" })),
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "deleted" })),
+        Err(e) => {
+            log::error!("Failed to delete calendar import: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to delete calendar import" }))
+        }
+    }
+}
+
+/// GET /api/calendar/:listing_id/external-blocks - Dates blocked by imported calendars
+#[get("/{listing_id}/external-blocks")]
+pub async fn get_external_blocks(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<CalendarQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let (start_date, end_date) = match (
+        NaiveDate::parse_from_str(&query.start_date, "%Y-%m-%d"),
+        NaiveDate::parse_from_str(&query.end_date, "%Y-%m-%d"),
+    ) {
+        (Ok(start), Ok(end)) => (start, end),
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid date format" }));
+        }
+    };
+
+    let blocks = sqlx::query_as::<_, ExternalBlock>(
+        r#"
+        SELECT b.feed_id, f.name as feed_name, b.uid, b.start_date, b.end_date, b.summary
+        FROM external_blocks b
+        JOIN calendar_import_feeds f ON b.feed_id = f.id
+        WHERE b.listing_id = $1 AND b.start_date <= $3 AND b.end_date > $2
+        ORDER BY b.start_date, b.feed_id
+        "#,
+    )
+    .bind(&listing_id)
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match blocks {
+        Ok(blocks) => HttpResponse::Ok().json(blocks),
+        Err(e) => {
+            log::error!("Failed to fetch external blocks: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(ip: &str) -> bool {
+        is_public_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn private_and_reserved_ipv4_are_rejected() {
+        assert!(public("93.184.216.34"));
+        for ip in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "198.18.0.1",
+            "255.255.255.255",
+        ] {
+            assert!(!public(ip), "{ip}");
+        }
+    }
+
+    #[test]
+    fn private_and_reserved_ipv6_are_rejected() {
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+        for ip in ["::", "::1", "fc00::1", "fe80::1", "ff02::1", "2001:db8::1"] {
+            assert!(!public(ip), "{ip}");
+        }
+    }
+
+    #[test]
+    fn embedded_ipv4_addresses_are_checked() {
+        for ip in [
+            // IPv4-mapped
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            // NAT64
+            "64:ff9b::7f00:1",
+            "64:ff9b::10.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            // 6to4
+            "2002:7f00:1::",
+            "2002:a00:1::1",
+            "2002:a9fe:a9fe::",
+            // IPv4-compatible
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "::169.254.169.254",
+        ] {
+            assert!(!public(ip), "{ip}");
+        }
+        assert!(public("::ffff:93.184.216.34"));
+        assert!(public("64:ff9b::93.184.216.34"));
+        assert!(public("2002:5db8:d822::1"));
+    }
+
+    #[test]
+    fn feed_urls_with_literal_private_addresses_are_refused() {
+        let check = |url: &str| check_feed_url(&reqwest::Url::parse(url).unwrap());
+        assert!(check("https://93.184.216.34/cal.ics").is_ok());
+        assert!(check("https://calendar.example.com/cal.ics").is_ok());
+        assert!(check("http://[64:ff9b::a9fe:a9fe]/latest").is_err());
+        assert!(check("http://[2002:7f00:1::]/").is_err());
+        assert!(check("ftp://93.184.216.34/cal.ics").is_err());
+    }
+
+    /// Serves feeds from local files, standing in for the other platform.
+    struct FileFetcher;
+
+    #[async_trait]
+    impl CalendarFetcher for FileFetcher {
+        async fn fetch(&self, url: &str) -> Result<String, FetchError> {
+            let path = url.strip_prefix("file://").unwrap_or(url);
+            std::fs::read_to_string(path).map_err(|e| FetchError(e.to_string()))
+        }
+    }
+
+    async fn test_pool() -> PgPool {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is set");
+        PgPool::connect(&url)
+            .await
+            .expect("TEST_DATABASE_URL is reachable")
+    }
+
+    fn ics(events: &[(&str, NaiveDate, NaiveDate)]) -> String {
+        let mut body = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n");
+        for (uid, start, end) in events {
+            body.push_str(&format!(
+                "BEGIN:VEVENT\r\nUID:{}\r\nDTSTART;VALUE=DATE:{}\r\nDTEND;VALUE=DATE:{}\r\nSUMMARY:Reserved\r\nEND:VEVENT\r\n",
+                uid,
+                start.format("%Y%m%d"),
+                end.format("%Y%m%d")
+            ));
+        }
+        body.push_str("END:VCALENDAR\r\n");
+        body
+    }
+
+    /// A listing with one import feed read from a file of its own; returns the feed.
+    async fn feed_with_file(pool: &PgPool) -> (ImportFeed, std::path::PathBuf) {
+        let id = uuid::Uuid::new_v4().to_string();
+        let host_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, email) VALUES ($1, $1 || '@host.test') RETURNING id",
+        )
+        .bind(format!("host-{}", id))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO listings (id, host_id, status, title) VALUES ($1, $2, 'published', 'Test flat')",
+        )
+        .bind(&id)
+        .bind(host_id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let path = std::env::temp_dir().join(format!("kamer-feed-{}.ics", id));
+        let feed = sqlx::query_as::<_, ImportFeed>(&format!(
+            r#"
+            INSERT INTO calendar_import_feeds (id, listing_id, name, url)
+            VALUES ($1, $1, 'Airbnb', $2)
+            RETURNING {}
+            "#,
+            IMPORT_FEED_COLUMNS
+        ))
+        .bind(&id)
+        .bind(format!("file://{}", path.display()))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        (feed, path)
+    }
+
+    async fn blocks(pool: &PgPool, feed: &ImportFeed) -> Vec<(String, NaiveDate, NaiveDate)> {
+        sqlx::query_as(
+            "SELECT uid, start_date, end_date FROM external_blocks WHERE feed_id = $1 ORDER BY start_date",
+        )
+        .bind(&feed.id)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn status(pool: &PgPool, feed: &ImportFeed) -> (String, Option<String>, i32) {
+        sqlx::query_as(
+            "SELECT status, last_error, event_count FROM calendar_import_feeds WHERE id = $1",
+        )
+        .bind(&feed.id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database in TEST_DATABASE_URL"]
+    async fn first_import_blocks_dates() {
+        let pool = test_pool().await;
+        let (feed, path) = feed_with_file(&pool).await;
+        let today = chrono::Utc::now().date_naive();
+        let (start, end) = (
+            today + chrono::Duration::days(10),
+            today + chrono::Duration::days(13),
+        );
+        std::fs::write(
+            &path,
+            ics(&[
+                ("stay-1", start, end),
+                // Already over, so not stored
+                (
+                    "past",
+                    today - chrono::Duration::days(5),
+                    today - chrono::Duration::days(2),
+                ),
+            ]),
+        )
+        .unwrap();
+
+        sync_feed(&pool, &FileFetcher, &feed).await.unwrap();
+
+        assert_eq!(
+            blocks(&pool, &feed).await,
+            [("stay-1".to_string(), start, end)]
+        );
+        assert_eq!(status(&pool, &feed).await, ("ok".to_string(), None, 1));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database in TEST_DATABASE_URL"]
+    async fn events_removed_upstream_are_unblocked() {
+        let pool = test_pool().await;
+        let (feed, path) = feed_with_file(&pool).await;
+        let today = chrono::Utc::now().date_naive();
+        let first = (
+            today + chrono::Duration::days(10),
+            today + chrono::Duration::days(13),
+        );
+        let second = (
+            today + chrono::Duration::days(20),
+            today + chrono::Duration::days(22),
+        );
+        std::fs::write(
+            &path,
+            ics(&[("stay-1", first.0, first.1), ("stay-2", second.0, second.1)]),
+        )
+        .unwrap();
+        sync_feed(&pool, &FileFetcher, &feed).await.unwrap();
+        assert_eq!(blocks(&pool, &feed).await.len(), 2);
+
+        std::fs::write(&path, ics(&[("stay-2", second.0, second.1)])).unwrap();
+        sync_feed(&pool, &FileFetcher, &feed).await.unwrap();
+
+        assert_eq!(
+            blocks(&pool, &feed).await,
+            [("stay-2".to_string(), second.0, second.1)]
+        );
+        assert_eq!(status(&pool, &feed).await.2, 1);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a migrated database in TEST_DATABASE_URL"]
+    async fn unreachable_feeds_keep_their_blocks() {
+        let pool = test_pool().await;
+        let (feed, path) = feed_with_file(&pool).await;
+        let today = chrono::Utc::now().date_naive();
+        let (start, end) = (
+            today + chrono::Duration::days(10),
+            today + chrono::Duration::days(13),
+        );
+        std::fs::write(&path, ics(&[("stay-1", start, end)])).unwrap();
+        sync_feed(&pool, &FileFetcher, &feed).await.unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        sync_feed(&pool, &FileFetcher, &feed).await.unwrap();
+
+        assert_eq!(blocks(&pool, &feed).await.len(), 1);
+        let (status, last_error, _) = status(&pool, &feed).await;
+        assert_eq!(status, "error");
+        assert!(last_error.is_some());
+    }
+}
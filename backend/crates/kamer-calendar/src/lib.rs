@@ -1,4 +1,22 @@
+pub mod availability;
+pub mod ical;
+pub mod imports;
+pub mod multi;
+pub mod pricing_rules;
 pub mod routes;
+pub mod settings;
+pub mod smart_pricing;
 
 // Re-export all route handlers
+pub use ical::{export_calendar, get_export_feed, regenerate_export_feed};
+pub use imports::{
+    create_import_feed, delete_import_feed, get_external_blocks, get_import_feeds, sync_import_feed,
+};
+pub use multi::{bulk_update_calendar_dates, get_multi_calendar};
+pub use pricing_rules::{
+    create_pricing_rule, delete_pricing_rule, get_pricing_rules, nightly_prices,
+    nightly_prices_for, update_pricing_rule, AppliedRule, NightPrice, PricingError,
+};
 pub use routes::*;
+pub use settings::{AdvanceNotice, CutoffTime, Discount, PreparationTime};
+pub use smart_pricing::{PriceFactor, Suggestion};
@@ -0,0 +1,378 @@
+use crate::pricing_rules::{nightly_prices_for, NightPrice};
+use crate::routes::{apply_calendar_update, CalendarUpdateError, UpdateCalendarDatesRequest};
+use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, NaiveDate};
+use kamer_core::Currency;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// Longest range the multi-calendar shows at once.
+const MAX_MULTI_RANGE_DAYS: i64 = 93;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+/// Dates are inclusive. Defaults to the next 31 days.
+#[derive(Debug, Deserialize)]
+pub struct MultiCalendarQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// One listing's row in the host's multi-calendar.
+#[derive(Debug, Serialize)]
+pub struct MultiCalendarListing {
+    pub listing_id: String,
+    pub title: Option<String>,
+    pub status: String,
+    pub currency: Currency,
+    pub days: Vec<MultiCalendarDay>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MultiCalendarDay {
+    pub date: NaiveDate,
+    /// Whether the night is free to book: not blocked, booked or busy on another platform.
+    pub available: bool,
+    /// Blocked by the host on this calendar.
+    pub blocked: bool,
+    /// Busy on a calendar imported from another platform.
+    pub externally_blocked: bool,
+    pub closed_to_arrival: bool,
+    pub closed_to_departure: bool,
+    pub min_nights_override: Option<i32>,
+    /// Effective price for a one-night stay, in the listing's currency.
+    pub price: f64,
+    /// `base`, `weekend`, `calendar`, `rule` or `smart`.
+    pub price_source: String,
+    /// The booking occupying the night, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub booking: Option<NightBooking>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NightBooking {
+    pub booking_id: String,
+    pub guest_name: Option<String>,
+    pub status: String,
+    pub check_in: NaiveDate,
+    pub check_out: NaiveDate,
+}
+
+/// A listing-day of the grid, as read from the database.
+#[derive(Debug, sqlx::FromRow)]
+struct MultiCalendarRow {
+    listing_id: String,
+    title: Option<String>,
+    status: String,
+    currency: Currency,
+    date: NaiveDate,
+    blocked: bool,
+    externally_blocked: bool,
+    closed_to_arrival: bool,
+    closed_to_departure: bool,
+    min_nights_override: Option<i32>,
+    booking_id: Option<String>,
+    guest_name: Option<String>,
+    booking_status: Option<String>,
+    check_in: Option<NaiveDate>,
+    check_out: Option<NaiveDate>,
+}
+
+/// The same change applied to several listings' calendars.
+#[derive(Debug, Deserialize)]
+pub struct BulkUpdateCalendarRequest {
+    pub listing_ids: Vec<String>,
+    #[serde(flatten)]
+    pub update: UpdateCalendarDatesRequest,
+}
+
+/// Every listing of the host against every day of the range, with the booking occupying it.
+const MULTI_CALENDAR_QUERY: &str = r#"
+    SELECT
+        l.id as listing_id, l.title, l.status, COALESCE(l.currency, 'XAF') as currency,
+        d::DATE as date,
+        COALESCE(cp.is_available = FALSE, FALSE) as blocked,
+        EXISTS (
+            SELECT 1 FROM external_blocks e
+            WHERE e.listing_id = l.id AND e.start_date <= d::DATE AND e.end_date > d::DATE
+        ) as externally_blocked,
+        COALESCE(cp.closed_to_arrival, FALSE) as closed_to_arrival,
+        COALESCE(cp.closed_to_departure, FALSE) as closed_to_departure,
+        cp.min_nights_override,
+        b.id as booking_id, u.username as guest_name, b.status as booking_status,
+        b.check_in, b.check_out
+    FROM listings l
+    CROSS JOIN generate_series($2::DATE, $3::DATE, INTERVAL '1 day') AS d
+    LEFT JOIN calendar_pricing cp ON cp.listing_id = l.id AND cp.date = d::DATE
+    LEFT JOIN LATERAL (
+        SELECT b.id, b.guest_id, b.status, b.check_in, b.check_out
+        FROM bookings b
+        WHERE b.listing_id = l.id
+          AND b.status IN ('confirmed', 'awaiting_payment', 'completed')
+          AND b.check_in <= d::DATE AND b.check_out > d::DATE
+        ORDER BY b.check_in
+        LIMIT 1
+    ) b ON TRUE
+    LEFT JOIN users u ON u.id = b.guest_id
+    WHERE l.host_id = $1
+    ORDER BY l.created_at, l.id, d
+"#;
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/calendar/multi - Availability, prices and bookings of all the host's listings
+#[get("/multi")]
+pub async fn get_multi_calendar(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<MultiCalendarQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let from = query
+        .from
+        .unwrap_or_else(|| chrono::Utc::now().date_naive());
+    let to = query.to.unwrap_or(from + Duration::days(30));
+    if to < from {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "to must not be before from" }));
+    }
+    if (to - from).num_days() >= MAX_MULTI_RANGE_DAYS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("A range can cover at most {} days", MAX_MULTI_RANGE_DAYS)
+        }));
+    }
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to fetch multi-calendar: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let rows = match sqlx::query_as::<_, MultiCalendarRow>(MULTI_CALENDAR_QUERY)
+        .bind(user_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&mut *conn)
+        .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            log::error!("Failed to fetch multi-calendar: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Effective prices go through each listing's pricing rules, resolved for all listings at once
+    let mut listing_ids: Vec<String> = rows.iter().map(|row| row.listing_id.clone()).collect();
+    listing_ids.dedup();
+    let mut prices: HashMap<String, HashMap<NaiveDate, NightPrice>> =
+        match nightly_prices_for(&mut conn, &listing_ids, from, to + Duration::days(1), None).await
+        {
+            Ok(prices) => prices
+                .into_iter()
+                .map(|(listing_id, nights)| {
+                    let nights = nights
+                        .into_iter()
+                        .map(|night| (night.date, night))
+                        .collect();
+                    (listing_id, nights)
+                })
+                .collect(),
+            Err(e) => {
+                log::error!("Failed to compute nightly prices: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Failed to compute nightly prices" }));
+            }
+        };
+
+    // Rows come grouped by listing, one per day
+    let mut listings: Vec<MultiCalendarListing> = Vec::new();
+    let mut listing_prices = HashMap::new();
+    for row in rows {
+        if listings
+            .last()
+            .is_none_or(|listing| listing.listing_id != row.listing_id)
+        {
+            listing_prices = prices.remove(&row.listing_id).unwrap_or_default();
+            listings.push(MultiCalendarListing {
+                listing_id: row.listing_id.clone(),
+                title: row.title.clone(),
+                status: row.status.clone(),
+                currency: row.currency,
+                days: Vec::new(),
+            });
+        }
+
+        let booking = match (
+            row.booking_id,
+            row.booking_status,
+            row.check_in,
+            row.check_out,
+        ) {
+            (Some(booking_id), Some(status), Some(check_in), Some(check_out)) => {
+                Some(NightBooking {
+                    booking_id,
+                    guest_name: row.guest_name,
+                    status,
+                    check_in,
+                    check_out,
+                })
+            }
+            _ => None,
+        };
+        let night = listing_prices.get(&row.date);
+        if let Some(listing) = listings.last_mut() {
+            listing.days.push(MultiCalendarDay {
+                date: row.date,
+                available: !row.blocked && !row.externally_blocked && booking.is_none(),
+                blocked: row.blocked,
+                externally_blocked: row.externally_blocked,
+                closed_to_arrival: row.closed_to_arrival,
+                closed_to_departure: row.closed_to_departure,
+                min_nights_override: row.min_nights_override,
+                price: night
+                    .map(|night| night.price.to_major())
+                    .unwrap_or_default(),
+                price_source: night
+                    .map(|night| night.source.to_string())
+                    .unwrap_or_default(),
+                booking,
+            });
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "from": from,
+        "to": to,
+        "listings": listings
+    }))
+}
+
+/// PUT /api/calendar/multi/dates - Apply the same block or price change to several listings
+#[put("/multi/dates")]
+pub async fn bulk_update_calendar_dates(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    body: web::Json<BulkUpdateCalendarRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let mut listing_ids = body.listing_ids.clone();
+    listing_ids.sort();
+    listing_ids.dedup();
+    if listing_ids.is_empty() {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Select at least one listing" }));
+    }
+
+    let target = match body.update.target() {
+        Ok(target) => target,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start calendar update: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let owned = sqlx::query_as::<_, (String, Currency)>(
+        r#"
+        SELECT id, COALESCE(currency, 'XAF') FROM listings
+        WHERE id = ANY($1) AND host_id = $2
+        ORDER BY id
+        "#,
+    )
+    .bind(&listing_ids)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await;
+
+    let owned = match owned {
+        Ok(owned) if owned.len() == listing_ids.len() => owned,
+        Ok(_) => {
+            return HttpResponse::Forbidden().json(serde_json::json!({
+                "error": "You don't have permission to modify all of these listings"
+            }));
+        }
+        Err(e) => {
+            log::error!("Failed to check listing ownership: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // All or nothing: one booked night anywhere leaves every calendar unchanged
+    let mut results = Vec::with_capacity(owned.len());
+    let mut booked_dates = Vec::new();
+    for (listing_id, currency) in &owned {
+        match apply_calendar_update(&mut tx, listing_id, *currency, &body.update, &target).await {
+            Ok(changed) => {
+                let created = changed.iter().filter(|day| day.created).count();
+                results.push(serde_json::json!({
+                    "listing_id": listing_id,
+                    "changed": changed.len(),
+                    "created": created,
+                    "updated": changed.len() - created,
+                    "changed_dates": changed.iter().map(|day| day.date).collect::<Vec<_>>()
+                }));
+            }
+            Err(CalendarUpdateError::Booked(booked)) => {
+                booked_dates.push(serde_json::json!({
+                    "listing_id": listing_id,
+                    "booked_dates": booked
+                }));
+            }
+            Err(CalendarUpdateError::Invalid(message)) => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": message,
+                    "listing_id": listing_id
+                }));
+            }
+            Err(CalendarUpdateError::Database(e)) => {
+                log::error!("Failed to update calendar dates: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Failed to update calendar dates" }));
+            }
+        }
+    }
+
+    if !booked_dates.is_empty() {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": "Some of these dates are already booked",
+            "listings": booked_dates
+        }));
+    }
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit calendar update: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update calendar dates" }));
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "message": "Calendar dates updated successfully",
+        "listings": results
+    }))
+}
@@ -0,0 +1,839 @@
+use crate::routes::verify_listing_ownership;
+use crate::smart_pricing::{city_occupancy, PriceFactor, SmartPricingInputs, Suggestion};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use kamer_core::{Currency, Money, MoneyError};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+
+pub const RULE_FIXED_PRICE: &str = "fixed_price";
+pub const RULE_ADJUSTMENT: &str = "adjustment";
+
+/// Where a night's price came from, before any adjustment rule.
+pub const SOURCE_BASE: &str = "base";
+pub const SOURCE_WEEKEND: &str = "weekend";
+pub const SOURCE_CALENDAR: &str = "calendar";
+pub const SOURCE_RULE: &str = "rule";
+pub const SOURCE_SMART: &str = "smart";
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PricingRule {
+    pub id: String,
+    pub listing_id: String,
+    pub name: String,
+    pub rule_type: String,
+    /// Fixed nightly price, in major units of the listing's currency.
+    pub price: Option<f64>,
+    pub adjustment_percent: Option<f64>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub recurs_yearly: bool,
+    pub weekdays: Vec<i32>,
+    pub min_nights: Option<i32>,
+    pub priority: i32,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    #[serde(skip)]
+    pub price_minor: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PricingRuleRequest {
+    pub name: String,
+    /// `fixed_price` or `adjustment`.
+    pub rule_type: String,
+    /// Nightly price for fixed-price rules.
+    pub price: Option<f64>,
+    /// Percentage added to the night's price for adjustments; negative for discounts.
+    pub adjustment_percent: Option<f64>,
+    /// Leave both dates out for a rule that applies all year.
+    pub start_date: Option<NaiveDate>,
+    /// Inclusive.
+    pub end_date: Option<NaiveDate>,
+    /// Repeat the date range every year. The range may wrap over New Year.
+    #[serde(default)]
+    pub recurs_yearly: bool,
+    /// ISO weekdays (1 = Monday ... 7 = Sunday) of the nights it applies to; all when empty.
+    #[serde(default)]
+    pub weekdays: Vec<i32>,
+    /// Only apply to stays of at least this many nights.
+    pub min_nights: Option<i32>,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// A rule that set or adjusted a night's price.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedRule {
+    pub id: String,
+    pub name: String,
+}
+
+/// The price of one night and how it was reached.
+#[derive(Debug, Clone)]
+pub struct NightPrice {
+    pub date: NaiveDate,
+    pub price: Money,
+    /// `base`, `weekend`, `calendar`, `rule` or `smart`.
+    pub source: &'static str,
+    /// Rules applied to this night, in the order applied.
+    pub rules: Vec<AppliedRule>,
+    /// Why smart pricing set this price; empty unless `source` is `smart`.
+    pub factors: Vec<PriceFactor>,
+    /// What smart pricing would charge for the night, whether or not it is enabled.
+    pub suggestion: Suggestion,
+}
+
+/// Why nightly prices could not be computed.
+#[derive(Debug)]
+pub enum PricingError {
+    Database(sqlx::Error),
+    Price(MoneyError),
+}
+
+impl From<sqlx::Error> for PricingError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<MoneyError> for PricingError {
+    fn from(e: MoneyError) -> Self {
+        Self::Price(e)
+    }
+}
+
+const PRICING_RULE_COLUMNS: &str = r#"
+    r.id, r.listing_id, r.name, r.rule_type, r.price_minor,
+    (r.price_minor / power(10::NUMERIC, currency_exponent(COALESCE(l.currency, 'XAF'))))::FLOAT8 as price,
+    r.adjustment_percent, r.start_date, r.end_date, r.recurs_yearly, r.weekdays, r.min_nights,
+    r.priority, r.created_at, r.updated_at
+"#;
+
+// ============================================================================
+// Rule Evaluation
+// ============================================================================
+
+impl PricingRule {
+    fn covers_date(&self, date: NaiveDate) -> bool {
+        let (Some(start), Some(end)) = (self.start_date, self.end_date) else {
+            return true;
+        };
+        if !self.recurs_yearly {
+            return start <= date && date <= end;
+        }
+        let day = (date.month(), date.day());
+        let (from, to) = ((start.month(), start.day()), (end.month(), end.day()));
+        if from <= to {
+            from <= day && day <= to
+        } else {
+            // Wraps over New Year, e.g. 15 December to 5 January
+            day >= from || day <= to
+        }
+    }
+
+    /// Whether the rule applies to the night of `date` in a stay of `stay_nights`.
+    /// Without a stay length, only rules that need no minimum stay apply.
+    fn applies(&self, date: NaiveDate, stay_nights: Option<i64>) -> bool {
+        let weekday = date.weekday().number_from_monday() as i32;
+        let long_enough = match self.min_nights.filter(|&min| min > 1) {
+            Some(min) => stay_nights.is_some_and(|nights| nights >= i64::from(min)),
+            None => true,
+        };
+        long_enough
+            && (self.weekdays.is_empty() || self.weekdays.contains(&weekday))
+            && self.covers_date(date)
+    }
+
+    fn applied(&self) -> AppliedRule {
+        AppliedRule {
+            id: self.id.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ListingBasePrice {
+    listing_id: String,
+    currency: Currency,
+    base_price_minor: i64,
+    weekend_price_minor: Option<i64>,
+    smart_pricing_enabled: bool,
+    smart_min_price_minor: Option<i64>,
+    smart_max_price_minor: Option<i64>,
+}
+
+/// Nightly prices for the nights from `from` up to, not including, `to`.
+///
+/// Each night starts at the base price, or the weekend price on Friday and Saturday nights.
+/// A price the host set on the calendar replaces it; otherwise the highest-priority matching
+/// fixed-price rule does. Failing both, smart pricing's suggestion does when the host has enabled
+/// it. The highest-priority matching adjustment is then applied on top.
+/// Pass the stay length when quoting so length-of-stay rules can apply.
+pub async fn nightly_prices(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    stay_nights: Option<i64>,
+) -> Result<Vec<NightPrice>, PricingError> {
+    let mut prices =
+        nightly_prices_for(conn, &[listing_id.to_string()], from, to, stay_nights).await?;
+    Ok(prices.remove(listing_id).unwrap_or_default())
+}
+
+/// [`nightly_prices`] for several listings at once, keyed by listing id.
+///
+/// Reads everything in a fixed number of queries however many listings are asked for.
+/// Listings that don't exist are left out.
+pub async fn nightly_prices_for(
+    conn: &mut PgConnection,
+    listing_ids: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+    stay_nights: Option<i64>,
+) -> Result<HashMap<String, Vec<NightPrice>>, PricingError> {
+    let bases = sqlx::query_as::<_, ListingBasePrice>(
+        r#"
+        SELECT
+            l.id as listing_id,
+            COALESCE(l.currency, 'XAF') as currency,
+            COALESCE(s.base_price_minor, l.price_per_night_minor, 0) as base_price_minor,
+            s.weekend_price_minor,
+            COALESCE(s.smart_pricing_enabled, FALSE) as smart_pricing_enabled,
+            s.smart_min_price_minor,
+            s.smart_max_price_minor
+        FROM listings l
+        LEFT JOIN listing_settings s ON s.listing_id = l.id
+        WHERE l.id = ANY($1)
+        "#,
+    )
+    .bind(listing_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    if bases.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut custom: HashMap<String, HashMap<NaiveDate, i64>> = HashMap::new();
+    for (listing_id, date, price_minor) in sqlx::query_as::<_, (String, NaiveDate, i64)>(
+        r#"
+        SELECT listing_id, date, price_minor FROM calendar_pricing
+        WHERE listing_id = ANY($1) AND custom_price AND date >= $2 AND date < $3
+        "#,
+    )
+    .bind(listing_ids)
+    .bind(from)
+    .bind(to)
+    .fetch_all(&mut *conn)
+    .await?
+    {
+        custom
+            .entry(listing_id)
+            .or_default()
+            .insert(date, price_minor);
+    }
+
+    let mut rules: HashMap<String, Vec<PricingRule>> = HashMap::new();
+    for rule in active_rules(&mut *conn, listing_ids).await? {
+        rules.entry(rule.listing_id.clone()).or_default().push(rule);
+    }
+
+    let mut occupancy = city_occupancy(&mut *conn, listing_ids, from, to).await?;
+    let today = chrono::Utc::now().date_naive();
+
+    let mut prices = HashMap::new();
+    for base in bases {
+        let smart = SmartPricingInputs {
+            today,
+            min_price_minor: base.smart_min_price_minor,
+            max_price_minor: base.smart_max_price_minor,
+            weekend_price_set: base.weekend_price_minor.is_some(),
+            occupancy: occupancy.remove(&base.listing_id).unwrap_or_default(),
+        };
+        let nights = price_nights(
+            &base,
+            custom.get(&base.listing_id),
+            rules.get(&base.listing_id).map_or(&[], Vec::as_slice),
+            &smart,
+            from,
+            to,
+            stay_nights,
+        )?;
+        prices.insert(base.listing_id, nights);
+    }
+    Ok(prices)
+}
+
+fn price_nights(
+    base: &ListingBasePrice,
+    custom: Option<&HashMap<NaiveDate, i64>>,
+    rules: &[PricingRule],
+    smart: &SmartPricingInputs,
+    from: NaiveDate,
+    to: NaiveDate,
+    stay_nights: Option<i64>,
+) -> Result<Vec<NightPrice>, PricingError> {
+    let mut nights = Vec::new();
+    let mut date = from;
+    while date < to {
+        let weekend = matches!(date.weekday().number_from_monday(), 5 | 6);
+        let (mut price, mut source) = match base.weekend_price_minor.filter(|_| weekend) {
+            Some(weekend_price) => (weekend_price, SOURCE_WEEKEND),
+            None => (base.base_price_minor, SOURCE_BASE),
+        };
+        let mut applied = Vec::new();
+        let mut factors = Vec::new();
+        let suggestion = smart.suggest(Money::from_minor(price, base.currency), date)?;
+
+        // Rules are sorted by priority, so the first match of each kind wins
+        let mut matching = rules.iter().filter(|rule| rule.applies(date, stay_nights));
+        let fixed = matching
+            .clone()
+            .find(|rule| rule.rule_type == RULE_FIXED_PRICE);
+        if let Some(&custom_price) = custom.and_then(|custom| custom.get(&date)) {
+            price = custom_price;
+            source = SOURCE_CALENDAR;
+        } else if let Some(rule) = fixed {
+            price = rule.price_minor.unwrap_or(price);
+            source = SOURCE_RULE;
+            applied.push(rule.applied());
+        } else if base.smart_pricing_enabled {
+            price = suggestion.price.minor();
+            source = SOURCE_SMART;
+            factors = suggestion.factors.clone();
+        }
+
+        let mut price = Money::from_minor(price, base.currency);
+        if let Some(rule) = matching.find(|rule| rule.rule_type == RULE_ADJUSTMENT) {
+            let adjustment = price.percent(rule.adjustment_percent.unwrap_or(0.0))?;
+            price = price.checked_add(adjustment)?.non_negative();
+            applied.push(rule.applied());
+        }
+
+        nights.push(NightPrice {
+            date,
+            price,
+            source,
+            rules: applied,
+            factors,
+            suggestion,
+        });
+        date += Duration::days(1);
+    }
+    Ok(nights)
+}
+
+/// The listings' active rules, highest priority first; ties go to the older rule.
+async fn active_rules(
+    conn: &mut PgConnection,
+    listing_ids: &[String],
+) -> Result<Vec<PricingRule>, sqlx::Error> {
+    sqlx::query_as::<_, PricingRule>(&format!(
+        r#"
+        SELECT {} FROM pricing_rules r
+        JOIN listings l ON r.listing_id = l.id
+        WHERE r.listing_id = ANY($1) AND r.active
+        ORDER BY r.priority DESC, r.created_at, r.id
+        "#,
+        PRICING_RULE_COLUMNS
+    ))
+    .bind(listing_ids)
+    .fetch_all(conn)
+    .await
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// Validate a rule; returns its fixed price, if any, in minor units.
+fn validate_pricing_rule(
+    body: &PricingRuleRequest,
+    currency: Currency,
+) -> Result<Option<i64>, String> {
+    if body.name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+
+    let price_minor = match body.rule_type.as_str() {
+        RULE_FIXED_PRICE => match body.price {
+            Some(price) if price > 0.0 => Some(
+                Money::from_major(price, currency)
+                    .map_err(|e| e.to_string())?
+                    .minor(),
+            ),
+            _ => return Err("Fixed-price rules need a price greater than zero".to_string()),
+        },
+        RULE_ADJUSTMENT => match body.adjustment_percent {
+            Some(percent) if percent != 0.0 && (-90.0..=500.0).contains(&percent) => None,
+            _ => {
+                return Err(
+                    "adjustment_percent must be between -90 and 500, and not zero".to_string(),
+                )
+            }
+        },
+        _ => return Err("Invalid rule type".to_string()),
+    };
+
+    match (body.start_date, body.end_date) {
+        (None, None) if body.recurs_yearly => {
+            return Err("Yearly rules need a start_date and end_date".to_string());
+        }
+        (None, None) => {}
+        (Some(start), Some(end)) if !body.recurs_yearly && end < start => {
+            return Err("end_date must not be before start_date".to_string());
+        }
+        (Some(_), Some(_)) => {}
+        _ => return Err("start_date and end_date must be given together".to_string()),
+    }
+
+    if body.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+        return Err("weekdays must be ISO weekday numbers, 1 (Monday) to 7 (Sunday)".to_string());
+    }
+    if body.min_nights.is_some_and(|min| min < 1) {
+        return Err("min_nights must be at least 1".to_string());
+    }
+    Ok(price_minor)
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/calendar/:listing_id/pricing-rules - List a listing's pricing rules
+#[get("/{listing_id}/pricing-rules")]
+pub async fn get_pricing_rules(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    let rules = match pool.acquire().await {
+        Ok(mut conn) => active_rules(&mut conn, std::slice::from_ref(&listing_id)).await,
+        Err(e) => Err(e),
+    };
+
+    match rules {
+        Ok(rules) => HttpResponse::Ok().json(rules),
+        Err(e) => {
+            log::error!("Failed to fetch pricing rules: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST /api/calendar/:listing_id/pricing-rules - Add a seasonal, weekday or length-of-stay rule
+#[post("/{listing_id}/pricing-rules")]
+pub async fn create_pricing_rule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<PricingRuleRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let listing_id = path.into_inner();
+
+    let currency = match verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        Ok(currency) => currency,
+        Err(response) => return response,
+    };
+
+    let price_minor = match validate_pricing_rule(&body, currency) {
+        Ok(price_minor) => price_minor,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let result = sqlx::query(
+        r#"
+        INSERT INTO pricing_rules (
+            id, listing_id, name, rule_type, price_minor, adjustment_percent, start_date,
+            end_date, recurs_yearly, weekdays, min_nights, priority
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        "#,
+    )
+    .bind(&id)
+    .bind(&listing_id)
+    .bind(body.name.trim())
+    .bind(&body.rule_type)
+    .bind(price_minor)
+    .bind(body.adjustment_percent.filter(|_| price_minor.is_none()))
+    .bind(body.start_date)
+    .bind(body.end_date)
+    .bind(body.recurs_yearly)
+    .bind(&body.weekdays)
+    .bind(body.min_nights)
+    .bind(body.priority)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "id": id })),
+        Err(e) => {
+            log::error!("Failed to create pricing rule: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to create pricing rule" }))
+        }
+    }
+}
+
+/// PUT /api/calendar/:listing_id/pricing-rules/:rule_id - Replace a pricing rule
+#[put("/{listing_id}/pricing-rules/{rule_id}")]
+pub async fn update_pricing_rule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: web::Json<PricingRuleRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let (listing_id, rule_id) = path.into_inner();
+
+    let currency = match verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        Ok(currency) => currency,
+        Err(response) => return response,
+    };
+
+    let price_minor = match validate_pricing_rule(&body, currency) {
+        Ok(price_minor) => price_minor,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
+
+    let result = sqlx::query(
+        r#"
+        UPDATE pricing_rules
+        SET name = $3, rule_type = $4, price_minor = $5, adjustment_percent = $6,
+            start_date = $7, end_date = $8, recurs_yearly = $9, weekdays = $10,
+            min_nights = $11, priority = $12, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND listing_id = $2 AND active
+        "#,
+    )
+    .bind(&rule_id)
+    .bind(&listing_id)
+    .bind(body.name.trim())
+    .bind(&body.rule_type)
+    .bind(price_minor)
+    .bind(body.adjustment_percent.filter(|_| price_minor.is_none()))
+    .bind(body.start_date)
+    .bind(body.end_date)
+    .bind(body.recurs_yearly)
+    .bind(&body.weekdays)
+    .bind(body.min_nights)
+    .bind(body.priority)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Pricing rule not found" }))
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "updated" })),
+        Err(e) => {
+            log::error!("Failed to update pricing rule: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to update pricing rule" }))
+        }
+    }
+}
+
+/// DELETE /api/calendar/:listing_id/pricing-rules/:rule_id - Remove a pricing rule
+#[delete("/{listing_id}/pricing-rules/{rule_id}")]
+pub async fn delete_pricing_rule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let (listing_id, rule_id) = path.into_inner();
+
+    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        return response;
+    }
+
+    // Deactivate rather than delete: price snapshots name the rules that priced each night
+    let result = sqlx::query(
+        r#"
+        UPDATE pricing_rules SET active = FALSE, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND listing_id = $2 AND active
+        "#,
+    )
+    .bind(&rule_id)
+    .bind(&listing_id)
+    .execute(pool.get_ref())
+    .await;
+
+    match result {
+        Ok(res) if res.rows_affected() == 0 => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Pricing rule not found" }))
+        }
+        Ok(_) => HttpResponse::Ok().json(serde_json::json!({ "status": "deleted" })),
+        Err(e) => {
+            log::error!("Failed to delete pricing rule: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to delete pricing rule" }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn rule(id: &str, start: Option<&str>, end: Option<&str>, recurs_yearly: bool) -> PricingRule {
+        PricingRule {
+            id: id.to_string(),
+            listing_id: "listing".to_string(),
+            name: id.to_string(),
+            rule_type: RULE_ADJUSTMENT.to_string(),
+            price: None,
+            adjustment_percent: Some(10.0),
+            start_date: start.map(day),
+            end_date: end.map(day),
+            recurs_yearly,
+            weekdays: Vec::new(),
+            min_nights: None,
+            priority: 0,
+            created_at: None,
+            updated_at: None,
+            price_minor: None,
+        }
+    }
+
+    fn fixed(id: &str, price_minor: i64, priority: i32) -> PricingRule {
+        PricingRule {
+            rule_type: RULE_FIXED_PRICE.to_string(),
+            adjustment_percent: None,
+            price_minor: Some(price_minor),
+            priority,
+            ..rule(id, None, None, false)
+        }
+    }
+
+    fn adjustment(id: &str, percent: f64, priority: i32) -> PricingRule {
+        PricingRule {
+            adjustment_percent: Some(percent),
+            priority,
+            ..rule(id, None, None, false)
+        }
+    }
+
+    fn base(weekend_price_minor: Option<i64>) -> ListingBasePrice {
+        ListingBasePrice {
+            listing_id: "listing".to_string(),
+            currency: Currency::XAF,
+            base_price_minor: 10_000,
+            weekend_price_minor,
+            smart_pricing_enabled: false,
+            smart_min_price_minor: None,
+            smart_max_price_minor: None,
+        }
+    }
+
+    fn smart() -> SmartPricingInputs {
+        SmartPricingInputs {
+            today: day("2026-01-01"),
+            min_price_minor: None,
+            max_price_minor: None,
+            weekend_price_set: false,
+            occupancy: HashMap::new(),
+        }
+    }
+
+    /// Price of the single night of `date` under `rules`, which come sorted by priority.
+    fn price_on(date: &str, rules: &[PricingRule], stay_nights: Option<i64>) -> NightPrice {
+        let from = day(date);
+        price_nights(
+            &base(None),
+            None,
+            rules,
+            &smart(),
+            from,
+            from + Duration::days(1),
+            stay_nights,
+        )
+        .unwrap()
+        .remove(0)
+    }
+
+    #[test]
+    fn dated_rules_cover_their_range_inclusively() {
+        let season = rule("season", Some("2026-07-01"), Some("2026-08-31"), false);
+        assert!(!season.covers_date(day("2026-06-30")));
+        assert!(season.covers_date(day("2026-07-01")));
+        assert!(season.covers_date(day("2026-08-31")));
+        assert!(!season.covers_date(day("2026-09-01")));
+        assert!(!season.covers_date(day("2027-07-15")));
+        assert!(rule("always", None, None, false).covers_date(day("2030-02-14")));
+    }
+
+    #[test]
+    fn yearly_rules_wrap_over_new_year() {
+        let holidays = rule("holidays", Some("2025-12-15"), Some("2026-01-05"), true);
+        assert!(holidays.covers_date(day("2030-12-15")));
+        assert!(holidays.covers_date(day("2030-12-31")));
+        assert!(holidays.covers_date(day("2031-01-01")));
+        assert!(holidays.covers_date(day("2031-01-05")));
+        assert!(!holidays.covers_date(day("2031-01-06")));
+        assert!(!holidays.covers_date(day("2030-12-14")));
+        assert!(!holidays.covers_date(day("2030-07-01")));
+    }
+
+    #[test]
+    fn yearly_rules_handle_february_29() {
+        let leap_day = rule("leap", Some("2024-02-29"), Some("2024-02-29"), true);
+        assert!(leap_day.covers_date(day("2028-02-29")));
+        assert!(!leap_day.covers_date(day("2027-02-28")));
+        assert!(!leap_day.covers_date(day("2027-03-01")));
+
+        let late_february = rule("feb", Some("2025-02-20"), Some("2025-02-28"), true);
+        assert!(late_february.covers_date(day("2028-02-28")));
+        assert!(!late_february.covers_date(day("2028-02-29")));
+
+        let into_march = rule("carnival", Some("2025-02-25"), Some("2025-03-02"), true);
+        assert!(into_march.covers_date(day("2028-02-29")));
+        assert!(into_march.covers_date(day("2027-03-01")));
+    }
+
+    #[test]
+    fn weekday_and_minimum_stay_conditions() {
+        let weekends = PricingRule {
+            weekdays: vec![5, 6],
+            ..rule("weekends", None, None, false)
+        };
+        // 2026-03-06 is a Friday
+        assert!(weekends.applies(day("2026-03-06"), None));
+        assert!(weekends.applies(day("2026-03-07"), None));
+        assert!(!weekends.applies(day("2026-03-08"), None));
+
+        let week_long = PricingRule {
+            min_nights: Some(7),
+            ..rule("weekly", None, None, false)
+        };
+        assert!(week_long.applies(day("2026-03-06"), Some(7)));
+        assert!(!week_long.applies(day("2026-03-06"), Some(6)));
+        assert!(!week_long.applies(day("2026-03-06"), None));
+
+        let one_night = PricingRule {
+            min_nights: Some(1),
+            ..rule("any", None, None, false)
+        };
+        assert!(one_night.applies(day("2026-03-06"), None));
+    }
+
+    #[test]
+    fn highest_priority_rule_of_each_kind_wins() {
+        let rules = [
+            fixed("peak", 20_000, 10),
+            adjustment("promo", -20.0, 5),
+            fixed("season", 15_000, 1),
+            adjustment("surcharge", 50.0, 0),
+        ];
+        let night = price_on("2026-03-04", &rules, None);
+        assert_eq!(night.price, Money::from_minor(16_000, Currency::XAF));
+        assert_eq!(night.source, SOURCE_RULE);
+        let applied: Vec<_> = night.rules.iter().map(|rule| rule.id.as_str()).collect();
+        assert_eq!(applied, ["peak", "promo"]);
+    }
+
+    #[test]
+    fn rules_that_do_not_match_fall_through() {
+        let rules = [
+            PricingRule {
+                min_nights: Some(7),
+                ..fixed("weekly", 8_000, 10)
+            },
+            fixed("season", 15_000, 1),
+        ];
+        assert_eq!(
+            price_on("2026-03-04", &rules, Some(3)).price,
+            Money::from_minor(15_000, Currency::XAF)
+        );
+        assert_eq!(
+            price_on("2026-03-04", &rules, Some(7)).price,
+            Money::from_minor(8_000, Currency::XAF)
+        );
+    }
+
+    #[test]
+    fn calendar_prices_replace_fixed_rules_but_not_adjustments() {
+        let from = day("2026-03-04");
+        let custom = HashMap::from([(from, 12_000)]);
+        let rules = [fixed("season", 15_000, 1), adjustment("promo", -10.0, 0)];
+        let night = price_nights(
+            &base(None),
+            Some(&custom),
+            &rules,
+            &smart(),
+            from,
+            from + Duration::days(1),
+            None,
+        )
+        .unwrap()
+        .remove(0);
+        assert_eq!(night.price, Money::from_minor(10_800, Currency::XAF));
+        assert_eq!(night.source, SOURCE_CALENDAR);
+        assert_eq!(night.rules.len(), 1);
+    }
+
+    #[test]
+    fn weekend_price_applies_on_friday_and_saturday_nights() {
+        let from = day("2026-03-05");
+        let nights = price_nights(
+            &base(Some(14_000)),
+            None,
+            &[],
+            &smart(),
+            from,
+            from + Duration::days(4),
+            None,
+        )
+        .unwrap();
+        let prices: Vec<_> = nights
+            .iter()
+            .map(|night| (night.price.minor(), night.source))
+            .collect();
+        assert_eq!(
+            prices,
+            [
+                (10_000, SOURCE_BASE),
+                (14_000, SOURCE_WEEKEND),
+                (14_000, SOURCE_WEEKEND),
+                (10_000, SOURCE_BASE),
+            ]
+        );
+    }
+}
@@ -1,13 +1,18 @@
+use crate::pricing_rules::{nightly_prices, AppliedRule};
+use crate::settings::{AdvanceNotice, CutoffTime, Discount, PreparationTime};
+use crate::smart_pricing::PriceFactor;
 use actix_web::{get, put, web, HttpRequest, HttpResponse, Responder};
-use chrono::NaiveDate;
+use chrono::{Duration, NaiveDate};
+use kamer_core::{Currency, Money};
 use serde::{Deserialize, Serialize};
 use sha1::Digest;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 
 // ============================================================================
 // Data Structures
 // ============================================================================
 
+/// Prices are stored in minor units of the listing's currency and read back in major units.
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct CalendarPricing {
     pub id: i32,
@@ -15,6 +20,40 @@ pub struct CalendarPricing {
     pub date: chrono::NaiveDate,
     pub price: f64,
     pub is_available: bool,
+    /// Whether the host set this day's price, rather than only blocking or unblocking it.
+    pub custom_price: bool,
+    pub closed_to_arrival: bool,
+    pub closed_to_departure: bool,
+    pub min_nights_override: Option<i32>,
+    pub created_at: Option<chrono::NaiveDateTime>,
+    pub updated_at: Option<chrono::NaiveDateTime>,
+}
+
+/// A day of the host's calendar with the price a one-night stay would be charged.
+#[derive(Debug, Serialize)]
+pub struct CalendarDay {
+    /// Set when the day has its own `calendar_pricing` row.
+    pub id: Option<i32>,
+    pub listing_id: String,
+    pub date: NaiveDate,
+    /// Effective nightly price, after pricing rules.
+    pub price: f64,
+    pub is_available: bool,
+    pub custom_price: bool,
+    /// No stay may start on this day.
+    pub closed_to_arrival: bool,
+    /// No stay may end on this day.
+    pub closed_to_departure: bool,
+    /// Minimum stay for stays that include this night, instead of the listing's usual minimum.
+    pub min_nights_override: Option<i32>,
+    /// `base`, `weekend`, `calendar`, `rule` or `smart`.
+    pub price_source: String,
+    /// Pricing rules that set or adjusted the price.
+    pub pricing_rules: Vec<AppliedRule>,
+    /// Smart pricing's suggestion for the night, shown whether or not it is enabled.
+    pub suggested_price: f64,
+    /// The factors behind the suggestion.
+    pub suggestion_factors: Vec<PriceFactor>,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
@@ -26,13 +65,16 @@ pub struct ListingSettings {
     pub base_price: Option<f64>,
     pub weekend_price: Option<f64>,
     pub smart_pricing_enabled: bool,
-    pub weekly_discount: f64,
-    pub monthly_discount: f64,
+    /// Bounds for smart pricing's suggestions.
+    pub smart_min_price: Option<f64>,
+    pub smart_max_price: Option<f64>,
+    pub weekly_discount: Discount,
+    pub monthly_discount: Discount,
     pub min_nights: i32,
     pub max_nights: i32,
-    pub advance_notice: String,
-    pub same_day_cutoff_time: String,
-    pub preparation_time: String,
+    pub advance_notice: AdvanceNotice,
+    pub same_day_cutoff_time: CutoffTime,
+    pub preparation_time: PreparationTime,
     pub availability_window: i32,
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
@@ -44,18 +86,53 @@ pub struct CalendarQuery {
     pub end_date: String,
 }
 
+/// Dates are given explicitly, as an inclusive range, or both.
 #[derive(Debug, Deserialize)]
 pub struct UpdateCalendarDatesRequest {
+    #[serde(default)]
     pub dates: Vec<String>,
+    pub start_date: Option<String>,
+    /// Inclusive.
+    pub end_date: Option<String>,
+    /// ISO weekdays (1 = Monday ... 7 = Sunday) to pick from the range; all days when empty.
+    #[serde(default)]
+    pub weekdays: Vec<i32>,
     pub price: Option<f64>,
+    /// Drop the days' custom prices so the base price and pricing rules apply again.
+    #[serde(default)]
+    pub reset_price: bool,
+    /// Left unchanged on existing days when not given; new days are available.
     pub is_available: Option<bool>,
+    pub closed_to_arrival: Option<bool>,
+    pub closed_to_departure: Option<bool>,
+    pub min_nights_override: Option<i32>,
+    /// Drop the days' minimum stay override so the listing's usual minimum applies again.
+    #[serde(default)]
+    pub clear_min_nights_override: bool,
+}
+
+/// A day covered by a confirmed or awaiting-payment booking that an update tried to block.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BookedDate {
+    pub date: NaiveDate,
+    pub booking_id: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct ChangedDate {
+    pub(crate) date: NaiveDate,
+    pub(crate) created: bool,
 }
 
+/// Settings to change. Values are checked by `validate`, so a bad one is reported against its
+/// field instead of failing the whole body.
 #[derive(Debug, Deserialize)]
 pub struct UpdateSettingsRequest {
     pub base_price: Option<f64>,
     pub weekend_price: Option<f64>,
     pub smart_pricing_enabled: Option<bool>,
+    pub smart_min_price: Option<f64>,
+    pub smart_max_price: Option<f64>,
     pub weekly_discount: Option<f64>,
     pub monthly_discount: Option<f64>,
     pub min_nights: Option<i32>,
@@ -72,18 +149,68 @@ pub struct UpdateSettingsRequest {
 
 // Local extract_user_id removed in favor of kamer_auth::extract_user_id
 
-async fn verify_listing_ownership(
+/// Longest range a single calendar update may cover.
+const MAX_UPDATE_RANGE_DAYS: i64 = 731;
+
+/// Longest minimum stay a day can require.
+pub(crate) const MAX_MIN_NIGHTS_OVERRIDE: i32 = 365;
+
+/// Longest range a single calendar read may cover.
+const MAX_CALENDAR_RANGE_DAYS: i64 = 731;
+
+/// The days an update targets: explicit dates plus the range's days on the chosen weekdays.
+/// Binds `$2` (dates), `$3`/`$4` (range) and `$5` (weekdays).
+const TARGET_DATES_CTE: &str = r#"
+    target AS (
+        SELECT DISTINCT d::DATE AS date
+        FROM (
+            SELECT unnest($2::DATE[]) AS d
+            UNION ALL
+            SELECT d
+            FROM generate_series($3::DATE, $4::DATE, INTERVAL '1 day') AS d
+            WHERE cardinality($5::INT[]) = 0 OR EXTRACT(ISODOW FROM d)::INT = ANY($5::INT[])
+        ) days
+    )
+"#;
+
+const CALENDAR_PRICING_COLUMNS: &str = r#"
+    cp.id, cp.listing_id, cp.date,
+    cp.price_minor / power(10, currency_exponent(COALESCE(l.currency, 'XAF'))) as price,
+    cp.is_available, cp.custom_price, cp.closed_to_arrival, cp.closed_to_departure,
+    cp.min_nights_override, cp.created_at, cp.updated_at
+"#;
+
+const LISTING_SETTINGS_SELECT: &str = r#"
+    SELECT
+        s.id, s.listing_id,
+        s.base_price_minor / power(10, currency_exponent(COALESCE(l.currency, 'XAF'))) as base_price,
+        s.weekend_price_minor / power(10, currency_exponent(COALESCE(l.currency, 'XAF'))) as weekend_price,
+        s.smart_pricing_enabled,
+        s.smart_min_price_minor / power(10, currency_exponent(COALESCE(l.currency, 'XAF'))) as smart_min_price,
+        s.smart_max_price_minor / power(10, currency_exponent(COALESCE(l.currency, 'XAF'))) as smart_max_price,
+        s.weekly_discount, s.monthly_discount, s.min_nights,
+        s.max_nights, s.advance_notice, s.same_day_cutoff_time, s.preparation_time,
+        s.availability_window, s.created_at, s.updated_at
+    FROM listing_settings s
+    JOIN listings l ON s.listing_id = l.id
+    WHERE s.listing_id = $1
+"#;
+
+/// Check the user hosts the listing. Returns the listing's currency, which prices are set in.
+pub(crate) async fn verify_listing_ownership(
     pool: &PgPool,
     listing_id: &str,
     user_id: i32,
-) -> Result<(), HttpResponse> {
-    let owner_check = sqlx::query_scalar::<_, i32>("SELECT host_id FROM listings WHERE id = $1")
-        .bind(listing_id)
-        .fetch_optional(pool)
-        .await;
+) -> Result<Currency, HttpResponse> {
+    let owner_check = sqlx::query_as::<_, (i32, Currency)>(
+        "SELECT host_id, COALESCE(currency, 'XAF') FROM listings WHERE id = $1",
+    )
+    .bind(listing_id)
+    .fetch_optional(pool)
+    .await;
 
     match owner_check {
-        Ok(Some(host_id)) if host_id == user_id => Ok(()),
+        Ok(Some((host_id, currency))) if host_id == user_id => Ok(currency),
         Ok(Some(_)) => Err(HttpResponse::Forbidden().json(serde_json::json!({
             "error": "You don't have permission to modify this listing"
         }))),
@@ -135,45 +262,318 @@ pub async fn get_calendar(
         }
     };
 
-    // Fetch calendar pricing for date range
-    let query_str = r#"
-        SELECT * FROM calendar_pricing
-        WHERE listing_id = $1 AND date >= $2 AND date <= $3
-        ORDER BY date ASC
-    "#;
+    if end_date < start_date {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "end_date must not be before start_date" }));
+    }
+    if (end_date - start_date).num_days() >= MAX_CALENDAR_RANGE_DAYS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("A range can cover at most {} days", MAX_CALENDAR_RANGE_DAYS)
+        }));
+    }
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to fetch calendar data: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
 
-    match sqlx::query_as::<_, CalendarPricing>(query_str)
+    // Fetch calendar pricing for date range
+    let query_str = format!(
+        r#"
+        SELECT {} FROM calendar_pricing cp
+        JOIN listings l ON cp.listing_id = l.id
+        WHERE cp.listing_id = $1 AND cp.date >= $2 AND cp.date <= $3
+        ORDER BY cp.date ASC
+        "#,
+        CALENDAR_PRICING_COLUMNS
+    );
+
+    let rows = match sqlx::query_as::<_, CalendarPricing>(&query_str)
         .bind(&listing_id)
         .bind(start_date)
         .bind(end_date)
-        .fetch_all(pool.get_ref())
+        .fetch_all(&mut *conn)
         .await
     {
-        Ok(pricing) => {
-            let json = serde_json::to_vec(&pricing).unwrap_or_default();
-            let etag = format!("\"{}\"", hex::encode(sha1::Sha1::digest(&json)));
+        Ok(rows) => rows,
+        Err(e) => {
+            log::error!("Failed to fetch calendar data: {:?}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Database error: {}", e)
+            }));
+        }
+    };
 
-            if let Some(tag) = req.headers().get(actix_web::http::header::IF_NONE_MATCH) {
-                if tag.to_str().ok() == Some(etag.as_str()) {
-                    return HttpResponse::NotModified().finish();
-                }
+    // Prices as a one-night stay would be charged; length-of-stay rules show up in quotes
+    let nights = match nightly_prices(
+        &mut conn,
+        &listing_id,
+        start_date,
+        end_date + Duration::days(1),
+        None,
+    )
+    .await
+    {
+        Ok(nights) => nights,
+        Err(e) => {
+            log::error!("Failed to compute nightly prices: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to compute nightly prices" }));
+        }
+    };
+
+    let mut rows = rows.into_iter().peekable();
+    let days: Vec<CalendarDay> = nights
+        .into_iter()
+        .map(|night| {
+            let row = rows.next_if(|row| row.date == night.date);
+            CalendarDay {
+                id: row.as_ref().map(|row| row.id),
+                listing_id: listing_id.clone(),
+                date: night.date,
+                price: night.price.to_major(),
+                is_available: row.as_ref().is_none_or(|row| row.is_available),
+                custom_price: row.as_ref().is_some_and(|row| row.custom_price),
+                closed_to_arrival: row.as_ref().is_some_and(|row| row.closed_to_arrival),
+                closed_to_departure: row.as_ref().is_some_and(|row| row.closed_to_departure),
+                min_nights_override: row.as_ref().and_then(|row| row.min_nights_override),
+                price_source: night.source.to_string(),
+                pricing_rules: night.rules,
+                suggested_price: night.suggestion.price.to_major(),
+                suggestion_factors: night.suggestion.factors,
+                created_at: row.as_ref().and_then(|row| row.created_at),
+                updated_at: row.as_ref().and_then(|row| row.updated_at),
             }
+        })
+        .collect();
+
+    let json = serde_json::to_vec(&days).unwrap_or_default();
+    let etag = format!("\"{}\"", hex::encode(sha1::Sha1::digest(&json)));
 
-            HttpResponse::Ok()
-                .insert_header((actix_web::http::header::ETAG, etag))
-                .insert_header(("Cache-Control", "private, max-age=0, must-revalidate"))
-                .json(pricing)
+    if let Some(tag) = req.headers().get(actix_web::http::header::IF_NONE_MATCH) {
+        if tag.to_str().ok() == Some(etag.as_str()) {
+            return HttpResponse::NotModified().finish();
         }
-        Err(e) => {
-            log::error!("Failed to fetch calendar data: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Database error: {}", e)
-            }))
+    }
+
+    HttpResponse::Ok()
+        .insert_header((actix_web::http::header::ETAG, etag))
+        .insert_header(("Cache-Control", "private, max-age=0, must-revalidate"))
+        .json(days)
+}
+
+/// The days an update targets, parsed from its request.
+pub(crate) struct CalendarUpdateTarget {
+    dates: Vec<NaiveDate>,
+    start_date: Option<NaiveDate>,
+    end_date: Option<NaiveDate>,
+}
+
+/// Why a calendar update was not applied.
+#[derive(Debug)]
+pub(crate) enum CalendarUpdateError {
+    /// Shown to the host as-is.
+    Invalid(String),
+    /// Blocking would take away nights guests hold.
+    Booked(Vec<BookedDate>),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CalendarUpdateError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl UpdateCalendarDatesRequest {
+    /// Validate the request and parse the days it targets.
+    pub(crate) fn target(&self) -> Result<CalendarUpdateTarget, String> {
+        if self.price.is_some() && self.reset_price {
+            return Err("Give either a price or reset_price, not both".to_string());
+        }
+        if self.price.is_some_and(|price| price < 0.0) {
+            return Err("price must not be negative".to_string());
+        }
+        if self.min_nights_override.is_some() && self.clear_min_nights_override {
+            return Err(
+                "Give either min_nights_override or clear_min_nights_override, not both"
+                    .to_string(),
+            );
+        }
+        if self
+            .min_nights_override
+            .is_some_and(|min| !(1..=MAX_MIN_NIGHTS_OVERRIDE).contains(&min))
+        {
+            return Err(format!(
+                "min_nights_override must be between 1 and {}",
+                MAX_MIN_NIGHTS_OVERRIDE
+            ));
+        }
+
+        let mut dates = Vec::with_capacity(self.dates.len());
+        for date_str in &self.dates {
+            match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+                Ok(date) => dates.push(date),
+                Err(_) => return Err(format!("Invalid date format: {}", date_str)),
+            }
+        }
+
+        let range = match (self.start_date.as_deref(), self.end_date.as_deref()) {
+            (None, None) => None,
+            (Some(start), Some(end)) => match (
+                NaiveDate::parse_from_str(start, "%Y-%m-%d"),
+                NaiveDate::parse_from_str(end, "%Y-%m-%d"),
+            ) {
+                (Ok(start), Ok(end)) if end < start => {
+                    return Err("end_date must not be before start_date".to_string());
+                }
+                (Ok(start), Ok(end)) if (end - start).num_days() >= MAX_UPDATE_RANGE_DAYS => {
+                    return Err(format!(
+                        "A range can cover at most {} days",
+                        MAX_UPDATE_RANGE_DAYS
+                    ));
+                }
+                (Ok(start), Ok(end)) => Some((start, end)),
+                _ => return Err("Invalid date range format".to_string()),
+            },
+            _ => return Err("start_date and end_date must be given together".to_string()),
+        };
+
+        if dates.is_empty() && range.is_none() {
+            return Err("No dates to update".to_string());
+        }
+        if self.weekdays.iter().any(|day| !(1..=7).contains(day)) {
+            return Err(
+                "weekdays must be ISO weekday numbers, 1 (Monday) to 7 (Sunday)".to_string(),
+            );
+        }
+
+        let (start_date, end_date) = range.unzip();
+        Ok(CalendarUpdateTarget {
+            dates,
+            start_date,
+            end_date,
+        })
+    }
+}
+
+/// Apply an update to one listing's calendar. Returns the days that changed, by date.
+///
+/// Run it in a transaction: the booked-date check and the write must see the same bookings.
+pub(crate) async fn apply_calendar_update(
+    conn: &mut PgConnection,
+    listing_id: &str,
+    currency: Currency,
+    body: &UpdateCalendarDatesRequest,
+    target: &CalendarUpdateTarget,
+) -> Result<Vec<ChangedDate>, CalendarUpdateError> {
+    // Get base price from settings or listing, in minor units
+    let price: i64 = match body.price {
+        Some(price) => match Money::from_major(price, currency) {
+            Ok(price) if !price.is_negative() => price.minor(),
+            _ => return Err(CalendarUpdateError::Invalid("Invalid price".to_string())),
+        },
+        None => sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(s.base_price_minor, l.price_per_night_minor, 0)
+            FROM listings l
+            LEFT JOIN listing_settings s ON s.listing_id = l.id
+            WHERE l.id = $1
+            "#,
+        )
+        .bind(listing_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or(0),
+    };
+    // Blocking must not take away nights a guest holds, whether confirmed or still paying
+    if body.is_available == Some(false) {
+        let booked = sqlx::query_as::<_, BookedDate>(&format!(
+            r#"
+            WITH {}
+            SELECT t.date, b.id as booking_id
+            FROM target t
+            JOIN bookings b ON b.listing_id = $1
+             AND b.status IN ('confirmed', 'awaiting_payment')
+             AND t.date >= b.check_in AND t.date < b.check_out
+            ORDER BY t.date
+            "#,
+            TARGET_DATES_CTE
+        ))
+        .bind(listing_id)
+        .bind(&target.dates)
+        .bind(target.start_date)
+        .bind(target.end_date)
+        .bind(&body.weekdays)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if !booked.is_empty() {
+            return Err(CalendarUpdateError::Booked(booked));
         }
     }
+
+    // Without a price, existing days keep theirs and new days get the base price; resetting
+    // puts the base price back and lets pricing rules apply to the days again. Availability and
+    // restrictions that aren't given are kept too.
+    // Days already in the requested state are left alone and not counted as changed.
+    let mut changed = sqlx::query_as::<_, ChangedDate>(&format!(
+        r#"
+        WITH {}
+        INSERT INTO calendar_pricing (
+            listing_id, date, price_minor, is_available, custom_price, closed_to_arrival,
+            closed_to_departure, min_nights_override, updated_at
+        )
+        SELECT $1, t.date, $6, COALESCE($7, TRUE), $8, COALESCE($10, FALSE), COALESCE($11, FALSE),
+            $12, CURRENT_TIMESTAMP
+        FROM target t
+        ON CONFLICT(listing_id, date) DO UPDATE SET
+            price_minor = CASE WHEN $8 OR $9 THEN EXCLUDED.price_minor ELSE calendar_pricing.price_minor END,
+            is_available = COALESCE($7, calendar_pricing.is_available),
+            custom_price = CASE WHEN $9 THEN FALSE ELSE calendar_pricing.custom_price OR $8 END,
+            closed_to_arrival = COALESCE($10, calendar_pricing.closed_to_arrival),
+            closed_to_departure = COALESCE($11, calendar_pricing.closed_to_departure),
+            min_nights_override = CASE
+                WHEN $13 THEN NULL
+                ELSE COALESCE($12, calendar_pricing.min_nights_override)
+            END,
+            updated_at = CURRENT_TIMESTAMP
+        WHERE ($7::BOOLEAN IS NOT NULL AND calendar_pricing.is_available IS DISTINCT FROM $7)
+           OR ($8 AND (calendar_pricing.price_minor <> EXCLUDED.price_minor OR NOT calendar_pricing.custom_price))
+           OR ($9 AND calendar_pricing.custom_price)
+           OR ($10::BOOLEAN IS NOT NULL AND calendar_pricing.closed_to_arrival <> $10)
+           OR ($11::BOOLEAN IS NOT NULL AND calendar_pricing.closed_to_departure <> $11)
+           OR ($12::INT IS NOT NULL AND calendar_pricing.min_nights_override IS DISTINCT FROM $12)
+           OR ($13 AND calendar_pricing.min_nights_override IS NOT NULL)
+        RETURNING date, (xmax = 0) as created
+        "#,
+        TARGET_DATES_CTE
+    ))
+    .bind(listing_id)
+    .bind(&target.dates)
+    .bind(target.start_date)
+    .bind(target.end_date)
+    .bind(&body.weekdays)
+    .bind(price)
+    .bind(body.is_available)
+    .bind(body.price.is_some())
+    .bind(body.reset_price)
+    .bind(body.closed_to_arrival)
+    .bind(body.closed_to_departure)
+    .bind(body.min_nights_override)
+    .bind(body.clear_min_nights_override)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    changed.sort_by_key(|day| day.date);
+    Ok(changed)
 }
 
-/// PUT /api/calendar/:listing_id/dates - Update pricing/availability for specific dates
+/// PUT /api/calendar/:listing_id/dates - Update pricing/availability for dates or a date range
 #[put("/{listing_id}/dates")]
 pub async fn update_calendar_dates(
     pool: web::Data<PgPool>,
@@ -188,93 +588,59 @@ pub async fn update_calendar_dates(
 
     let listing_id = path.into_inner();
 
-    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
-        return response;
-    }
-
-    // Get base price from listing or settings
-    let base_price: f64 = match sqlx::query_scalar::<_, Option<f64>>(
-        "SELECT base_price FROM listing_settings WHERE listing_id = $1",
-    )
-    .bind(&listing_id)
-    .fetch_one(pool.get_ref())
-    .await
-    {
-        Ok(Some(bp)) => bp,
-        _ => sqlx::query_scalar::<_, Option<f64>>(
-            "SELECT price_per_night FROM listings WHERE id = $1",
-        )
-        .bind(&listing_id)
-        .fetch_one(pool.get_ref())
-        .await
-        .unwrap_or(None)
-        .unwrap_or(0.0),
+    let currency = match verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        Ok(currency) => currency,
+        Err(response) => return response,
     };
 
-    let price = body.price.unwrap_or(base_price);
-    let is_available: bool = body.is_available.unwrap_or(true);
-
-    // Update or insert pricing for each date
-    for date_str in &body.dates {
-        let date = match NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-            Ok(d) => d,
-            Err(_) => {
-                return HttpResponse::BadRequest().json(serde_json::json!({
-                    "error": format!("Invalid date format: {}", date_str)
-                }));
-            }
-        };
+    let target = match body.target() {
+        Ok(target) => target,
+        Err(message) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+    };
 
-        // If caller did not provide a price, we should not overwrite existing price on update.
-        // We still need a price value for inserts; use base price fallback for that.
-        let result = if body.price.is_some() {
-            // Explicit price provided: upsert both price and availability
-            sqlx::query(
-                r#"
-                INSERT INTO calendar_pricing (listing_id, date, price, is_available, updated_at)
-                VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
-                ON CONFLICT(listing_id, date) DO UPDATE SET
-                    price = EXCLUDED.price,
-                    is_available = EXCLUDED.is_available,
-                    updated_at = CURRENT_TIMESTAMP
-                "#,
-            )
-            .bind(&listing_id)
-            .bind(date)
-            .bind(price)
-            .bind(is_available)
-            .execute(pool.get_ref())
-            .await
-        } else {
-            // No price provided: only toggle availability on update; keep existing price.
-            // On insert, use base price fallback.
-            sqlx::query(
-                r#"
-                INSERT INTO calendar_pricing (listing_id, date, price, is_available, updated_at)
-                VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
-                ON CONFLICT(listing_id, date) DO UPDATE SET
-                    is_available = EXCLUDED.is_available,
-                    updated_at = CURRENT_TIMESTAMP
-                "#,
-            )
-            .bind(&listing_id)
-            .bind(date)
-            .bind(price)
-            .bind(is_available)
-            .execute(pool.get_ref())
-            .await
-        };
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start calendar update: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
 
-        if let Err(e) = result {
-            log::error!("Failed to update calendar date {}: {:?}", date_str, e);
-            return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": format!("Failed to update date {}: {}", date_str, e)
+    let changed = match apply_calendar_update(&mut tx, &listing_id, currency, &body, &target).await
+    {
+        Ok(changed) => changed,
+        Err(CalendarUpdateError::Invalid(message)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({ "error": message }));
+        }
+        Err(CalendarUpdateError::Booked(booked)) => {
+            return HttpResponse::Conflict().json(serde_json::json!({
+                "error": "Some of these dates are already booked",
+                "booked_dates": booked
             }));
         }
+        Err(CalendarUpdateError::Database(e)) => {
+            log::error!("Failed to update calendar dates: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to update calendar dates" }));
+        }
+    };
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit calendar update: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update calendar dates" }));
     }
 
+    let created = changed.iter().filter(|day| day.created).count();
     HttpResponse::Ok().json(serde_json::json!({
-        "message": "Calendar dates updated successfully"
+        "message": "Calendar dates updated successfully",
+        "changed": changed.len(),
+        "created": created,
+        "updated": changed.len() - created,
+        "changed_dates": changed.iter().map(|day| day.date).collect::<Vec<_>>()
     }))
 }
 
@@ -296,12 +662,10 @@ pub async fn get_settings(
         return response;
     }
 
-    match sqlx::query_as::<_, ListingSettings>(
-        "SELECT * FROM listing_settings WHERE listing_id = $1",
-    )
-    .bind(&listing_id)
-    .fetch_optional(pool.get_ref())
-    .await
+    match sqlx::query_as::<_, ListingSettings>(LISTING_SETTINGS_SELECT)
+        .bind(&listing_id)
+        .fetch_optional(pool.get_ref())
+        .await
     {
         Ok(Some(settings)) => HttpResponse::Ok().json(settings),
         Ok(None) => {
@@ -319,12 +683,10 @@ pub async fn get_settings(
             match result {
                 Ok(_) => {
                     // Fetch the newly created settings
-                    match sqlx::query_as::<_, ListingSettings>(
-                        "SELECT * FROM listing_settings WHERE listing_id = $1",
-                    )
-                    .bind(&listing_id)
-                    .fetch_one(pool.get_ref())
-                    .await
+                    match sqlx::query_as::<_, ListingSettings>(LISTING_SETTINGS_SELECT)
+                        .bind(&listing_id)
+                        .fetch_one(pool.get_ref())
+                        .await
                     {
                         Ok(settings) => HttpResponse::Ok().json(settings),
                         Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
@@ -361,83 +723,166 @@ pub async fn update_settings(
 
     let listing_id = path.into_inner();
 
-    if let Err(response) = verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
-        return response;
+    let currency = match verify_listing_ownership(pool.get_ref(), &listing_id, user_id).await {
+        Ok(currency) => currency,
+        Err(response) => return response,
+    };
+
+    let update = match body.validate(currency) {
+        Ok(update) => update,
+        Err(fields) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "Invalid settings",
+                "fields": fields
+            }));
+        }
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            log::error!("Failed to start settings update: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // Settings are created with their defaults the first time they are read or updated
+    if let Err(e) = sqlx::query(
+        r#"
+        INSERT INTO listing_settings (listing_id)
+        VALUES ($1)
+        ON CONFLICT (listing_id) DO NOTHING
+        "#,
+    )
+    .bind(&listing_id)
+    .execute(&mut *tx)
+    .await
+    {
+        log::error!("Failed to create settings: {:?}", e);
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": format!("Failed to create settings: {}", e)
+        }));
     }
 
     // Build dynamic update query
     let mut query_builder: sqlx::QueryBuilder<sqlx::Postgres> =
         sqlx::QueryBuilder::new("UPDATE listing_settings SET updated_at = CURRENT_TIMESTAMP");
 
-    if let Some(base_price) = body.base_price {
-        query_builder.push(", base_price = ");
+    if let Some(base_price) = update.base_price {
+        query_builder.push(", base_price_minor = ");
         query_builder.push_bind(base_price);
     }
-    if let Some(weekend_price) = body.weekend_price {
-        query_builder.push(", weekend_price = ");
+    if let Some(weekend_price) = update.weekend_price {
+        query_builder.push(", weekend_price_minor = ");
         query_builder.push_bind(weekend_price);
     }
-    if let Some(smart_pricing) = body.smart_pricing_enabled {
+    if let Some(smart_pricing) = update.smart_pricing_enabled {
         query_builder.push(", smart_pricing_enabled = ");
         query_builder.push_bind(smart_pricing);
     }
-    if let Some(weekly_discount) = body.weekly_discount {
+    if let Some(min_price) = update.smart_min_price {
+        query_builder.push(", smart_min_price_minor = ");
+        query_builder.push_bind(min_price);
+    }
+    if let Some(max_price) = update.smart_max_price {
+        query_builder.push(", smart_max_price_minor = ");
+        query_builder.push_bind(max_price);
+    }
+    if let Some(weekly_discount) = update.weekly_discount {
         query_builder.push(", weekly_discount = ");
         query_builder.push_bind(weekly_discount);
     }
-    if let Some(monthly_discount) = body.monthly_discount {
+    if let Some(monthly_discount) = update.monthly_discount {
         query_builder.push(", monthly_discount = ");
         query_builder.push_bind(monthly_discount);
     }
-    if let Some(min_nights) = body.min_nights {
+    if let Some(min_nights) = update.min_nights {
         query_builder.push(", min_nights = ");
         query_builder.push_bind(min_nights);
     }
-    if let Some(max_nights) = body.max_nights {
+    if let Some(max_nights) = update.max_nights {
         query_builder.push(", max_nights = ");
         query_builder.push_bind(max_nights);
     }
-    if let Some(ref advance_notice) = body.advance_notice {
+    if let Some(advance_notice) = update.advance_notice {
         query_builder.push(", advance_notice = ");
         query_builder.push_bind(advance_notice);
     }
-    if let Some(ref cutoff_time) = body.same_day_cutoff_time {
+    if let Some(cutoff_time) = update.same_day_cutoff_time {
         query_builder.push(", same_day_cutoff_time = ");
         query_builder.push_bind(cutoff_time);
     }
-    if let Some(ref prep_time) = body.preparation_time {
+    if let Some(prep_time) = update.preparation_time {
         query_builder.push(", preparation_time = ");
         query_builder.push_bind(prep_time);
     }
-    if let Some(window) = body.availability_window {
+    if let Some(window) = update.availability_window {
         query_builder.push(", availability_window = ");
         query_builder.push_bind(window);
     }
 
     query_builder.push(" WHERE listing_id = ");
     query_builder.push_bind(&listing_id);
-
-    match query_builder.build().execute(pool.get_ref()).await {
-        Ok(_) => {
-            // Fetch updated settings
-            match sqlx::query_as::<_, ListingSettings>(
-                "SELECT * FROM listing_settings WHERE listing_id = $1",
-            )
-            .bind(&listing_id)
-            .fetch_one(pool.get_ref())
-            .await
-            {
-                Ok(settings) => HttpResponse::Ok().json(settings),
-                Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("Failed to fetch updated settings: {}", e)
-                })),
-            }
-        }
+    query_builder.push(
+        r#"
+        RETURNING
+            COALESCE(min_nights, 1) <= COALESCE(max_nights, min_nights, 1) as nights_valid,
+            smart_min_price_minor IS NULL OR smart_max_price_minor IS NULL
+                OR smart_min_price_minor <= smart_max_price_minor as smart_prices_valid
+        "#,
+    );
+
+    // One side of a pair may be checked against the value already stored for the other
+    let (nights_valid, smart_prices_valid) = match query_builder
+        .build_query_as::<(bool, bool)>()
+        .fetch_one(&mut *tx)
+        .await
+    {
+        Ok(valid) => valid,
         Err(e) => {
             log::error!("Failed to update settings: {:?}", e);
-            HttpResponse::InternalServerError().json(serde_json::json!({
+            return HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Failed to update settings: {}", e)
-            }))
+            }));
+        }
+    };
+    let mut fields = crate::settings::FieldErrors::new();
+    if !nights_valid {
+        fields.insert("max_nights", "must not be below min_nights".to_string());
+    }
+    if !smart_prices_valid {
+        fields.insert(
+            "smart_max_price",
+            "must not be below smart_min_price".to_string(),
+        );
+    }
+    if !fields.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Invalid settings",
+            "fields": fields
+        }));
+    }
+
+    // Fetch updated settings
+    let settings = match sqlx::query_as::<_, ListingSettings>(LISTING_SETTINGS_SELECT)
+        .bind(&listing_id)
+        .fetch_one(&mut *tx)
+        .await
+    {
+        Ok(settings) => settings,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to fetch updated settings: {}", e)
+            }));
         }
+    };
+
+    if let Err(e) = tx.commit().await {
+        log::error!("Failed to commit settings: {:?}", e);
+        return HttpResponse::InternalServerError()
+            .json(serde_json::json!({ "error": "Failed to update settings" }));
     }
+
+    HttpResponse::Ok().json(settings)
 }
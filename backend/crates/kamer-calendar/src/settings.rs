@@ -0,0 +1,352 @@
+use crate::routes::{UpdateSettingsRequest, MAX_MIN_NIGHTS_OVERRIDE};
+use chrono::{NaiveTime, Timelike};
+use kamer_core::{Currency, Money};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Furthest ahead, in months, a listing can open its calendar.
+pub const MAX_AVAILABILITY_WINDOW_MONTHS: i32 = 24;
+
+/// Validation errors keyed by the request field they belong to.
+pub type FieldErrors = BTreeMap<&'static str, String>;
+
+// ============================================================================
+// Setting Values
+// ============================================================================
+
+/// How long before check-in a guest must book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdvanceNotice {
+    SameDay,
+    OneDay,
+    TwoDays,
+    ThreeDays,
+    FourDays,
+    FiveDays,
+    SixDays,
+    SevenDays,
+}
+
+impl AdvanceNotice {
+    pub const ALL: [AdvanceNotice; 8] = [
+        AdvanceNotice::SameDay,
+        AdvanceNotice::OneDay,
+        AdvanceNotice::TwoDays,
+        AdvanceNotice::ThreeDays,
+        AdvanceNotice::FourDays,
+        AdvanceNotice::FiveDays,
+        AdvanceNotice::SixDays,
+        AdvanceNotice::SevenDays,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AdvanceNotice::SameDay => "same_day",
+            AdvanceNotice::OneDay => "1_day",
+            AdvanceNotice::TwoDays => "2_days",
+            AdvanceNotice::ThreeDays => "3_days",
+            AdvanceNotice::FourDays => "4_days",
+            AdvanceNotice::FiveDays => "5_days",
+            AdvanceNotice::SixDays => "6_days",
+            AdvanceNotice::SevenDays => "7_days",
+        }
+    }
+}
+
+impl FromStr for AdvanceNotice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|notice| notice.as_str() == s)
+            .ok_or_else(|| one_of(Self::ALL.iter().map(|notice| notice.as_str())))
+    }
+}
+
+impl fmt::Display for AdvanceNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Nights blocked before and after each reservation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreparationTime {
+    None,
+    OneNight,
+    TwoNights,
+}
+
+impl PreparationTime {
+    pub const ALL: [PreparationTime; 3] = [
+        PreparationTime::None,
+        PreparationTime::OneNight,
+        PreparationTime::TwoNights,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PreparationTime::None => "none",
+            PreparationTime::OneNight => "1_night",
+            PreparationTime::TwoNights => "2_nights",
+        }
+    }
+}
+
+impl FromStr for PreparationTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|time| time.as_str() == s)
+            .ok_or_else(|| one_of(Self::ALL.iter().map(|time| time.as_str())))
+    }
+}
+
+impl fmt::Display for PreparationTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Latest time of day a same-day booking can be made, stored as 24-hour `HH:MM`.
+///
+/// Parsing also takes an unpadded hour, as in `9:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CutoffTime(NaiveTime);
+
+impl FromStr for CutoffTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || "must be a time of day as HH:MM".to_string();
+        let (hour, minute) = s.trim().split_once(':').ok_or_else(invalid)?;
+        if !(1..=2).contains(&hour.len()) || minute.len() != 2 {
+            return Err(invalid());
+        }
+        let hour = hour.parse::<u32>().map_err(|_| invalid())?;
+        let minute = minute.parse::<u32>().map_err(|_| invalid())?;
+        NaiveTime::from_hms_opt(hour, minute, 0)
+            .map(CutoffTime)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for CutoffTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0.hour(), self.0.minute())
+    }
+}
+
+/// A discount in percent, from 0 to 100.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(transparent)]
+#[sqlx(transparent)]
+pub struct Discount(f64);
+
+impl Discount {
+    pub fn new(percent: f64) -> Result<Self, String> {
+        if percent.is_finite() && (0.0..=100.0).contains(&percent) {
+            Ok(Discount(percent))
+        } else {
+            Err("must be between 0 and 100".to_string())
+        }
+    }
+}
+
+fn one_of<'a>(values: impl Iterator<Item = &'a str>) -> String {
+    format!("must be one of {}", values.collect::<Vec<_>>().join(", "))
+}
+
+/// Serde and Postgres `TEXT` support for a setting stored as its string form.
+macro_rules! text_setting {
+    ($setting:ty) => {
+        impl Serialize for $setting {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $setting {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                value.parse().map_err(serde::de::Error::custom)
+            }
+        }
+
+        impl Type<Postgres> for $setting {
+            fn type_info() -> PgTypeInfo {
+                <String as Type<Postgres>>::type_info()
+            }
+
+            fn compatible(ty: &PgTypeInfo) -> bool {
+                <String as Type<Postgres>>::compatible(ty)
+            }
+        }
+
+        impl<'r> Decode<'r, Postgres> for $setting {
+            fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+                let value = <&str as Decode<Postgres>>::decode(value)?;
+                Ok(value.parse::<$setting>()?)
+            }
+        }
+
+        impl Encode<'_, Postgres> for $setting {
+            fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+                <String as Encode<Postgres>>::encode_by_ref(&self.to_string(), buf)
+            }
+        }
+    };
+}
+
+text_setting!(AdvanceNotice);
+text_setting!(PreparationTime);
+text_setting!(CutoffTime);
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+/// A settings update that passed validation, with prices in minor units.
+#[derive(Debug, Default)]
+pub(crate) struct SettingsUpdate {
+    pub(crate) base_price: Option<i64>,
+    pub(crate) weekend_price: Option<i64>,
+    pub(crate) smart_pricing_enabled: Option<bool>,
+    pub(crate) smart_min_price: Option<i64>,
+    pub(crate) smart_max_price: Option<i64>,
+    pub(crate) weekly_discount: Option<Discount>,
+    pub(crate) monthly_discount: Option<Discount>,
+    pub(crate) min_nights: Option<i32>,
+    pub(crate) max_nights: Option<i32>,
+    pub(crate) advance_notice: Option<AdvanceNotice>,
+    pub(crate) same_day_cutoff_time: Option<CutoffTime>,
+    pub(crate) preparation_time: Option<PreparationTime>,
+    pub(crate) availability_window: Option<i32>,
+}
+
+/// Run `check` on a present value, recording its error against `field`.
+fn check<T, U>(
+    errors: &mut FieldErrors,
+    field: &'static str,
+    value: Option<T>,
+    check: impl FnOnce(T) -> Result<U, String>,
+) -> Option<U> {
+    match value.map(check)? {
+        Ok(value) => Some(value),
+        Err(message) => {
+            errors.insert(field, message);
+            None
+        }
+    }
+}
+
+fn in_range(value: i32, min: i32, max: i32) -> Result<i32, String> {
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("must be between {} and {}", min, max))
+    }
+}
+
+impl UpdateSettingsRequest {
+    /// Check every field, collecting all errors rather than stopping at the first.
+    pub(crate) fn validate(&self, currency: Currency) -> Result<SettingsUpdate, FieldErrors> {
+        let mut errors = FieldErrors::new();
+        let price = |amount: f64| match Money::from_major(amount, currency) {
+            Ok(money) if money.minor() >= 0 => Ok(money.minor()),
+            Ok(_) => Err("must not be negative".to_string()),
+            Err(_) => Err("must be a valid amount".to_string()),
+        };
+
+        let update = SettingsUpdate {
+            base_price: check(&mut errors, "base_price", self.base_price, price),
+            weekend_price: check(&mut errors, "weekend_price", self.weekend_price, price),
+            smart_pricing_enabled: self.smart_pricing_enabled,
+            smart_min_price: check(&mut errors, "smart_min_price", self.smart_min_price, price),
+            smart_max_price: check(
+                &mut errors,
+                "smart_max_price",
+                self.smart_max_price,
+                |amount| match price(amount)? {
+                    0 => Err("must be greater than 0".to_string()),
+                    minor => Ok(minor),
+                },
+            ),
+            weekly_discount: check(
+                &mut errors,
+                "weekly_discount",
+                self.weekly_discount,
+                Discount::new,
+            ),
+            monthly_discount: check(
+                &mut errors,
+                "monthly_discount",
+                self.monthly_discount,
+                Discount::new,
+            ),
+            min_nights: check(&mut errors, "min_nights", self.min_nights, |nights| {
+                in_range(nights, 1, MAX_MIN_NIGHTS_OVERRIDE)
+            }),
+            max_nights: check(&mut errors, "max_nights", self.max_nights, |nights| {
+                if nights >= 1 {
+                    Ok(nights)
+                } else {
+                    Err("must be at least 1".to_string())
+                }
+            }),
+            advance_notice: check(
+                &mut errors,
+                "advance_notice",
+                self.advance_notice.as_deref(),
+                str::parse,
+            ),
+            same_day_cutoff_time: check(
+                &mut errors,
+                "same_day_cutoff_time",
+                self.same_day_cutoff_time.as_deref(),
+                str::parse,
+            ),
+            preparation_time: check(
+                &mut errors,
+                "preparation_time",
+                self.preparation_time.as_deref(),
+                str::parse,
+            ),
+            availability_window: check(
+                &mut errors,
+                "availability_window",
+                self.availability_window,
+                |months| in_range(months, 1, MAX_AVAILABILITY_WINDOW_MONTHS),
+            ),
+        };
+
+        if let (Some(min), Some(max)) = (update.smart_min_price, update.smart_max_price) {
+            if max < min {
+                errors.insert(
+                    "smart_max_price",
+                    "must not be below smart_min_price".to_string(),
+                );
+            }
+        }
+        if let (Some(min), Some(max)) = (update.min_nights, update.max_nights) {
+            if max < min {
+                errors.insert("max_nights", "must not be below min_nights".to_string());
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(update)
+        } else {
+            Err(errors)
+        }
+    }
+}
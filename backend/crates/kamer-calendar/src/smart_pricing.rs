@@ -0,0 +1,377 @@
+use chrono::{Datelike, NaiveDate};
+use kamer_core::{Money, MoneyError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+/// Fewer published listings than this in the city and occupancy is left out of suggestions.
+const MIN_PEER_LISTINGS: i64 = 5;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+/// One reason a suggested price differs from the listing's usual price.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceFactor {
+    /// `lead_time`, `day_of_week`, `occupancy`, `min_price` or `max_price`.
+    pub factor: String,
+    /// Change to the usual price, in percent.
+    pub percent: f64,
+    pub description: String,
+}
+
+/// A suggested nightly price and the factors behind it.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub price: Money,
+    pub factors: Vec<PriceFactor>,
+}
+
+/// What smart pricing knows about a listing besides its usual price.
+#[derive(Debug)]
+pub(crate) struct SmartPricingInputs {
+    /// The day lead time is counted from.
+    pub(crate) today: NaiveDate,
+    pub(crate) min_price_minor: Option<i64>,
+    pub(crate) max_price_minor: Option<i64>,
+    /// Whether the host set a weekend price, which then stands in for the day-of-week factor.
+    pub(crate) weekend_price_set: bool,
+    pub(crate) occupancy: HashMap<NaiveDate, CityOccupancy>,
+}
+
+/// How many of the other published listings in the city are booked on a night.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CityOccupancy {
+    pub(crate) listings: i64,
+    pub(crate) booked: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ListingOccupancy {
+    listing_id: String,
+    date: NaiveDate,
+    listings: i64,
+    booked: i64,
+}
+
+// ============================================================================
+// Suggestions
+// ============================================================================
+
+impl PriceFactor {
+    fn new(factor: &str, percent: f64, description: String) -> Self {
+        Self {
+            factor: factor.to_string(),
+            percent,
+            description,
+        }
+    }
+}
+
+fn lead_time_factor(days: i64) -> Option<PriceFactor> {
+    let percent = match days {
+        0..=2 => -15.0,
+        3..=6 => -5.0,
+        60.. => 5.0,
+        _ => return None,
+    };
+    let when = match days {
+        0 => "Tonight".to_string(),
+        1 => "Tomorrow".to_string(),
+        days => format!("In {} days", days),
+    };
+    let reason = if percent < 0.0 {
+        "last-minute nights are discounted so they still get booked"
+    } else {
+        "nights booked far ahead rarely need a discount"
+    };
+    let description = format!("{}: {}", when, reason);
+    Some(PriceFactor::new("lead_time", percent, description))
+}
+
+fn day_of_week_factor(date: NaiveDate) -> Option<PriceFactor> {
+    let day = match date.weekday().number_from_monday() {
+        5 => "Friday",
+        6 => "Saturday",
+        _ => return None,
+    };
+    Some(PriceFactor::new(
+        "day_of_week",
+        10.0,
+        format!("{} nights are in higher demand", day),
+    ))
+}
+
+fn occupancy_factor(occupancy: &CityOccupancy) -> Option<PriceFactor> {
+    if occupancy.listings < MIN_PEER_LISTINGS {
+        return None;
+    }
+    let rate = occupancy.booked as f64 / occupancy.listings as f64;
+    let percent = match rate {
+        rate if rate >= 0.8 => 20.0,
+        rate if rate >= 0.6 => 10.0,
+        rate if rate < 0.2 => -10.0,
+        _ => return None,
+    };
+    Some(PriceFactor::new(
+        "occupancy",
+        percent,
+        format!(
+            "{} of {} other listings in the city are booked",
+            occupancy.booked, occupancy.listings
+        ),
+    ))
+}
+
+impl SmartPricingInputs {
+    /// Suggest a price for the night of `date` starting from the listing's usual price for it.
+    ///
+    /// Factor percentages are added up and applied once, then the result is kept within the
+    /// host's bounds. The same inputs always give the same suggestion.
+    pub(crate) fn suggest(&self, usual: Money, date: NaiveDate) -> Result<Suggestion, MoneyError> {
+        let days_ahead = (date - self.today).num_days();
+        let mut factors: Vec<PriceFactor> = [
+            (days_ahead >= 0)
+                .then(|| lead_time_factor(days_ahead))
+                .flatten(),
+            (!self.weekend_price_set)
+                .then(|| day_of_week_factor(date))
+                .flatten(),
+            self.occupancy.get(&date).and_then(occupancy_factor),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let percent: f64 = factors.iter().map(|factor| factor.percent).sum();
+        let price = usual.checked_add(usual.percent(percent)?)?.non_negative();
+
+        let currency = usual.currency();
+        let bounded = match (self.min_price_minor, self.max_price_minor) {
+            (Some(min), _) if price.minor() < min => {
+                Some(("min_price", min, "Raised to your minimum price"))
+            }
+            (_, Some(max)) if price.minor() > max => {
+                Some(("max_price", max, "Lowered to your maximum price"))
+            }
+            _ => None,
+        };
+        let price = match bounded {
+            Some((factor, bound, description)) if usual.is_positive() => {
+                let change = (bound - price.minor()) as f64 / usual.minor() as f64 * 100.0;
+                factors.push(PriceFactor::new(
+                    factor,
+                    (change * 10.0).round() / 10.0,
+                    description.to_string(),
+                ));
+                Money::from_minor(bound, currency)
+            }
+            Some((_, bound, _)) => Money::from_minor(bound, currency),
+            None => price,
+        };
+
+        Ok(Suggestion { price, factors })
+    }
+}
+
+/// Occupancy of the other published listings in each listing's city for each night from `from`
+/// up to, not including, `to`, keyed by listing id. Listings without peers are left out.
+pub(crate) async fn city_occupancy(
+    conn: &mut PgConnection,
+    listing_ids: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<String, HashMap<NaiveDate, CityOccupancy>>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ListingOccupancy>(
+        r#"
+        WITH peers AS (
+            SELECT l.id as listing_id, p.id
+            FROM listings l
+            JOIN listings p ON LOWER(p.city) = LOWER(l.city)
+             AND p.id <> l.id
+             AND p.status = 'published'
+            WHERE l.id = ANY($1)
+        ),
+        peer_counts AS (
+            SELECT listing_id, COUNT(*) as listings FROM peers GROUP BY listing_id
+        )
+        SELECT
+            c.listing_id,
+            d::DATE as date,
+            c.listings,
+            COUNT(DISTINCT b.listing_id) as booked
+        FROM peer_counts c
+        CROSS JOIN generate_series($2::DATE, $3::DATE - 1, INTERVAL '1 day') AS d
+        LEFT JOIN bookings b ON b.listing_id IN (
+                SELECT p.id FROM peers p WHERE p.listing_id = c.listing_id
+            )
+         AND b.status IN ('confirmed', 'completed')
+         AND b.check_in <= d::DATE AND b.check_out > d::DATE
+        GROUP BY c.listing_id, c.listings, d
+        "#,
+    )
+    .bind(listing_ids)
+    .bind(from)
+    .bind(to)
+    .fetch_all(conn)
+    .await?;
+
+    let mut occupancy: HashMap<String, HashMap<NaiveDate, CityOccupancy>> = HashMap::new();
+    for row in rows {
+        occupancy.entry(row.listing_id).or_default().insert(
+            row.date,
+            CityOccupancy {
+                listings: row.listings,
+                booked: row.booked,
+            },
+        );
+    }
+    Ok(occupancy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kamer_core::Currency;
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn xaf(minor: i64) -> Money {
+        Money::from_minor(minor, Currency::XAF)
+    }
+
+    fn percent(factor: Option<PriceFactor>) -> Option<f64> {
+        factor.map(|factor| factor.percent)
+    }
+
+    fn occupancy(listings: i64, booked: i64) -> CityOccupancy {
+        CityOccupancy { listings, booked }
+    }
+
+    /// Inputs with today on Monday 2 March 2026 and no bounds or occupancy data.
+    fn inputs() -> SmartPricingInputs {
+        SmartPricingInputs {
+            today: day("2026-03-02"),
+            min_price_minor: None,
+            max_price_minor: None,
+            weekend_price_set: false,
+            occupancy: HashMap::new(),
+        }
+    }
+
+    fn factor_names(suggestion: &Suggestion) -> Vec<&str> {
+        suggestion
+            .factors
+            .iter()
+            .map(|factor| factor.factor.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lead_time_discounts_last_minute_and_raises_far_ahead() {
+        assert_eq!(percent(lead_time_factor(0)), Some(-15.0));
+        assert_eq!(percent(lead_time_factor(2)), Some(-15.0));
+        assert_eq!(percent(lead_time_factor(3)), Some(-5.0));
+        assert_eq!(percent(lead_time_factor(6)), Some(-5.0));
+        assert_eq!(percent(lead_time_factor(7)), None);
+        assert_eq!(percent(lead_time_factor(59)), None);
+        assert_eq!(percent(lead_time_factor(60)), Some(5.0));
+        assert_eq!(percent(lead_time_factor(365)), Some(5.0));
+        assert_eq!(
+            lead_time_factor(1).unwrap().description,
+            "Tomorrow: last-minute nights are discounted so they still get booked"
+        );
+    }
+
+    #[test]
+    fn day_of_week_raises_friday_and_saturday_nights() {
+        assert_eq!(percent(day_of_week_factor(day("2026-03-05"))), None);
+        assert_eq!(percent(day_of_week_factor(day("2026-03-06"))), Some(10.0));
+        assert_eq!(percent(day_of_week_factor(day("2026-03-07"))), Some(10.0));
+        assert_eq!(percent(day_of_week_factor(day("2026-03-08"))), None);
+    }
+
+    #[test]
+    fn occupancy_needs_enough_peers() {
+        assert_eq!(percent(occupancy_factor(&occupancy(4, 4))), None);
+        assert_eq!(percent(occupancy_factor(&occupancy(10, 8))), Some(20.0));
+        assert_eq!(percent(occupancy_factor(&occupancy(10, 6))), Some(10.0));
+        assert_eq!(percent(occupancy_factor(&occupancy(10, 5))), None);
+        assert_eq!(percent(occupancy_factor(&occupancy(10, 2))), None);
+        assert_eq!(percent(occupancy_factor(&occupancy(10, 1))), Some(-10.0));
+        assert_eq!(percent(occupancy_factor(&occupancy(5, 0))), Some(-10.0));
+    }
+
+    #[test]
+    fn factors_add_up_before_being_applied() {
+        let mut inputs = inputs();
+        // Friday, four days ahead, in a busy city: -5% + 10% + 20%
+        inputs.occupancy.insert(day("2026-03-06"), occupancy(10, 9));
+        let suggestion = inputs.suggest(xaf(10_000), day("2026-03-06")).unwrap();
+        assert_eq!(suggestion.price, xaf(12_500));
+        assert_eq!(
+            factor_names(&suggestion),
+            ["lead_time", "day_of_week", "occupancy"]
+        );
+    }
+
+    #[test]
+    fn weekend_price_replaces_the_day_of_week_factor() {
+        let inputs = SmartPricingInputs {
+            weekend_price_set: true,
+            ..inputs()
+        };
+        let suggestion = inputs.suggest(xaf(10_000), day("2026-03-13")).unwrap();
+        assert_eq!(suggestion.price, xaf(10_000));
+        assert!(suggestion.factors.is_empty());
+    }
+
+    #[test]
+    fn past_nights_get_no_lead_time_factor() {
+        let suggestion = inputs().suggest(xaf(10_000), day("2026-03-01")).unwrap();
+        assert_eq!(suggestion.price, xaf(10_000));
+        assert!(suggestion.factors.is_empty());
+    }
+
+    #[test]
+    fn suggestions_stay_within_the_hosts_bounds() {
+        let inputs = SmartPricingInputs {
+            min_price_minor: Some(9_000),
+            max_price_minor: Some(10_500),
+            ..inputs()
+        };
+
+        // Tonight: -15% would be 8 500
+        let low = inputs.suggest(xaf(10_000), day("2026-03-02")).unwrap();
+        assert_eq!(low.price, xaf(9_000));
+        assert_eq!(factor_names(&low), ["lead_time", "min_price"]);
+        assert_eq!(low.factors[1].percent, 5.0);
+
+        // Friday, 60+ days ahead: +15% would be 11 500
+        let high = inputs.suggest(xaf(10_000), day("2026-05-08")).unwrap();
+        assert_eq!(high.price, xaf(10_500));
+        assert_eq!(
+            factor_names(&high),
+            ["lead_time", "day_of_week", "max_price"]
+        );
+        assert_eq!(high.factors[2].percent, -10.0);
+
+        // Within bounds: left alone
+        let middle = inputs.suggest(xaf(10_000), day("2026-03-18")).unwrap();
+        assert_eq!(middle.price, xaf(10_000));
+    }
+
+    #[test]
+    fn bounds_apply_to_a_zero_usual_price() {
+        let inputs = SmartPricingInputs {
+            min_price_minor: Some(5_000),
+            ..inputs()
+        };
+        let suggestion = inputs.suggest(xaf(0), day("2026-03-18")).unwrap();
+        assert_eq!(suggestion.price, xaf(5_000));
+        assert!(suggestion.factors.is_empty());
+    }
+}
@@ -6,7 +6,9 @@ pub use uuid::Uuid;
 
 // Modules
 pub mod error;
+pub mod money;
 pub mod types;
 
 // Re-export key items
 pub use error::{AppError, AppResult};
+pub use money::{Currency, Money, MoneyError};
@@ -0,0 +1,582 @@
+//! Exact money amounts.
+//!
+//! A [`Money`] is an integer number of minor units (cents, or whole francs for XAF) plus an
+//! ISO 4217 currency. Arithmetic is checked: adding different currencies or overflowing is an
+//! error rather than a silently wrong total. Wherever a fractional minor unit appears
+//! (converting from a float, taking a percentage) it is rounded half away from zero.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::types::{PgRecordDecoder, PgRecordEncoder};
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Currencies without minor units. Keep in sync with `currency_exponent()` in the migrations.
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &[
+    "BIF", "CLP", "DJF", "GNF", "ISK", "JPY", "KMF", "KRW", "PYG", "RWF", "UGX", "VND", "VUV",
+    "XAF", "XOF", "XPF",
+];
+/// Currencies with three decimal places.
+const THREE_DECIMAL_CURRENCIES: &[&str] = &["BHD", "IQD", "JOD", "KWD", "LYD", "OMR", "TND"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoneyError {
+    InvalidCurrency,
+    InvalidAmount,
+    CurrencyMismatch(Currency, Currency),
+    Overflow,
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCurrency => write!(f, "invalid currency code"),
+            Self::InvalidAmount => write!(f, "invalid amount"),
+            Self::CurrencyMismatch(a, b) => write!(f, "currency mismatch: {} and {}", a, b),
+            Self::Overflow => write!(f, "amount out of range"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+// ============================================================================
+// Currency
+// ============================================================================
+
+/// An upper-case ISO 4217 currency code.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    pub const XAF: Currency = Currency(*b"XAF");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const USD: Currency = Currency(*b"USD");
+
+    pub fn new(code: &str) -> Result<Self, MoneyError> {
+        let code = code.trim().as_bytes();
+        match code {
+            [a, b, c] if code.iter().all(u8::is_ascii_alphabetic) => Ok(Self([
+                a.to_ascii_uppercase(),
+                b.to_ascii_uppercase(),
+                c.to_ascii_uppercase(),
+            ])),
+            _ => Err(MoneyError::InvalidCurrency),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Always three ASCII letters
+        std::str::from_utf8(&self.0).unwrap_or_default()
+    }
+
+    /// Number of decimal places in the currency's minor unit.
+    pub fn exponent(&self) -> u32 {
+        if ZERO_DECIMAL_CURRENCIES.contains(&self.as_str()) {
+            0
+        } else if THREE_DECIMAL_CURRENCIES.contains(&self.as_str()) {
+            3
+        } else {
+            2
+        }
+    }
+
+    fn minor_per_major(&self) -> i64 {
+        10_i64.pow(self.exponent())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Currency({})", self.as_str())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Self::new(&code).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Type<Postgres> for Currency {
+    fn type_info() -> PgTypeInfo {
+        <String as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <String as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Currency {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let code = <&str as Decode<Postgres>>::decode(value)?;
+        Ok(Self::new(code)?)
+    }
+}
+
+impl Encode<'_, Postgres> for Currency {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        <&str as Encode<Postgres>>::encode_by_ref(&self.as_str(), buf)
+    }
+}
+
+// ============================================================================
+// Money
+// ============================================================================
+
+/// An exact amount in a currency's minor units.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+    minor: i64,
+    currency: Currency,
+}
+
+/// Divide, rounding half away from zero.
+fn div_round(numerator: i128, denominator: i128) -> i128 {
+    let quotient = numerator / denominator;
+    let remainder = numerator % denominator;
+    if remainder.abs() * 2 >= denominator.abs() {
+        quotient + numerator.signum() * denominator.signum()
+    } else {
+        quotient
+    }
+}
+
+impl Money {
+    pub const fn from_minor(minor: i64, currency: Currency) -> Self {
+        Self { minor, currency }
+    }
+
+    pub const fn zero(currency: Currency) -> Self {
+        Self::from_minor(0, currency)
+    }
+
+    /// Convert a major-unit float (e.g. from a JSON body), rounding to the nearest minor unit.
+    pub fn from_major(amount: f64, currency: Currency) -> Result<Self, MoneyError> {
+        if !amount.is_finite() {
+            return Err(MoneyError::InvalidAmount);
+        }
+        let minor = (amount * currency.minor_per_major() as f64).round();
+        if minor.abs() >= i64::MAX as f64 {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(Self::from_minor(minor as i64, currency))
+    }
+
+    /// Parse a decimal string such as `"1250.5"`, rounding extra decimals.
+    pub fn parse(amount: &str, currency: Currency) -> Result<Self, MoneyError> {
+        let amount = amount.trim();
+        let (negative, digits) = match amount.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, amount),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() && fraction.is_empty()
+            || fraction.len() > 18
+            || !whole
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(MoneyError::InvalidAmount);
+        }
+
+        let exponent = currency.exponent() as usize;
+        let parse = |s: &str| -> Result<i128, MoneyError> {
+            if s.is_empty() {
+                Ok(0)
+            } else {
+                s.parse::<i128>().map_err(|_| MoneyError::Overflow)
+            }
+        };
+        let scale = 10_i128.pow(fraction.len() as u32);
+        let exact = parse(whole)?
+            .checked_mul(scale)
+            .and_then(|w| w.checked_add(parse(fraction).ok()?))
+            .ok_or(MoneyError::Overflow)?;
+        let minor = if fraction.len() > exponent {
+            div_round(exact, 10_i128.pow((fraction.len() - exponent) as u32))
+        } else {
+            exact * 10_i128.pow((exponent - fraction.len()) as u32)
+        };
+        let minor = if negative { -minor } else { minor };
+        i64::try_from(minor)
+            .map(|minor| Self::from_minor(minor, currency))
+            .map_err(|_| MoneyError::Overflow)
+    }
+
+    pub fn minor(&self) -> i64 {
+        self.minor
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// The amount in major units, for JSON responses and legacy `DOUBLE PRECISION` columns.
+    pub fn to_major(&self) -> f64 {
+        self.minor as f64 / self.currency.minor_per_major() as f64
+    }
+
+    /// The amount as a decimal string with the currency's minor-unit decimals: `1250.50`,
+    /// `15000` for XAF.
+    pub fn to_decimal(&self) -> String {
+        let exponent = self.currency.exponent();
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let abs = self.minor.unsigned_abs();
+        if exponent == 0 {
+            return format!("{}{}", sign, abs);
+        }
+        let scale = 10_u64.pow(exponent);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            abs / scale,
+            abs % scale,
+            width = exponent as usize
+        )
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor == 0
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.minor > 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor < 0
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency, other.currency))
+        }
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Self, MoneyError> {
+        self.same_currency(&other)?;
+        let minor = self
+            .minor
+            .checked_add(other.minor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor, self.currency))
+    }
+
+    pub fn checked_sub(self, other: Money) -> Result<Self, MoneyError> {
+        self.same_currency(&other)?;
+        let minor = self
+            .minor
+            .checked_sub(other.minor)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor, self.currency))
+    }
+
+    pub fn checked_mul(self, factor: i64) -> Result<Self, MoneyError> {
+        let minor = self.minor.checked_mul(factor).ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor, self.currency))
+    }
+
+    /// Split into `parts` equal shares, rounded to the nearest minor unit.
+    pub fn checked_div(self, parts: i64) -> Result<Self, MoneyError> {
+        if parts == 0 {
+            return Err(MoneyError::InvalidAmount);
+        }
+        let minor = div_round(self.minor as i128, parts as i128);
+        i64::try_from(minor)
+            .map(|minor| Self::from_minor(minor, self.currency))
+            .map_err(|_| MoneyError::Overflow)
+    }
+
+    /// `percent`% of this amount, rounded to the nearest minor unit.
+    pub fn percent(self, percent: f64) -> Result<Self, MoneyError> {
+        if !percent.is_finite() {
+            return Err(MoneyError::InvalidAmount);
+        }
+        // Work in millionths of a percent so common rates like 12.5% or 19.25% stay exact
+        let micro_percent = (percent * 1_000_000.0).round() as i128;
+        let minor = div_round(self.minor as i128 * micro_percent, 100_000_000);
+        i64::try_from(minor)
+            .map(|minor| Self::from_minor(minor, self.currency))
+            .map_err(|_| MoneyError::Overflow)
+    }
+
+    /// `part / whole` of this amount, rounded to the nearest minor unit.
+    pub fn share(self, part: i64, whole: i64) -> Result<Self, MoneyError> {
+        if whole == 0 {
+            return Err(MoneyError::InvalidAmount);
+        }
+        let minor = div_round(self.minor as i128 * part as i128, whole as i128);
+        i64::try_from(minor)
+            .map(|minor| Self::from_minor(minor, self.currency))
+            .map_err(|_| MoneyError::Overflow)
+    }
+
+    /// The smaller of two amounts in the same currency.
+    pub fn min(self, other: Money) -> Result<Self, MoneyError> {
+        self.same_currency(&other)?;
+        Ok(if other.minor < self.minor {
+            other
+        } else {
+            self
+        })
+    }
+
+    /// Clamp negative amounts to zero.
+    pub fn non_negative(self) -> Self {
+        Self::from_minor(self.minor.max(0), self.currency)
+    }
+}
+
+/// Amounts only compare within one currency.
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.currency == other.currency).then(|| self.minor.cmp(&other.minor))
+    }
+}
+
+impl fmt::Display for Money {
+    /// `1250.50 EUR`, `15000 XAF`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.to_decimal(), self.currency)
+    }
+}
+
+impl fmt::Debug for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Money({})", self)
+    }
+}
+
+/// Stored as the `money_amount` composite type: `(minor BIGINT, currency TEXT)`. Tables keep
+/// the two as separate columns and select them with `to_money(amount_minor, currency)`; a plain
+/// `ROW(amount_minor, currency)` decodes too.
+impl Type<Postgres> for Money {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("money_amount")
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        *ty == Self::type_info() || *ty == PgTypeInfo::with_name("record")
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Money {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let mut decoder = PgRecordDecoder::new(value)?;
+        let minor = decoder.try_decode::<i64>()?;
+        let currency = decoder.try_decode::<Currency>()?;
+        Ok(Self::from_minor(minor, currency))
+    }
+}
+
+impl Encode<'_, Postgres> for Money {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        let mut encoder = PgRecordEncoder::new(buf);
+        encoder.encode(self.minor).encode(self.currency);
+        encoder.finish();
+        IsNull::No
+    }
+}
+
+/// Serialized as `{"amount": "1250.50", "currency": "EUR"}`; the amount is a decimal string
+/// so it survives JSON clients that parse numbers as floats.
+#[derive(Serialize, Deserialize)]
+struct MoneyRepr {
+    amount: AmountRepr,
+    currency: Currency,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum AmountRepr {
+    Decimal(String),
+    Number(f64),
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoneyRepr {
+            amount: AmountRepr::Decimal(self.to_decimal()),
+            currency: self.currency,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = MoneyRepr::deserialize(deserializer)?;
+        match repr.amount {
+            AmountRepr::Decimal(amount) => Money::parse(&amount, repr.currency),
+            AmountRepr::Number(amount) => Money::from_major(amount, repr.currency),
+        }
+        .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd(minor: i64) -> Money {
+        Money::from_minor(minor, Currency::USD)
+    }
+
+    fn xaf(minor: i64) -> Money {
+        Money::from_minor(minor, Currency::XAF)
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(usd(1050).checked_add(usd(250)), Ok(usd(1300)));
+        assert_eq!(usd(1050).checked_sub(usd(2000)), Ok(usd(-950)));
+        assert_eq!(usd(1050).checked_mul(3), Ok(usd(3150)));
+        assert_eq!(
+            usd(1).checked_add(xaf(1)),
+            Err(MoneyError::CurrencyMismatch(Currency::USD, Currency::XAF))
+        );
+        assert_eq!(usd(i64::MAX).checked_add(usd(1)), Err(MoneyError::Overflow));
+        assert_eq!(usd(i64::MIN).checked_sub(usd(1)), Err(MoneyError::Overflow));
+        assert_eq!(usd(i64::MAX).checked_mul(2), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn checked_div_rounds_half_away_from_zero() {
+        assert_eq!(usd(1000).checked_div(3), Ok(usd(333)));
+        assert_eq!(usd(1000).checked_div(8), Ok(usd(125)));
+        assert_eq!(usd(1001).checked_div(2), Ok(usd(501)));
+        assert_eq!(usd(-1001).checked_div(2), Ok(usd(-501)));
+        assert_eq!(usd(1).checked_div(0), Err(MoneyError::InvalidAmount));
+    }
+
+    #[test]
+    fn percent() {
+        assert_eq!(xaf(1000).percent(12.5), Ok(xaf(125)));
+        assert_eq!(usd(10000).percent(19.25), Ok(usd(1925)));
+        assert_eq!(usd(15).percent(50.0), Ok(usd(8)));
+        assert_eq!(usd(-15).percent(50.0), Ok(usd(-8)));
+        assert_eq!(usd(999).percent(0.0), Ok(usd(0)));
+        assert_eq!(usd(999).percent(100.0), Ok(usd(999)));
+        assert_eq!(usd(999).percent(f64::NAN), Err(MoneyError::InvalidAmount));
+    }
+
+    #[test]
+    fn percent_and_remainder_add_up() {
+        let total = usd(33333);
+        let fee = total.percent(15.0).unwrap();
+        let earning = total.checked_sub(fee).unwrap();
+        assert_eq!(fee.checked_add(earning), Ok(total));
+    }
+
+    #[test]
+    fn share() {
+        // A 25.00 refund of a 100.00 charge that carried a 3.00 fee reverses 0.75 of the fee
+        assert_eq!(usd(2500).share(300, 10000), Ok(usd(75)));
+        assert_eq!(usd(1).share(1, 2), Ok(usd(1)));
+        assert_eq!(usd(100).share(1, 3), Ok(usd(33)));
+        assert_eq!(usd(100).share(1, 0), Err(MoneyError::InvalidAmount));
+        assert_eq!(usd(i64::MAX).share(2, 1), Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn parse() {
+        let eur = Currency::EUR;
+        assert_eq!(
+            Money::parse("1250.5", eur),
+            Ok(Money::from_minor(125050, eur))
+        );
+        assert_eq!(
+            Money::parse(" 1250 ", eur),
+            Ok(Money::from_minor(125000, eur))
+        );
+        assert_eq!(Money::parse(".5", eur), Ok(Money::from_minor(50, eur)));
+        assert_eq!(
+            Money::parse("1250.555", eur),
+            Ok(Money::from_minor(125056, eur))
+        );
+        assert_eq!(Money::parse("-0.005", Currency::USD), Ok(usd(-1)));
+        assert_eq!(Money::parse("1250.5", Currency::XAF), Ok(xaf(1251)));
+        let kwd = Currency::new("KWD").unwrap();
+        assert_eq!(
+            Money::parse("12.3456", kwd),
+            Ok(Money::from_minor(12346, kwd))
+        );
+
+        for invalid in ["", ".", "-", "abc", "1.2.3", "1,50", "+1", "1e3"] {
+            assert_eq!(
+                Money::parse(invalid, eur),
+                Err(MoneyError::InvalidAmount),
+                "{:?}",
+                invalid
+            );
+        }
+        assert_eq!(
+            Money::parse("99999999999999999999", eur),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn from_major_rounds_to_minor_units() {
+        assert_eq!(Money::from_major(19.99, Currency::USD), Ok(usd(1999)));
+        assert_eq!(Money::from_major(0.1 + 0.2, Currency::USD), Ok(usd(30)));
+        assert_eq!(Money::from_major(0.125, Currency::USD), Ok(usd(13)));
+        assert_eq!(Money::from_major(-0.125, Currency::USD), Ok(usd(-13)));
+        assert_eq!(Money::from_major(1250.5, Currency::XAF), Ok(xaf(1251)));
+        assert_eq!(
+            Money::from_major(f64::INFINITY, Currency::USD),
+            Err(MoneyError::InvalidAmount)
+        );
+        assert_eq!(
+            Money::from_major(1e300, Currency::USD),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn display_and_serde() {
+        assert_eq!(usd(-105).to_string(), "-1.05 USD");
+        assert_eq!(xaf(15000).to_string(), "15000 XAF");
+        let kwd = Currency::new("KWD").unwrap();
+        assert_eq!(Money::from_minor(1005, kwd).to_decimal(), "1.005");
+
+        let json = serde_json::to_value(usd(125050)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "amount": "1250.50", "currency": "USD" })
+        );
+        assert_eq!(serde_json::from_value::<Money>(json).unwrap(), usd(125050));
+        let number = serde_json::json!({ "amount": 1250.5, "currency": "XAF" });
+        assert_eq!(serde_json::from_value::<Money>(number).unwrap(), xaf(1251));
+    }
+}
@@ -0,0 +1,243 @@
+//! Exchange rates and price conversion for display.
+//!
+//! Rates are stored against [`BASE_CURRENCY`]. Conversions are for showing prices in the
+//! requester's currency only: bookings always settle in the listing's currency.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Every rate is the value of one unit of a currency in this currency.
+pub const BASE_CURRENCY: &str = "XAF";
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ExchangeRate {
+    pub currency: String,
+    /// Value of one unit of `currency` in the base currency.
+    pub rate_to_base: f64,
+    pub rate_date: NaiveDate,
+    pub source: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+/// A rate as sent by an admin or read from an import file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateInput {
+    pub currency: String,
+    pub rate_to_base: f64,
+    /// Defaults to today.
+    pub rate_date: Option<NaiveDate>,
+}
+
+/// An amount converted for display, with the rate that was used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConvertedAmount {
+    pub currency: String,
+    pub amount: f64,
+    /// Units of `currency` per unit of the original currency.
+    pub rate: f64,
+    pub rate_date: NaiveDate,
+}
+
+/// Validate an ISO 4217 code, returning it upper-cased.
+pub fn normalize_currency(code: &str) -> Option<String> {
+    let code = code.trim();
+    (code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| code.to_ascii_uppercase())
+}
+
+/// All known rates, loaded once per request.
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRates {
+    rates: HashMap<String, (f64, NaiveDate)>,
+}
+
+impl ExchangeRates {
+    pub async fn load<'e>(executor: impl sqlx::PgExecutor<'e>) -> Result<Self, sqlx::Error> {
+        let rows = sqlx::query_as::<_, (String, f64, NaiveDate)>(
+            "SELECT currency, rate_to_base, rate_date FROM exchange_rates WHERE rate_to_base > 0",
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(Self {
+            rates: rows
+                .into_iter()
+                .map(|(currency, rate, date)| (currency, (rate, date)))
+                .collect(),
+        })
+    }
+
+    fn to_base(&self, currency: &str) -> Option<(f64, Option<NaiveDate>)> {
+        if currency == BASE_CURRENCY {
+            return Some((1.0, None));
+        }
+        self.rates
+            .get(currency)
+            .map(|&(rate, date)| (rate, Some(date)))
+    }
+
+    /// Convert `amount` from one currency to another. `None` when either rate is unknown.
+    ///
+    /// The rate date is that of the older of the two rates involved.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<ConvertedAmount> {
+        let (from_rate, from_date) = self.to_base(from)?;
+        let (to_rate, to_date) = self.to_base(to)?;
+        let rate = from_rate / to_rate;
+        let rate_date = match (from_date, to_date) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(date), None) | (None, Some(date)) => date,
+            (None, None) => chrono::Utc::now().date_naive(),
+        };
+
+        Some(ConvertedAmount {
+            currency: to.to_string(),
+            amount: ((amount * rate) * 100.0).round() / 100.0,
+            rate,
+            rate_date,
+        })
+    }
+}
+
+/// The currency a requester wants prices shown in, with the rates to get there.
+#[derive(Debug, Clone)]
+pub struct DisplayCurrency {
+    pub currency: String,
+    rates: ExchangeRates,
+}
+
+impl DisplayCurrency {
+    /// Use `requested` (e.g. a `?currency=` parameter) if valid, otherwise the user's profile
+    /// preference. `None` when neither is set.
+    pub async fn resolve(
+        conn: &mut sqlx::PgConnection,
+        requested: Option<&str>,
+        user_id: Option<i32>,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let mut currency = requested.and_then(normalize_currency);
+        if currency.is_none() {
+            if let Some(user_id) = user_id {
+                currency = sqlx::query_scalar::<_, Option<String>>(
+                    "SELECT currency FROM user_profiles WHERE user_id = $1",
+                )
+                .bind(user_id)
+                .fetch_optional(&mut *conn)
+                .await?
+                .flatten()
+                .as_deref()
+                .and_then(normalize_currency);
+            }
+        }
+
+        let Some(currency) = currency else {
+            return Ok(None);
+        };
+        let rates = ExchangeRates::load(&mut *conn).await?;
+        Ok(Some(Self { currency, rates }))
+    }
+
+    /// Convert an amount into the display currency.
+    pub fn convert(&self, amount: f64, from: &str) -> Option<ConvertedAmount> {
+        self.rates.convert(amount, from, &self.currency)
+    }
+}
+
+// ============================================================================
+// Rate Management
+// ============================================================================
+
+pub async fn list_rates<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+) -> Result<Vec<ExchangeRate>, sqlx::Error> {
+    sqlx::query_as::<_, ExchangeRate>(
+        "SELECT currency, rate_to_base, rate_date, source, updated_at FROM exchange_rates ORDER BY currency",
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Check a batch of rates before saving any of them.
+pub fn validate_rates(rates: &[RateInput]) -> Result<Vec<RateInput>, String> {
+    if rates.is_empty() {
+        return Err("No rates given".to_string());
+    }
+    rates
+        .iter()
+        .map(|rate| {
+            let currency = normalize_currency(&rate.currency)
+                .ok_or_else(|| format!("Invalid currency code: {}", rate.currency))?;
+            if currency == BASE_CURRENCY {
+                return Err(format!("{} is the base currency", BASE_CURRENCY));
+            }
+            if !(rate.rate_to_base.is_finite() && rate.rate_to_base > 0.0) {
+                return Err(format!("Invalid rate for {}", currency));
+            }
+            Ok(RateInput {
+                currency,
+                rate_to_base: rate.rate_to_base,
+                rate_date: rate.rate_date,
+            })
+        })
+        .collect()
+}
+
+/// Insert or replace validated rates.
+pub async fn save_rates(
+    conn: &mut sqlx::PgConnection,
+    rates: &[RateInput],
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    let today = chrono::Utc::now().date_naive();
+    for rate in rates {
+        sqlx::query(
+            r#"
+            INSERT INTO exchange_rates (currency, rate_to_base, rate_date, source, updated_at)
+            VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)
+            ON CONFLICT (currency) DO UPDATE SET
+                rate_to_base = EXCLUDED.rate_to_base,
+                rate_date = EXCLUDED.rate_date,
+                source = EXCLUDED.source,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(&rate.currency)
+        .bind(rate.rate_to_base)
+        .bind(rate.rate_date.unwrap_or(today))
+        .bind(source)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Parse `currency,rate_to_base[,rate_date]` lines. Blank lines, `#` comments and a header
+/// row are skipped.
+pub fn parse_rates_file(contents: &str) -> Result<Vec<RateInput>, String> {
+    let mut rates = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if index == 0 && fields[0].eq_ignore_ascii_case("currency") {
+            continue;
+        }
+        let invalid = || format!("Invalid rate on line {}", index + 1);
+
+        let (currency, rate, date) = match fields.as_slice() {
+            [currency, rate] => (*currency, *rate, None),
+            [currency, rate, date] => (*currency, *rate, Some(*date).filter(|d| !d.is_empty())),
+            _ => return Err(invalid()),
+        };
+        rates.push(RateInput {
+            currency: currency.to_string(),
+            rate_to_base: rate.parse().map_err(|_| invalid())?,
+            rate_date: date
+                .map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d"))
+                .transpose()
+                .map_err(|_| invalid())?,
+        });
+    }
+    Ok(rates)
+}
@@ -0,0 +1,345 @@
+//! `Idempotency-Key` support for state-changing endpoints.
+//!
+//! The first response for a key is stored with a fingerprint of the request and replayed for
+//! retries within [`KEY_TTL_HOURS`]. Keys are scoped to the authenticated user.
+//!
+//! While its request runs a key is locked. Handlers that change data mark the key committed with
+//! [`KeyLock::mark_committed`] in the same transaction as their changes. A request that dies
+//! before committing (a crashed replica, a lost database connection) leaves the lock behind;
+//! after [`LOCK_TIMEOUT_MINUTES`] a retry takes the key over and runs the request again. A key
+//! that was committed is never run again, even if its response couldn't be stored.
+
+use actix_web::body::{self, BoxBody};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use std::future::Future;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+/// Set on responses that were replayed from a previous request.
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+/// How long a stored response is replayed.
+pub const KEY_TTL_HOURS: i32 = 24;
+/// How long a request may hold its key before a retry can take it over.
+pub const LOCK_TIMEOUT_MINUTES: i32 = 5;
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Job kind: delete idempotency keys past their TTL.
+pub const PURGE_EXPIRED_KEYS: &str = "db.purge_idempotency_keys";
+
+/// A key claimed by the running request.
+#[derive(Debug, Clone)]
+pub struct KeyLock {
+    user_id: i32,
+    key: String,
+    /// Proves the key is still ours when marking it committed or storing the response.
+    locked_at: NaiveDateTime,
+}
+
+impl KeyLock {
+    /// Record that the request's changes are committed. Call it in the handler's transaction,
+    /// just before committing, so the key and the changes are saved together.
+    ///
+    /// Fails with [`sqlx::Error::RowNotFound`] if a retry took over the key meanwhile; the
+    /// handler must then roll back, since the retry is making the same changes.
+    pub async fn mark_committed<'e>(
+        &self,
+        executor: impl PgExecutor<'e>,
+    ) -> Result<(), sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE idempotency_keys SET committed_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND idempotency_key = $2 AND locked_at = $3
+              AND response_status IS NULL
+            "#,
+        )
+        .bind(self.user_id)
+        .bind(&self.key)
+        .bind(self.locked_at)
+        .execute(executor)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of claiming a key.
+enum Claim {
+    /// The key is ours.
+    Locked(KeyLock),
+    /// The key was already used, and is done or still locked by another request.
+    Used(StoredKey),
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct StoredKey {
+    request_fingerprint: String,
+    response_status: Option<i32>,
+    response_content_type: Option<String>,
+    response_body: Option<Vec<u8>>,
+    committed_at: Option<NaiveDateTime>,
+}
+
+/// Hash of everything that identifies the request: method, path and JSON body.
+fn fingerprint(req: &HttpRequest, body: &impl Serialize) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str());
+    hasher.update(b" ");
+    hasher.update(req.path());
+    hasher.update(b"\n");
+    hasher.update(serde_json::to_vec(body).unwrap_or_default());
+    hex::encode(hasher.finalize())
+}
+
+fn error_response(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(serde_json::json!({ "error": message }))
+}
+
+/// Run `handler` at most once per `Idempotency-Key`.
+///
+/// Without the header the handler simply runs, and gets no [`KeyLock`]. Retries with the same
+/// key and request get the stored response; reusing a key for a different request is rejected
+/// with 422, and a retry arriving while the first request is still running gets 409. Server
+/// errors are not stored, so those requests can be retried with the same key, unless the
+/// handler had already committed its changes.
+pub async fn run<H, F>(
+    pool: &PgPool,
+    req: &HttpRequest,
+    user_id: i32,
+    body: &impl Serialize,
+    handler: H,
+) -> HttpResponse
+where
+    H: FnOnce(Option<KeyLock>) -> F,
+    F: Future<Output = HttpResponse>,
+{
+    let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        None => return handler(None).await,
+        Some(value) => match value.to_str().map(str::trim) {
+            Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+            _ => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    "Idempotency-Key must be 1-255 visible characters",
+                );
+            }
+        },
+    };
+    let fingerprint = fingerprint(req, body);
+
+    let lock = match claim_key(pool, user_id, &key, &fingerprint).await {
+        Ok(Claim::Locked(lock)) => lock,
+        Ok(Claim::Used(stored)) => return replay(stored, &fingerprint),
+        Err(e) => {
+            log::error!("Failed to claim idempotency key: {:?}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Database error");
+        }
+    };
+
+    let response = handler(Some(lock.clone())).await;
+    store_response(pool, &lock, response).await
+}
+
+/// Lock the key for this request: a new key, or one whose previous request lost its lock.
+/// Returns the existing row if the key was already used.
+async fn claim_key(
+    pool: &PgPool,
+    user_id: i32,
+    key: &str,
+    fingerprint: &str,
+) -> Result<Claim, sqlx::Error> {
+    // An expired key may be reused as if it were new
+    sqlx::query(
+        r#"
+        DELETE FROM idempotency_keys
+        WHERE user_id = $1 AND idempotency_key = $2
+          AND created_at < NOW() - make_interval(hours => $3)
+        "#,
+    )
+    .bind(user_id)
+    .bind(key)
+    .bind(KEY_TTL_HOURS)
+    .execute(pool)
+    .await?;
+
+    // Only the same request may take over a stale lock, and only if it never committed
+    let locked_at = sqlx::query_scalar::<_, NaiveDateTime>(
+        r#"
+        INSERT INTO idempotency_keys (user_id, idempotency_key, request_fingerprint, locked_at)
+        VALUES ($1, $2, $3, CURRENT_TIMESTAMP)
+        ON CONFLICT (user_id, idempotency_key) DO UPDATE
+        SET locked_at = CURRENT_TIMESTAMP
+        WHERE idempotency_keys.response_status IS NULL
+          AND idempotency_keys.committed_at IS NULL
+          AND idempotency_keys.request_fingerprint = EXCLUDED.request_fingerprint
+          AND COALESCE(idempotency_keys.locked_at, idempotency_keys.created_at)
+              < NOW() - make_interval(mins => $4)
+        RETURNING locked_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(key)
+    .bind(fingerprint)
+    .bind(LOCK_TIMEOUT_MINUTES)
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(locked_at) = locked_at {
+        return Ok(Claim::Locked(KeyLock {
+            user_id,
+            key: key.to_string(),
+            locked_at,
+        }));
+    }
+
+    let stored = sqlx::query_as::<_, StoredKey>(
+        r#"
+        SELECT request_fingerprint, response_status, response_content_type, response_body,
+               committed_at
+        FROM idempotency_keys
+        WHERE user_id = $1 AND idempotency_key = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(key)
+    .fetch_one(pool)
+    .await?;
+    Ok(Claim::Used(stored))
+}
+
+fn replay(stored: StoredKey, fingerprint: &str) -> HttpResponse {
+    if stored.request_fingerprint != fingerprint {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key has already been used for a different request",
+        );
+    }
+
+    let Some(status) = stored
+        .response_status
+        .and_then(|status| u16::try_from(status).ok())
+        .and_then(|status| StatusCode::from_u16(status).ok())
+    else {
+        let message = if stored.committed_at.is_some() {
+            "A request with this Idempotency-Key was already processed"
+        } else {
+            "A request with this Idempotency-Key is still being processed"
+        };
+        return error_response(StatusCode::CONFLICT, message);
+    };
+
+    let mut response = HttpResponse::build(status);
+    response.insert_header((REPLAYED_HEADER, "true"));
+    if let Some(content_type) = stored.response_content_type {
+        response.insert_header((CONTENT_TYPE, content_type));
+    }
+    response.body(stored.response_body.unwrap_or_default())
+}
+
+/// Save the handler's response for replays and hand it back to the client.
+///
+/// If the response can't be saved the key is released and the client gets a server error, so
+/// it retries rather than being told forever that the request is still running. A key whose
+/// handler committed stays locked instead: running the request again would repeat its changes.
+async fn store_response(pool: &PgPool, lock: &KeyLock, response: HttpResponse) -> HttpResponse {
+    let status = response.status();
+
+    if status.is_server_error() {
+        release_key(pool, lock).await;
+        return response;
+    }
+
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let (response, response_body) = response.into_parts();
+
+    let bytes = match body::to_bytes(response_body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to read response for idempotency key: {:?}", e);
+            release_key(pool, lock).await;
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error");
+        }
+    };
+
+    // Only while the key is still ours: a retry may have taken over a lock that ran too long
+    let stored = sqlx::query(
+        r#"
+        UPDATE idempotency_keys
+        SET response_status = $4, response_content_type = $5, response_body = $6,
+            completed_at = CURRENT_TIMESTAMP
+        WHERE user_id = $1 AND idempotency_key = $2 AND locked_at = $3
+          AND response_status IS NULL
+        "#,
+    )
+    .bind(lock.user_id)
+    .bind(&lock.key)
+    .bind(lock.locked_at)
+    .bind(status.as_u16() as i32)
+    .bind(&content_type)
+    .bind(bytes.as_ref())
+    .execute(pool)
+    .await;
+
+    match stored {
+        Ok(result) if result.rows_affected() > 0 => {}
+        Ok(_) => {
+            log::warn!("Idempotency key lock was taken over before the response was stored");
+        }
+        Err(e) => {
+            log::error!("Failed to store idempotent response: {:?}", e);
+            release_key(pool, lock).await;
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error");
+        }
+    }
+
+    response.set_body(BoxBody::new(bytes))
+}
+
+/// Forget an in-progress key so the request can be retried with it, unless its handler
+/// already committed.
+async fn release_key(pool: &PgPool, lock: &KeyLock) {
+    if let Err(e) = sqlx::query(
+        r#"
+        DELETE FROM idempotency_keys
+        WHERE user_id = $1 AND idempotency_key = $2 AND locked_at = $3
+          AND response_status IS NULL AND committed_at IS NULL
+        "#,
+    )
+    .bind(lock.user_id)
+    .bind(&lock.key)
+    .bind(lock.locked_at)
+    .execute(pool)
+    .await
+    {
+        log::error!("Failed to release idempotency key: {:?}", e);
+    }
+}
+
+/// Delete keys whose stored responses are no longer replayed.
+pub async fn purge_expired_keys(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM idempotency_keys WHERE created_at < NOW() - make_interval(hours => $1)",
+    )
+    .bind(KEY_TTL_HOURS)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        log::info!(
+            "Deleted {} expired idempotency keys",
+            result.rows_affected()
+        );
+    }
+    Ok(result.rows_affected())
+}
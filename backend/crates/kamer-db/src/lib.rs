@@ -4,3 +4,5 @@ pub use sqlx::PgPool;
 pub use kamer_core::{AppError, AppResult};
 
 // Database utilities will be added here as we migrate code
+pub mod exchange_rates;
+pub mod idempotency;
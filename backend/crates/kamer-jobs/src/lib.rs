@@ -0,0 +1,8 @@
+pub mod registry;
+pub mod runner;
+pub mod store;
+
+// Re-export key items
+pub use registry::{JobFuture, JobRegistry};
+pub use runner::{JobRunner, RunnerConfig};
+pub use store::{enqueue, purge_succeeded_jobs, Job, JobStats, PURGE_SUCCEEDED_JOBS};
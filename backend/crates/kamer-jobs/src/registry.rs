@@ -0,0 +1,108 @@
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+/// Future returned by a job handler; the error string is stored in `jobs.last_error`.
+pub type JobFuture = BoxFuture<'static, Result<(), String>>;
+
+type Handler = Box<dyn Fn(PgPool, serde_json::Value) -> JobFuture + Send + Sync>;
+
+/// A job kind that is enqueued automatically at a fixed interval.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub kind: String,
+    pub every: Duration,
+}
+
+/// Maps job kinds to their handlers.
+///
+/// Domain crates expose plain async functions; the binary wires them up here so this crate
+/// doesn't have to depend on every domain.
+#[derive(Default)]
+pub struct JobRegistry {
+    handlers: HashMap<String, Handler>,
+    give_up_handlers: HashMap<String, Handler>,
+    schedules: Vec<Schedule>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the handler for `kind`. The handler receives the job's JSON payload.
+    pub fn register<F, Fut, E>(mut self, kind: &str, handler: F) -> Self
+    where
+        F: Fn(PgPool, serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.handlers.insert(kind.to_string(), boxed(handler));
+        self
+    }
+
+    /// Run `handler` once a job of `kind` has failed its last attempt, e.g. to flag the work
+    /// for manual follow-up. It receives the same payload as the job.
+    pub fn on_give_up<F, Fut, E>(mut self, kind: &str, handler: F) -> Self
+    where
+        F: Fn(PgPool, serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: Display,
+    {
+        self.give_up_handlers
+            .insert(kind.to_string(), boxed(handler));
+        self
+    }
+
+    /// Enqueue `kind` every `every`, across all replicas combined.
+    pub fn every(mut self, kind: &str, every: Duration) -> Self {
+        self.schedules.push(Schedule {
+            kind: kind.to_string(),
+            every,
+        });
+        self
+    }
+
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    pub(crate) fn run(
+        &self,
+        kind: &str,
+        pool: PgPool,
+        payload: serde_json::Value,
+    ) -> Option<JobFuture> {
+        self.handlers
+            .get(kind)
+            .map(|handler| handler(pool, payload))
+    }
+
+    pub(crate) fn give_up(
+        &self,
+        kind: &str,
+        pool: PgPool,
+        payload: serde_json::Value,
+    ) -> Option<JobFuture> {
+        self.give_up_handlers
+            .get(kind)
+            .map(|handler| handler(pool, payload))
+    }
+}
+
+fn boxed<F, Fut, E>(handler: F) -> Handler
+where
+    F: Fn(PgPool, serde_json::Value) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: Display,
+{
+    Box::new(move |pool, payload| {
+        handler(pool, payload)
+            .map(|res| res.map_err(|e| e.to_string()))
+            .boxed()
+    })
+}
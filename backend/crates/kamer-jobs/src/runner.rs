@@ -0,0 +1,179 @@
+use crate::registry::JobRegistry;
+use crate::store;
+use sqlx::PgPool;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Runner tuning, read from the environment like the rest of the server configuration.
+#[derive(Debug, Clone)]
+pub struct RunnerConfig {
+    /// How long to sleep when the queue is empty (`JOB_POLL_INTERVAL_MS`, default 5000).
+    pub poll_interval: Duration,
+    /// Maximum run time of a single job before it counts as failed (`JOB_TIMEOUT_SECS`, default 300).
+    pub job_timeout: Duration,
+    /// Running jobs locked longer than this are assumed orphaned (`JOB_STALE_AFTER_SECS`, default 900).
+    pub stale_after: Duration,
+}
+
+impl RunnerConfig {
+    pub fn from_env() -> Self {
+        let read = |key: &str, default: u64| {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            poll_interval: Duration::from_millis(read("JOB_POLL_INTERVAL_MS", 5_000)),
+            job_timeout: Duration::from_secs(read("JOB_TIMEOUT_SECS", 300)),
+            stale_after: Duration::from_secs(read("JOB_STALE_AFTER_SECS", 900)),
+        }
+    }
+}
+
+/// Polls the `jobs` table and runs due jobs one at a time.
+///
+/// Any number of replicas can run a runner against the same database: jobs and schedules are
+/// claimed with `FOR UPDATE SKIP LOCKED`, so each job runs on exactly one of them.
+pub struct JobRunner {
+    pool: PgPool,
+    registry: Arc<JobRegistry>,
+    config: RunnerConfig,
+    worker_id: String,
+}
+
+impl JobRunner {
+    pub fn new(pool: PgPool, registry: JobRegistry, config: RunnerConfig) -> Self {
+        let host = env::var("HOSTNAME").unwrap_or_else(|_| "local".to_string());
+        Self {
+            pool,
+            registry: Arc::new(registry),
+            config,
+            worker_id: format!("{}-{}", host, std::process::id()),
+        }
+    }
+
+    /// Start the polling loop on the current runtime.
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        for schedule in self.registry.schedules() {
+            let secs = schedule.every.as_secs().clamp(1, i32::MAX as u64) as i32;
+            if let Err(e) = store::upsert_schedule(&self.pool, &schedule.kind, secs).await {
+                log::error!("Failed to register schedule {}: {:?}", schedule.kind, e);
+            }
+        }
+        log::info!("Job runner {} started", self.worker_id);
+
+        loop {
+            if let Err(e) = store::enqueue_due_schedules(&self.pool).await {
+                log::error!("Failed to enqueue scheduled jobs: {:?}", e);
+            }
+            match store::requeue_stale(&self.pool, self.config.stale_after.as_secs() as i64).await {
+                Ok(jobs) => {
+                    if !jobs.is_empty() {
+                        log::warn!("Released {} stale jobs", jobs.len());
+                    }
+                    for job in jobs.iter().filter(|job| job.status == "failed") {
+                        self.give_up(job).await;
+                    }
+                }
+                Err(e) => log::error!("Failed to requeue stale jobs: {:?}", e),
+            }
+
+            // Drain everything that's due before sleeping again
+            loop {
+                match store::claim_next(&self.pool, &self.worker_id).await {
+                    Ok(Some(job)) => self.execute(job).await,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("Failed to claim job: {:?}", e);
+                        break;
+                    }
+                }
+            }
+
+            tokio::time::sleep(self.config.poll_interval).await;
+        }
+    }
+
+    async fn execute(&self, job: store::Job) {
+        let started = std::time::Instant::now();
+        let outcome = match self
+            .registry
+            .run(&job.kind, self.pool.clone(), job.payload.clone())
+        {
+            // Run on its own task so a panicking handler fails the job instead of the runner
+            Some(fut) => {
+                match tokio::spawn(tokio::time::timeout(self.config.job_timeout, fut)).await {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(_)) => Err(format!(
+                        "Job timed out after {}s",
+                        self.config.job_timeout.as_secs()
+                    )),
+                    Err(join_err) => Err(format!("Job panicked: {}", join_err)),
+                }
+            }
+            None => Err(format!("No handler registered for job kind '{}'", job.kind)),
+        };
+
+        let recorded = match &outcome {
+            Ok(()) => {
+                log::info!(
+                    "job id={} kind={} succeeded latency_ms={}",
+                    job.id,
+                    job.kind,
+                    started.elapsed().as_millis()
+                );
+                store::mark_succeeded(&self.pool, job.id).await
+            }
+            Err(error) => {
+                log::error!(
+                    "job id={} kind={} attempt={}/{} failed: {}",
+                    job.id,
+                    job.kind,
+                    job.attempts,
+                    job.max_attempts,
+                    error
+                );
+                match store::mark_failed(&self.pool, &job, error).await {
+                    Ok(true) => {
+                        self.give_up(&job).await;
+                        Ok(())
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        if let Err(e) = recorded {
+            log::error!("Failed to record result of job {}: {:?}", job.id, e);
+        }
+    }
+
+    /// Run the give-up handler of a job that has failed for good, if its kind has one.
+    async fn give_up(&self, job: &store::Job) {
+        let Some(fut) = self
+            .registry
+            .give_up(&job.kind, self.pool.clone(), job.payload.clone())
+        else {
+            return;
+        };
+        let result = match tokio::spawn(tokio::time::timeout(self.config.job_timeout, fut)).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err("timed out".to_string()),
+            Err(join_err) => Err(format!("panicked: {}", join_err)),
+        };
+        if let Err(e) = result {
+            log::error!(
+                "Give-up handler for job id={} kind={} failed: {}",
+                job.id,
+                job.kind,
+                e
+            );
+        }
+    }
+}
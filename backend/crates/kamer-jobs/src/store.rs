@@ -0,0 +1,301 @@
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+
+/// Job kind that deletes old succeeded jobs.
+pub const PURGE_SUCCEEDED_JOBS: &str = "jobs.purge_succeeded";
+
+/// A row of the `jobs` table.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: chrono::NaiveDateTime,
+    pub locked_at: Option<chrono::NaiveDateTime>,
+    pub locked_by: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+    pub finished_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Job counts per kind and status, for the admin dashboard.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct JobStats {
+    pub kind: String,
+    pub status: String,
+    pub count: i64,
+    pub last_finished_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Delay before retry number `attempts`: 30s doubling per attempt, capped at one hour.
+pub fn backoff_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    (30_i64 << exponent).min(3600)
+}
+
+/// Enqueue a job to run as soon as a worker is free.
+///
+/// Accepts a transaction, so a job can be enqueued atomically with the change that needs it.
+pub async fn enqueue<'e>(
+    executor: impl PgExecutor<'e>,
+    kind: &str,
+    payload: serde_json::Value,
+) -> Result<i64, sqlx::Error> {
+    enqueue_at(executor, kind, payload, None).await
+}
+
+/// Enqueue a job that becomes due at `run_at` (or immediately when `None`).
+pub async fn enqueue_at<'e>(
+    executor: impl PgExecutor<'e>,
+    kind: &str,
+    payload: serde_json::Value,
+    run_at: Option<chrono::NaiveDateTime>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "INSERT INTO jobs (kind, payload, run_at) VALUES ($1, $2, COALESCE($3, NOW()::timestamp)) RETURNING id",
+    )
+    .bind(kind)
+    .bind(payload)
+    .bind(run_at)
+    .fetch_one(executor)
+    .await
+}
+
+/// Claim the oldest due job. `SKIP LOCKED` lets several replicas poll the same table
+/// without blocking on, or double-running, each other's jobs.
+pub(crate) async fn claim_next(pool: &PgPool, worker_id: &str) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE jobs
+        SET status = 'running', attempts = attempts + 1, locked_at = NOW(), locked_by = $1,
+            updated_at = NOW()
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE status = 'queued' AND run_at <= NOW()
+            ORDER BY run_at
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(worker_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub(crate) async fn mark_succeeded(pool: &PgPool, job_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'succeeded', locked_at = NULL, locked_by = NULL, last_error = NULL,
+            finished_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failure: requeue with backoff, or give up once `max_attempts` is reached.
+/// Returns whether the job was given up.
+pub(crate) async fn mark_failed(
+    pool: &PgPool,
+    job: &Job,
+    error: &str,
+) -> Result<bool, sqlx::Error> {
+    let give_up = job.attempts >= job.max_attempts;
+    if give_up {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'failed', locked_at = NULL, locked_by = NULL, last_error = $2,
+                finished_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(job.id)
+        .bind(error)
+        .execute(pool)
+        .await?;
+    } else {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'queued', locked_at = NULL, locked_by = NULL, last_error = $2,
+                run_at = NOW() + make_interval(secs => $3), updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(job.id)
+        .bind(error)
+        .bind(backoff_seconds(job.attempts) as f64)
+        .execute(pool)
+        .await?;
+    }
+    Ok(give_up)
+}
+
+/// Release jobs whose worker died mid-run (locked for longer than `stale_after_secs`).
+///
+/// The lost run already counted as an attempt when it was claimed, so the job is retried with
+/// the usual backoff, or marked failed once `max_attempts` is reached: a job that keeps taking
+/// its worker down must not be requeued forever. Returns the released jobs with their new status.
+pub(crate) async fn requeue_stale(
+    pool: &PgPool,
+    stale_after_secs: i64,
+) -> Result<Vec<Job>, sqlx::Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE jobs
+        SET status = CASE WHEN attempts >= max_attempts THEN 'failed' ELSE 'queued' END,
+            locked_at = NULL, locked_by = NULL,
+            last_error = 'Worker lost while running job',
+            run_at = CASE
+                WHEN attempts >= max_attempts THEN run_at
+                -- Same as backoff_seconds()
+                ELSE NOW() + make_interval(secs => LEAST(30 * power(2, LEAST(GREATEST(attempts - 1, 0), 16)), 3600))
+            END,
+            finished_at = CASE WHEN attempts >= max_attempts THEN NOW() ELSE finished_at END,
+            updated_at = NOW()
+        WHERE status = 'running' AND locked_at < NOW() - make_interval(secs => $1)
+        RETURNING *
+        "#,
+    )
+    .bind(stale_after_secs as f64)
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete succeeded jobs that finished more than `retention_days` ago.
+///
+/// Recurring jobs add a row per run, so without this the table grows without bound. Failed
+/// jobs are kept for the admin to inspect and retry.
+pub async fn purge_succeeded_jobs(pool: &PgPool, retention_days: i32) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM jobs
+        WHERE status = 'succeeded' AND finished_at < NOW() - make_interval(days => $1)
+        "#,
+    )
+    .bind(retention_days)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        log::info!("Deleted {} succeeded jobs", result.rows_affected());
+    }
+    Ok(result.rows_affected())
+}
+
+/// Make sure every recurring job has a schedule row, keeping the interval in sync with code.
+pub(crate) async fn upsert_schedule(
+    pool: &PgPool,
+    kind: &str,
+    interval_seconds: i32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO job_schedules (kind, interval_seconds)
+        VALUES ($1, $2)
+        ON CONFLICT (kind) DO UPDATE SET interval_seconds = EXCLUDED.interval_seconds
+        "#,
+    )
+    .bind(kind)
+    .bind(interval_seconds)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Enqueue a job for every due schedule and push its next run forward, in one transaction.
+pub(crate) async fn enqueue_due_schedules(pool: &PgPool) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let kinds: Vec<String> = sqlx::query_scalar(
+        r#"
+        WITH due AS (
+            SELECT kind FROM job_schedules
+            WHERE next_run_at <= NOW()
+            FOR UPDATE SKIP LOCKED
+        )
+        UPDATE job_schedules s
+        SET next_run_at = NOW() + make_interval(secs => s.interval_seconds),
+            last_enqueued_at = NOW()
+        FROM due
+        WHERE s.kind = due.kind
+        RETURNING s.kind
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for kind in &kinds {
+        sqlx::query("INSERT INTO jobs (kind) VALUES ($1)")
+            .bind(kind)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(kinds)
+}
+
+/// List jobs, newest first, optionally filtered by status and kind.
+pub async fn list_jobs(
+    pool: &PgPool,
+    status: Option<&str>,
+    kind: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Job>, sqlx::Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        SELECT * FROM jobs
+        WHERE ($1::TEXT IS NULL OR status = $1)
+          AND ($2::TEXT IS NULL OR kind = $2)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(status)
+    .bind(kind)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn job_stats(pool: &PgPool) -> Result<Vec<JobStats>, sqlx::Error> {
+    sqlx::query_as::<_, JobStats>(
+        r#"
+        SELECT kind, status, COUNT(*) as count, MAX(finished_at) as last_finished_at
+        FROM jobs
+        GROUP BY kind, status
+        ORDER BY kind, status
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Requeue a failed job for immediate retry, resetting its attempt count.
+pub async fn retry_job(pool: &PgPool, job_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = 'queued', attempts = 0, run_at = NOW(), finished_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND status = 'failed'
+        "#,
+    )
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -1,4 +1,10 @@
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, NaiveDate};
+use kamer_calendar::availability::{
+    calendar_version, listing_availability, MAX_AVAILABILITY_RANGE_DAYS,
+};
+use kamer_core::{Currency, Money};
+use kamer_db::exchange_rates::{ConvertedAmount, DisplayCurrency};
 use moka::future::Cache;
 use serde::{Deserialize, Serialize};
 use sha1::{Digest, Sha1};
@@ -73,6 +79,18 @@ pub struct Listing {
     #[sqlx(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub scenic_views: Option<String>,
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pets_allowed: Option<bool>,
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_pets: Option<i32>,
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guests_included: Option<i32>,
+    #[sqlx(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_guest_fee: Option<f64>,
 }
 
 // ============================================================================
@@ -401,6 +419,7 @@ pub async fn get_host_listings(
             host_location: host_location.clone(),
             host_languages: host_languages.clone(),
             host_bio: host_bio.clone(),
+            display_price: None,
         });
     }
 
@@ -489,6 +508,22 @@ pub struct ListingWithDetails {
     pub host_languages: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub host_bio: Option<String>,
+    /// Nightly price in the requester's currency; never cached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_price: Option<ConvertedAmount>,
+}
+
+/// `?currency=` override for the currency prices are shown in.
+#[derive(Debug, Deserialize)]
+pub struct DisplayCurrencyQuery {
+    pub currency: Option<String>,
+}
+
+/// Dates are inclusive. Defaults to the next 90 days.
+#[derive(Debug, Deserialize)]
+pub struct AvailabilityQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
@@ -574,6 +609,12 @@ pub struct UpdateListingRequest {
     pub cancellation_policy: Option<String>,
     pub getting_around: Option<String>,
     pub scenic_views: Option<Vec<String>>,
+    pub pets_allowed: Option<bool>,
+    pub max_pets: Option<i32>,
+    /// Guests covered by the nightly price before `extra_guest_fee` applies.
+    pub guests_included: Option<i32>,
+    /// Per-night fee for each guest beyond `guests_included`.
+    pub extra_guest_fee: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -698,6 +739,12 @@ async fn get_listing_with_details(
           AND is_available = FALSE 
           AND date >= CURRENT_DATE
           AND date <= CURRENT_DATE + INTERVAL '1 year'
+        UNION ALL
+        SELECT start_date AS check_in, end_date AS check_out
+        FROM external_blocks
+        WHERE listing_id = $1
+          AND end_date >= CURRENT_DATE
+          AND start_date <= CURRENT_DATE + INTERVAL '1 year'
         "#,
     )
     .bind(listing_id)
@@ -775,6 +822,7 @@ async fn get_listing_with_details(
         host_location,
         host_languages,
         host_bio,
+        display_price: None,
     })
 }
 
@@ -805,6 +853,54 @@ fn validate_listing_for_publish(listing: &Listing) -> Result<(), String> {
     Ok(())
 }
 
+/// Run a listing update. When the currency changes to one with `rescale` more (or fewer)
+/// decimals, calendar, settings and pricing rule prices are rescaled in the same transaction so
+/// their value is unchanged.
+async fn update_listing_prices(
+    pool: &PgPool,
+    mut update: sqlx::QueryBuilder<'_, sqlx::Postgres>,
+    listing_id: &str,
+    rescale: i32,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    update.build().execute(&mut *tx).await?;
+
+    if rescale != 0 {
+        sqlx::query(
+            "UPDATE calendar_pricing SET price_minor = ROUND(price_minor * power(10::NUMERIC, $2)) WHERE listing_id = $1",
+        )
+        .bind(listing_id)
+        .bind(rescale)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE listing_settings SET
+                base_price_minor = ROUND(base_price_minor * power(10::NUMERIC, $2)),
+                weekend_price_minor = ROUND(weekend_price_minor * power(10::NUMERIC, $2)),
+                smart_min_price_minor = ROUND(smart_min_price_minor * power(10::NUMERIC, $2)),
+                smart_max_price_minor = ROUND(smart_max_price_minor * power(10::NUMERIC, $2))
+            WHERE listing_id = $1
+            "#,
+        )
+        .bind(listing_id)
+        .bind(rescale)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE pricing_rules SET price_minor = ROUND(price_minor * power(10::NUMERIC, $2)) WHERE listing_id = $1",
+        )
+        .bind(listing_id)
+        .bind(rescale)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
 // ============================================================================
 // API Endpoints
 // ============================================================================
@@ -880,6 +976,45 @@ pub async fn create_listing(
     resp
 }
 
+/// The requester's display currency: `?currency=`, else their profile preference.
+///
+/// Failures are only logged, since prices are still returned in the listing's currency.
+async fn display_currency(
+    pool: &PgPool,
+    req: &HttpRequest,
+    requested: Option<&str>,
+) -> Option<DisplayCurrency> {
+    let user_id = match requested {
+        Some(_) => None,
+        None => kamer_auth::extract_user_id(req, pool).await.ok(),
+    };
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to acquire connection: {:?}", e);
+            return None;
+        }
+    };
+    match DisplayCurrency::resolve(&mut conn, requested, user_id).await {
+        Ok(display) => display,
+        Err(e) => {
+            log::error!("Failed to load exchange rates: {:?}", e);
+            None
+        }
+    }
+}
+
+fn with_display_price(
+    mut details: ListingWithDetails,
+    display: Option<&DisplayCurrency>,
+) -> ListingWithDetails {
+    details.display_price = display.and_then(|display| {
+        let price = details.listing.price_per_night?;
+        display.convert(price, details.listing.currency.as_deref().unwrap_or("XAF"))
+    });
+    details
+}
+
 /// GET /api/listings/:id - Get listing details
 #[get("/{id}")]
 pub async fn get_listing(
@@ -887,12 +1022,15 @@ pub async fn get_listing(
     listing_cache: web::Data<Cache<String, ListingWithDetails>>,
     req: HttpRequest,
     path: web::Path<String>,
+    display_query: web::Query<DisplayCurrencyQuery>,
 ) -> impl Responder {
     let listing_id = path.into_inner();
+    let display = display_currency(pool.get_ref(), &req, display_query.currency.as_deref()).await;
 
     // Check cache first
     if let Some(cached) = listing_cache.get(&listing_id).await {
         log::info!("Cache hit for listing {}", listing_id);
+        let cached = with_display_price(cached, display.as_ref());
         let accept = req
             .headers()
             .get(actix_web::http::header::ACCEPT)
@@ -934,6 +1072,7 @@ pub async fn get_listing(
             listing_cache
                 .insert(listing_id.clone(), listing.clone())
                 .await;
+            let listing = with_display_price(listing, display.as_ref());
             let accept = req
                 .headers()
                 .get(actix_web::http::header::ACCEPT)
@@ -984,6 +1123,102 @@ pub async fn get_listing(
     }
 }
 
+/// GET /api/listings/:id/availability - Public availability and nightly prices for a date range
+#[get("/{id}/availability")]
+pub async fn get_listing_availability(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<AvailabilityQuery>,
+) -> impl Responder {
+    let listing_id = path.into_inner();
+    let today = chrono::Utc::now().date_naive();
+    let from = query.from.unwrap_or(today);
+    let to = query.to.unwrap_or(from + Duration::days(89));
+
+    if to < from {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "to must not be before from" }));
+    }
+    if (to - from).num_days() >= MAX_AVAILABILITY_RANGE_DAYS {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("A range can cover at most {} days", MAX_AVAILABILITY_RANGE_DAYS)
+        }));
+    }
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::error!("Failed to fetch listing availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    let version = match calendar_version(&mut conn, &listing_id).await {
+        Ok(Some(version)) => version,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Listing not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch listing availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    // The version changes with every booking, block and price change; the date moves past
+    // nights out of reach and shifts lead-time pricing
+    let version_key = format!(
+        "{}:{}:{}:{}:{:?}:{}",
+        listing_id, from, to, version.version, version.listing_updated_at, today
+    );
+    let if_none_match = req
+        .headers()
+        .get(actix_web::http::header::IF_NONE_MATCH)
+        .and_then(|tag| tag.to_str().ok());
+    let etag = format!("\"{}\"", hex::encode(Sha1::digest(&version_key)));
+    if !version.smart_pricing_enabled && if_none_match == Some(etag.as_str()) {
+        return HttpResponse::NotModified().finish();
+    }
+
+    let days = match listing_availability(&mut conn, &listing_id, from, to).await {
+        Ok(days) => days,
+        Err(e) => {
+            log::error!("Failed to compute listing availability: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to compute availability" }));
+        }
+    };
+
+    // Smart prices also move with bookings elsewhere in the city, so tag what was computed
+    let etag = if version.smart_pricing_enabled {
+        let json = serde_json::to_vec(&days).unwrap_or_default();
+        let mut hasher = Sha1::new();
+        hasher.update(&version_key);
+        hasher.update(&json);
+        let etag = format!("\"{}\"", hex::encode(hasher.finalize()));
+        if if_none_match == Some(etag.as_str()) {
+            return HttpResponse::NotModified().finish();
+        }
+        etag
+    } else {
+        etag
+    };
+
+    HttpResponse::Ok()
+        .insert_header((actix_web::http::header::ETAG, etag))
+        .insert_header(("Cache-Control", "public, max-age=0, must-revalidate"))
+        .json(serde_json::json!({
+            "listing_id": listing_id,
+            "currency": version.currency,
+            "from": from,
+            "to": to,
+            "days": days
+        }))
+}
+
 /// PUT /api/listings/:id - Update listing (autosave)
 #[put("/{id}")]
 pub async fn update_listing(
@@ -1002,14 +1237,56 @@ pub async fn update_listing(
 
     let listing_id = path.into_inner();
 
+    if body.extra_guest_fee.is_some_and(|fee| fee < 0.0)
+        || body.max_pets.is_some_and(|max| max < 0)
+        || body.guests_included.is_some_and(|included| included < 0)
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Guest and pet settings cannot be negative"
+        }));
+    }
+
+    let new_currency = match body.currency.as_deref().map(Currency::new).transpose() {
+        Ok(currency) => currency,
+        Err(_) => {
+            return HttpResponse::BadRequest()
+                .json(serde_json::json!({ "error": "Invalid currency code" }));
+        }
+    };
+
     // Verify ownership
-    let owner_check = sqlx::query_scalar::<_, i32>("SELECT host_id FROM listings WHERE id = $1")
-        .bind(&listing_id)
-        .fetch_optional(pool.get_ref())
-        .await;
+    let owner_check = sqlx::query_as::<_, (i32, Currency)>(
+        "SELECT host_id, COALESCE(currency, 'XAF') FROM listings WHERE id = $1",
+    )
+    .bind(&listing_id)
+    .fetch_optional(pool.get_ref())
+    .await;
 
     match owner_check {
-        Ok(Some(host_id)) if host_id == user_id => {
+        Ok(Some((host_id, old_currency))) if host_id == user_id => {
+            // Prices are stored in minor units of the listing's (new) currency
+            let currency = new_currency.unwrap_or(old_currency);
+            let to_minor = |amount: Option<f64>| {
+                amount
+                    .map(|amount| Money::from_major(amount, currency).map(|money| money.minor()))
+                    .transpose()
+            };
+            let (price_minor, cleaning_fee_minor, extra_guest_fee_minor) = match (
+                to_minor(body.price_per_night),
+                to_minor(body.cleaning_fee),
+                to_minor(body.extra_guest_fee),
+            ) {
+                (Ok(price), Ok(cleaning_fee), Ok(extra_guest_fee)) => {
+                    (price, cleaning_fee, extra_guest_fee)
+                }
+                _ => {
+                    return HttpResponse::BadRequest()
+                        .json(serde_json::json!({ "error": "Invalid price" }));
+                }
+            };
+            // Existing prices keep their value when the currency's decimals change
+            let rescale = currency.exponent() as i32 - old_currency.exponent() as i32;
+
             // Build dynamic update query using QueryBuilder to prevent SQL injection
             let mut query_builder: sqlx::QueryBuilder<sqlx::Postgres> =
                 sqlx::QueryBuilder::new("UPDATE listings SET updated_at = CURRENT_TIMESTAMP");
@@ -1046,18 +1323,29 @@ pub async fn update_listing(
                 query_builder.push(", longitude = ");
                 query_builder.push_bind(longitude);
             }
-            if let Some(price) = body.price_per_night {
-                query_builder.push(", price_per_night = ");
-                query_builder.push_bind(price);
+            for (column, minor) in [
+                ("price_per_night_minor", price_minor),
+                ("cleaning_fee_minor", cleaning_fee_minor),
+                ("extra_guest_fee_minor", extra_guest_fee_minor),
+            ] {
+                match minor {
+                    Some(minor) => {
+                        query_builder.push(format!(", {} = ", column));
+                        query_builder.push_bind(minor);
+                    }
+                    None if rescale != 0 => {
+                        query_builder
+                            .push(format!(", {0} = ROUND({0} * power(10::NUMERIC, ", column));
+                        query_builder.push_bind(rescale);
+                        query_builder.push("))");
+                    }
+                    None => {}
+                }
             }
-            if let Some(ref currency) = body.currency {
+            if let Some(currency) = new_currency {
                 query_builder.push(", currency = ");
                 query_builder.push_bind(currency);
             }
-            if let Some(cleaning_fee) = body.cleaning_fee {
-                query_builder.push(", cleaning_fee = ");
-                query_builder.push_bind(cleaning_fee);
-            }
             if let Some(max_guests) = body.max_guests {
                 query_builder.push(", max_guests = ");
                 query_builder.push_bind(max_guests);
@@ -1109,24 +1397,39 @@ pub async fn update_listing(
                 query_builder.push(", scenic_views = ");
                 query_builder.push_bind(json);
             }
+            if let Some(pets_allowed) = body.pets_allowed {
+                query_builder.push(", pets_allowed = ");
+                query_builder.push_bind(pets_allowed);
+            }
+            if let Some(max_pets) = body.max_pets {
+                query_builder.push(", max_pets = ");
+                query_builder.push_bind(max_pets);
+            }
+            if let Some(guests_included) = body.guests_included {
+                query_builder.push(", guests_included = ");
+                query_builder.push_bind(guests_included);
+            }
 
             query_builder.push(" WHERE id = ");
             query_builder.push_bind(&listing_id);
 
-            let result = match query_builder.build().execute(pool.get_ref()).await {
-                Ok(_) => {
-                    // Invalidate cache
-                    listing_cache.invalidate(&listing_id).await;
-                    listing_list_cache.invalidate_all();
-                    HttpResponse::Ok().json(serde_json::json!({
-                        "id": listing_id,
-                        "updated": true
-                    }))
-                }
-                Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-                    "error": format!("Failed to update listing: {}", e)
-                })),
-            };
+            let result =
+                match update_listing_prices(pool.get_ref(), query_builder, &listing_id, rescale)
+                    .await
+                {
+                    Ok(()) => {
+                        // Invalidate cache
+                        listing_cache.invalidate(&listing_id).await;
+                        listing_list_cache.invalidate_all();
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "id": listing_id,
+                            "updated": true
+                        }))
+                    }
+                    Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": format!("Failed to update listing: {}", e)
+                    })),
+                };
             log::info!(
                 "update_listing latency_ms={}",
                 started.elapsed().as_millis()
@@ -1319,6 +1622,7 @@ pub async fn get_my_listings(
             host_location: l.host_location,
             host_languages: l.host_languages,
             host_bio: l.host_bio,
+            display_price: None,
         });
     }
 
@@ -1778,8 +2082,10 @@ pub async fn get_all_listings(
     listing_cache: web::Data<Cache<String, Vec<ListingWithDetails>>>,
     req: HttpRequest,
     query: web::Query<ListingFilters>,
+    display_query: web::Query<DisplayCurrencyQuery>,
 ) -> impl Responder {
     let started = std::time::Instant::now();
+    let display = display_currency(pool.get_ref(), &req, display_query.currency.as_deref()).await;
 
     // Try to get from cache
     let cache_key = match serde_json::to_string(&*query) {
@@ -1789,6 +2095,10 @@ pub async fn get_all_listings(
 
     if let Some(cached) = listing_cache.get(&cache_key).await {
         log::info!("Cache hit for {}", cache_key);
+        let cached: Vec<ListingWithDetails> = cached
+            .into_iter()
+            .map(|details| with_display_price(details, display.as_ref()))
+            .collect();
         let accept = req
             .headers()
             .get(actix_web::http::header::ACCEPT)
@@ -1971,11 +2281,16 @@ pub async fn get_all_listings(
             host_location: None,
             host_languages: None,
             host_bio: None,
+            display_price: None,
         });
     }
 
     // Save to cache
     listing_cache.insert(cache_key.clone(), out.clone()).await;
+    let out: Vec<ListingWithDetails> = out
+        .into_iter()
+        .map(|details| with_display_price(details, display.as_ref()))
+        .collect();
 
     log::info!(
         "get_all_listings latency_ms={} (cache miss)",
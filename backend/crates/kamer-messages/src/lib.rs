@@ -1,4 +1,12 @@
+pub mod realtime;
 pub mod routes;
+pub mod system;
+
+pub use realtime::{
+    mark_conversation_read, messaging_events, messaging_socket, publish, send_typing, MessagingHub,
+    RealtimeEvent,
+};
+pub use system::{post_message, post_message_on, update_message_metadata, ConversationParties};
 
 // Re-export all route handlers
 pub use routes::*;
@@ -0,0 +1,1109 @@
+use crate::routes::Message;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use bytes::Bytes;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Postgres channel messaging events are published on, shared by every replica.
+pub const MESSAGING_CHANNEL: &str = "messaging_events";
+
+/// Job kind: forget connections whose replica stopped reporting them and announce their users
+/// as offline.
+pub const PRUNE_STALE_CONNECTIONS: &str = "messages.prune_connections";
+
+/// How often connections are pinged and their presence refreshed.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(25);
+
+/// A WebSocket client that hasn't answered pings for this long is dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A connection not refreshed for this many seconds no longer counts its user as online.
+const PRESENCE_TIMEOUT_SECS: f64 = 90.0;
+
+/// Typing events from one connection are passed on at most this often per conversation.
+const TYPING_THROTTLE: Duration = Duration::from_secs(2);
+
+/// Events kept for connections that fall behind before they are told to resync.
+const EVENT_BUFFER: usize = 1024;
+
+/// Largest frame a client may send; commands are small JSON objects.
+const MAX_CLIENT_FRAME: usize = 16 * 1024;
+
+/// Wait before reconnecting the listener after its connection failed.
+const LISTENER_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+/// An event pushed to a user's open connections, as a JSON object tagged by `type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RealtimeEvent {
+    /// A message was posted into one of the user's conversations.
+    Message { message: Message },
+    /// `reader_id` read the messages the others had sent into the conversation.
+    Read {
+        conversation_id: String,
+        reader_id: i32,
+        read_at: NaiveDateTime,
+    },
+    /// `user_id` is typing in the conversation.
+    Typing {
+        conversation_id: String,
+        user_id: i32,
+    },
+    /// Someone the user shares a conversation with came online or went offline.
+    Presence { user_id: i32, online: bool },
+    /// Events for this connection were dropped; refetch conversations to catch up.
+    Resync,
+    /// A command sent over the WebSocket was rejected.
+    Error { error: String },
+}
+
+/// What goes over the Postgres channel.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Notification {
+    /// Sent by the `messages` insert trigger; each replica loads the message itself.
+    Message {
+        message_id: String,
+        recipients: Vec<i32>,
+    },
+    Event {
+        recipients: Vec<i32>,
+        event: RealtimeEvent,
+    },
+}
+
+/// An event on its way to this replica's connections.
+#[derive(Debug)]
+struct Delivery {
+    /// Users the event is for. Presence events are instead for everyone sharing a conversation
+    /// with the user they are about.
+    recipients: Vec<i32>,
+    event: RealtimeEvent,
+}
+
+/// Commands a WebSocket client can send.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientCommand {
+    Typing { conversation_id: String },
+    Read { conversation_id: String },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamQuery {
+    /// Bearer token, for clients that can't set headers on WebSocket and EventSource requests.
+    pub access_token: Option<String>,
+}
+
+/// Hands events from every replica to the connections open on this one.
+#[derive(Clone)]
+pub struct MessagingHub {
+    events: broadcast::Sender<Arc<Delivery>>,
+    /// Connections open on this replica, whose presence it keeps fresh.
+    connections: Arc<Mutex<HashSet<String>>>,
+}
+
+/// What a connection needs to tell which events are its user's.
+struct Subscriber {
+    user_id: i32,
+    /// The user's conversations with their guest and host.
+    conversations: HashMap<String, [i32; 2]>,
+    /// Users the user shares a conversation with, whose presence they see.
+    counterparts: HashSet<i32>,
+    last_typing: HashMap<String, Instant>,
+}
+
+/// Registration of an open connection; dropping it closes the connection's presence.
+struct ConnectionGuard {
+    pool: PgPool,
+    connections: Arc<Mutex<HashSet<String>>>,
+    connection_id: String,
+    user_id: i32,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ConversationParticipants {
+    id: String,
+    guest_id: i32,
+    host_id: i32,
+}
+
+// ============================================================================
+// Hub
+// ============================================================================
+
+impl Default for MessagingHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessagingHub {
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            events,
+            connections: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Start listening for events and keeping this replica's connections marked online.
+    ///
+    /// The listener holds on to its connection, so `listener_pool` should be a pool of its own
+    /// rather than the one requests use. Events published while the listener reconnects are
+    /// missed; clients catch up through the REST endpoints.
+    pub fn spawn(&self, pool: PgPool, listener_pool: PgPool) {
+        let hub = self.clone();
+        let listener_db = pool.clone();
+        tokio::spawn(async move { hub.listen(listener_db, listener_pool).await });
+
+        let hub = self.clone();
+        tokio::spawn(async move { hub.refresh_presence(pool).await });
+    }
+
+    async fn listen(self, pool: PgPool, listener_pool: PgPool) {
+        loop {
+            let mut listener = match PgListener::connect_with(&listener_pool).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Failed to connect messaging listener: {:?}", e);
+                    tokio::time::sleep(LISTENER_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            if let Err(e) = listener.listen(MESSAGING_CHANNEL).await {
+                log::error!("Failed to listen on {}: {:?}", MESSAGING_CHANNEL, e);
+                tokio::time::sleep(LISTENER_RETRY_DELAY).await;
+                continue;
+            }
+            log::info!("Listening for messaging events on {}", MESSAGING_CHANNEL);
+
+            // recv() reconnects by itself; an error means it couldn't
+            loop {
+                match listener.recv().await {
+                    Ok(notification) => self.dispatch(&pool, notification.payload()).await,
+                    Err(e) => {
+                        log::error!("Messaging listener failed: {:?}", e);
+                        break;
+                    }
+                }
+            }
+            tokio::time::sleep(LISTENER_RETRY_DELAY).await;
+        }
+    }
+
+    async fn dispatch(&self, pool: &PgPool, payload: &str) {
+        // Nothing to load or send if nobody is connected here
+        if self.events.receiver_count() == 0 {
+            return;
+        }
+
+        let delivery = match serde_json::from_str::<Notification>(payload) {
+            Ok(Notification::Event { recipients, event }) => Delivery { recipients, event },
+            Ok(Notification::Message {
+                message_id,
+                recipients,
+            }) => {
+                match sqlx::query_as::<_, Message>("SELECT * FROM messages WHERE id = $1")
+                    .bind(&message_id)
+                    .fetch_optional(pool)
+                    .await
+                {
+                    Ok(Some(message)) => Delivery {
+                        recipients,
+                        event: RealtimeEvent::Message { message },
+                    },
+                    Ok(None) => return,
+                    Err(e) => {
+                        log::error!("Failed to load message {}: {:?}", message_id, e);
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Ignoring malformed messaging event: {:?}", e);
+                return;
+            }
+        };
+
+        // Fails only when every receiver has gone since the check above
+        let _ = self.events.send(Arc::new(delivery));
+    }
+
+    async fn refresh_presence(self, pool: PgPool) {
+        let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            interval.tick().await;
+            let ids: Vec<String> = match self.connections.lock() {
+                Ok(connections) => connections.iter().cloned().collect(),
+                Err(_) => continue,
+            };
+            if ids.is_empty() {
+                continue;
+            }
+            if let Err(e) = sqlx::query(
+                "UPDATE realtime_connections SET last_seen_at = LOCALTIMESTAMP WHERE id = ANY($1)",
+            )
+            .bind(&ids)
+            .execute(&pool)
+            .await
+            {
+                log::error!("Failed to refresh messaging presence: {:?}", e);
+            }
+        }
+    }
+
+    /// Record a new connection for `user_id`, announcing them online if it's their first.
+    async fn connect(&self, pool: &PgPool, user_id: i32) -> Result<ConnectionGuard, sqlx::Error> {
+        let connection_id = Uuid::new_v4().to_string();
+        let was_online = sqlx::query_scalar::<_, bool>(
+            r#"
+            WITH live AS (
+                SELECT EXISTS (
+                    SELECT 1 FROM realtime_connections
+                    WHERE user_id = $2 AND last_seen_at > LOCALTIMESTAMP - make_interval(secs => $3)
+                ) AS online
+            )
+            INSERT INTO realtime_connections (id, user_id, connected_at, last_seen_at)
+            VALUES ($1, $2, LOCALTIMESTAMP, LOCALTIMESTAMP)
+            RETURNING (SELECT online FROM live)
+            "#,
+        )
+        .bind(&connection_id)
+        .bind(user_id)
+        .bind(PRESENCE_TIMEOUT_SECS)
+        .fetch_one(pool)
+        .await?;
+
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.insert(connection_id.clone());
+        }
+        let guard = ConnectionGuard {
+            pool: pool.clone(),
+            connections: self.connections.clone(),
+            connection_id,
+            user_id,
+        };
+
+        if !was_online {
+            publish(
+                pool,
+                &[],
+                RealtimeEvent::Presence {
+                    user_id,
+                    online: true,
+                },
+            )
+            .await?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(&self.connection_id);
+        }
+        let pool = self.pool.clone();
+        let connection_id = std::mem::take(&mut self.connection_id);
+        let user_id = self.user_id;
+        // Without a runtime the server is shutting down; the prune job cleans up instead
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = disconnect(&pool, &connection_id, user_id).await {
+                    log::error!("Failed to close messaging connection: {:?}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Remove a connection, announcing its user offline if it was their last.
+async fn disconnect(pool: &PgPool, connection_id: &str, user_id: i32) -> Result<(), sqlx::Error> {
+    let still_online = sqlx::query_scalar::<_, bool>(
+        r#"
+        WITH closed AS (
+            DELETE FROM realtime_connections WHERE id = $1
+        )
+        SELECT EXISTS (
+            SELECT 1 FROM realtime_connections
+            WHERE user_id = $2 AND id <> $1
+              AND last_seen_at > LOCALTIMESTAMP - make_interval(secs => $3)
+        )
+        "#,
+    )
+    .bind(connection_id)
+    .bind(user_id)
+    .bind(PRESENCE_TIMEOUT_SECS)
+    .fetch_one(pool)
+    .await?;
+
+    if !still_online {
+        publish(
+            pool,
+            &[],
+            RealtimeEvent::Presence {
+                user_id,
+                online: false,
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Publish an event to the open connections of `recipients` on every replica.
+pub async fn publish(
+    pool: &PgPool,
+    recipients: &[i32],
+    event: RealtimeEvent,
+) -> Result<(), sqlx::Error> {
+    let notification = Notification::Event {
+        recipients: recipients.to_vec(),
+        event,
+    };
+    sqlx::query("SELECT pg_notify($1, $2::TEXT)")
+        .bind(MESSAGING_CHANNEL)
+        .bind(sqlx::types::Json(&notification))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Forget connections not refreshed within the presence timeout, e.g. from a replica that
+/// crashed, and announce users left without a live connection as offline.
+pub async fn prune_stale_connections(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let offline = sqlx::query_scalar::<_, i32>(
+        r#"
+        WITH pruned AS (
+            DELETE FROM realtime_connections
+            WHERE last_seen_at <= LOCALTIMESTAMP - make_interval(secs => $1)
+            RETURNING user_id
+        )
+        SELECT DISTINCT p.user_id
+        FROM pruned p
+        WHERE NOT EXISTS (
+            SELECT 1 FROM realtime_connections rc
+            WHERE rc.user_id = p.user_id
+              AND rc.last_seen_at > LOCALTIMESTAMP - make_interval(secs => $1)
+        )
+        "#,
+    )
+    .bind(PRESENCE_TIMEOUT_SECS)
+    .fetch_all(pool)
+    .await?;
+
+    for &user_id in &offline {
+        publish(
+            pool,
+            &[],
+            RealtimeEvent::Presence {
+                user_id,
+                online: false,
+            },
+        )
+        .await?;
+    }
+
+    if !offline.is_empty() {
+        log::info!(
+            "Marked {} users offline after stale connections",
+            offline.len()
+        );
+    }
+    Ok(offline.len() as u64)
+}
+
+// ============================================================================
+// Subscribers
+// ============================================================================
+
+impl Subscriber {
+    async fn load(pool: &PgPool, user_id: i32) -> Result<Self, sqlx::Error> {
+        let conversations = sqlx::query_as::<_, ConversationParticipants>(
+            "SELECT id, guest_id, host_id FROM conversations WHERE guest_id = $1 OR host_id = $1",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+        let mut subscriber = Subscriber {
+            user_id,
+            conversations: HashMap::new(),
+            counterparts: HashSet::new(),
+            last_typing: HashMap::new(),
+        };
+        for conversation in conversations {
+            subscriber.join(
+                conversation.id,
+                [conversation.guest_id, conversation.host_id],
+            );
+        }
+        Ok(subscriber)
+    }
+
+    fn join(&mut self, conversation_id: String, participants: [i32; 2]) {
+        self.counterparts.extend(
+            participants
+                .iter()
+                .copied()
+                .filter(|&participant| participant != self.user_id),
+        );
+        self.conversations.insert(conversation_id, participants);
+    }
+
+    /// Whether the event is for this connection's user.
+    fn wants(&mut self, delivery: &Delivery) -> bool {
+        match &delivery.event {
+            RealtimeEvent::Presence { user_id, .. } => self.counterparts.contains(user_id),
+            RealtimeEvent::Typing { user_id, .. } if *user_id == self.user_id => false,
+            event => {
+                if !delivery.recipients.contains(&self.user_id) {
+                    return false;
+                }
+                // Conversations started after connecting
+                if let (RealtimeEvent::Message { message }, &[guest_id, host_id]) =
+                    (event, delivery.recipients.as_slice())
+                {
+                    if !self.conversations.contains_key(&message.conversation_id) {
+                        self.join(message.conversation_id.clone(), [guest_id, host_id]);
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    async fn participants(
+        &mut self,
+        pool: &PgPool,
+        conversation_id: &str,
+    ) -> Result<Option<[i32; 2]>, sqlx::Error> {
+        if let Some(participants) = self.conversations.get(conversation_id) {
+            return Ok(Some(*participants));
+        }
+        let participants = conversation_participants(pool, conversation_id, self.user_id).await?;
+        if let Some(participants) = participants {
+            self.join(conversation_id.to_string(), participants);
+        }
+        Ok(participants)
+    }
+
+    /// Presence of the user's counterparts who are online now.
+    async fn online_counterparts(&self, pool: &PgPool) -> Result<Vec<RealtimeEvent>, sqlx::Error> {
+        let counterparts: Vec<i32> = self.counterparts.iter().copied().collect();
+        let online = sqlx::query_scalar::<_, i32>(
+            r#"
+            SELECT DISTINCT user_id FROM realtime_connections
+            WHERE user_id = ANY($1) AND last_seen_at > LOCALTIMESTAMP - make_interval(secs => $2)
+            "#,
+        )
+        .bind(&counterparts)
+        .bind(PRESENCE_TIMEOUT_SECS)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(online
+            .into_iter()
+            .map(|user_id| RealtimeEvent::Presence {
+                user_id,
+                online: true,
+            })
+            .collect())
+    }
+
+    /// Run a command from the client, returning an error event to send back if it failed.
+    async fn handle(&mut self, pool: &PgPool, text: &str) -> Option<RealtimeEvent> {
+        let command = match serde_json::from_str::<ClientCommand>(text) {
+            Ok(command) => command,
+            Err(e) => {
+                return Some(RealtimeEvent::Error {
+                    error: format!("Invalid command: {}", e),
+                })
+            }
+        };
+        let conversation_id = match &command {
+            ClientCommand::Typing { conversation_id } | ClientCommand::Read { conversation_id } => {
+                conversation_id
+            }
+        };
+
+        let participants = match self.participants(pool, conversation_id).await {
+            Ok(Some(participants)) => participants,
+            Ok(None) => {
+                return Some(RealtimeEvent::Error {
+                    error: "Access denied".to_string(),
+                })
+            }
+            Err(e) => {
+                log::error!("Failed to check conversation participation: {:?}", e);
+                return Some(RealtimeEvent::Error {
+                    error: "Database error".to_string(),
+                });
+            }
+        };
+
+        let result = match command {
+            ClientCommand::Typing { conversation_id } => {
+                let now = Instant::now();
+                match self.last_typing.get(&conversation_id) {
+                    Some(last) if now.duration_since(*last) < TYPING_THROTTLE => Ok(()),
+                    _ => {
+                        self.last_typing.insert(conversation_id.clone(), now);
+                        publish_typing(pool, conversation_id, self.user_id, participants).await
+                    }
+                }
+            }
+            ClientCommand::Read { conversation_id } => {
+                mark_read(pool, conversation_id, self.user_id, participants)
+                    .await
+                    .map(|_| ())
+            }
+        };
+
+        match result {
+            Ok(()) => None,
+            Err(e) => {
+                log::error!("Failed to handle messaging command: {:?}", e);
+                Some(RealtimeEvent::Error {
+                    error: "Database error".to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// The guest and host of a conversation, if `user_id` is one of them.
+async fn conversation_participants(
+    pool: &PgPool,
+    conversation_id: &str,
+    user_id: i32,
+) -> Result<Option<[i32; 2]>, sqlx::Error> {
+    let row = sqlx::query_as::<_, (i32, i32)>(
+        "SELECT guest_id, host_id FROM conversations WHERE id = $1 AND (guest_id = $2 OR host_id = $2)",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(guest_id, host_id)| [guest_id, host_id]))
+}
+
+/// Mark the messages others sent into a conversation as read by `reader_id` and send the read
+/// receipt to its participants. Returns how many messages were marked.
+async fn mark_read(
+    pool: &PgPool,
+    conversation_id: String,
+    reader_id: i32,
+    participants: [i32; 2],
+) -> Result<i64, sqlx::Error> {
+    let (marked, read_at) = sqlx::query_as::<_, (i64, Option<NaiveDateTime>)>(
+        r#"
+        WITH marked AS (
+            UPDATE messages SET read_at = LOCALTIMESTAMP
+            WHERE conversation_id = $1 AND sender_id <> $2 AND read_at IS NULL
+            RETURNING read_at
+        )
+        SELECT COUNT(*), MAX(read_at) FROM marked
+        "#,
+    )
+    .bind(&conversation_id)
+    .bind(reader_id)
+    .fetch_one(pool)
+    .await?;
+
+    if let Some(read_at) = read_at {
+        publish(
+            pool,
+            &participants,
+            RealtimeEvent::Read {
+                conversation_id,
+                reader_id,
+                read_at,
+            },
+        )
+        .await?;
+    }
+    Ok(marked)
+}
+
+async fn publish_typing(
+    pool: &PgPool,
+    conversation_id: String,
+    user_id: i32,
+    participants: [i32; 2],
+) -> Result<(), sqlx::Error> {
+    publish(
+        pool,
+        &participants,
+        RealtimeEvent::Typing {
+            conversation_id,
+            user_id,
+        },
+    )
+    .await
+}
+
+/// The user opening a stream, from the usual headers or cookie, or the `access_token` parameter.
+async fn stream_user_id(
+    req: &HttpRequest,
+    pool: &PgPool,
+    query: &StreamQuery,
+) -> Result<i32, actix_web::Error> {
+    match kamer_auth::extract_user_id(req, pool).await {
+        Ok(user_id) => Ok(user_id),
+        Err(err) => match query.access_token.as_deref() {
+            Some(token) => kamer_auth::extract_user_id_from_bearer(token, pool).await,
+            None => Err(err),
+        },
+    }
+}
+
+/// Set up a new stream: load what the user may see and register their presence.
+async fn open_stream(
+    pool: &PgPool,
+    hub: &MessagingHub,
+    user_id: i32,
+) -> Result<(Subscriber, ConnectionGuard, Vec<RealtimeEvent>), sqlx::Error> {
+    let subscriber = Subscriber::load(pool, user_id).await?;
+    let guard = hub.connect(pool, user_id).await?;
+    let presence = subscriber.online_counterparts(pool).await?;
+    Ok((subscriber, guard, presence))
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// GET /api/messages/ws - Real-time messaging over WebSocket
+///
+/// Pushes `RealtimeEvent`s as JSON text frames. Clients send `{"type": "typing", ...}` and
+/// `{"type": "read", ...}` commands with a `conversation_id`.
+#[get("/ws")]
+pub async fn messaging_socket(
+    pool: web::Data<PgPool>,
+    hub: web::Data<MessagingHub>,
+    req: HttpRequest,
+    body: web::Payload,
+    query: web::Query<StreamQuery>,
+) -> actix_web::Result<HttpResponse> {
+    let user_id = stream_user_id(&req, pool.get_ref(), &query).await?;
+
+    let mut events = hub.events.subscribe();
+    let (mut subscriber, guard, presence) = match open_stream(pool.get_ref(), &hub, user_id).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::error!("Failed to open messaging socket: {:?}", e);
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Database error"
+            })));
+        }
+    };
+
+    let (response, mut session, frames) = actix_ws::handle(&req, body)?;
+    let mut frames = frames.max_frame_size(MAX_CLIENT_FRAME);
+    let pool = pool.into_inner();
+
+    actix_web::rt::spawn(async move {
+        let _guard = guard;
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut last_pong = Instant::now();
+        let mut outgoing: VecDeque<RealtimeEvent> = presence.into();
+
+        let reason = 'socket: loop {
+            while let Some(event) = outgoing.pop_front() {
+                let text = serde_json::to_string(&event).unwrap_or_default();
+                if session.text(text).await.is_err() {
+                    break 'socket None;
+                }
+            }
+
+            tokio::select! {
+                frame = frames.recv() => match frame {
+                    Some(Ok(actix_ws::Message::Text(text))) => {
+                        outgoing.extend(subscriber.handle(&pool, &text).await);
+                    }
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break None;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Pong(_))) => last_pong = Instant::now(),
+                    Some(Ok(actix_ws::Message::Close(reason))) => break reason,
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => break None,
+                },
+                delivery = events.recv() => match delivery {
+                    Ok(delivery) => {
+                        if subscriber.wants(&delivery) {
+                            outgoing.push_back(delivery.event.clone());
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        outgoing.push_back(RealtimeEvent::Resync);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break None,
+                },
+                _ = heartbeat.tick() => {
+                    if last_pong.elapsed() > CLIENT_TIMEOUT || session.ping(b"").await.is_err() {
+                        break None;
+                    }
+                }
+            }
+        };
+
+        let _ = session.close(reason).await;
+    });
+
+    Ok(response)
+}
+
+/// GET /api/messages/events - Real-time messaging as server-sent events
+///
+/// Fallback for clients that can't use WebSockets: the same events as `data:` lines. Typing and
+/// read receipts are sent through the POST endpoints instead.
+#[get("/events")]
+pub async fn messaging_events(
+    pool: web::Data<PgPool>,
+    hub: web::Data<MessagingHub>,
+    req: HttpRequest,
+    query: web::Query<StreamQuery>,
+) -> impl Responder {
+    let user_id = match stream_user_id(&req, pool.get_ref(), &query).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let events = hub.events.subscribe();
+    let (subscriber, guard, presence) = match open_stream(pool.get_ref(), &hub, user_id).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::error!("Failed to open messaging event stream: {:?}", e);
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Database error"
+            }));
+        }
+    };
+
+    struct EventStream {
+        events: broadcast::Receiver<Arc<Delivery>>,
+        subscriber: Subscriber,
+        heartbeat: tokio::time::Interval,
+        outgoing: VecDeque<RealtimeEvent>,
+        _guard: ConnectionGuard,
+    }
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.reset();
+    let state = EventStream {
+        events,
+        subscriber,
+        heartbeat,
+        outgoing: presence.into(),
+        _guard: guard,
+    };
+
+    let stream = futures_util::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.outgoing.pop_front() {
+                let data = serde_json::to_string(&event).unwrap_or_default();
+                let chunk = Bytes::from(format!("data: {}\n\n", data));
+                return Some((Ok::<_, actix_web::Error>(chunk), state));
+            }
+
+            tokio::select! {
+                delivery = state.events.recv() => match delivery {
+                    Ok(delivery) => {
+                        if state.subscriber.wants(&delivery) {
+                            state.outgoing.push_back(delivery.event.clone());
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        state.outgoing.push_back(RealtimeEvent::Resync);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+                _ = state.heartbeat.tick() => {
+                    // Comment line, keeps proxies from closing an idle stream
+                    return Some((Ok(Bytes::from_static(b": ping\n\n")), state));
+                }
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
+
+/// POST /api/messages/conversations/{id}/read - Mark a conversation's messages as read
+#[post("/conversations/{id}/read")]
+pub async fn mark_conversation_read(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let conversation_id = path.into_inner();
+
+    let participants =
+        match conversation_participants(pool.get_ref(), &conversation_id, user_id).await {
+            Ok(Some(participants)) => participants,
+            Ok(None) => {
+                return HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": "Access denied"
+                }))
+            }
+            Err(e) => {
+                log::error!("Database error: {:?}", e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Database error"
+                }));
+            }
+        };
+
+    match mark_read(pool.get_ref(), conversation_id, user_id, participants).await {
+        Ok(marked) => HttpResponse::Ok().json(serde_json::json!({ "marked_read": marked })),
+        Err(e) => {
+            log::error!("Failed to mark messages as read: {:?}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to mark messages as read"
+            }))
+        }
+    }
+}
+
+/// POST /api/messages/conversations/{id}/typing - Tell the other participants the user is typing
+#[post("/conversations/{id}/typing")]
+pub async fn send_typing(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let conversation_id = path.into_inner();
+
+    let participants =
+        match conversation_participants(pool.get_ref(), &conversation_id, user_id).await {
+            Ok(Some(participants)) => participants,
+            Ok(None) => {
+                return HttpResponse::Forbidden().json(serde_json::json!({
+                    "error": "Access denied"
+                }))
+            }
+            Err(e) => {
+                log::error!("Database error: {:?}", e);
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Database error"
+                }));
+            }
+        };
+
+    match publish_typing(pool.get_ref(), conversation_id, user_id, participants).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            log::error!("Failed to publish typing event: {:?}", e);
+            HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to send typing event"
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscriber(user_id: i32, conversations: &[(&str, [i32; 2])]) -> Subscriber {
+        let mut subscriber = Subscriber {
+            user_id,
+            conversations: HashMap::new(),
+            counterparts: HashSet::new(),
+            last_typing: HashMap::new(),
+        };
+        for (id, participants) in conversations {
+            subscriber.join(id.to_string(), *participants);
+        }
+        subscriber
+    }
+
+    fn delivery(recipients: &[i32], event: RealtimeEvent) -> Delivery {
+        Delivery {
+            recipients: recipients.to_vec(),
+            event,
+        }
+    }
+
+    fn message(conversation_id: &str, sender_id: i32) -> RealtimeEvent {
+        RealtimeEvent::Message {
+            message: Message {
+                id: "m1".to_string(),
+                conversation_id: conversation_id.to_string(),
+                sender_id,
+                content: "Hello".to_string(),
+                read_at: None,
+                created_at: NaiveDateTime::default(),
+                message_type: "text".to_string(),
+                metadata: None,
+            },
+        }
+    }
+
+    fn typing(conversation_id: &str, user_id: i32) -> RealtimeEvent {
+        RealtimeEvent::Typing {
+            conversation_id: conversation_id.to_string(),
+            user_id,
+        }
+    }
+
+    #[test]
+    fn events_go_to_their_recipients_only() {
+        let mut guest = subscriber(2, &[("c1", [2, 1])]);
+        assert!(guest.wants(&delivery(&[2, 1], message("c1", 1))));
+        assert!(!guest.wants(&delivery(&[3, 1], message("c2", 1))));
+        assert!(!guest.wants(&delivery(&[3, 1], typing("c2", 1))));
+    }
+
+    #[test]
+    fn own_messages_reach_the_senders_other_connections() {
+        let mut guest = subscriber(2, &[("c1", [2, 1])]);
+        assert!(guest.wants(&delivery(&[2, 1], message("c1", 2))));
+    }
+
+    #[test]
+    fn own_typing_is_not_echoed() {
+        let mut guest = subscriber(2, &[("c1", [2, 1])]);
+        assert!(!guest.wants(&delivery(&[2, 1], typing("c1", 2))));
+        assert!(guest.wants(&delivery(&[2, 1], typing("c1", 1))));
+    }
+
+    #[test]
+    fn presence_is_for_users_sharing_a_conversation() {
+        let mut guest = subscriber(2, &[("c1", [2, 1])]);
+        let online = |user_id| {
+            delivery(
+                &[],
+                RealtimeEvent::Presence {
+                    user_id,
+                    online: true,
+                },
+            )
+        };
+        assert!(guest.wants(&online(1)));
+        assert!(!guest.wants(&online(3)));
+        assert!(!guest.wants(&online(2)));
+    }
+
+    #[test]
+    fn messages_in_new_conversations_join_them() {
+        let mut host = subscriber(1, &[("c1", [2, 1])]);
+        assert!(host.wants(&delivery(&[3, 1], message("c2", 3))));
+        assert_eq!(host.conversations.get("c2"), Some(&[3, 1]));
+        assert!(host.counterparts.contains(&3));
+        assert!(host.wants(&delivery(
+            &[],
+            RealtimeEvent::Presence {
+                user_id: 3,
+                online: false,
+            },
+        )));
+    }
+
+    #[test]
+    fn trigger_payloads_name_the_message_and_its_participants() {
+        let payload = r#"{"kind" : "message", "message_id" : "m1", "recipients" : [2,1]}"#;
+        match serde_json::from_str::<Notification>(payload).unwrap() {
+            Notification::Message {
+                message_id,
+                recipients,
+            } => {
+                assert_eq!(message_id, "m1");
+                assert_eq!(recipients, vec![2, 1]);
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn published_events_read_back() {
+        let payload = r#"{
+            "kind": "event",
+            "recipients": [2, 1],
+            "event": {
+                "type": "read",
+                "conversation_id": "c1",
+                "reader_id": 2,
+                "read_at": "2026-05-01T10:30:00"
+            }
+        }"#;
+        match serde_json::from_str::<Notification>(payload).unwrap() {
+            Notification::Event {
+                recipients,
+                event:
+                    RealtimeEvent::Read {
+                        conversation_id,
+                        reader_id,
+                        read_at,
+                    },
+            } => {
+                assert_eq!(recipients, vec![2, 1]);
+                assert_eq!(conversation_id, "c1");
+                assert_eq!(reader_id, 2);
+                assert_eq!(read_at.to_string(), "2026-05-01 10:30:00");
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+
+        let published = serde_json::to_string(&Notification::Event {
+            recipients: vec![1],
+            event: typing("c1", 2),
+        })
+        .unwrap();
+        match serde_json::from_str::<Notification>(&published).unwrap() {
+            Notification::Event {
+                recipients,
+                event:
+                    RealtimeEvent::Typing {
+                        conversation_id,
+                        user_id,
+                    },
+            } => {
+                assert_eq!(recipients, vec![1]);
+                assert_eq!(conversation_id, "c1");
+                assert_eq!(user_id, 2);
+            }
+            other => panic!("unexpected notification: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        assert!(serde_json::from_str::<Notification>("not json").is_err());
+        assert!(serde_json::from_str::<Notification>(r#"{"kind":"other"}"#).is_err());
+        assert!(
+            serde_json::from_str::<Notification>(r#"{"kind":"message","recipients":[1]}"#).is_err()
+        );
+    }
+}
@@ -1,4 +1,6 @@
+use crate::system::MESSAGE_TEXT;
 use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use kamer_db::idempotency::{self, KeyLock};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use uuid::Uuid;
@@ -26,7 +28,7 @@ pub struct ConversationWithDetails {
     pub listing_image: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Message {
     pub id: String,
     pub conversation_id: String,
@@ -34,6 +36,9 @@ pub struct Message {
     pub content: String,
     pub read_at: Option<chrono::NaiveDateTime>,
     pub created_at: chrono::NaiveDateTime,
+    /// `text` for chat messages; anything else is posted by the platform.
+    pub message_type: String,
+    pub metadata: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,14 +48,14 @@ pub struct UserSummary {
     pub avatar: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateConversationRequest {
     pub listing_id: String,
     pub host_id: i32,
     pub message: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SendMessageRequest {
     pub conversation_id: String,
     pub content: String,
@@ -78,6 +83,18 @@ pub async fn create_conversation(
         Err(err) => return HttpResponse::from_error(err),
     };
 
+    idempotency::run(pool.get_ref(), &req, user_id, &*body, |lock| {
+        start_conversation(pool.get_ref(), user_id, &body, lock)
+    })
+    .await
+}
+
+async fn start_conversation(
+    pool: &PgPool,
+    user_id: i32,
+    body: &CreateConversationRequest,
+    lock: Option<KeyLock>,
+) -> HttpResponse {
     // Check if conversation already exists
     let existing_conversation = sqlx::query_as::<_, Conversation>(
         "SELECT * FROM conversations WHERE listing_id = $1 AND guest_id = $2 AND host_id = $3",
@@ -85,7 +102,7 @@ pub async fn create_conversation(
     .bind(&body.listing_id)
     .bind(user_id)
     .bind(body.host_id)
-    .fetch_optional(pool.get_ref())
+    .fetch_optional(pool)
     .await;
 
     let conversation_id = match existing_conversation {
@@ -99,7 +116,7 @@ pub async fn create_conversation(
             .bind(&body.listing_id)
             .bind(user_id)
             .bind(body.host_id)
-            .execute(pool.get_ref())
+            .execute(pool)
             .await;
 
             match result {
@@ -122,14 +139,14 @@ pub async fn create_conversation(
 
     // Send the initial message
     let message_id = Uuid::new_v4().to_string();
-    match sqlx::query(
-        "INSERT INTO messages (id, conversation_id, sender_id, content) VALUES ($1, $2, $3, $4)",
+    match insert_message(
+        pool,
+        &message_id,
+        &conversation_id,
+        user_id,
+        &body.message,
+        lock.as_ref(),
     )
-    .bind(&message_id)
-    .bind(&conversation_id)
-    .bind(user_id)
-    .bind(&body.message)
-    .execute(pool.get_ref())
     .await
     {
         Ok(_) => {
@@ -138,7 +155,7 @@ pub async fn create_conversation(
                 "UPDATE conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = $1",
             )
             .bind(&conversation_id)
-            .execute(pool.get_ref())
+            .execute(pool)
             .await;
 
             HttpResponse::Ok().json(serde_json::json!({
@@ -155,6 +172,33 @@ pub async fn create_conversation(
     }
 }
 
+/// Insert a chat message, marking the request's idempotency key committed in the same
+/// transaction so a retry can't post it twice.
+async fn insert_message(
+    pool: &PgPool,
+    message_id: &str,
+    conversation_id: &str,
+    sender_id: i32,
+    content: &str,
+    lock: Option<&KeyLock>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO messages (id, conversation_id, sender_id, content) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .bind(sender_id)
+    .bind(content)
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(lock) = lock {
+        lock.mark_committed(&mut *tx).await?;
+    }
+    tx.commit().await
+}
+
 #[derive(Debug, sqlx::FromRow)]
 struct ConversationRow {
     id: String,
@@ -170,6 +214,8 @@ struct ConversationRow {
     last_message_content: Option<String>,
     last_message_read_at: Option<chrono::NaiveDateTime>,
     last_message_created_at: Option<chrono::NaiveDateTime>,
+    last_message_type: Option<String>,
+    last_message_metadata: Option<serde_json::Value>,
 
     other_username: Option<String>,
     listing_title: Option<String>,
@@ -193,6 +239,8 @@ pub async fn get_conversations(pool: web::Data<PgPool>, req: HttpRequest) -> imp
             m.content as last_message_content, 
             m.read_at as last_message_read_at,
             m.created_at as last_message_created_at,
+            m.message_type as last_message_type,
+            m.metadata as last_message_metadata,
             u.username as other_username,
             l.title as listing_title,
             (SELECT url FROM listing_photos lp WHERE lp.listing_id = l.id AND lp.is_cover = TRUE LIMIT 1) as listing_image
@@ -237,6 +285,10 @@ pub async fn get_conversations(pool: web::Data<PgPool>, req: HttpRequest) -> imp
                     content: row.last_message_content.unwrap_or_default(),
                     read_at: row.last_message_read_at,
                     created_at: row.last_message_created_at.unwrap_or_default(),
+                    message_type: row
+                        .last_message_type
+                        .unwrap_or_else(|| MESSAGE_TEXT.to_string()),
+                    metadata: row.last_message_metadata,
                 })
             } else {
                 None
@@ -335,6 +387,18 @@ pub async fn send_message(
         Err(err) => return HttpResponse::from_error(err),
     };
 
+    idempotency::run(pool.get_ref(), &req, user_id, &*body, |lock| {
+        post_chat_message(pool.get_ref(), user_id, &body, lock)
+    })
+    .await
+}
+
+async fn post_chat_message(
+    pool: &PgPool,
+    user_id: i32,
+    body: &SendMessageRequest,
+    lock: Option<KeyLock>,
+) -> HttpResponse {
     // Verify participation
     let participation = sqlx::query_scalar::<_, i32>(
         "SELECT 1 FROM conversations WHERE id = $1 AND (guest_id = $2 OR host_id = $3)",
@@ -342,28 +406,30 @@ pub async fn send_message(
     .bind(&body.conversation_id)
     .bind(user_id)
     .bind(user_id)
-    .fetch_optional(pool.get_ref())
+    .fetch_optional(pool)
     .await;
 
     match participation {
         Ok(Some(_)) => {
             let message_id = Uuid::new_v4().to_string();
-            match sqlx::query(
-                "INSERT INTO messages (id, conversation_id, sender_id, content) VALUES ($1, $2, $3, $4)"
+            match insert_message(
+                pool,
+                &message_id,
+                &body.conversation_id,
+                user_id,
+                &body.content,
+                lock.as_ref(),
             )
-            .bind(&message_id)
-            .bind(&body.conversation_id)
-            .bind(user_id)
-            .bind(&body.content)
-            .execute(pool.get_ref())
             .await
             {
                 Ok(_) => {
                     // Update conversation updated_at
-                    let _ = sqlx::query("UPDATE conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = $1")
-                        .bind(&body.conversation_id)
-                        .execute(pool.get_ref())
-                        .await;
+                    let _ = sqlx::query(
+                        "UPDATE conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+                    )
+                    .bind(&body.conversation_id)
+                    .execute(pool)
+                    .await;
 
                     HttpResponse::Ok().json(serde_json::json!({
                         "id": message_id,
@@ -0,0 +1,117 @@
+use sqlx::{PgConnection, PgPool};
+
+/// Message typed by a participant.
+pub const MESSAGE_TEXT: &str = "text";
+/// Notice posted by the platform about a booking event (cancellation, change request, ...).
+pub const MESSAGE_SYSTEM: &str = "system";
+/// Host offer of a custom price for specific dates; `metadata` carries the offer details.
+pub const MESSAGE_SPECIAL_OFFER: &str = "special_offer";
+/// Host invitation to book specific dates without a further request step.
+pub const MESSAGE_PRE_APPROVAL: &str = "pre_approval";
+
+/// Participants of the conversation a platform message is posted into.
+#[derive(Debug, Clone, Copy)]
+pub struct ConversationParties<'a> {
+    pub listing_id: &'a str,
+    pub guest_id: i32,
+    pub host_id: i32,
+}
+
+/// Find or create the conversation between a listing's guest and host, post a typed message
+/// into it and return the message id.
+pub async fn post_message(
+    pool: &PgPool,
+    parties: ConversationParties<'_>,
+    sender_id: i32,
+    message_type: &str,
+    content: &str,
+    metadata: Option<serde_json::Value>,
+) -> Result<String, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    post_message_on(
+        &mut conn,
+        parties,
+        sender_id,
+        message_type,
+        content,
+        metadata,
+    )
+    .await
+}
+
+/// [`post_message`] on the caller's connection, so a message can be posted in the same
+/// transaction as the change it announces.
+pub async fn post_message_on(
+    conn: &mut PgConnection,
+    parties: ConversationParties<'_>,
+    sender_id: i32,
+    message_type: &str,
+    content: &str,
+    metadata: Option<serde_json::Value>,
+) -> Result<String, sqlx::Error> {
+    let existing = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM conversations WHERE listing_id = $1 AND guest_id = $2 AND host_id = $3",
+    )
+    .bind(parties.listing_id)
+    .bind(parties.guest_id)
+    .bind(parties.host_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let conversation_id = match existing {
+        Some(id) => id,
+        None => {
+            let new_id = uuid::Uuid::new_v4().to_string();
+            sqlx::query(
+                "INSERT INTO conversations (id, listing_id, guest_id, host_id) VALUES ($1, $2, $3, $4)",
+            )
+            .bind(&new_id)
+            .bind(parties.listing_id)
+            .bind(parties.guest_id)
+            .bind(parties.host_id)
+            .execute(&mut *conn)
+            .await?;
+            new_id
+        }
+    };
+
+    let message_id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        r#"
+        INSERT INTO messages (id, conversation_id, sender_id, content, message_type, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(&message_id)
+    .bind(&conversation_id)
+    .bind(sender_id)
+    .bind(content)
+    .bind(message_type)
+    .bind(metadata)
+    .execute(&mut *conn)
+    .await?;
+
+    // Update conversation timestamp
+    sqlx::query("UPDATE conversations SET updated_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(&conversation_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(message_id)
+}
+
+/// Merge `patch` into a message's metadata, e.g. to mark an offer as booked.
+pub async fn update_message_metadata(
+    pool: &PgPool,
+    message_id: &str,
+    patch: serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE messages SET metadata = COALESCE(metadata, '{}'::jsonb) || $2 WHERE id = $1",
+    )
+    .bind(message_id)
+    .bind(patch)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
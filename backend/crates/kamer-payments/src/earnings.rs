@@ -0,0 +1,62 @@
+use crate::{ledger, payouts};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Debug, Deserialize)]
+pub struct PayoutListQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// GET /api/earnings/balance - Pending, available and paid-out balances of the authenticated host
+#[get("/balance")]
+pub async fn get_earnings_balance(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    match ledger::host_balances(pool.get_ref(), user_id).await {
+        Ok(balances) => HttpResponse::Ok().json(serde_json::json!({ "balances": balances })),
+        Err(e) => {
+            log::error!("Failed to fetch host balances: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch balances" }))
+        }
+    }
+}
+
+/// GET /api/earnings/payouts - Payout batches of the authenticated host
+#[get("/payouts")]
+pub async fn get_my_payouts(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<PayoutListQuery>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match payouts::list_batches(
+        pool.get_ref(),
+        Some(user_id),
+        query.status.as_deref(),
+        limit,
+        offset,
+    )
+    .await
+    {
+        Ok(batches) => HttpResponse::Ok().json(batches),
+        Err(e) => {
+            log::error!("Failed to fetch payouts: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Failed to fetch payouts" }))
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::provider::{
+    CollectionRequest, CollectionResponse, PaymentError, PaymentProvider, PaymentStatus,
+    RefundRequest, RefundResponse, StatusQuery,
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// In-memory provider for local development and end-to-end tests.
+///
+/// Collections and refunds succeed immediately, except for payer numbers ending in `0000`,
+/// which are declined so the failure path can be exercised too.
+#[derive(Default)]
+pub struct FakeProvider {
+    collections: Mutex<HashMap<String, PaymentStatus>>,
+}
+
+impl FakeProvider {
+    fn outcome_for(phone: &str) -> PaymentStatus {
+        if phone.ends_with("0000") {
+            PaymentStatus::Failed
+        } else {
+            PaymentStatus::Succeeded
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for FakeProvider {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn request_collection(
+        &self,
+        request: &CollectionRequest,
+    ) -> Result<CollectionResponse, PaymentError> {
+        let reference = format!("fake-{}", request.payment_id);
+        let status = Self::outcome_for(&request.phone);
+        self.collections
+            .lock()
+            .unwrap()
+            .insert(reference.clone(), status);
+
+        Ok(CollectionResponse {
+            provider_reference: reference,
+            // Reported as pending so the webhook/polling path runs like it does for real providers
+            status: PaymentStatus::Pending,
+            payment_url: None,
+        })
+    }
+
+    async fn collection_status(&self, query: &StatusQuery) -> Result<PaymentStatus, PaymentError> {
+        // Unknown references (e.g. after a restart) are treated as paid
+        Ok(self
+            .collections
+            .lock()
+            .unwrap()
+            .get(&query.provider_reference)
+            .copied()
+            .unwrap_or(PaymentStatus::Succeeded))
+    }
+
+    async fn refund(&self, request: &RefundRequest) -> Result<RefundResponse, PaymentError> {
+        Ok(RefundResponse {
+            provider_reference: format!("fake-refund-{}", request.refund_id),
+            status: PaymentStatus::Succeeded,
+        })
+    }
+
+    async fn refund_status(
+        &self,
+        _provider_reference: &str,
+    ) -> Result<PaymentStatus, PaymentError> {
+        Ok(PaymentStatus::Succeeded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kamer_core::{Currency, Money};
+
+    fn collection(phone: &str) -> CollectionRequest {
+        CollectionRequest {
+            payment_id: "pay-1".to_string(),
+            amount: Money::from_minor(10_000, Currency::XAF),
+            phone: phone.to_string(),
+            description: "Booking".to_string(),
+            callback_url: "https://api.example.com/callback".to_string(),
+        }
+    }
+
+    async fn settle(provider: &FakeProvider, phone: &str) -> PaymentStatus {
+        let request = collection(phone);
+        let response = provider.request_collection(&request).await.unwrap();
+        assert_eq!(response.status, PaymentStatus::Pending);
+        provider
+            .collection_status(&StatusQuery {
+                payment_id: request.payment_id,
+                provider_reference: response.provider_reference,
+                amount: request.amount,
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn collections_settle_when_polled() {
+        let provider = FakeProvider::default();
+        assert_eq!(
+            settle(&provider, "237670000001").await,
+            PaymentStatus::Succeeded
+        );
+    }
+
+    #[tokio::test]
+    async fn numbers_ending_in_0000_are_declined() {
+        let provider = FakeProvider::default();
+        assert_eq!(
+            settle(&provider, "237670000000").await,
+            PaymentStatus::Failed
+        );
+    }
+
+    #[tokio::test]
+    async fn refunds_succeed() {
+        let provider = FakeProvider::default();
+        let response = provider
+            .refund(&RefundRequest {
+                refund_id: "ref-1".to_string(),
+                payment_id: "pay-1".to_string(),
+                original_reference: "fake-pay-1".to_string(),
+                amount: Money::from_minor(5_000, Currency::XAF),
+                phone: "237670000001".to_string(),
+                reason: "Booking refund".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status, PaymentStatus::Succeeded);
+        assert_eq!(
+            provider
+                .refund_status(&response.provider_reference)
+                .await
+                .unwrap(),
+            PaymentStatus::Succeeded
+        );
+    }
+}
@@ -0,0 +1,610 @@
+use kamer_core::{Currency, Money, MoneyError};
+use serde::Serialize;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+use std::env;
+
+/// Guest money held by the platform.
+pub const ACCOUNT_CASH: &str = "cash";
+/// Service fees the platform keeps.
+pub const ACCOUNT_PLATFORM_REVENUE: &str = "platform_revenue";
+/// What the platform owes each host (entries carry `host_id`).
+pub const ACCOUNT_HOST_PAYABLE: &str = "host_payable";
+
+/// Host earnings become available for payout this long after check-in.
+const EARNINGS_HOLD_AFTER_CHECK_IN_HOURS: i32 = 24;
+
+/// Host balances per currency, derived from `host_payable` entries.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct HostBalance {
+    pub currency: Currency,
+    /// Earned on bookings whose check-in hold has not passed yet.
+    pub pending: Money,
+    /// Ready to be included in the next payout batch.
+    pub available: Money,
+    /// Included in a payout batch that has not been paid yet.
+    pub in_transit: Money,
+    pub paid_out: Money,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ChargeContext {
+    booking_id: String,
+    host_id: i32,
+    amount: Money,
+    /// Tourist tax and VAT from the booking's price snapshot.
+    tax_total: Option<Json<Money>>,
+    available_at: chrono::NaiveDateTime,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct RefundContext {
+    booking_id: String,
+    host_id: i32,
+    amount: Money,
+    available_at: chrono::NaiveDateTime,
+}
+
+/// How a guest charge divides between the platform and the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChargeSplit {
+    pub service_fee: Money,
+    pub host_earning: Money,
+    /// Taxes the host must remit; passed through to them without a fee.
+    pub tax: Money,
+}
+
+/// Split a charge that includes `tax`, taking `fee_percent` of the pre-tax amount only.
+///
+/// The tax is capped at the charge, and the earning is what's left after the fee and the
+/// tax, so the three always add up to the charge exactly.
+pub fn split_charge(
+    amount: Money,
+    tax: Money,
+    fee_percent: f64,
+) -> Result<ChargeSplit, MoneyError> {
+    let tax = tax.non_negative().min(amount.non_negative())?;
+    let taxable = amount.checked_sub(tax)?;
+    let service_fee = taxable.percent(fee_percent)?;
+    Ok(ChargeSplit {
+        service_fee,
+        host_earning: taxable.checked_sub(service_fee)?,
+        tax,
+    })
+}
+
+/// Percent of each booking kept as the host service fee (`HOST_SERVICE_FEE_PERCENT`, default 3).
+fn host_fee_percent() -> f64 {
+    env::var("HOST_SERVICE_FEE_PERCENT")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|pct| pct.is_finite())
+        .map(|pct| pct.clamp(0.0, 100.0))
+        .unwrap_or(3.0)
+}
+
+/// Amounts that can't be split or don't balance abort the caller's transaction.
+fn ledger_error(message: impl std::fmt::Display) -> sqlx::Error {
+    sqlx::Error::Protocol(format!("Ledger: {}", message))
+}
+
+/// One leg of a ledger transaction, in minor units of the transaction's currency.
+struct Entry<'a> {
+    account: &'static str,
+    entry_type: &'static str,
+    host_id: Option<i32>,
+    amount: i64,
+    available_at: Option<chrono::NaiveDateTime>,
+    payout_batch_id: Option<&'a str>,
+}
+
+/// Write a balanced transaction. Re-posting the same `transaction_id` is a no-op, so callers
+/// don't need to track whether a retry already posted it.
+async fn post_transaction(
+    conn: &mut PgConnection,
+    transaction_id: &str,
+    booking_id: Option<&str>,
+    currency: Currency,
+    entries: &[Entry<'_>],
+) -> Result<(), sqlx::Error> {
+    let sum: i128 = entries.iter().map(|e| i128::from(e.amount)).sum();
+    if sum != 0 {
+        return Err(ledger_error(format_args!(
+            "transaction {} is off balance by {} minor units",
+            transaction_id, sum
+        )));
+    }
+
+    for entry in entries {
+        sqlx::query(
+            r#"
+            INSERT INTO ledger_entries
+                (transaction_id, booking_id, host_id, account, entry_type, amount_minor, currency,
+                 available_at, payout_batch_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, COALESCE($8, NOW()::timestamp), $9)
+            ON CONFLICT (transaction_id, account, entry_type) DO NOTHING
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(booking_id)
+        .bind(entry.host_id)
+        .bind(entry.account)
+        .bind(entry.entry_type)
+        .bind(entry.amount)
+        .bind(currency)
+        .bind(entry.available_at)
+        .bind(entry.payout_batch_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Record a successful guest payment: the charge, the platform's service fee, the host's
+/// earning and the taxes the host must remit. The earning and taxes become available for
+/// payout after check-in.
+pub async fn post_booking_charge(
+    conn: &mut PgConnection,
+    payment_id: &str,
+) -> Result<(), sqlx::Error> {
+    let charge = sqlx::query_as::<_, ChargeContext>(
+        r#"
+        SELECT p.booking_id, l.host_id, to_money(p.amount_minor, p.currency) as amount,
+               b.price_snapshot->'tax_total' as tax_total,
+               (b.check_in + make_interval(hours => $2))::timestamp as available_at
+        FROM payments p
+        JOIN bookings b ON p.booking_id = b.id
+        JOIN listings l ON b.listing_id = l.id
+        WHERE p.id = $1
+        "#,
+    )
+    .bind(payment_id)
+    .bind(EARNINGS_HOLD_AFTER_CHECK_IN_HOURS)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let currency = charge.amount.currency();
+    // Snapshots in another currency than the payment can't be split; treat them as untaxed
+    let tax = charge
+        .tax_total
+        .map(|Json(tax)| tax)
+        .filter(|tax| tax.currency() == currency)
+        .unwrap_or(Money::zero(currency));
+    let split = split_charge(charge.amount, tax, host_fee_percent()).map_err(ledger_error)?;
+
+    let mut entries = vec![
+        Entry {
+            account: ACCOUNT_CASH,
+            entry_type: "guest_charge",
+            host_id: None,
+            amount: charge.amount.minor(),
+            available_at: None,
+            payout_batch_id: None,
+        },
+        Entry {
+            account: ACCOUNT_PLATFORM_REVENUE,
+            entry_type: "service_fee",
+            host_id: None,
+            amount: -split.service_fee.minor(),
+            available_at: None,
+            payout_batch_id: None,
+        },
+        Entry {
+            account: ACCOUNT_HOST_PAYABLE,
+            entry_type: "host_earning",
+            host_id: Some(charge.host_id),
+            amount: -split.host_earning.minor(),
+            available_at: Some(charge.available_at),
+            payout_batch_id: None,
+        },
+    ];
+    if !split.tax.is_zero() {
+        entries.push(Entry {
+            account: ACCOUNT_HOST_PAYABLE,
+            entry_type: "tax",
+            host_id: Some(charge.host_id),
+            amount: -split.tax.minor(),
+            available_at: Some(charge.available_at),
+            payout_batch_id: None,
+        });
+    }
+
+    post_transaction(
+        conn,
+        &format!("charge:{}", payment_id),
+        Some(&charge.booking_id),
+        currency,
+        &entries,
+    )
+    .await
+}
+
+/// Record the service fee on a booking confirmed without an online payment
+/// (`PAYMENTS_REQUIRED=false`).
+///
+/// The guest pays the host directly, so the platform holds none of the money: the host owes
+/// the fee instead, and it is deducted from their next payout once the check-in hold passes.
+/// Does nothing unless the booking is confirmed and has no successful payment.
+pub async fn post_offline_booking(
+    conn: &mut PgConnection,
+    booking_id: &str,
+) -> Result<(), sqlx::Error> {
+    post_offline_fee(conn, booking_id, &format!("offline:{}", booking_id)).await
+}
+
+/// Bring the fee on an offline booking in line with its total after an accepted change.
+pub async fn post_offline_alteration(
+    conn: &mut PgConnection,
+    booking_id: &str,
+    alteration_id: &str,
+) -> Result<(), sqlx::Error> {
+    post_offline_fee(
+        conn,
+        booking_id,
+        &format!("offline:{}:{}", booking_id, alteration_id),
+    )
+    .await
+}
+
+/// Net fee posted so far on an offline booking, in minor units (positive: owed by the host).
+async fn offline_fee_posted(conn: &mut PgConnection, booking_id: &str) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(SUM(amount_minor), 0)::BIGINT
+        FROM ledger_entries
+        WHERE booking_id = $1 AND account = 'host_payable'
+          AND (transaction_id LIKE 'offline:%' OR transaction_id LIKE 'offline_refund:%')
+        "#,
+    )
+    .bind(booking_id)
+    .fetch_one(conn)
+    .await
+}
+
+/// Post the difference between the fee on the booking's current total and what was already
+/// posted for it.
+async fn post_offline_fee(
+    conn: &mut PgConnection,
+    booking_id: &str,
+    transaction_id: &str,
+) -> Result<(), sqlx::Error> {
+    let charge = sqlx::query_as::<_, ChargeContext>(
+        r#"
+        SELECT b.id as booking_id, l.host_id, to_money(b.total_price_minor, b.currency) as amount,
+               b.price_snapshot->'tax_total' as tax_total,
+               (b.check_in + make_interval(hours => $2))::timestamp as available_at
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1 AND b.status = 'confirmed'
+          AND NOT EXISTS (
+              SELECT 1 FROM payments p WHERE p.booking_id = b.id AND p.status = 'succeeded'
+          )
+        "#,
+    )
+    .bind(booking_id)
+    .bind(EARNINGS_HOLD_AFTER_CHECK_IN_HOURS)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(charge) = charge else {
+        return Ok(());
+    };
+
+    let currency = charge.amount.currency();
+    let tax = charge
+        .tax_total
+        .map(|Json(tax)| tax)
+        .filter(|tax| tax.currency() == currency)
+        .unwrap_or(Money::zero(currency));
+    let split = split_charge(charge.amount, tax, host_fee_percent()).map_err(ledger_error)?;
+    let adjustment = split.service_fee.minor() - offline_fee_posted(conn, booking_id).await?;
+    if adjustment == 0 {
+        return Ok(());
+    }
+    let entry_type = if adjustment > 0 {
+        "service_fee"
+    } else {
+        "refund"
+    };
+
+    post_transaction(
+        conn,
+        transaction_id,
+        Some(booking_id),
+        currency,
+        &[
+            Entry {
+                account: ACCOUNT_HOST_PAYABLE,
+                entry_type,
+                host_id: Some(charge.host_id),
+                amount: adjustment,
+                available_at: Some(charge.available_at),
+                payout_batch_id: None,
+            },
+            Entry {
+                account: ACCOUNT_PLATFORM_REVENUE,
+                entry_type,
+                host_id: None,
+                amount: -adjustment,
+                available_at: None,
+                payout_batch_id: None,
+            },
+        ],
+    )
+    .await
+}
+
+/// Reverse the service fee on the share of an offline booking the host refunds to the guest.
+/// Does nothing for bookings without an offline fee.
+pub async fn post_offline_refund(
+    conn: &mut PgConnection,
+    booking_id: &str,
+    amount: Money,
+) -> Result<(), sqlx::Error> {
+    let booking: Option<(i32, i64, chrono::NaiveDateTime)> = sqlx::query_as(
+        r#"
+        SELECT l.host_id, b.total_price_minor,
+               (b.check_in + make_interval(hours => $3))::timestamp
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1 AND b.currency = $2
+        "#,
+    )
+    .bind(booking_id)
+    .bind(amount.currency())
+    .bind(EARNINGS_HOLD_AFTER_CHECK_IN_HOURS)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some((host_id, total, available_at)) = booking else {
+        return Ok(());
+    };
+    let fee = offline_fee_posted(conn, booking_id).await?;
+    if total <= 0 || fee <= 0 {
+        return Ok(());
+    }
+
+    let reversal = amount
+        .min(Money::from_minor(total, amount.currency()))
+        .and_then(|refunded| refunded.share(fee, total))
+        .map_err(ledger_error)?;
+    if reversal.is_zero() {
+        return Ok(());
+    }
+
+    post_transaction(
+        conn,
+        &format!("offline_refund:{}", booking_id),
+        Some(booking_id),
+        amount.currency(),
+        &[
+            Entry {
+                account: ACCOUNT_HOST_PAYABLE,
+                entry_type: "refund",
+                host_id: Some(host_id),
+                amount: -reversal.minor(),
+                available_at: Some(available_at),
+                payout_batch_id: None,
+            },
+            Entry {
+                account: ACCOUNT_PLATFORM_REVENUE,
+                entry_type: "refund",
+                host_id: None,
+                amount: reversal.minor(),
+                available_at: None,
+                payout_batch_id: None,
+            },
+        ],
+    )
+    .await
+}
+
+/// Record a refund to the guest. The service fee, host earning and taxes are reversed in
+/// proportion to the refunded share of the charge; if the earning was already paid out, the
+/// reversal is deducted from the host's next payout.
+pub async fn post_refund(conn: &mut PgConnection, refund_id: &str) -> Result<(), sqlx::Error> {
+    let refund = sqlx::query_as::<_, RefundContext>(
+        r#"
+        SELECT r.booking_id, e.host_id, to_money(r.amount_minor, r.currency) as amount,
+               e.available_at
+        FROM payment_refunds r
+        JOIN ledger_entries e
+          ON e.transaction_id = 'charge:' || r.payment_id AND e.account = 'host_payable'
+             AND e.entry_type = 'host_earning'
+        WHERE r.id = $1
+        "#,
+    )
+    .bind(refund_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    // Payments settled before the ledger existed have no charge to reverse
+    let Some(refund) = refund else {
+        return Ok(());
+    };
+
+    let (charged, fee_charged, tax_charged): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            COALESCE(SUM(amount_minor) FILTER (WHERE account = 'cash'), 0)::BIGINT,
+            COALESCE(-SUM(amount_minor) FILTER (WHERE account = 'platform_revenue'), 0)::BIGINT,
+            COALESCE(-SUM(amount_minor) FILTER (WHERE entry_type = 'tax'), 0)::BIGINT
+        FROM ledger_entries
+        WHERE transaction_id = (SELECT 'charge:' || payment_id FROM payment_refunds WHERE id = $1)
+        "#,
+    )
+    .bind(refund_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let zero = Money::zero(refund.amount.currency());
+    let (fee_reversal, tax_reversal) = if charged > 0 {
+        (
+            refund
+                .amount
+                .share(fee_charged, charged)
+                .map_err(ledger_error)?,
+            refund
+                .amount
+                .share(tax_charged, charged)
+                .map_err(ledger_error)?,
+        )
+    } else {
+        (zero, zero)
+    };
+    let earning_reversal = refund
+        .amount
+        .checked_sub(fee_reversal)
+        .and_then(|rest| rest.checked_sub(tax_reversal))
+        .map_err(ledger_error)?;
+
+    let mut entries = vec![
+        Entry {
+            account: ACCOUNT_CASH,
+            entry_type: "refund",
+            host_id: None,
+            amount: -refund.amount.minor(),
+            available_at: None,
+            payout_batch_id: None,
+        },
+        Entry {
+            account: ACCOUNT_PLATFORM_REVENUE,
+            entry_type: "refund",
+            host_id: None,
+            amount: fee_reversal.minor(),
+            available_at: None,
+            payout_batch_id: None,
+        },
+        Entry {
+            account: ACCOUNT_HOST_PAYABLE,
+            entry_type: "refund",
+            host_id: Some(refund.host_id),
+            amount: earning_reversal.minor(),
+            available_at: Some(refund.available_at),
+            payout_batch_id: None,
+        },
+    ];
+    if !tax_reversal.is_zero() {
+        entries.push(Entry {
+            account: ACCOUNT_HOST_PAYABLE,
+            entry_type: "tax_refund",
+            host_id: Some(refund.host_id),
+            amount: tax_reversal.minor(),
+            available_at: Some(refund.available_at),
+            payout_batch_id: None,
+        });
+    }
+
+    post_transaction(
+        conn,
+        &format!("refund:{}", refund_id),
+        Some(&refund.booking_id),
+        refund.amount.currency(),
+        &entries,
+    )
+    .await
+}
+
+/// Record money sent to a host for a payout batch.
+pub async fn post_payout(
+    conn: &mut PgConnection,
+    batch_id: &str,
+    host_id: i32,
+    amount: Money,
+) -> Result<(), sqlx::Error> {
+    post_transaction(
+        conn,
+        &format!("payout:{}", batch_id),
+        None,
+        amount.currency(),
+        &[
+            Entry {
+                account: ACCOUNT_HOST_PAYABLE,
+                entry_type: "payout",
+                host_id: Some(host_id),
+                amount: amount.minor(),
+                available_at: None,
+                payout_batch_id: Some(batch_id),
+            },
+            Entry {
+                account: ACCOUNT_CASH,
+                entry_type: "payout",
+                host_id: None,
+                amount: -amount.minor(),
+                available_at: None,
+                payout_batch_id: Some(batch_id),
+            },
+        ],
+    )
+    .await
+}
+
+/// Pending, available, in-transit and paid-out balances for a host, per currency.
+pub async fn host_balances(pool: &PgPool, host_id: i32) -> Result<Vec<HostBalance>, sqlx::Error> {
+    sqlx::query_as::<_, HostBalance>(
+        r#"
+        SELECT
+            currency,
+            to_money(COALESCE(-SUM(amount_minor) FILTER (
+                WHERE entry_type <> 'payout' AND payout_batch_id IS NULL AND available_at > NOW()
+            ), 0)::BIGINT, currency) as pending,
+            to_money(COALESCE(-SUM(amount_minor) FILTER (
+                WHERE entry_type <> 'payout' AND payout_batch_id IS NULL AND available_at <= NOW()
+            ), 0)::BIGINT, currency) as available,
+            to_money(COALESCE(-SUM(amount_minor) FILTER (
+                WHERE payout_batch_id IS NOT NULL
+            ), 0)::BIGINT, currency) as in_transit,
+            to_money(COALESCE(SUM(amount_minor) FILTER (
+                WHERE entry_type = 'payout'
+            ), 0)::BIGINT, currency) as paid_out
+        FROM ledger_entries
+        WHERE account = 'host_payable' AND host_id = $1
+        GROUP BY currency
+        ORDER BY currency
+        "#,
+    )
+    .bind(host_id)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xaf(minor: i64) -> Money {
+        Money::from_minor(minor, Currency::XAF)
+    }
+
+    #[test]
+    fn fee_is_taken_before_tax() {
+        // 100 000 stay plus 5 000 tourist tax, 3% fee on the stay only
+        let split = split_charge(xaf(105_000), xaf(5_000), 3.0).unwrap();
+        assert_eq!(split.service_fee, xaf(3_000));
+        assert_eq!(split.host_earning, xaf(97_000));
+        assert_eq!(split.tax, xaf(5_000));
+    }
+
+    #[test]
+    fn split_adds_up_to_the_charge() {
+        let amount = Money::from_minor(33_333, Currency::USD);
+        let split = split_charge(amount, Money::from_minor(1_111, Currency::USD), 3.5).unwrap();
+        let total = split
+            .service_fee
+            .checked_add(split.host_earning)
+            .and_then(|sum| sum.checked_add(split.tax));
+        assert_eq!(total, Ok(amount));
+    }
+
+    #[test]
+    fn tax_is_capped_at_the_charge() {
+        let split = split_charge(xaf(4_000), xaf(5_000), 3.0).unwrap();
+        assert_eq!(split.tax, xaf(4_000));
+        assert_eq!(split.service_fee, xaf(0));
+        assert_eq!(split.host_earning, xaf(0));
+
+        let split = split_charge(xaf(4_000), xaf(-100), 10.0).unwrap();
+        assert_eq!(split.tax, xaf(0));
+        assert_eq!(split.service_fee, xaf(400));
+    }
+}
@@ -0,0 +1,17 @@
+pub mod earnings;
+pub mod fake;
+pub mod ledger;
+pub mod mtn_momo;
+pub mod orange_money;
+pub mod payouts;
+pub mod provider;
+pub mod refunds;
+pub mod routes;
+pub mod webhook;
+
+pub use provider::{PaymentProvider, PaymentProviders};
+pub use refunds::{request_alteration_refund, request_refund};
+
+// Re-export all route handlers
+pub use earnings::*;
+pub use routes::*;
@@ -0,0 +1,248 @@
+use crate::provider::{
+    format_amount, CollectionRequest, CollectionResponse, PaymentError, PaymentProvider,
+    PaymentStatus, RefundRequest, RefundResponse, StatusQuery,
+};
+use async_trait::async_trait;
+use moka::future::Cache;
+use serde::Deserialize;
+use std::env;
+use std::time::Duration;
+
+/// MTN Mobile Money Open API adapter.
+///
+/// Collections use the `collection` product (request-to-pay); refunds use the `disbursement`
+/// product, which has its own subscription key and access token.
+pub struct MtnMomoProvider {
+    client: reqwest::Client,
+    base_url: String,
+    target_environment: String,
+    api_user: String,
+    api_key: String,
+    collection_key: String,
+    disbursement_key: Option<String>,
+    tokens: Cache<&'static str, String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransferStatusResponse {
+    status: String,
+    reason: Option<serde_json::Value>,
+}
+
+impl MtnMomoProvider {
+    /// Configured from `MTN_MOMO_*` variables; `None` unless user, key and collection key are set.
+    pub fn from_env() -> Option<Self> {
+        let api_user = env::var("MTN_MOMO_API_USER").ok()?;
+        let api_key = env::var("MTN_MOMO_API_KEY").ok()?;
+        let collection_key = env::var("MTN_MOMO_COLLECTION_KEY").ok()?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .ok()?;
+
+        Some(Self {
+            client,
+            base_url: env::var("MTN_MOMO_BASE_URL")
+                .unwrap_or_else(|_| "https://sandbox.momodeveloper.mtn.com".to_string()),
+            target_environment: env::var("MTN_MOMO_TARGET_ENV")
+                .unwrap_or_else(|_| "sandbox".to_string()),
+            api_user,
+            api_key,
+            collection_key,
+            disbursement_key: env::var("MTN_MOMO_DISBURSEMENT_KEY").ok(),
+            // Tokens are valid for an hour; refresh a little early
+            tokens: Cache::builder()
+                .max_capacity(2)
+                .time_to_live(Duration::from_secs(3000))
+                .build(),
+        })
+    }
+
+    async fn access_token(
+        &self,
+        product: &'static str,
+        subscription_key: &str,
+    ) -> Result<String, PaymentError> {
+        if let Some(token) = self.tokens.get(&product).await {
+            return Ok(token);
+        }
+
+        let resp = self
+            .client
+            .post(format!("{}/{}/token/", self.base_url, product))
+            .basic_auth(&self.api_user, Some(&self.api_key))
+            .header("Ocp-Apim-Subscription-Key", subscription_key)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(PaymentError::Transport(format!(
+                "MTN {} token request failed with {}",
+                product,
+                resp.status()
+            )));
+        }
+        let token = resp.json::<TokenResponse>().await?.access_token;
+        self.tokens.insert(product, token.clone()).await;
+        Ok(token)
+    }
+
+    fn disbursement_key(&self) -> Result<&str, PaymentError> {
+        self.disbursement_key
+            .as_deref()
+            .ok_or(PaymentError::Unsupported(
+                "MTN refunds (MTN_MOMO_DISBURSEMENT_KEY not set)",
+            ))
+    }
+
+    async fn transfer_status(
+        &self,
+        product: &'static str,
+        subscription_key: &str,
+        path: &str,
+    ) -> Result<PaymentStatus, PaymentError> {
+        let token = self.access_token(product, subscription_key).await?;
+        let resp = self
+            .client
+            .get(format!("{}/{}/{}", self.base_url, product, path))
+            .bearer_auth(token)
+            .header("X-Target-Environment", &self.target_environment)
+            .header("Ocp-Apim-Subscription-Key", subscription_key)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(PaymentError::Transport(format!(
+                "MTN status request failed with {}",
+                resp.status()
+            )));
+        }
+        let body = resp.json::<TransferStatusResponse>().await?;
+        Ok(match body.status.as_str() {
+            "SUCCESSFUL" => PaymentStatus::Succeeded,
+            "FAILED" | "REJECTED" | "TIMEOUT" => {
+                log::warn!("MTN transfer {} failed: {:?}", path, body.reason);
+                PaymentStatus::Failed
+            }
+            _ => PaymentStatus::Pending,
+        })
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MtnMomoProvider {
+    fn name(&self) -> &'static str {
+        "mtn_momo"
+    }
+
+    async fn request_collection(
+        &self,
+        request: &CollectionRequest,
+    ) -> Result<CollectionResponse, PaymentError> {
+        let token = self
+            .access_token("collection", &self.collection_key)
+            .await?;
+
+        // Our payment id doubles as X-Reference-Id, so a retried request is rejected as a
+        // duplicate instead of debiting the payer twice.
+        let resp = self
+            .client
+            .post(format!("{}/collection/v1_0/requesttopay", self.base_url))
+            .bearer_auth(token)
+            .header("X-Reference-Id", &request.payment_id)
+            .header("X-Target-Environment", &self.target_environment)
+            .header("X-Callback-Url", &request.callback_url)
+            .header("Ocp-Apim-Subscription-Key", &self.collection_key)
+            .json(&serde_json::json!({
+                "amount": format_amount(request.amount),
+                "currency": request.amount.currency(),
+                "externalId": request.payment_id,
+                "payer": { "partyIdType": "MSISDN", "partyId": request.phone },
+                "payerMessage": request.description,
+                "payeeNote": request.description,
+            }))
+            .send()
+            .await?;
+
+        match resp.status().as_u16() {
+            200..=299 => Ok(CollectionResponse {
+                provider_reference: request.payment_id.clone(),
+                status: PaymentStatus::Pending,
+                payment_url: None,
+            }),
+            400..=499 => Err(PaymentError::Rejected(format!(
+                "MTN request-to-pay rejected ({}): {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))),
+            _ => Err(PaymentError::Transport(format!(
+                "MTN request-to-pay failed with {}",
+                resp.status()
+            ))),
+        }
+    }
+
+    async fn collection_status(&self, query: &StatusQuery) -> Result<PaymentStatus, PaymentError> {
+        self.transfer_status(
+            "collection",
+            &self.collection_key,
+            &format!("v1_0/requesttopay/{}", query.provider_reference),
+        )
+        .await
+    }
+
+    async fn refund(&self, request: &RefundRequest) -> Result<RefundResponse, PaymentError> {
+        let key = self.disbursement_key()?;
+        let token = self.access_token("disbursement", key).await?;
+
+        let resp = self
+            .client
+            .post(format!("{}/disbursement/v1_0/refund", self.base_url))
+            .bearer_auth(token)
+            .header("X-Reference-Id", &request.refund_id)
+            .header("X-Target-Environment", &self.target_environment)
+            .header("Ocp-Apim-Subscription-Key", key)
+            .json(&serde_json::json!({
+                "amount": format_amount(request.amount),
+                "currency": request.amount.currency(),
+                "externalId": request.refund_id,
+                "payerMessage": request.reason,
+                "payeeNote": request.reason,
+                "referenceIdToRefund": request.original_reference,
+            }))
+            .send()
+            .await?;
+
+        match resp.status().as_u16() {
+            // 409: this refund id was already submitted by an earlier attempt
+            200..=299 | 409 => Ok(RefundResponse {
+                provider_reference: request.refund_id.clone(),
+                status: PaymentStatus::Pending,
+            }),
+            400..=499 => Err(PaymentError::Rejected(format!(
+                "MTN refund rejected ({}): {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))),
+            _ => Err(PaymentError::Transport(format!(
+                "MTN refund failed with {}",
+                resp.status()
+            ))),
+        }
+    }
+
+    async fn refund_status(&self, provider_reference: &str) -> Result<PaymentStatus, PaymentError> {
+        let key = self.disbursement_key()?;
+        self.transfer_status(
+            "disbursement",
+            key,
+            &format!("v1_0/refund/{}", provider_reference),
+        )
+        .await
+    }
+}
@@ -0,0 +1,214 @@
+use crate::provider::{
+    CollectionRequest, CollectionResponse, PaymentError, PaymentProvider, PaymentStatus,
+    RefundRequest, RefundResponse, StatusQuery,
+};
+use async_trait::async_trait;
+use kamer_core::Money;
+use moka::future::Cache;
+use serde::Deserialize;
+use std::env;
+use std::time::Duration;
+
+/// Orange Money Web Payment (Cameroon) adapter.
+///
+/// The payer approves on an Orange-hosted page (`payment_url`). The WebPay API has no refund
+/// endpoint, so refunds are reported as [`PaymentError::Unsupported`] and settled by hand.
+pub struct OrangeMoneyProvider {
+    client: reqwest::Client,
+    base_url: String,
+    client_id: String,
+    client_secret: String,
+    merchant_key: String,
+    /// Orange's sandbox only accepts its test currency `OUV`.
+    currency_override: Option<String>,
+    return_url: String,
+    cancel_url: String,
+    tokens: Cache<&'static str, String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct WebPaymentResponse {
+    pay_token: String,
+    payment_url: String,
+}
+
+#[derive(Deserialize)]
+struct TransactionStatusResponse {
+    status: String,
+}
+
+impl OrangeMoneyProvider {
+    /// Configured from `ORANGE_MONEY_*` variables; `None` unless client credentials and
+    /// merchant key are set.
+    pub fn from_env() -> Option<Self> {
+        let client_id = env::var("ORANGE_MONEY_CLIENT_ID").ok()?;
+        let client_secret = env::var("ORANGE_MONEY_CLIENT_SECRET").ok()?;
+        let merchant_key = env::var("ORANGE_MONEY_MERCHANT_KEY").ok()?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .ok()?;
+        let frontend_url =
+            env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:5173".to_string());
+
+        Some(Self {
+            client,
+            base_url: env::var("ORANGE_MONEY_BASE_URL")
+                .unwrap_or_else(|_| "https://api.orange.com".to_string()),
+            client_id,
+            client_secret,
+            merchant_key,
+            currency_override: env::var("ORANGE_MONEY_CURRENCY").ok(),
+            return_url: env::var("ORANGE_MONEY_RETURN_URL")
+                .unwrap_or_else(|_| format!("{}/trips", frontend_url)),
+            cancel_url: env::var("ORANGE_MONEY_CANCEL_URL")
+                .unwrap_or_else(|_| format!("{}/trips", frontend_url)),
+            tokens: Cache::builder()
+                .max_capacity(1)
+                .time_to_live(Duration::from_secs(3000))
+                .build(),
+        })
+    }
+
+    async fn access_token(&self) -> Result<String, PaymentError> {
+        if let Some(token) = self.tokens.get(&"oauth").await {
+            return Ok(token);
+        }
+
+        let resp = self
+            .client
+            .post(format!("{}/oauth/v3/token", self.base_url))
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(PaymentError::Transport(format!(
+                "Orange token request failed with {}",
+                resp.status()
+            )));
+        }
+        let token = resp.json::<TokenResponse>().await?.access_token;
+        self.tokens.insert("oauth", token.clone()).await;
+        Ok(token)
+    }
+
+    fn currency(&self, amount: Money) -> String {
+        self.currency_override
+            .clone()
+            .unwrap_or_else(|| amount.currency().to_string())
+    }
+}
+
+/// Orange takes amounts in whole units of the currency.
+fn whole_units(amount: Money) -> i64 {
+    let minor_per_unit = 10_i64.pow(amount.currency().exponent());
+    // Dividing by a positive number cannot overflow
+    amount
+        .checked_div(minor_per_unit)
+        .map_or(0, |units| units.minor())
+}
+
+#[async_trait]
+impl PaymentProvider for OrangeMoneyProvider {
+    fn name(&self) -> &'static str {
+        "orange_money"
+    }
+
+    async fn request_collection(
+        &self,
+        request: &CollectionRequest,
+    ) -> Result<CollectionResponse, PaymentError> {
+        let token = self.access_token().await?;
+
+        let resp = self
+            .client
+            .post(format!(
+                "{}/orange-money-webpay/cm/v1/webpayment",
+                self.base_url
+            ))
+            .bearer_auth(token)
+            .json(&serde_json::json!({
+                "merchant_key": self.merchant_key,
+                "currency": self.currency(request.amount),
+                "order_id": request.payment_id,
+                "amount": whole_units(request.amount),
+                "return_url": self.return_url,
+                "cancel_url": self.cancel_url,
+                "notif_url": request.callback_url,
+                "lang": "fr",
+                "reference": request.description,
+            }))
+            .send()
+            .await?;
+
+        match resp.status().as_u16() {
+            200..=299 => {
+                let body = resp.json::<WebPaymentResponse>().await?;
+                Ok(CollectionResponse {
+                    provider_reference: body.pay_token,
+                    status: PaymentStatus::Pending,
+                    payment_url: Some(body.payment_url),
+                })
+            }
+            400..=499 => Err(PaymentError::Rejected(format!(
+                "Orange web payment rejected ({}): {}",
+                resp.status(),
+                resp.text().await.unwrap_or_default()
+            ))),
+            _ => Err(PaymentError::Transport(format!(
+                "Orange web payment failed with {}",
+                resp.status()
+            ))),
+        }
+    }
+
+    async fn collection_status(&self, query: &StatusQuery) -> Result<PaymentStatus, PaymentError> {
+        let token = self.access_token().await?;
+
+        let resp = self
+            .client
+            .post(format!(
+                "{}/orange-money-webpay/cm/v1/transactionstatus",
+                self.base_url
+            ))
+            .bearer_auth(token)
+            .json(&serde_json::json!({
+                "order_id": query.payment_id,
+                "amount": whole_units(query.amount),
+                "pay_token": query.provider_reference,
+            }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(PaymentError::Transport(format!(
+                "Orange status request failed with {}",
+                resp.status()
+            )));
+        }
+
+        let body = resp.json::<TransactionStatusResponse>().await?;
+        Ok(match body.status.as_str() {
+            "SUCCESS" => PaymentStatus::Succeeded,
+            "FAILED" | "EXPIRED" => PaymentStatus::Failed,
+            _ => PaymentStatus::Pending,
+        })
+    }
+
+    async fn refund(&self, _request: &RefundRequest) -> Result<RefundResponse, PaymentError> {
+        Err(PaymentError::Unsupported("Orange Money refunds"))
+    }
+
+    async fn refund_status(
+        &self,
+        _provider_reference: &str,
+    ) -> Result<PaymentStatus, PaymentError> {
+        Err(PaymentError::Unsupported("Orange Money refunds"))
+    }
+}
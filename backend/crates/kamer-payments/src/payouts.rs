@@ -0,0 +1,244 @@
+use crate::ledger;
+use kamer_core::Money;
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// Job kind: group available host earnings into payout batches.
+pub const CREATE_PAYOUT_BATCHES: &str = "payments.create_payout_batches";
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PayoutBatch {
+    pub id: String,
+    pub host_id: i32,
+    pub amount: Money,
+    /// Tourist tax and VAT included in `amount`, which the host must remit.
+    pub tax_total: Money,
+    pub currency: String,
+    pub entry_count: i32,
+    pub payout_method: Option<String>,
+    pub status: String,
+    pub reference: Option<String>,
+    pub paid_at: Option<String>,
+    pub created_at: Option<String>,
+}
+
+/// A payout batch with the host details finance needs to send the money.
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct PayoutExportRow {
+    pub batch_id: String,
+    pub host_id: i32,
+    pub host_name: Option<String>,
+    pub host_email: String,
+    pub payout_method: Option<String>,
+    pub tax_id: Option<String>,
+    /// Decimal amount in `currency`, e.g. `1250.50`.
+    pub amount: String,
+    /// Decimal tax included in `amount`.
+    pub tax_total: String,
+    pub currency: String,
+    pub entry_count: i32,
+    pub status: String,
+    pub reference: Option<String>,
+    pub created_at: Option<String>,
+    pub paid_at: Option<String>,
+}
+
+/// Outcome of marking a batch as paid.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MarkPaidResult {
+    Paid,
+    NotFound,
+    AlreadyPaid,
+}
+
+const BATCH_COLUMNS: &str = r#"
+    id, host_id, to_money(amount_minor, currency) as amount, to_money(tax_minor, currency) as tax_total,
+    currency, entry_count, payout_method, status, reference,
+    paid_at::TEXT as paid_at, created_at::TEXT as created_at
+"#;
+
+/// Move every host's available earnings into a new payout batch, one per host and currency.
+/// Hosts whose available balance is zero or negative (e.g. after a refund) are skipped.
+pub async fn create_payout_batches(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Serialize batching across replicas so earnings can't land in two batches
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('payout_batches'))")
+        .execute(&mut *tx)
+        .await?;
+
+    let eligible: Vec<(i32, String)> = sqlx::query_as(
+        r#"
+        SELECT host_id, currency
+        FROM ledger_entries
+        WHERE account = 'host_payable' AND entry_type <> 'payout'
+          AND payout_batch_id IS NULL AND available_at <= NOW()
+        GROUP BY host_id, currency
+        HAVING -SUM(amount_minor) > 0
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for (host_id, currency) in &eligible {
+        let batch_id = uuid::Uuid::new_v4().to_string();
+
+        sqlx::query(
+            r#"
+            INSERT INTO payout_batches (id, host_id, currency, payout_method)
+            VALUES ($1, $2, $3, (SELECT payout_method FROM user_profiles WHERE user_id = $2))
+            "#,
+        )
+        .bind(&batch_id)
+        .bind(host_id)
+        .bind(currency)
+        .execute(&mut *tx)
+        .await?;
+
+        // Claim the entries and total them in one statement, so the batch amount always
+        // matches exactly the entries it contains
+        sqlx::query(
+            r#"
+            WITH claimed AS (
+                UPDATE ledger_entries
+                SET payout_batch_id = $1
+                WHERE account = 'host_payable' AND entry_type <> 'payout'
+                  AND host_id = $2 AND currency = $3
+                  AND payout_batch_id IS NULL AND available_at <= NOW()
+                RETURNING amount_minor, entry_type
+            )
+            UPDATE payout_batches
+            SET amount_minor = (SELECT COALESCE(-SUM(amount_minor), 0) FROM claimed),
+                tax_minor = (
+                    SELECT COALESCE(-SUM(amount_minor), 0) FROM claimed
+                    WHERE entry_type IN ('tax', 'tax_refund')
+                ),
+                entry_count = (SELECT COUNT(*) FROM claimed)
+            WHERE id = $1
+            "#,
+        )
+        .bind(&batch_id)
+        .bind(host_id)
+        .bind(currency)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    if !eligible.is_empty() {
+        log::info!("Created {} payout batches", eligible.len());
+    }
+    Ok(eligible.len() as u64)
+}
+
+/// List payout batches, newest first, optionally for one host and/or status.
+pub async fn list_batches(
+    pool: &PgPool,
+    host_id: Option<i32>,
+    status: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PayoutBatch>, sqlx::Error> {
+    sqlx::query_as::<_, PayoutBatch>(&format!(
+        r#"
+        SELECT {} FROM payout_batches
+        WHERE ($1::INTEGER IS NULL OR host_id = $1)
+          AND ($2::TEXT IS NULL OR status = $2)
+        ORDER BY created_at DESC, id
+        LIMIT $3 OFFSET $4
+        "#,
+        BATCH_COLUMNS
+    ))
+    .bind(host_id)
+    .bind(status)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+/// Batches joined with host payout details, for the finance CSV export.
+pub async fn export_rows(
+    pool: &PgPool,
+    status: Option<&str>,
+) -> Result<Vec<PayoutExportRow>, sqlx::Error> {
+    sqlx::query_as::<_, PayoutExportRow>(
+        r#"
+        SELECT
+            pb.id as batch_id, pb.host_id, up.legal_name as host_name, u.email as host_email,
+            pb.payout_method, up.tax_id,
+            ROUND(pb.amount_minor / power(10::NUMERIC, currency_exponent(pb.currency)),
+                  currency_exponent(pb.currency))::TEXT as amount,
+            ROUND(pb.tax_minor / power(10::NUMERIC, currency_exponent(pb.currency)),
+                  currency_exponent(pb.currency))::TEXT as tax_total,
+            pb.currency, pb.entry_count, pb.status,
+            pb.reference, pb.created_at::TEXT as created_at, pb.paid_at::TEXT as paid_at
+        FROM payout_batches pb
+        JOIN users u ON pb.host_id = u.id
+        LEFT JOIN user_profiles up ON pb.host_id = up.user_id
+        WHERE ($1::TEXT IS NULL OR pb.status = $1)
+        ORDER BY pb.created_at, pb.id
+        "#,
+    )
+    .bind(status)
+    .fetch_all(pool)
+    .await
+}
+
+/// Render export rows as CSV.
+pub fn export_csv(rows: &[PayoutExportRow]) -> Result<String, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Mark a pending batch as paid and record the payout in the ledger.
+pub async fn mark_batch_paid(
+    pool: &PgPool,
+    batch_id: &str,
+    paid_by: i32,
+    reference: Option<&str>,
+) -> Result<MarkPaidResult, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let batch: Option<(i32, Money, String)> = sqlx::query_as(
+        r#"
+        SELECT host_id, to_money(amount_minor, currency), status
+        FROM payout_batches WHERE id = $1 FOR UPDATE
+        "#,
+    )
+    .bind(batch_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((host_id, amount, status)) = batch else {
+        return Ok(MarkPaidResult::NotFound);
+    };
+    if status == "paid" {
+        return Ok(MarkPaidResult::AlreadyPaid);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE payout_batches
+        SET status = 'paid', reference = $2, paid_by = $3, paid_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(batch_id)
+    .bind(reference)
+    .bind(paid_by)
+    .execute(&mut *tx)
+    .await?;
+
+    ledger::post_payout(&mut tx, batch_id, host_id, amount).await?;
+
+    tx.commit().await?;
+    Ok(MarkPaidResult::Paid)
+}
@@ -0,0 +1,170 @@
+use crate::fake::FakeProvider;
+use crate::mtn_momo::MtnMomoProvider;
+use crate::orange_money::OrangeMoneyProvider;
+use async_trait::async_trait;
+use kamer_core::Money;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+
+/// Status of a collection or refund as reported by the provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl PaymentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Pending => "pending",
+            PaymentStatus::Succeeded => "succeeded",
+            PaymentStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Ask the payer to approve a mobile-money debit.
+#[derive(Debug, Clone)]
+pub struct CollectionRequest {
+    /// Our payment id, sent to the provider as the external/order id.
+    pub payment_id: String,
+    pub amount: Money,
+    /// Payer MSISDN in international format without `+`, e.g. `2376XXXXXXXX`.
+    pub phone: String,
+    pub description: String,
+    /// Signed URL the provider calls back when the payment settles.
+    pub callback_url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectionResponse {
+    /// Provider-side id used for status checks and refunds.
+    pub provider_reference: String,
+    pub status: PaymentStatus,
+    /// Page the payer must visit to approve, for providers that use a redirect flow.
+    pub payment_url: Option<String>,
+}
+
+/// Identifies a collection when asking the provider for its status.
+#[derive(Debug, Clone)]
+pub struct StatusQuery {
+    pub payment_id: String,
+    pub provider_reference: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct RefundRequest {
+    /// Our refund id; providers use it to deduplicate retried refund calls.
+    pub refund_id: String,
+    pub payment_id: String,
+    /// Provider reference of the original collection.
+    pub original_reference: String,
+    pub amount: Money,
+    pub phone: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct RefundResponse {
+    pub provider_reference: String,
+    pub status: PaymentStatus,
+}
+
+#[derive(Debug)]
+pub enum PaymentError {
+    /// The provider could not be reached or returned a malformed response; worth retrying.
+    Transport(String),
+    /// The provider rejected the request.
+    Rejected(String),
+    /// The provider has no API for this operation; it must be handled manually.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Transport(msg) => write!(f, "Payment provider unavailable: {}", msg),
+            PaymentError::Rejected(msg) => write!(f, "Payment rejected: {}", msg),
+            PaymentError::Unsupported(op) => write!(f, "Operation not supported: {}", op),
+        }
+    }
+}
+
+impl From<reqwest::Error> for PaymentError {
+    fn from(err: reqwest::Error) -> Self {
+        PaymentError::Transport(err.to_string())
+    }
+}
+
+/// A mobile-money operator we can collect from and refund to.
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Stable identifier used in URLs and the `payments.provider` column.
+    fn name(&self) -> &'static str;
+
+    async fn request_collection(
+        &self,
+        request: &CollectionRequest,
+    ) -> Result<CollectionResponse, PaymentError>;
+
+    async fn collection_status(&self, query: &StatusQuery) -> Result<PaymentStatus, PaymentError>;
+
+    async fn refund(&self, request: &RefundRequest) -> Result<RefundResponse, PaymentError>;
+
+    /// Status of a refund previously started with [`PaymentProvider::refund`].
+    async fn refund_status(&self, provider_reference: &str) -> Result<PaymentStatus, PaymentError>;
+}
+
+/// Providers enabled for this deployment, keyed by [`PaymentProvider::name`].
+#[derive(Clone, Default)]
+pub struct PaymentProviders {
+    providers: HashMap<&'static str, Arc<dyn PaymentProvider>>,
+}
+
+impl PaymentProviders {
+    /// Enable every provider whose credentials are present in the environment.
+    /// The fake provider is only enabled with `PAYMENT_FAKE_ENABLED=true`.
+    pub fn from_env() -> Self {
+        let mut providers = Self::default();
+        if let Some(mtn) = MtnMomoProvider::from_env() {
+            providers.insert(Arc::new(mtn));
+        }
+        if let Some(orange) = OrangeMoneyProvider::from_env() {
+            providers.insert(Arc::new(orange));
+        }
+        if env::var("PAYMENT_FAKE_ENABLED").unwrap_or_default() == "true" {
+            providers.insert(Arc::new(FakeProvider::default()));
+        }
+        providers
+    }
+
+    pub fn insert(&mut self, provider: Arc<dyn PaymentProvider>) {
+        self.providers.insert(provider.name(), provider);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn PaymentProvider>> {
+        self.providers.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names: Vec<_> = self.providers.keys().copied().collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+/// Format an amount the way mobile-money APIs expect: with the currency's minor-unit decimals,
+/// so whole units for zero-decimal currencies such as XAF.
+pub fn format_amount(amount: Money) -> String {
+    amount.to_decimal()
+}
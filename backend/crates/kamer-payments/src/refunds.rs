@@ -0,0 +1,377 @@
+use crate::ledger;
+use crate::provider::{PaymentError, PaymentProviders, PaymentStatus, RefundRequest};
+use kamer_core::Money;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+
+/// Job kind: send a booking refund to the payer's mobile-money account.
+pub const REFUND_BOOKING: &str = "payments.refund";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RefundJob {
+    refund_id: String,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct RefundRow {
+    id: String,
+    payment_id: String,
+    status: String,
+    provider_reference: Option<String>,
+    amount: Money,
+    reason: Option<String>,
+    provider: String,
+    payment_reference: Option<String>,
+    phone: String,
+}
+
+/// Refund up to `amount` of the booking's successful payment.
+///
+/// Creates the refund record, posts it to the ledger and enqueues the job that talks to the
+/// provider, on the caller's connection so all of it commits together with the cancellation. Returns `None` when the booking
+/// was never paid through the platform or a refund already exists. For a booking the guest paid
+/// the host directly, the host refunds them and only the service fee on that share is reversed.
+pub async fn request_refund(
+    conn: &mut PgConnection,
+    booking_id: &str,
+    amount: Money,
+    reason: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    if !amount.is_positive() {
+        return Ok(None);
+    }
+
+    let refund_id = insert_refund(&mut *conn, booking_id, None, amount, reason).await?;
+    if refund_id.is_none() {
+        ledger::post_offline_refund(&mut *conn, booking_id, amount).await?;
+    }
+    Ok(refund_id)
+}
+
+/// Refund the price decrease of an accepted change request, like [`request_refund`].
+///
+/// Each change request is refunded at most once; returns `None` when the booking was never
+/// paid through the platform.
+pub async fn request_alteration_refund(
+    conn: &mut PgConnection,
+    booking_id: &str,
+    alteration_id: &str,
+    amount: Money,
+) -> Result<Option<String>, sqlx::Error> {
+    if !amount.is_positive() {
+        return Ok(None);
+    }
+    insert_refund(
+        conn,
+        booking_id,
+        Some(alteration_id),
+        amount,
+        "Booking changed to a lower price",
+    )
+    .await
+}
+
+/// Record a refund of at most what is left of the payment after earlier refunds, post it to
+/// the ledger and enqueue the job that sends it.
+async fn insert_refund(
+    conn: &mut PgConnection,
+    booking_id: &str,
+    alteration_id: Option<&str>,
+    amount: Money,
+    reason: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let refund_id: Option<String> = sqlx::query_scalar(
+        r#"
+        INSERT INTO payment_refunds
+            (id, payment_id, booking_id, alteration_id, amount_minor, currency, reason)
+        SELECT $1, p.id, p.booking_id, $5, LEAST($3, p.amount_minor - refunded.total), p.currency, $4
+        FROM payments p
+        CROSS JOIN LATERAL (
+            SELECT COALESCE(SUM(r.amount_minor), 0) as total
+            FROM payment_refunds r WHERE r.payment_id = p.id
+        ) refunded
+        WHERE p.booking_id = $2 AND p.status = 'succeeded' AND p.amount_minor > refunded.total
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(booking_id)
+    .bind(amount.minor())
+    .bind(reason)
+    .bind(alteration_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(refund_id) = &refund_id {
+        ledger::post_refund(&mut *conn, refund_id).await?;
+        kamer_jobs::enqueue(
+            &mut *conn,
+            REFUND_BOOKING,
+            serde_json::json!(RefundJob {
+                refund_id: refund_id.clone()
+            }),
+        )
+        .await?;
+    }
+
+    Ok(refund_id)
+}
+
+/// What to record for a refund, given the provider's answer as `(reference, status)`.
+#[derive(Debug, PartialEq, Eq)]
+enum RefundUpdate {
+    Succeeded {
+        reference: String,
+    },
+    /// The provider can't perform the refund; an admin has to settle it.
+    ManualRequired {
+        reference: Option<String>,
+        reason: String,
+    },
+    /// Still pending at the provider; poll again later.
+    Pending {
+        reference: String,
+    },
+    /// The provider couldn't be reached; try again later.
+    Retry(String),
+}
+
+fn refund_update(result: Result<(String, PaymentStatus), PaymentError>) -> RefundUpdate {
+    match result {
+        Ok((reference, PaymentStatus::Succeeded)) => RefundUpdate::Succeeded { reference },
+        Ok((reference, PaymentStatus::Failed)) => RefundUpdate::ManualRequired {
+            reference: Some(reference),
+            reason: "Provider reported the refund as failed".to_string(),
+        },
+        Ok((reference, PaymentStatus::Pending)) => RefundUpdate::Pending { reference },
+        Err(PaymentError::Transport(msg)) => RefundUpdate::Retry(msg),
+        Err(err) => RefundUpdate::ManualRequired {
+            reference: None,
+            reason: err.to_string(),
+        },
+    }
+}
+
+async fn set_refund_status(
+    pool: &PgPool,
+    refund_id: &str,
+    status: &str,
+    provider_reference: Option<&str>,
+    failure_reason: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE payment_refunds
+        SET status = $2, provider_reference = COALESCE($3, provider_reference),
+            failure_reason = $4, updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(refund_id)
+    .bind(status)
+    .bind(provider_reference)
+    .bind(failure_reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Job handler for [`REFUND_BOOKING`].
+///
+/// Returns an error while the provider still reports the refund as pending, so the job runner
+/// polls again with backoff; once the job gives up, [`give_up_refund`] flags it. Refunds the
+/// provider cannot perform are flagged `manual_required` for an admin to settle.
+pub async fn process_refund(
+    pool: &PgPool,
+    providers: &PaymentProviders,
+    payload: serde_json::Value,
+) -> Result<(), String> {
+    let job: RefundJob =
+        serde_json::from_value(payload).map_err(|e| format!("Invalid refund payload: {}", e))?;
+
+    let refund = sqlx::query_as::<_, RefundRow>(
+        r#"
+        SELECT r.id, r.payment_id, r.status, r.provider_reference,
+               to_money(r.amount_minor, r.currency) as amount, r.reason,
+               p.provider, p.provider_reference as payment_reference, p.phone
+        FROM payment_refunds r
+        JOIN payments p ON r.payment_id = p.id
+        WHERE r.id = $1
+        "#,
+    )
+    .bind(&job.refund_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Refund {} not found", job.refund_id))?;
+
+    if refund.status != "pending" {
+        return Ok(());
+    }
+
+    let Some(provider) = providers.get(&refund.provider) else {
+        return set_refund_status(
+            pool,
+            &refund.id,
+            "manual_required",
+            None,
+            Some("Payment provider is not configured"),
+        )
+        .await
+        .map_err(|e| e.to_string());
+    };
+
+    // A previous attempt already reached the provider: only poll for the outcome
+    let result = match &refund.provider_reference {
+        Some(reference) => provider
+            .refund_status(reference)
+            .await
+            .map(|status| (reference.clone(), status)),
+        None => {
+            let request = RefundRequest {
+                refund_id: refund.id.clone(),
+                payment_id: refund.payment_id.clone(),
+                original_reference: refund.payment_reference.clone().unwrap_or_default(),
+                amount: refund.amount,
+                phone: refund.phone.clone(),
+                reason: refund
+                    .reason
+                    .clone()
+                    .unwrap_or_else(|| "Booking refund".to_string()),
+            };
+            provider
+                .refund(&request)
+                .await
+                .map(|resp| (resp.provider_reference, resp.status))
+        }
+    };
+
+    match refund_update(result) {
+        RefundUpdate::Succeeded { reference } => {
+            log::info!("Refund {} succeeded", refund.id);
+            set_refund_status(pool, &refund.id, "succeeded", Some(&reference), None)
+                .await
+                .map_err(|e| e.to_string())
+        }
+        RefundUpdate::ManualRequired { reference, reason } => {
+            log::warn!("Refund {} needs manual handling: {}", refund.id, reason);
+            set_refund_status(
+                pool,
+                &refund.id,
+                "manual_required",
+                reference.as_deref(),
+                Some(&reason),
+            )
+            .await
+            .map_err(|e| e.to_string())
+        }
+        RefundUpdate::Pending { reference } => {
+            set_refund_status(pool, &refund.id, "pending", Some(&reference), None)
+                .await
+                .map_err(|e| e.to_string())?;
+            Err(format!(
+                "Refund {} is still pending at the provider",
+                refund.id
+            ))
+        }
+        RefundUpdate::Retry(error) => Err(error),
+    }
+}
+
+/// Give-up handler for [`REFUND_BOOKING`]: a refund the provider never confirmed within the
+/// job's attempts is flagged `manual_required`, so it doesn't sit in `pending` forever.
+pub async fn give_up_refund(pool: &PgPool, payload: serde_json::Value) -> Result<(), String> {
+    let job: RefundJob =
+        serde_json::from_value(payload).map_err(|e| format!("Invalid refund payload: {}", e))?;
+
+    let flagged = sqlx::query(
+        r#"
+        UPDATE payment_refunds
+        SET status = 'manual_required',
+            failure_reason = 'Provider did not confirm the refund after repeated attempts',
+            updated_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = 'pending'
+        "#,
+    )
+    .bind(&job.refund_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if flagged.rows_affected() > 0 {
+        log::warn!(
+            "Refund {} needs manual handling: no confirmation from the provider",
+            job.refund_id
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeProvider;
+    use crate::provider::PaymentProvider;
+    use kamer_core::Currency;
+
+    #[tokio::test]
+    async fn fake_refund_succeeds() {
+        let provider = FakeProvider::default();
+        let result = provider
+            .refund(&RefundRequest {
+                refund_id: "ref-1".to_string(),
+                payment_id: "pay-1".to_string(),
+                original_reference: "fake-pay-1".to_string(),
+                amount: Money::from_minor(5_000, Currency::XAF),
+                phone: "237670000001".to_string(),
+                reason: "Booking refund".to_string(),
+            })
+            .await
+            .map(|resp| (resp.provider_reference, resp.status));
+        assert_eq!(
+            refund_update(result),
+            RefundUpdate::Succeeded {
+                reference: "fake-refund-ref-1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn pending_refunds_are_polled_again() {
+        assert_eq!(
+            refund_update(Ok(("r1".to_string(), PaymentStatus::Pending))),
+            RefundUpdate::Pending {
+                reference: "r1".to_string()
+            }
+        );
+        assert_eq!(
+            refund_update(Err(PaymentError::Transport("timeout".to_string()))),
+            RefundUpdate::Retry("timeout".to_string())
+        );
+    }
+
+    #[test]
+    fn failed_or_unsupported_refunds_need_manual_handling() {
+        assert!(matches!(
+            refund_update(Ok(("r1".to_string(), PaymentStatus::Failed))),
+            RefundUpdate::ManualRequired { reference: Some(reference), .. } if reference == "r1"
+        ));
+        assert!(matches!(
+            refund_update(Err(PaymentError::Unsupported("refund"))),
+            RefundUpdate::ManualRequired {
+                reference: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            refund_update(Err(PaymentError::Rejected(
+                "insufficient funds".to_string()
+            ))),
+            RefundUpdate::ManualRequired {
+                reference: None,
+                ..
+            }
+        ));
+    }
+}
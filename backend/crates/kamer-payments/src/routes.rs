@@ -0,0 +1,705 @@
+use crate::provider::{
+    CollectionRequest, PaymentError, PaymentProviders, PaymentStatus, StatusQuery,
+};
+use crate::{ledger, refunds, webhook};
+use actix_web::{get, post, route, web, HttpRequest, HttpResponse, Responder};
+use kamer_core::Money;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+// ============================================================================
+// Data Structures
+// ============================================================================
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct Payment {
+    pub id: String,
+    pub booking_id: String,
+    pub payer_id: i32,
+    pub provider: String,
+    pub provider_reference: Option<String>,
+    pub amount: Money,
+    pub currency: String,
+    pub phone: String,
+    pub status: String,
+    pub failure_reason: Option<String>,
+    pub payment_url: Option<String>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InitiatePaymentRequest {
+    pub provider: String,
+    pub phone: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookQuery {
+    pub payment_id: String,
+    pub signature: String,
+}
+
+const PAYMENT_COLUMNS: &str = r#"
+    id, booking_id, payer_id, provider, provider_reference,
+    to_money(amount_minor, currency) as amount, currency, phone, status,
+    failure_reason, payment_url, created_at::TEXT as created_at, updated_at::TEXT as updated_at
+"#;
+
+// ============================================================================
+// Helper Functions
+// ============================================================================
+
+/// Normalize a Cameroonian mobile number to the MSISDN format providers expect (`2376XXXXXXXX`).
+fn normalize_phone(phone: &str) -> Option<String> {
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    let digits = digits.strip_prefix("00").unwrap_or(&digits).to_string();
+    match digits.len() {
+        9 => Some(format!("237{}", digits)),
+        12 if digits.starts_with("237") => Some(digits),
+        _ => None,
+    }
+}
+
+async fn fetch_payment(pool: &PgPool, payment_id: &str) -> Result<Option<Payment>, sqlx::Error> {
+    sqlx::query_as::<_, Payment>(&format!(
+        "SELECT {} FROM payments WHERE id = $1",
+        PAYMENT_COLUMNS
+    ))
+    .bind(payment_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Apply a provider-reported status to a pending payment.
+///
+/// Every transition is guarded on the current status, so concurrent webhooks and polls apply
+/// it once. A payment that settles after its booking was cancelled or expired is refunded.
+async fn apply_status(
+    pool: &PgPool,
+    payment: &Payment,
+    status: PaymentStatus,
+) -> Result<(), sqlx::Error> {
+    match status {
+        PaymentStatus::Pending => Ok(()),
+        PaymentStatus::Failed => {
+            sqlx::query(
+                r#"
+                UPDATE payments
+                SET status = 'failed', failure_reason = 'Declined or expired at the provider',
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE id = $1 AND status = 'pending'
+                "#,
+            )
+            .bind(&payment.id)
+            .execute(pool)
+            .await?;
+            Ok(())
+        }
+        PaymentStatus::Succeeded => {
+            let mut tx = pool.begin().await?;
+
+            let updated = sqlx::query(
+                r#"
+                UPDATE payments SET status = 'succeeded', updated_at = CURRENT_TIMESTAMP
+                WHERE id = $1 AND status = 'pending'
+                "#,
+            )
+            .bind(&payment.id)
+            .execute(&mut *tx)
+            .await?;
+            if updated.rows_affected() == 0 {
+                return Ok(());
+            }
+
+            ledger::post_booking_charge(&mut tx, &payment.id).await?;
+
+            let confirmed = sqlx::query(
+                r#"
+                UPDATE bookings SET status = 'confirmed', updated_at = CURRENT_TIMESTAMP
+                WHERE id = $1 AND status = 'awaiting_payment'
+                "#,
+            )
+            .bind(&payment.booking_id)
+            .execute(&mut *tx)
+            .await?;
+
+            if confirmed.rows_affected() == 0 {
+                log::warn!(
+                    "Payment {} settled after booking {} stopped awaiting payment; refunding",
+                    payment.id,
+                    payment.booking_id
+                );
+                refunds::request_refund(
+                    &mut tx,
+                    &payment.booking_id,
+                    payment.amount,
+                    "Booking was no longer available when the payment arrived",
+                )
+                .await?;
+            }
+
+            tx.commit().await?;
+            log::info!("Payment {} succeeded", payment.id);
+            Ok(())
+        }
+    }
+}
+
+/// Ask the provider for the current status of a pending payment and apply it. Returns the
+/// status the provider reported; a payment that can't be checked stays pending.
+async fn sync_payment(
+    pool: &PgPool,
+    providers: &PaymentProviders,
+    payment: &Payment,
+) -> Result<PaymentStatus, PaymentError> {
+    let (Some(provider), Some(reference)) = (
+        providers.get(&payment.provider),
+        payment.provider_reference.as_ref(),
+    ) else {
+        return Ok(PaymentStatus::Pending);
+    };
+
+    let status = provider
+        .collection_status(&StatusQuery {
+            payment_id: payment.id.clone(),
+            provider_reference: reference.clone(),
+            amount: payment.amount,
+        })
+        .await?;
+
+    apply_status(pool, payment, status)
+        .await
+        .map_err(|e| PaymentError::Transport(format!("Database error: {}", e)))?;
+    Ok(status)
+}
+
+// ============================================================================
+// API Endpoints
+// ============================================================================
+
+/// POST /api/payments/bookings/{booking_id} - Start a mobile-money payment for a booking
+#[post("/bookings/{booking_id}")]
+pub async fn initiate_payment(
+    pool: web::Data<PgPool>,
+    providers: web::Data<PaymentProviders>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<InitiatePaymentRequest>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let booking_id = path.into_inner();
+
+    let Some(provider) = providers.get(&body.provider) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "Unsupported payment provider",
+            "providers": providers.names()
+        }));
+    };
+
+    let Some(phone) = normalize_phone(&body.phone) else {
+        return HttpResponse::BadRequest()
+            .json(serde_json::json!({ "error": "Invalid mobile money number" }));
+    };
+
+    let booking = sqlx::query_as::<_, (i32, String, Money, String)>(
+        r#"
+        SELECT b.guest_id, COALESCE(b.status, 'pending'),
+               to_money(b.total_price_minor, b.currency), l.title
+        FROM bookings b
+        JOIN listings l ON b.listing_id = l.id
+        WHERE b.id = $1
+        "#,
+    )
+    .bind(&booking_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    let (guest_id, status, amount, listing_title) = match booking {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Booking not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch booking for payment: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if guest_id != user_id {
+        return HttpResponse::Forbidden()
+            .json(serde_json::json!({ "error": "Only the guest can pay for this booking" }));
+    }
+
+    if status != "awaiting_payment" {
+        return HttpResponse::Conflict().json(serde_json::json!({
+            "error": format!("A {} booking cannot be paid", status)
+        }));
+    }
+
+    // Only one collection may be in flight: hand back the existing one unless it just settled
+    let existing = sqlx::query_as::<_, Payment>(&format!(
+        "SELECT {} FROM payments WHERE booking_id = $1 AND status = 'pending'",
+        PAYMENT_COLUMNS
+    ))
+    .bind(&booking_id)
+    .fetch_optional(pool.get_ref())
+    .await;
+
+    match existing {
+        Ok(Some(payment)) => {
+            if let Err(e) = sync_payment(pool.get_ref(), providers.get_ref(), &payment).await {
+                log::warn!("Failed to refresh payment {}: {}", payment.id, e);
+            }
+            match fetch_payment(pool.get_ref(), &payment.id).await {
+                Ok(Some(payment)) if payment.status != "failed" => {
+                    return HttpResponse::Ok().json(payment);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Failed to fetch payment: {:?}", e);
+                    return HttpResponse::InternalServerError()
+                        .json(serde_json::json!({ "error": "Database error" }));
+                }
+            }
+        }
+        Ok(None) => {}
+        Err(e) => {
+            log::error!("Failed to fetch pending payment: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    }
+
+    let payment_id = uuid::Uuid::new_v4().to_string();
+    let Some(callback_url) = webhook::callback_url(provider.name(), &payment_id) else {
+        log::error!("PAYMENTS_CALLBACK_BASE_URL or PAYMENTS_WEBHOOK_SECRET is not set");
+        return HttpResponse::ServiceUnavailable()
+            .json(serde_json::json!({ "error": "Payments are not configured" }));
+    };
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO payments (id, booking_id, payer_id, provider, amount_minor, currency, phone)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(&payment_id)
+    .bind(&booking_id)
+    .bind(user_id)
+    .bind(provider.name())
+    .bind(amount.minor())
+    .bind(amount.currency())
+    .bind(&phone)
+    .execute(pool.get_ref())
+    .await;
+
+    if let Err(e) = inserted {
+        log::error!("Failed to create payment: {:?}", e);
+        return HttpResponse::Conflict()
+            .json(serde_json::json!({ "error": "A payment is already in progress" }));
+    }
+
+    let request = CollectionRequest {
+        payment_id: payment_id.clone(),
+        amount,
+        phone,
+        description: format!("Booking {}", listing_title),
+        callback_url,
+    };
+
+    let (update, error_response) = match provider.request_collection(&request).await {
+        Ok(resp) => (
+            sqlx::query(
+                r#"
+                UPDATE payments
+                SET provider_reference = $2, payment_url = $3, updated_at = CURRENT_TIMESTAMP
+                WHERE id = $1
+                "#,
+            )
+            .bind(&payment_id)
+            .bind(resp.provider_reference)
+            .bind(resp.payment_url),
+            None,
+        ),
+        Err(err) => {
+            log::error!("Payment {} could not be started: {}", payment_id, err);
+            let response = match &err {
+                PaymentError::Rejected(_) => HttpResponse::BadRequest().json(
+                    serde_json::json!({ "error": "The payment was rejected by the provider" }),
+                ),
+                _ => HttpResponse::BadGateway().json(
+                    serde_json::json!({ "error": "Payment provider is unavailable, please retry" }),
+                ),
+            };
+            (
+                sqlx::query(
+                    r#"
+                    UPDATE payments
+                    SET status = 'failed', failure_reason = $2, updated_at = CURRENT_TIMESTAMP
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(&payment_id)
+                .bind(err.to_string()),
+                Some(response),
+            )
+        }
+    };
+
+    if let Err(e) = update.execute(pool.get_ref()).await {
+        log::error!("Failed to update payment {}: {:?}", payment_id, e);
+    }
+    if let Some(response) = error_response {
+        return response;
+    }
+
+    match fetch_payment(pool.get_ref(), &payment_id).await {
+        Ok(Some(payment)) => HttpResponse::Created().json(payment),
+        Ok(None) => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Payment not found" }))
+        }
+        Err(e) => {
+            log::error!("Failed to fetch payment: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// GET /api/payments/{id} - Get a payment, refreshing its status from the provider while pending
+#[get("/{id}")]
+pub async fn get_payment(
+    pool: web::Data<PgPool>,
+    providers: web::Data<PaymentProviders>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = match kamer_auth::extract_user_id(&req, pool.get_ref()).await {
+        Ok(id) => id,
+        Err(err) => return HttpResponse::from_error(err),
+    };
+    let payment_id = path.into_inner();
+
+    let payment = match fetch_payment(pool.get_ref(), &payment_id).await {
+        Ok(Some(payment)) => payment,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Payment not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch payment: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if payment.payer_id != user_id {
+        return HttpResponse::Forbidden().json(serde_json::json!({ "error": "Not authorized" }));
+    }
+
+    if payment.status != "pending" {
+        return HttpResponse::Ok().json(payment);
+    }
+
+    if let Err(e) = sync_payment(pool.get_ref(), providers.get_ref(), &payment).await {
+        log::warn!("Failed to refresh payment {}: {}", payment.id, e);
+        return HttpResponse::Ok().json(payment);
+    }
+
+    match fetch_payment(pool.get_ref(), &payment_id).await {
+        Ok(Some(payment)) => HttpResponse::Ok().json(payment),
+        Ok(None) => {
+            HttpResponse::NotFound().json(serde_json::json!({ "error": "Payment not found" }))
+        }
+        Err(e) => {
+            log::error!("Failed to fetch payment: {:?}", e);
+            HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }))
+        }
+    }
+}
+
+/// POST|PUT /api/payments/webhooks/{provider} - Provider payment callback
+///
+/// The body is only used for deduplication: the payment status is always re-read from the
+/// provider, so a forged or replayed body cannot confirm a booking.
+#[route("/webhooks/{provider}", method = "POST", method = "PUT")]
+pub async fn payment_webhook(
+    pool: web::Data<PgPool>,
+    providers: web::Data<PaymentProviders>,
+    path: web::Path<String>,
+    query: web::Query<WebhookQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    let provider_name = path.into_inner();
+
+    if !webhook::verify_signature(&provider_name, &query.payment_id, &query.signature) {
+        log::warn!(
+            "Rejected {} webhook with invalid signature for payment {}",
+            provider_name,
+            query.payment_id
+        );
+        return HttpResponse::Unauthorized()
+            .json(serde_json::json!({ "error": "Invalid signature" }));
+    }
+
+    let event_key =
+        match webhook::record_event(pool.get_ref(), &provider_name, &query.payment_id, &body).await
+        {
+            Ok(Some(key)) => key,
+            Ok(None) => {
+                return HttpResponse::Ok().json(serde_json::json!({ "status": "duplicate" }));
+            }
+            Err(e) => {
+                log::error!("Failed to record payment webhook: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .json(serde_json::json!({ "error": "Database error" }));
+            }
+        };
+
+    let payment = match fetch_payment(pool.get_ref(), &query.payment_id).await {
+        Ok(Some(payment)) if payment.provider == provider_name => payment,
+        Ok(_) => {
+            return HttpResponse::NotFound()
+                .json(serde_json::json!({ "error": "Payment not found" }));
+        }
+        Err(e) => {
+            log::error!("Failed to fetch payment: {:?}", e);
+            let _ = webhook::forget_event(pool.get_ref(), &provider_name, &event_key).await;
+            return HttpResponse::InternalServerError()
+                .json(serde_json::json!({ "error": "Database error" }));
+        }
+    };
+
+    if payment.status == "pending" {
+        match sync_payment(pool.get_ref(), providers.get_ref(), &payment).await {
+            Ok(PaymentStatus::Pending) => {
+                // Callbacks can arrive before the provider reports the payment settled; a
+                // redelivery of the same body must be checked again, not dropped as a duplicate
+                if let Err(e) =
+                    webhook::forget_event(pool.get_ref(), &provider_name, &event_key).await
+                {
+                    log::error!("Failed to forget payment webhook: {:?}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                log::error!(
+                    "Failed to process webhook for payment {}: {}",
+                    payment.id,
+                    e
+                );
+                // Let the provider's retry go through the same path again
+                let _ = webhook::forget_event(pool.get_ref(), &provider_name, &event_key).await;
+                return HttpResponse::BadGateway()
+                    .json(serde_json::json!({ "error": "Could not confirm payment status" }));
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({ "status": "processed" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::FakeProvider;
+    use actix_web::{test, App};
+    use std::sync::Arc;
+
+    /// These tests need a migrated database; run them with `--ignored`.
+    async fn test_pool() -> PgPool {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is set");
+        std::env::set_var("PAYMENTS_WEBHOOK_SECRET", "test-secret");
+        std::env::set_var("PAYMENTS_CALLBACK_BASE_URL", "");
+        PgPool::connect(&url)
+            .await
+            .expect("TEST_DATABASE_URL is reachable")
+    }
+
+    /// A booking in `status` with a pending fake-provider payment; returns the payment id.
+    async fn pending_payment(pool: &PgPool, providers: &PaymentProviders, status: &str) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        let host_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, email) VALUES ($1, $1 || '@host.test') RETURNING id",
+        )
+        .bind(format!("host-{}", id))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let guest_id: i32 = sqlx::query_scalar(
+            "INSERT INTO users (username, email) VALUES ($1, $1 || '@guest.test') RETURNING id",
+        )
+        .bind(format!("guest-{}", id))
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO listings (id, host_id, status, title) VALUES ($1, $2, 'published', 'Test flat')")
+            .bind(&id)
+            .bind(host_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO bookings (id, listing_id, guest_id, check_in, check_out, guests,
+                                  total_price_minor, currency, status)
+            VALUES ($1, $1, $2, CURRENT_DATE + 30, CURRENT_DATE + 32, 1, 20000, 'XAF', $3)
+            "#,
+        )
+        .bind(&id)
+        .bind(guest_id)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let payment_id = uuid::Uuid::new_v4().to_string();
+        let response = providers
+            .get("fake")
+            .unwrap()
+            .request_collection(&CollectionRequest {
+                payment_id: payment_id.clone(),
+                amount: Money::from_minor(20_000, kamer_core::Currency::XAF),
+                phone: "237670000001".to_string(),
+                description: "Booking".to_string(),
+                callback_url: String::new(),
+            })
+            .await
+            .unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO payments (id, booking_id, payer_id, provider, provider_reference,
+                                  amount_minor, currency, phone)
+            VALUES ($1, $2, $3, 'fake', $4, 20000, 'XAF', '237670000001')
+            "#,
+        )
+        .bind(&payment_id)
+        .bind(&id)
+        .bind(guest_id)
+        .bind(&response.provider_reference)
+        .execute(pool)
+        .await
+        .unwrap();
+        payment_id
+    }
+
+    async fn deliver_webhook(pool: &PgPool, providers: &PaymentProviders, payment_id: &str) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .app_data(web::Data::new(providers.clone()))
+                .service(web::scope("/api/payments").service(payment_webhook)),
+        )
+        .await;
+        let uri = webhook::callback_url("fake", payment_id).unwrap();
+        let resp = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&uri)
+                .set_payload(r#"{"status":"SUCCESSFUL"}"#)
+                .to_request(),
+        )
+        .await;
+        assert!(resp.status().is_success());
+    }
+
+    fn fake_providers() -> PaymentProviders {
+        let mut providers = PaymentProviders::default();
+        providers.insert(Arc::new(FakeProvider::default()));
+        providers
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database in TEST_DATABASE_URL"]
+    async fn webhook_confirms_the_booking_and_posts_the_charge() {
+        let pool = test_pool().await;
+        let providers = fake_providers();
+        let payment_id = pending_payment(&pool, &providers, "awaiting_payment").await;
+
+        deliver_webhook(&pool, &providers, &payment_id).await;
+
+        let payment = fetch_payment(&pool, &payment_id).await.unwrap().unwrap();
+        assert_eq!(payment.status, "succeeded");
+        let booking_status: String =
+            sqlx::query_scalar("SELECT status FROM bookings WHERE id = $1")
+                .bind(&payment.booking_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(booking_status, "confirmed");
+        let cash: i64 = sqlx::query_scalar(
+            "SELECT SUM(amount_minor)::BIGINT FROM ledger_entries WHERE transaction_id = $1 AND account = 'cash'",
+        )
+        .bind(format!("charge:{}", payment_id))
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(cash, 20_000);
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database in TEST_DATABASE_URL"]
+    async fn late_payment_is_refunded() {
+        let pool = test_pool().await;
+        let providers = fake_providers();
+        let payment_id = pending_payment(&pool, &providers, "expired").await;
+
+        deliver_webhook(&pool, &providers, &payment_id).await;
+
+        let (refund_id, amount, status): (String, i64, String) = sqlx::query_as(
+            "SELECT id, amount_minor, status FROM payment_refunds WHERE payment_id = $1",
+        )
+        .bind(&payment_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((amount, status.as_str()), (20_000, "pending"));
+
+        refunds::process_refund(
+            &pool,
+            &providers,
+            serde_json::json!({ "refund_id": refund_id }),
+        )
+        .await
+        .unwrap();
+        let status: String = sqlx::query_scalar("SELECT status FROM payment_refunds WHERE id = $1")
+            .bind(&refund_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, "succeeded");
+    }
+
+    #[actix_web::test]
+    #[ignore = "needs a migrated database in TEST_DATABASE_URL"]
+    async fn refunds_the_provider_never_confirms_need_manual_handling() {
+        let pool = test_pool().await;
+        let providers = fake_providers();
+        let payment_id = pending_payment(&pool, &providers, "expired").await;
+        deliver_webhook(&pool, &providers, &payment_id).await;
+
+        let refund_id: String =
+            sqlx::query_scalar("SELECT id FROM payment_refunds WHERE payment_id = $1")
+                .bind(&payment_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        refunds::give_up_refund(&pool, serde_json::json!({ "refund_id": refund_id }))
+            .await
+            .unwrap();
+
+        let status: String = sqlx::query_scalar("SELECT status FROM payment_refunds WHERE id = $1")
+            .bind(&refund_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(status, "manual_required");
+    }
+}
@@ -0,0 +1,99 @@
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::env;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Secret used to sign callback URLs (`PAYMENTS_WEBHOOK_SECRET`).
+fn webhook_secret() -> Option<String> {
+    env::var("PAYMENTS_WEBHOOK_SECRET")
+        .ok()
+        .filter(|s| !s.is_empty())
+}
+
+fn mac_for(secret: &str, provider: &str, payment_id: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(provider.as_bytes());
+    mac.update(b":");
+    mac.update(payment_id.as_bytes());
+    mac
+}
+
+/// Build the callback URL handed to the provider for `payment_id`.
+///
+/// Neither MTN nor Orange sign their callbacks, so we sign the URL itself: only the provider
+/// we gave it to knows a valid `signature` for this payment. `None` when payments are not
+/// configured (`PAYMENTS_CALLBACK_BASE_URL` / `PAYMENTS_WEBHOOK_SECRET` missing).
+pub fn callback_url(provider: &str, payment_id: &str) -> Option<String> {
+    let secret = webhook_secret()?;
+    let base = env::var("PAYMENTS_CALLBACK_BASE_URL").ok()?;
+    let signature = hex::encode(
+        mac_for(&secret, provider, payment_id)
+            .finalize()
+            .into_bytes(),
+    );
+    Some(format!(
+        "{}/api/payments/webhooks/{}?payment_id={}&signature={}",
+        base.trim_end_matches('/'),
+        provider,
+        payment_id,
+        signature
+    ))
+}
+
+/// Check a callback's signature in constant time.
+pub fn verify_signature(provider: &str, payment_id: &str, signature: &str) -> bool {
+    let (Some(secret), Ok(signature)) = (webhook_secret(), hex::decode(signature)) else {
+        return false;
+    };
+    mac_for(&secret, provider, payment_id)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+/// Record a webhook delivery and return its event key, or `None` if the identical delivery was
+/// already recorded, in which case it must not be processed again.
+pub async fn record_event(
+    pool: &PgPool,
+    provider: &str,
+    payment_id: &str,
+    body: &[u8],
+) -> Result<Option<String>, sqlx::Error> {
+    let mut hasher = Sha256::new();
+    hasher.update(payment_id.as_bytes());
+    hasher.update(body);
+    let event_key = hex::encode(hasher.finalize());
+    let payload = serde_json::from_slice::<serde_json::Value>(body).ok();
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO payment_webhook_events (provider, event_key, payment_id, payload)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (provider, event_key) DO NOTHING
+        "#,
+    )
+    .bind(provider)
+    .bind(&event_key)
+    .bind(payment_id)
+    .bind(payload)
+    .execute(pool)
+    .await?;
+
+    Ok((result.rows_affected() > 0).then_some(event_key))
+}
+
+/// Drop a recorded delivery that could not be processed, so the provider's retry is handled.
+pub async fn forget_event(
+    pool: &PgPool,
+    provider: &str,
+    event_key: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM payment_webhook_events WHERE provider = $1 AND event_key = $2")
+        .bind(provider)
+        .bind(event_key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+-- Cancellation policy enforcement: price snapshot, refund outcome and host penalties.
+-- Amounts are BIGINT minor units in the booking's currency.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='price_snapshot') THEN
+        ALTER TABLE bookings ADD COLUMN price_snapshot JSONB;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='cancelled_at') THEN
+        ALTER TABLE bookings ADD COLUMN cancelled_at TIMESTAMP;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='cancelled_by') THEN
+        ALTER TABLE bookings ADD COLUMN cancelled_by TEXT; -- guest, host
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='cancellation_policy') THEN
+        ALTER TABLE bookings ADD COLUMN cancellation_policy TEXT;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='refund_amount_minor') THEN
+        ALTER TABLE bookings ADD COLUMN refund_amount_minor BIGINT;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='cancellation_penalty_minor') THEN
+        ALTER TABLE bookings ADD COLUMN cancellation_penalty_minor BIGINT;
+    END IF;
+END $$;
+
+-- Penalties charged to hosts who cancel a guest's reservation
+CREATE TABLE IF NOT EXISTS host_cancellation_penalties (
+    id SERIAL PRIMARY KEY,
+    booking_id TEXT NOT NULL UNIQUE,
+    host_id INTEGER NOT NULL,
+    amount_minor BIGINT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    reason TEXT,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, settled, waived
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (booking_id) REFERENCES bookings(id) ON DELETE CASCADE,
+    FOREIGN KEY (host_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_host_cancellation_penalties_host_id ON host_cancellation_penalties(host_id);
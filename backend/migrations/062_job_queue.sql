@@ -0,0 +1,34 @@
+-- Postgres-backed background job queue
+CREATE TABLE IF NOT EXISTS jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}'::jsonb,
+    status TEXT NOT NULL DEFAULT 'queued', -- queued, running, succeeded, failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_at TIMESTAMP,
+    locked_by TEXT,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+-- Workers poll for the oldest due queued job
+CREATE INDEX IF NOT EXISTS idx_jobs_queued_run_at ON jobs(run_at) WHERE status = 'queued';
+CREATE INDEX IF NOT EXISTS idx_jobs_status_kind ON jobs(status, kind);
+-- Succeeded jobs are purged by age
+CREATE INDEX IF NOT EXISTS idx_jobs_succeeded_finished_at ON jobs(finished_at) WHERE status = 'succeeded';
+
+-- Recurring jobs: whichever replica claims a due row enqueues the next run
+CREATE TABLE IF NOT EXISTS job_schedules (
+    kind TEXT PRIMARY KEY,
+    interval_seconds INTEGER NOT NULL,
+    next_run_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_enqueued_at TIMESTAMP
+);
+
+-- Lifecycle jobs filter on these
+CREATE INDEX IF NOT EXISTS idx_bookings_status_created_at ON bookings(status, created_at);
+CREATE INDEX IF NOT EXISTS idx_sessions_expires_at ON sessions(expires_at);
@@ -0,0 +1,61 @@
+-- Mobile-money payments collected for bookings, their refunds and received webhooks.
+-- Amounts are BIGINT minor units of their currency.
+CREATE TABLE IF NOT EXISTS payments (
+    id TEXT PRIMARY KEY,
+    booking_id TEXT NOT NULL,
+    payer_id INTEGER NOT NULL,
+    provider TEXT NOT NULL, -- mtn_momo, orange_money, fake
+    provider_reference TEXT,
+    amount_minor BIGINT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    phone TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, succeeded, failed
+    failure_reason TEXT,
+    payment_url TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (booking_id) REFERENCES bookings(id) ON DELETE CASCADE,
+    FOREIGN KEY (payer_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_payments_booking_id ON payments(booking_id);
+CREATE INDEX IF NOT EXISTS idx_payments_status ON payments(status, created_at);
+-- At most one collection in flight, and a booking is paid at most once
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payments_booking_pending ON payments(booking_id) WHERE status = 'pending';
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payments_booking_succeeded ON payments(booking_id) WHERE status = 'succeeded';
+
+CREATE TABLE IF NOT EXISTS payment_refunds (
+    id TEXT PRIMARY KEY,
+    payment_id TEXT NOT NULL,
+    booking_id TEXT NOT NULL,
+    alteration_id TEXT, -- set for refunds of a price decrease after a change request
+    provider_reference TEXT,
+    amount_minor BIGINT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    reason TEXT,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, succeeded, failed, manual_required
+    failure_reason TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (payment_id) REFERENCES payments(id) ON DELETE CASCADE,
+    FOREIGN KEY (booking_id) REFERENCES bookings(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_refunds_status ON payment_refunds(status);
+-- At most one cancellation refund per booking and one refund per change request
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payment_refunds_booking_id ON payment_refunds(booking_id) WHERE alteration_id IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payment_refunds_alteration_id ON payment_refunds(alteration_id);
+
+-- Providers retry callbacks; each distinct delivery is processed once
+CREATE TABLE IF NOT EXISTS payment_webhook_events (
+    id BIGSERIAL PRIMARY KEY,
+    provider TEXT NOT NULL,
+    event_key TEXT NOT NULL,
+    payment_id TEXT,
+    payload JSONB,
+    received_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (provider, event_key)
+);
+
+-- Bookings awaiting payment are held for a limited time
+CREATE INDEX IF NOT EXISTS idx_bookings_awaiting_payment ON bookings(updated_at) WHERE status = 'awaiting_payment';
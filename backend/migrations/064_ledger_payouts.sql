@@ -0,0 +1,46 @@
+-- Double-entry ledger of booking money movements and host payout batches. Amounts are BIGINT
+-- minor units of their currency, so every transaction sums to exactly zero and batches total
+-- exactly the entries they contain.
+CREATE TABLE IF NOT EXISTS payout_batches (
+    id TEXT PRIMARY KEY,
+    host_id INTEGER NOT NULL,
+    amount_minor BIGINT NOT NULL DEFAULT 0,
+    tax_minor BIGINT NOT NULL DEFAULT 0, -- taxes included in the amount, for the host to remit
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    entry_count INTEGER NOT NULL DEFAULT 0,
+    payout_method TEXT,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, paid
+    reference TEXT,
+    paid_by INTEGER,
+    paid_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (host_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (paid_by) REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_payout_batches_host_id ON payout_batches(host_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_payout_batches_status ON payout_batches(status, created_at);
+
+-- Every transaction's entries sum to zero. Debits are positive, credits negative.
+-- Accounts: cash (guest funds held), platform_revenue (service fees), host_payable (owed to hosts)
+CREATE TABLE IF NOT EXISTS ledger_entries (
+    id BIGSERIAL PRIMARY KEY,
+    transaction_id TEXT NOT NULL, -- charge:<payment>, refund:<refund>, offline:<booking>[:<alteration>], offline_refund:<booking>, payout:<batch>
+    booking_id TEXT,
+    host_id INTEGER,
+    account TEXT NOT NULL,
+    entry_type TEXT NOT NULL, -- guest_charge, service_fee, host_earning, tax, refund, tax_refund, payout
+    amount_minor BIGINT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    available_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    payout_batch_id TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (transaction_id, account, entry_type),
+    FOREIGN KEY (booking_id) REFERENCES bookings(id) ON DELETE SET NULL,
+    FOREIGN KEY (host_id) REFERENCES users(id) ON DELETE SET NULL,
+    FOREIGN KEY (payout_batch_id) REFERENCES payout_batches(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_booking_id ON ledger_entries(booking_id);
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_host_payable ON ledger_entries(host_id, currency, available_at) WHERE account = 'host_payable';
+CREATE INDEX IF NOT EXISTS idx_ledger_entries_payout_batch_id ON ledger_entries(payout_batch_id);
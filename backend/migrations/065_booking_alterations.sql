@@ -0,0 +1,38 @@
+-- Typed messages, so the platform can post system notices into conversations
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='messages' AND column_name='message_type') THEN
+        ALTER TABLE messages ADD COLUMN message_type TEXT NOT NULL DEFAULT 'text'; -- text, system
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='messages' AND column_name='metadata') THEN
+        ALTER TABLE messages ADD COLUMN metadata JSONB;
+    END IF;
+END $$;
+
+-- Guest requests to change the dates or guest count of an existing booking. Totals are BIGINT
+-- minor units in the booking's currency.
+CREATE TABLE IF NOT EXISTS booking_alterations (
+    id TEXT PRIMARY KEY,
+    booking_id TEXT NOT NULL,
+    requested_by INTEGER NOT NULL,
+    old_check_in DATE NOT NULL,
+    old_check_out DATE NOT NULL,
+    old_guests INTEGER NOT NULL,
+    old_total_price_minor BIGINT NOT NULL,
+    new_check_in DATE NOT NULL,
+    new_check_out DATE NOT NULL,
+    new_guests INTEGER NOT NULL,
+    new_total_price_minor BIGINT NOT NULL,
+    price_difference_minor BIGINT NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    price_snapshot JSONB,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, accepted, declined, withdrawn
+    responded_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (booking_id) REFERENCES bookings(id) ON DELETE CASCADE,
+    FOREIGN KEY (requested_by) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_alterations_booking_id ON booking_alterations(booking_id, created_at);
+-- One open request per booking
+CREATE UNIQUE INDEX IF NOT EXISTS idx_booking_alterations_pending ON booking_alterations(booking_id) WHERE status = 'pending';
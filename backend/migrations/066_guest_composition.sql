@@ -0,0 +1,46 @@
+-- Guest composition on bookings and guest/pet settings on listings
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='adults') THEN
+        ALTER TABLE bookings ADD COLUMN adults INTEGER;
+        UPDATE bookings SET adults = guests;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='children') THEN
+        ALTER TABLE bookings ADD COLUMN children INTEGER NOT NULL DEFAULT 0;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='infants') THEN
+        ALTER TABLE bookings ADD COLUMN infants INTEGER NOT NULL DEFAULT 0;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='pets') THEN
+        ALTER TABLE bookings ADD COLUMN pets INTEGER NOT NULL DEFAULT 0;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='listings' AND column_name='pets_allowed') THEN
+        ALTER TABLE listings ADD COLUMN pets_allowed BOOLEAN NOT NULL DEFAULT FALSE;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='listings' AND column_name='max_pets') THEN
+        ALTER TABLE listings ADD COLUMN max_pets INTEGER;
+    END IF;
+    -- Guests covered by the nightly price; each additional adult or child pays extra_guest_fee per night
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='listings' AND column_name='guests_included') THEN
+        ALTER TABLE listings ADD COLUMN guests_included INTEGER;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='listings' AND column_name='extra_guest_fee_minor') THEN
+        ALTER TABLE listings ADD COLUMN extra_guest_fee_minor BIGINT NOT NULL DEFAULT 0; -- in the listing's currency
+    END IF;
+
+    -- Requested composition on change requests (NULL for requests made before this migration)
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='booking_alterations' AND column_name='new_adults') THEN
+        ALTER TABLE booking_alterations ADD COLUMN new_adults INTEGER;
+        ALTER TABLE booking_alterations ADD COLUMN new_children INTEGER;
+        ALTER TABLE booking_alterations ADD COLUMN new_infants INTEGER;
+        ALTER TABLE booking_alterations ADD COLUMN new_pets INTEGER;
+    END IF;
+    -- Composition the request was quoted against
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='booking_alterations' AND column_name='old_adults') THEN
+        ALTER TABLE booking_alterations ADD COLUMN old_adults INTEGER;
+        ALTER TABLE booking_alterations ADD COLUMN old_children INTEGER;
+        ALTER TABLE booking_alterations ADD COLUMN old_infants INTEGER;
+        ALTER TABLE booking_alterations ADD COLUMN old_pets INTEGER;
+    END IF;
+END $$;
@@ -0,0 +1,24 @@
+-- Human-readable booking references and per-host invoice numbering
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='reference') THEN
+        ALTER TABLE bookings ADD COLUMN reference TEXT;
+    END IF;
+END $$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_bookings_reference ON bookings(reference);
+
+-- Last invoice number issued by each host
+CREATE TABLE IF NOT EXISTS host_invoice_counters (
+    host_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    last_number INTEGER NOT NULL DEFAULT 0
+);
+
+-- An invoice number is assigned once per booking and never reused
+CREATE TABLE IF NOT EXISTS booking_invoices (
+    booking_id TEXT PRIMARY KEY REFERENCES bookings(id) ON DELETE CASCADE,
+    host_id INTEGER NOT NULL,
+    invoice_number INTEGER NOT NULL,
+    issued_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, -- printed as the invoice date
+    UNIQUE(host_id, invoice_number)
+);
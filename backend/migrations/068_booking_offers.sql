@@ -0,0 +1,27 @@
+-- Special offers and pre-approvals sent by hosts inside a conversation
+CREATE TABLE IF NOT EXISTS booking_offers (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    listing_id TEXT NOT NULL REFERENCES listings(id) ON DELETE CASCADE,
+    guest_id INTEGER NOT NULL,
+    host_id INTEGER NOT NULL,
+    offer_type TEXT NOT NULL, -- special_offer, pre_approval
+    check_in DATE NOT NULL,
+    check_out DATE NOT NULL,
+    adults INTEGER NOT NULL,
+    children INTEGER NOT NULL DEFAULT 0,
+    infants INTEGER NOT NULL DEFAULT 0,
+    pets INTEGER NOT NULL DEFAULT 0,
+    -- Host-set total for special offers, in minor units; NULL for pre-approvals, which book at
+    -- the standard price
+    total_price_minor BIGINT,
+    currency TEXT NOT NULL DEFAULT 'XAF',
+    status TEXT NOT NULL DEFAULT 'active', -- active, booked, withdrawn
+    expires_at TIMESTAMP NOT NULL,
+    message_id TEXT,
+    booking_id TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_offers_conversation ON booking_offers(conversation_id, status);
@@ -0,0 +1,60 @@
+-- Platform promo codes. Fixed amounts and limits are BIGINT minor units of the code's currency,
+-- and such codes only apply to bookings in that currency.
+CREATE TABLE IF NOT EXISTS promo_codes (
+    id TEXT PRIMARY KEY,
+    code TEXT NOT NULL UNIQUE, -- stored upper-case
+    description TEXT,
+    discount_type TEXT NOT NULL, -- percentage, fixed
+    discount_percent DOUBLE PRECISION, -- percentage discounts
+    discount_amount_minor BIGINT, -- fixed discounts
+    currency TEXT, -- required for fixed discounts and limits
+    max_discount_minor BIGINT, -- cap for percentage discounts
+    min_total_minor BIGINT,
+    starts_at TIMESTAMP,
+    ends_at TIMESTAMP,
+    max_uses INTEGER,
+    max_uses_per_user INTEGER DEFAULT 1,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INTEGER,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT promo_codes_discount_check CHECK (
+        (discount_type = 'percentage' AND discount_percent IS NOT NULL)
+        OR (discount_type = 'fixed' AND discount_amount_minor IS NOT NULL)
+    ),
+    CONSTRAINT promo_codes_currency_check CHECK (
+        currency IS NOT NULL
+        OR (discount_amount_minor IS NULL AND max_discount_minor IS NULL AND min_total_minor IS NULL)
+    )
+);
+
+-- One row per booking that used a code; usage limits ignore declined, expired and cancelled bookings
+CREATE TABLE IF NOT EXISTS promo_code_redemptions (
+    id SERIAL PRIMARY KEY,
+    promo_code_id TEXT NOT NULL REFERENCES promo_codes(id) ON DELETE CASCADE,
+    booking_id TEXT NOT NULL UNIQUE REFERENCES bookings(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL,
+    amount_minor BIGINT NOT NULL,
+    currency TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_promo_code_redemptions_code ON promo_code_redemptions(promo_code_id, user_id);
+
+-- Host discount rules on a listing
+CREATE TABLE IF NOT EXISTS host_promotions (
+    id TEXT PRIMARY KEY,
+    listing_id TEXT NOT NULL REFERENCES listings(id) ON DELETE CASCADE,
+    host_id INTEGER NOT NULL,
+    rule_type TEXT NOT NULL, -- last_minute, early_bird, first_bookings
+    discount_percent DOUBLE PRECISION NOT NULL,
+    days_before INTEGER, -- last_minute: check-in within N days; early_bird: at least N days ahead
+    max_bookings INTEGER, -- first_bookings: the listing's first N bookings
+    starts_on DATE,
+    ends_on DATE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_host_promotions_listing ON host_promotions(listing_id) WHERE active;
@@ -0,0 +1,15 @@
+-- Stored responses for requests sent with an Idempotency-Key header, replayed for 24 hours
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    idempotency_key TEXT NOT NULL,
+    request_fingerprint TEXT NOT NULL, -- sha256 of method, path and body
+    response_status INTEGER, -- NULL while the first request is still running
+    response_content_type TEXT,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    committed_at TIMESTAMP, -- set in the handler's transaction once its changes are saved
+    completed_at TIMESTAMP,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created_at ON idempotency_keys(created_at);
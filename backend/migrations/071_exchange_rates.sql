@@ -0,0 +1,14 @@
+-- Exchange rates against the base currency (XAF), used to show prices in the guest's currency.
+-- Bookings still settle in the listing's currency; the rate shown is kept in the price snapshot.
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    currency TEXT PRIMARY KEY,
+    rate_to_base DOUBLE PRECISION NOT NULL, -- value of one unit in XAF
+    rate_date DATE NOT NULL,
+    source TEXT, -- admin, import, peg
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The CFA franc is pegged to the euro
+INSERT INTO exchange_rates (currency, rate_to_base, rate_date, source)
+VALUES ('EUR', 655.957, CURRENT_DATE, 'peg')
+ON CONFLICT (currency) DO NOTHING;
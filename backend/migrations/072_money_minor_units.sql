@@ -0,0 +1,119 @@
+-- Store prices as BIGINT minor units (cents; whole francs for XAF) in the listing's currency
+-- instead of DOUBLE PRECISION. The application writes the *_minor columns through
+-- kamer_core::Money.
+
+-- Decimal places of a currency's minor unit. Keep in sync with kamer_core::money.
+CREATE OR REPLACE FUNCTION currency_exponent(code TEXT) RETURNS INTEGER AS $$
+    SELECT CASE
+        WHEN UPPER(code) IN ('BIF', 'CLP', 'DJF', 'GNF', 'ISK', 'JPY', 'KMF', 'KRW', 'PYG', 'RWF',
+                             'UGX', 'VND', 'VUV', 'XAF', 'XOF', 'XPF') THEN 0
+        WHEN UPPER(code) IN ('BHD', 'IQD', 'JOD', 'KWD', 'LYD', 'OMR', 'TND') THEN 3
+        ELSE 2
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- A kamer_core::Money as a single value: select with to_money(amount_minor, currency)
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'money_amount') THEN
+        CREATE TYPE money_amount AS (minor BIGINT, currency TEXT);
+    END IF;
+END $$;
+
+CREATE OR REPLACE FUNCTION to_money(minor BIGINT, currency TEXT) RETURNS money_amount AS $$
+    SELECT ROW(minor, currency)::money_amount
+$$ LANGUAGE SQL IMMUTABLE STRICT;
+
+-- Listings: the old columns stay as read-only major-unit mirrors for searches and JSON
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='listings' AND column_name='price_per_night_minor') THEN
+        ALTER TABLE listings
+            ADD COLUMN price_per_night_minor BIGINT,
+            ADD COLUMN cleaning_fee_minor BIGINT;
+
+        UPDATE listings SET
+            price_per_night_minor = ROUND(price_per_night::NUMERIC * power(10::NUMERIC, currency_exponent(COALESCE(currency, 'XAF')))),
+            cleaning_fee_minor = ROUND(cleaning_fee::NUMERIC * power(10::NUMERIC, currency_exponent(COALESCE(currency, 'XAF'))));
+
+        ALTER TABLE listings
+            DROP COLUMN price_per_night,
+            DROP COLUMN cleaning_fee;
+
+        ALTER TABLE listings
+            ADD COLUMN price_per_night DOUBLE PRECISION GENERATED ALWAYS AS
+                (price_per_night_minor / power(10, currency_exponent(COALESCE(currency, 'XAF')))) STORED,
+            ADD COLUMN cleaning_fee DOUBLE PRECISION GENERATED ALWAYS AS
+                (cleaning_fee_minor / power(10, currency_exponent(COALESCE(currency, 'XAF')))) STORED,
+            ADD COLUMN extra_guest_fee DOUBLE PRECISION GENERATED ALWAYS AS
+                (extra_guest_fee_minor / power(10, currency_exponent(COALESCE(currency, 'XAF')))) STORED;
+    END IF;
+END $$;
+
+-- Dropped along with the old column
+CREATE INDEX IF NOT EXISTS idx_listings_price_per_night ON listings(price_per_night);
+CREATE INDEX IF NOT EXISTS idx_listings_published_price
+  ON listings(price_per_night)
+  WHERE status = 'published' AND price_per_night IS NOT NULL;
+
+-- Bookings: settle in a fixed currency, recorded on the booking itself
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='total_price_minor') THEN
+        ALTER TABLE bookings
+            ADD COLUMN currency TEXT,
+            ADD COLUMN total_price_minor BIGINT;
+
+        UPDATE bookings b SET currency = COALESCE(b.price_snapshot->>'currency', l.currency, 'XAF')
+        FROM listings l
+        WHERE l.id = b.listing_id;
+
+        UPDATE bookings SET currency = 'XAF' WHERE currency IS NULL;
+
+        UPDATE bookings
+        SET total_price_minor = ROUND(total_price::NUMERIC * power(10::NUMERIC, currency_exponent(currency)));
+
+        ALTER TABLE bookings
+            ALTER COLUMN currency SET DEFAULT 'XAF',
+            ALTER COLUMN currency SET NOT NULL,
+            ALTER COLUMN total_price_minor SET NOT NULL,
+            DROP COLUMN total_price;
+
+        ALTER TABLE bookings
+            ADD COLUMN total_price DOUBLE PRECISION GENERATED ALWAYS AS
+                (total_price_minor / power(10, currency_exponent(currency))) STORED;
+    END IF;
+END $$;
+
+-- Calendar prices are in the listing's currency; reads convert with currency_exponent()
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='calendar_pricing' AND column_name='price_minor') THEN
+        ALTER TABLE calendar_pricing ADD COLUMN price_minor BIGINT;
+
+        UPDATE calendar_pricing cp
+        SET price_minor = ROUND(cp.price::NUMERIC * power(10::NUMERIC, currency_exponent(COALESCE(l.currency, 'XAF'))))
+        FROM listings l
+        WHERE l.id = cp.listing_id;
+
+        ALTER TABLE calendar_pricing
+            ALTER COLUMN price_minor SET NOT NULL,
+            DROP COLUMN price;
+    END IF;
+
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='listing_settings' AND column_name='base_price_minor') THEN
+        ALTER TABLE listing_settings
+            ADD COLUMN base_price_minor BIGINT,
+            ADD COLUMN weekend_price_minor BIGINT;
+
+        UPDATE listing_settings s SET
+            base_price_minor = ROUND(s.base_price::NUMERIC * power(10::NUMERIC, currency_exponent(COALESCE(l.currency, 'XAF')))),
+            weekend_price_minor = ROUND(s.weekend_price::NUMERIC * power(10::NUMERIC, currency_exponent(COALESCE(l.currency, 'XAF'))))
+        FROM listings l
+        WHERE l.id = s.listing_id;
+
+        ALTER TABLE listing_settings
+            DROP COLUMN base_price,
+            DROP COLUMN weekend_price;
+    END IF;
+END $$;
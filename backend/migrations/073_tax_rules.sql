@@ -0,0 +1,20 @@
+-- Tourist taxes and VAT, matched to listings by country and (optionally) city
+CREATE TABLE IF NOT EXISTS tax_rules (
+    id TEXT PRIMARY KEY,
+    country TEXT NOT NULL,
+    city TEXT, -- NULL applies to the whole country
+    name TEXT NOT NULL, -- shown on quotes and receipts, e.g. "Taxe de séjour"
+    tax_type TEXT NOT NULL, -- per_night, per_guest_night, percentage
+    rate DOUBLE PRECISION, -- percentage taxes, charged on the stay price after discounts
+    amount_minor BIGINT, -- per_night and per_guest_night taxes, in minor units of currency
+    currency TEXT,
+    vat_registered_only BOOLEAN NOT NULL DEFAULT FALSE, -- only hosts with a tax ID charge it
+    effective_from DATE NOT NULL, -- compared with the booking's check-in date
+    effective_to DATE, -- inclusive; NULL while still in force
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by INTEGER,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_tax_rules_location ON tax_rules(LOWER(country), LOWER(city)) WHERE active;
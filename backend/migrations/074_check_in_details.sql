@@ -0,0 +1,18 @@
+-- Private arrival details, shared with confirmed guests shortly before check-in
+CREATE TABLE IF NOT EXISTS listing_check_in_details (
+    listing_id TEXT PRIMARY KEY REFERENCES listings(id) ON DELETE CASCADE,
+    check_in_method TEXT NOT NULL, -- keypad, lockbox, smart_lock, building_staff, host_greets, other
+    instructions TEXT, -- door codes, directions, parking
+    wifi_network TEXT,
+    wifi_password TEXT,
+    exact_address TEXT, -- listings.address stays the public, approximate location
+    release_days_before INTEGER NOT NULL DEFAULT 2, -- days before check-in the guest can see them
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Set once the details have been posted into the booking's conversation
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS check_in_details_sent_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_bookings_check_in_details_pending
+    ON bookings(check_in) WHERE status = 'confirmed' AND check_in_details_sent_at IS NULL;
@@ -0,0 +1,7 @@
+-- Secret tokens for per-listing iCalendar export feeds (channel sync with other platforms)
+CREATE TABLE IF NOT EXISTS calendar_export_feeds (
+    listing_id TEXT PRIMARY KEY REFERENCES listings(id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    rotated_at TIMESTAMP -- last time the host regenerated the token
+);
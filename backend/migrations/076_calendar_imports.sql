@@ -0,0 +1,33 @@
+-- iCalendar feeds from other platforms (Airbnb, Booking.com, ...) that block a listing's dates
+CREATE TABLE IF NOT EXISTS calendar_import_feeds (
+    id TEXT PRIMARY KEY,
+    listing_id TEXT NOT NULL REFERENCES listings(id) ON DELETE CASCADE,
+    name TEXT, -- host's label, e.g. "Airbnb"
+    url TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending', -- pending, ok, error
+    last_error TEXT,
+    event_count INTEGER NOT NULL DEFAULT 0,
+    last_synced_at TIMESTAMP, -- last attempt, successful or not
+    last_success_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (listing_id, url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendar_import_feeds_last_synced ON calendar_import_feeds(last_synced_at NULLS FIRST);
+
+-- Dates busy on another platform. Kept apart from calendar_pricing so a sync never overwrites
+-- the host's own prices and blocks; each sync replaces all of a feed's rows.
+CREATE TABLE IF NOT EXISTS external_blocks (
+    id SERIAL PRIMARY KEY,
+    feed_id TEXT NOT NULL REFERENCES calendar_import_feeds(id) ON DELETE CASCADE,
+    listing_id TEXT NOT NULL REFERENCES listings(id) ON DELETE CASCADE,
+    uid TEXT NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL, -- exclusive, like bookings.check_out
+    summary TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_external_blocks_listing_dates ON external_blocks(listing_id, start_date, end_date);
+CREATE INDEX IF NOT EXISTS idx_external_blocks_feed ON external_blocks(feed_id);
@@ -0,0 +1,31 @@
+-- Seasonal, weekday and length-of-stay pricing rules
+CREATE TABLE IF NOT EXISTS pricing_rules (
+    id TEXT PRIMARY KEY,
+    listing_id TEXT NOT NULL REFERENCES listings(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    rule_type TEXT NOT NULL, -- fixed_price, adjustment
+    price_minor BIGINT, -- fixed_price, in minor units of the listing's currency
+    adjustment_percent DOUBLE PRECISION, -- adjustment, e.g. -10 or 25
+    start_date DATE, -- NULL with end_date for rules that apply all year
+    end_date DATE, -- inclusive
+    recurs_yearly BOOLEAN NOT NULL DEFAULT FALSE, -- only month and day of the range are used
+    weekdays INTEGER[] NOT NULL DEFAULT '{}', -- ISO weekdays of the night; empty for every day
+    min_nights INTEGER, -- length of stay the rule needs
+    priority INTEGER NOT NULL DEFAULT 0, -- higher wins; ties go to the older rule
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_pricing_rules_listing ON pricing_rules(listing_id) WHERE active;
+
+-- Days whose price the host set, as opposed to rows only stored to block or unblock the day
+ALTER TABLE calendar_pricing ADD COLUMN IF NOT EXISTS custom_price BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE calendar_pricing cp
+SET custom_price = TRUE
+FROM listings l
+LEFT JOIN listing_settings s ON s.listing_id = l.id
+WHERE cp.listing_id = l.id
+  AND NOT cp.custom_price
+  AND cp.price_minor <> COALESCE(s.base_price_minor, l.price_per_night_minor, 0);
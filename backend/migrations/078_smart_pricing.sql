@@ -0,0 +1,6 @@
+-- Bounds smart pricing keeps its suggestions within, in minor units of the listing's currency
+ALTER TABLE listing_settings ADD COLUMN IF NOT EXISTS smart_min_price_minor BIGINT;
+ALTER TABLE listing_settings ADD COLUMN IF NOT EXISTS smart_max_price_minor BIGINT;
+
+-- Smart pricing counts how many listings in the city are booked each night
+CREATE INDEX IF NOT EXISTS idx_listings_published_city ON listings(LOWER(city)) WHERE status = 'published';
@@ -0,0 +1,52 @@
+-- Bumped whenever anything that decides a listing's public availability or nightly prices
+-- changes, so the availability calendar can answer conditional requests without recomputing.
+CREATE TABLE IF NOT EXISTS listing_calendar_versions (
+    listing_id TEXT PRIMARY KEY REFERENCES listings(id) ON DELETE CASCADE,
+    version BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE OR REPLACE FUNCTION bump_listing_calendar_version() RETURNS TRIGGER AS $$
+DECLARE
+    changed_listing TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed_listing := OLD.listing_id;
+    ELSE
+        changed_listing := NEW.listing_id;
+    END IF;
+
+    INSERT INTO listing_calendar_versions (listing_id, version)
+    SELECT l.id, 1 FROM listings l WHERE l.id = changed_listing
+    ON CONFLICT (listing_id) DO UPDATE SET
+        version = listing_calendar_versions.version + 1,
+        updated_at = CURRENT_TIMESTAMP;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS bookings_calendar_version ON bookings;
+CREATE TRIGGER bookings_calendar_version
+    AFTER INSERT OR DELETE OR UPDATE OF status, check_in, check_out ON bookings
+    FOR EACH ROW EXECUTE FUNCTION bump_listing_calendar_version();
+
+DROP TRIGGER IF EXISTS calendar_pricing_calendar_version ON calendar_pricing;
+CREATE TRIGGER calendar_pricing_calendar_version
+    AFTER INSERT OR UPDATE OR DELETE ON calendar_pricing
+    FOR EACH ROW EXECUTE FUNCTION bump_listing_calendar_version();
+
+DROP TRIGGER IF EXISTS external_blocks_calendar_version ON external_blocks;
+CREATE TRIGGER external_blocks_calendar_version
+    AFTER INSERT OR UPDATE OR DELETE ON external_blocks
+    FOR EACH ROW EXECUTE FUNCTION bump_listing_calendar_version();
+
+DROP TRIGGER IF EXISTS pricing_rules_calendar_version ON pricing_rules;
+CREATE TRIGGER pricing_rules_calendar_version
+    AFTER INSERT OR UPDATE OR DELETE ON pricing_rules
+    FOR EACH ROW EXECUTE FUNCTION bump_listing_calendar_version();
+
+DROP TRIGGER IF EXISTS listing_settings_calendar_version ON listing_settings;
+CREATE TRIGGER listing_settings_calendar_version
+    AFTER INSERT OR UPDATE OR DELETE ON listing_settings
+    FOR EACH ROW EXECUTE FUNCTION bump_listing_calendar_version();
@@ -0,0 +1,8 @@
+-- Per-day stay restrictions set by the host
+ALTER TABLE calendar_pricing ADD COLUMN IF NOT EXISTS closed_to_arrival BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE calendar_pricing ADD COLUMN IF NOT EXISTS closed_to_departure BOOLEAN NOT NULL DEFAULT FALSE;
+-- Minimum stay for any stay that includes the night, instead of the listing's usual minimum
+ALTER TABLE calendar_pricing ADD COLUMN IF NOT EXISTS min_nights_override INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_calendar_pricing_restrictions ON calendar_pricing(listing_id, date)
+    WHERE closed_to_arrival OR closed_to_departure OR min_nights_override IS NOT NULL;
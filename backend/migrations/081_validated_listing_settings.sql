@@ -0,0 +1,74 @@
+-- Listing settings used to accept any text for advance notice, cutoff time and preparation
+-- time, and any number for discounts. Normalize what was stored to the values the API now
+-- accepts, then keep it that way.
+
+-- Advance notice: 'same_day', '1_day' or '2_days' to '7_days'. Longer notice is capped at a week.
+UPDATE listing_settings
+SET advance_notice = CASE
+    WHEN n IS NULL OR n = 0 THEN 'same_day'
+    WHEN n = 1 THEN '1_day'
+    ELSE LEAST(n, 7) || '_days'
+END
+FROM (
+    SELECT id AS settings_id,
+        CASE
+            WHEN LOWER(TRIM(advance_notice)) ~ '^same[ _-]?day$' THEN 0
+            ELSE NULLIF(SUBSTRING(LOWER(TRIM(advance_notice)) FROM '^(\d{1,3})(?:[ _-]?days?)?$'), '')::INT
+        END AS n
+    FROM listing_settings
+) parsed
+WHERE parsed.settings_id = listing_settings.id;
+
+-- Preparation time: 'none', '1_night' or '2_nights'. Longer is capped at two nights.
+UPDATE listing_settings
+SET preparation_time = CASE
+    WHEN n IS NULL OR n = 0 THEN 'none'
+    WHEN n = 1 THEN '1_night'
+    ELSE '2_nights'
+END
+FROM (
+    SELECT id AS settings_id,
+        NULLIF(SUBSTRING(LOWER(TRIM(preparation_time)) FROM '^(\d{1,3})(?:[ _-]?nights?)?$'), '')::INT AS n
+    FROM listing_settings
+) parsed
+WHERE parsed.settings_id = listing_settings.id;
+
+-- Same-day cutoff time: 24-hour 'HH:MM'. Anything unreadable goes back to noon.
+UPDATE listing_settings
+SET same_day_cutoff_time = CASE
+    WHEN TRIM(same_day_cutoff_time) ~ '^([01]?[0-9]|2[0-3]):[0-5][0-9]$'
+        THEN LPAD(TRIM(same_day_cutoff_time), 5, '0')
+    ELSE '12:00'
+END;
+
+-- Discounts are percentages
+UPDATE listing_settings
+SET weekly_discount = LEAST(GREATEST(COALESCE(weekly_discount, 0), 0), 100),
+    monthly_discount = LEAST(GREATEST(COALESCE(monthly_discount, 0), 0), 100);
+
+ALTER TABLE listing_settings
+    ALTER COLUMN advance_notice SET NOT NULL,
+    ALTER COLUMN same_day_cutoff_time SET NOT NULL,
+    ALTER COLUMN preparation_time SET NOT NULL,
+    ALTER COLUMN weekly_discount SET NOT NULL,
+    ALTER COLUMN monthly_discount SET NOT NULL;
+
+ALTER TABLE listing_settings DROP CONSTRAINT IF EXISTS listing_settings_advance_notice_check;
+ALTER TABLE listing_settings
+ADD CONSTRAINT listing_settings_advance_notice_check
+CHECK (advance_notice IN ('same_day', '1_day', '2_days', '3_days', '4_days', '5_days', '6_days', '7_days'));
+
+ALTER TABLE listing_settings DROP CONSTRAINT IF EXISTS listing_settings_preparation_time_check;
+ALTER TABLE listing_settings
+ADD CONSTRAINT listing_settings_preparation_time_check
+CHECK (preparation_time IN ('none', '1_night', '2_nights'));
+
+ALTER TABLE listing_settings DROP CONSTRAINT IF EXISTS listing_settings_cutoff_time_check;
+ALTER TABLE listing_settings
+ADD CONSTRAINT listing_settings_cutoff_time_check
+CHECK (same_day_cutoff_time ~ '^([01][0-9]|2[0-3]):[0-5][0-9]$');
+
+ALTER TABLE listing_settings DROP CONSTRAINT IF EXISTS listing_settings_discounts_check;
+ALTER TABLE listing_settings
+ADD CONSTRAINT listing_settings_discounts_check
+CHECK (weekly_discount BETWEEN 0 AND 100 AND monthly_discount BETWEEN 0 AND 100);
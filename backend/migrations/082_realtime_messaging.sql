@@ -0,0 +1,36 @@
+-- Open real-time messaging connections, for presence across replicas. A connection is live while
+-- its replica keeps touching last_seen_at; rows left behind by a crashed replica go stale.
+CREATE TABLE IF NOT EXISTS realtime_connections (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    connected_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_realtime_connections_user ON realtime_connections(user_id, last_seen_at);
+
+-- Announce every new message on the messaging channel. The payload only names the message and
+-- its conversation's participants; each replica loads the message itself, which keeps the
+-- payload under NOTIFY's size limit whatever the message holds.
+CREATE OR REPLACE FUNCTION notify_new_message() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify(
+        'messaging_events',
+        json_build_object(
+            'kind', 'message',
+            'message_id', NEW.id,
+            'recipients', (
+                SELECT json_build_array(c.guest_id, c.host_id)
+                FROM conversations c
+                WHERE c.id = NEW.conversation_id
+            )
+        )::TEXT
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS messages_notify_new ON messages;
+CREATE TRIGGER messages_notify_new
+    AFTER INSERT ON messages
+    FOR EACH ROW EXECUTE FUNCTION notify_new_message();
@@ -0,0 +1,9 @@
+-- When the request holding an idempotency key started running. A key whose request died
+-- without storing a response can be taken over once its lock is old enough.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='idempotency_keys' AND column_name='locked_at') THEN
+        ALTER TABLE idempotency_keys ADD COLUMN locked_at TIMESTAMP;
+        UPDATE idempotency_keys SET locked_at = created_at WHERE response_status IS NULL;
+    END IF;
+END $$;
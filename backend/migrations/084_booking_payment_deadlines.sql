@@ -0,0 +1,17 @@
+-- When a booking awaiting payment releases its dates. Holds used to run from updated_at, which
+-- every later change to the booking pushed back.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name='bookings' AND column_name='payment_due_at') THEN
+        ALTER TABLE bookings ADD COLUMN payment_due_at TIMESTAMP;
+        UPDATE bookings SET payment_due_at = updated_at + INTERVAL '60 minutes'
+        WHERE status = 'awaiting_payment';
+    END IF;
+END $$;
+
+-- Reminders posted before a request expires unanswered or an unpaid booking is released.
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS request_reminder_sent_at TIMESTAMP;
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS payment_reminder_sent_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_bookings_payment_due
+    ON bookings(payment_due_at) WHERE status = 'awaiting_payment';
@@ -4,11 +4,16 @@ use actix_web::{
     web, App, HttpServer,
 };
 use dotenv::dotenv;
+use kamer_calendar::imports::{CalendarFetcher, HttpCalendarFetcher};
+use kamer_jobs::{JobRegistry, JobRunner, RunnerConfig};
 use kamer_listings::ListingWithDetails;
+use kamer_messages::MessagingHub;
+use kamer_payments::PaymentProviders;
 use moka::future::Cache;
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use std::env;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 #[actix_web::main]
@@ -74,7 +79,7 @@ async fn main() -> std::io::Result<()> {
         .max_connections(max_conns)
         .min_connections(0)
         .acquire_timeout(Duration::from_millis(acquire_timeout_ms))
-        .connect_lazy_with(connection_options);
+        .connect_lazy_with(connection_options.clone());
 
     // Optionally run migrations on startup when explicitly enabled.
     // Default is to skip to avoid blocking startup; set MIGRATE_ON_START=true to enable.
@@ -106,6 +111,42 @@ async fn main() -> std::io::Result<()> {
         }
     }
 
+    // Mobile-money providers with credentials configured in the environment
+    let payment_providers = PaymentProviders::from_env();
+    if !fast_start {
+        println!("Payment providers enabled: {:?}", payment_providers.names());
+    }
+
+    // Background jobs for the booking lifecycle. Jobs are claimed with SKIP LOCKED, so every
+    // replica can run a runner; set JOBS_ENABLED=false to keep a replica HTTP-only.
+    if env::var("JOBS_ENABLED").unwrap_or_else(|_| "true".into()) != "false" {
+        JobRunner::new(
+            pool.clone(),
+            job_registry(payment_providers.clone()),
+            RunnerConfig::from_env(),
+        )
+        .spawn();
+        if !fast_start {
+            println!("Background job runner started.");
+        }
+    }
+
+    // Real-time messaging reaches every replica through Postgres LISTEN/NOTIFY. The listener keeps
+    // its connection for good, so it gets a pool of its own; LISTEN needs a session, so point
+    // REALTIME_DATABASE_URL at a direct connection if DATABASE_URL goes through a transaction pooler.
+    let listener_options = match env::var("REALTIME_DATABASE_URL") {
+        Ok(url) => PgConnectOptions::from_str(&url)
+            .expect("Malformed REALTIME_DATABASE_URL")
+            .statement_cache_capacity(0),
+        Err(_) => connection_options,
+    };
+    let listener_pool = PgPoolOptions::new()
+        .max_connections(1)
+        .min_connections(0)
+        .connect_lazy_with(listener_options);
+    let messaging_hub = MessagingHub::new();
+    messaging_hub.spawn(pool.clone(), listener_pool);
+
     // Initialize S3 storage
     let s3_storage = match kamer_storage::S3Storage::new() {
         Ok(storage) => storage,
@@ -151,6 +192,8 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(s3_storage.clone()))
             .app_data(web::Data::new(listing_cache.clone()))
             .app_data(web::Data::new(single_listing_cache.clone()))
+            .app_data(web::Data::new(payment_providers.clone()))
+            .app_data(web::Data::new(messaging_hub.clone()))
             .service(
                 web::scope("/api")
                     .wrap(DefaultHeaders::new().add(("X-Robots-Tag", "noindex, nofollow")))
@@ -169,3 +212,161 @@ async fn main() -> std::io::Result<()> {
 
     server.workers(workers).bind("0.0.0.0:8082")?.run().await
 }
+
+/// Register background job handlers and their schedules.
+fn job_registry(payment_providers: PaymentProviders) -> JobRegistry {
+    // Pending requests not answered within this many hours are expired
+    let pending_expiry_hours: i64 = env::var("PENDING_BOOKING_EXPIRY_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(24);
+
+    // Succeeded jobs are deleted after this many days
+    let job_retention_days: i32 = env::var("JOB_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(7);
+
+    let calendar_fetcher: Arc<dyn CalendarFetcher> = Arc::new(HttpCalendarFetcher::new());
+
+    JobRegistry::new()
+        .register(
+            kamer_bookings::jobs::EXPIRE_PENDING_BOOKINGS,
+            move |pool, _| async move {
+                kamer_bookings::jobs::expire_pending_bookings(&pool, pending_expiry_hours)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_bookings::jobs::EXPIRE_PENDING_BOOKINGS,
+            Duration::from_secs(15 * 60),
+        )
+        .register(
+            kamer_bookings::jobs::EXPIRE_UNPAID_BOOKINGS,
+            |pool, _| async move {
+                kamer_bookings::jobs::expire_unpaid_bookings(&pool)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_bookings::jobs::EXPIRE_UNPAID_BOOKINGS,
+            Duration::from_secs(5 * 60),
+        )
+        .register(
+            kamer_bookings::jobs::SEND_BOOKING_REMINDERS,
+            move |pool, _| async move {
+                kamer_bookings::jobs::send_booking_reminders(&pool, pending_expiry_hours)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_bookings::jobs::SEND_BOOKING_REMINDERS,
+            Duration::from_secs(5 * 60),
+        )
+        .register(kamer_bookings::jobs::COMPLETE_STAYS, |pool, _| async move {
+            kamer_bookings::jobs::complete_finished_stays(&pool)
+                .await
+                .map(|_| ())
+        })
+        .every(
+            kamer_bookings::jobs::COMPLETE_STAYS,
+            Duration::from_secs(60 * 60),
+        )
+        .register(
+            kamer_bookings::jobs::RELEASE_CHECK_IN_DETAILS,
+            |pool, _| async move {
+                kamer_bookings::jobs::release_check_in_details(&pool)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_bookings::jobs::RELEASE_CHECK_IN_DETAILS,
+            Duration::from_secs(15 * 60),
+        )
+        .register(
+            kamer_auth::sessions::CLEANUP_EXPIRED_SESSIONS,
+            |pool, _| async move {
+                kamer_auth::sessions::cleanup_expired_sessions(&pool)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_auth::sessions::CLEANUP_EXPIRED_SESSIONS,
+            Duration::from_secs(6 * 60 * 60),
+        )
+        .register(
+            kamer_db::idempotency::PURGE_EXPIRED_KEYS,
+            |pool, _| async move {
+                kamer_db::idempotency::purge_expired_keys(&pool)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_db::idempotency::PURGE_EXPIRED_KEYS,
+            Duration::from_secs(60 * 60),
+        )
+        .register(
+            kamer_jobs::PURGE_SUCCEEDED_JOBS,
+            move |pool, _| async move {
+                kamer_jobs::purge_succeeded_jobs(&pool, job_retention_days)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_jobs::PURGE_SUCCEEDED_JOBS,
+            Duration::from_secs(24 * 60 * 60),
+        )
+        .register(
+            kamer_messages::realtime::PRUNE_STALE_CONNECTIONS,
+            |pool, _| async move {
+                kamer_messages::realtime::prune_stale_connections(&pool)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_messages::realtime::PRUNE_STALE_CONNECTIONS,
+            Duration::from_secs(5 * 60),
+        )
+        .register(
+            kamer_payments::payouts::CREATE_PAYOUT_BATCHES,
+            |pool, _| async move {
+                kamer_payments::payouts::create_payout_batches(&pool)
+                    .await
+                    .map(|_| ())
+            },
+        )
+        .every(
+            kamer_payments::payouts::CREATE_PAYOUT_BATCHES,
+            Duration::from_secs(24 * 60 * 60),
+        )
+        .register(
+            kamer_calendar::imports::SYNC_CALENDAR_IMPORTS,
+            move |pool, _| {
+                let fetcher = calendar_fetcher.clone();
+                async move {
+                    kamer_calendar::imports::sync_due_feeds(&pool, fetcher.as_ref())
+                        .await
+                        .map(|_| ())
+                }
+            },
+        )
+        .every(
+            kamer_calendar::imports::SYNC_CALENDAR_IMPORTS,
+            Duration::from_secs(5 * 60),
+        )
+        .register(kamer_payments::refunds::REFUND_BOOKING, move |pool, payload| {
+            let providers = payment_providers.clone();
+            async move { kamer_payments::refunds::process_refund(&pool, &providers, payload).await }
+        })
+        .on_give_up(kamer_payments::refunds::REFUND_BOOKING, |pool, payload| async move {
+            kamer_payments::refunds::give_up_refund(&pool, payload).await
+        })
+}
@@ -901,7 +901,7 @@ const ListingEditor: React.FC = () => {
                                                         {Array.from({ length: 24 }).map((_, i) => {
                                                             const hour = i;
                                                             const time = `${hour === 0 ? 12 : (hour > 12 ? hour - 12 : hour)}:00 ${hour < 12 ? 'AM' : 'PM'}`;
-                                                            return <option key={i} value={`${hour}:00`}>{time}</option>;
+                                                            return <option key={i} value={`${hour.toString().padStart(2, '0')}:00`}>{time}</option>;
                                                         })}
                                                     </select>
                                                     <div className="absolute right-0 top-1/2 -translate-y-1/2 pointer-events-none">